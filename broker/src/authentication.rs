@@ -1,65 +1,170 @@
-use mqtt::common::utils::*;
-use std::{
-    fs::File,
-    io::{BufRead, BufReader, Error},
-};
-
-pub struct AuthData {
-    pub users: Vec<String>,
+use mqtt::server::server_credentials::ServerCredentials;
+use std::io::{BufRead, Error, ErrorKind};
+
+/// ## UsersCommand
+///
+/// Subcomando del broker que administra los usuarios de su archivo de credenciales
+///
+/// ### Variantes
+/// - `List`: lista los usuarios habilitados
+/// - `Add`: habilita un usuario nuevo
+/// - `Rotate`: cambia la contraseña de un usuario habilitado
+/// - `Remove`: deshabilita un usuario
+///
+/// ### Consideraciones
+/// - Si no se indica la contraseña se lee de la entrada estandar, para que no quede
+///   en el historial de la terminal
+/// - Un broker en ejecución recarga el archivo sin reiniciarse
+///
+#[derive(Debug, PartialEq)]
+pub enum UsersCommand {
+    List,
+    Add(String, Option<String>),
+    Rotate(String, Option<String>),
+    Remove(String),
 }
 
-fn read_config_file(file: &File) -> Option<Vec<String>> {
-    let mut lines = Vec::new();
-    let reader = BufReader::new(file);
+pub const USERS_USAGE: &str =
+    "Uso: broker users list | add <usuario> [contraseña] | rotate <usuario> [contraseña] | remove <usuario>";
 
-    for line in reader.lines() {
-        match line {
-            Ok(linea) => lines.push(linea),
-            Err(e) => {
-                eprintln!("Error al leer linea: {}", e);
-                return None;
+impl UsersCommand {
+    /// ### parse
+    ///
+    /// Interpreta los argumentos que siguen a `broker users`
+    ///
+    pub fn parse(args: &[String]) -> Result<Self, Error> {
+        let command = match args {
+            [command] if command == "list" => UsersCommand::List,
+            [command, user] if command == "add" => UsersCommand::Add(user.clone(), None),
+            [command, user, password] if command == "add" => {
+                UsersCommand::Add(user.clone(), Some(password.clone()))
+            }
+            [command, user] if command == "rotate" => UsersCommand::Rotate(user.clone(), None),
+            [command, user, password] if command == "rotate" => {
+                UsersCommand::Rotate(user.clone(), Some(password.clone()))
             }
-        }
+            [command, user] if command == "remove" => UsersCommand::Remove(user.clone()),
+            _ => return Err(Error::new(ErrorKind::InvalidInput, USERS_USAGE)),
+        };
+        Ok(command)
     }
 
-    Some(lines)
-}
+    /// ### run
+    ///
+    /// Ejecuta el subcomando sobre un archivo de credenciales
+    ///
+    /// ### Parametros
+    /// - `credentials_path`: archivo de credenciales del broker
+    /// - `input`: entrada de la que se lee la contraseña si no se indicó
+    ///
+    /// ### Retorno
+    /// - `Result<String, Error>`: mensaje para el usuario
+    ///
+    pub fn run(self, credentials_path: &str, input: &mut dyn BufRead) -> Result<String, Error> {
+        let credentials = match ServerCredentials::from_file(credentials_path) {
+            Ok(credentials) => credentials,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                ServerCredentials::with_file(credentials_path)
+            }
+            Err(e) => return Err(e),
+        };
 
-impl AuthData {
-    pub fn from_file(file_path: String) -> Result<Self, Error> {
-        let mut users = Vec::new();
-
-        let open_file: Option<File> = open_config_file(&file_path);
-        match open_file {
-            None => {
-                return Err(Error::new(
-                    std::io::ErrorKind::NotFound,
-                    "No se encontro el archivo",
-                ))
+        let message = match self {
+            UsersCommand::List => return Ok(credentials.users().join("\n")),
+            UsersCommand::Add(user, password) => {
+                if credentials.contains(&user) {
+                    return Err(Error::new(
+                        ErrorKind::AlreadyExists,
+                        format!("El usuario '{}' ya existe", user),
+                    ));
+                }
+                credentials.set_password(&user, &read_password(password, input)?)?;
+                format!("Usuario '{}' agregado", user)
             }
-            Some(_) => (),
-        }
-
-        let file = read_config_file(&open_file.unwrap());
-        match file {
-            None => {
-                return Err(Error::new(
-                    std::io::ErrorKind::InvalidData,
-                    "Error al leer el archivo",
-                ))
+            UsersCommand::Rotate(user, password) => {
+                if !credentials.contains(&user) {
+                    return Err(not_found(&user));
+                }
+                credentials.set_password(&user, &read_password(password, input)?)?;
+                format!("Contraseña de '{}' actualizada", user)
             }
-            Some(lines) => {
-                let mut is_usernames = true;
-                for (i, line) in lines.into_iter().enumerate() {
-                    if i == 0 && line == "broker_registered_apps: " {
-                        is_usernames = true;
-                    } else if is_usernames {
-                        users.push(line);
-                    }
+            UsersCommand::Remove(user) => {
+                if !credentials.remove_user(&user) {
+                    return Err(not_found(&user));
                 }
+                format!("Usuario '{}' eliminado", user)
             }
-        }
+        };
+
+        credentials.save()?;
+        Ok(message)
+    }
+}
+
+fn read_password(password: Option<String>, input: &mut dyn BufRead) -> Result<String, Error> {
+    if let Some(password) = password {
+        return Ok(password);
+    }
+
+    let mut line = String::new();
+    input.read_line(&mut line)?;
+    let password = line.trim_end_matches(['\r', '\n']).to_string();
+    if password.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "La contraseña no puede estar vacia",
+        ));
+    }
+    Ok(password)
+}
+
+fn not_found(user: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("El usuario '{}' no existe", user),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs::remove_file;
+
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_users_command() {
+        let path = "test_broker_users.tmp";
+        let run = |command: &[&str], input: &str| {
+            UsersCommand::parse(&args(command))?.run(path, &mut input.as_bytes())
+        };
+
+        assert!(UsersCommand::parse(&args(&["delete", "drone1"])).is_err());
+        run(&["add", "drone1", "1234"], "").unwrap();
+        run(&["add", "monitoringapp"], "5678\n").unwrap();
+        assert_eq!(
+            run(&["add", "drone1", "4321"], "").unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+        assert_eq!(run(&["list"], "").unwrap(), "drone1\nmonitoringapp");
+
+        run(&["rotate", "drone1"], "4321\n").unwrap();
+        let credentials = ServerCredentials::from_file(path).unwrap();
+        assert!(credentials.verify_password("drone1", b"4321"));
+        assert!(credentials.verify_password("monitoringapp", b"5678"));
+
+        run(&["remove", "monitoringapp"], "").unwrap();
+        assert_eq!(
+            run(&["rotate", "monitoringapp", "1234"], "")
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(run(&["list"], "").unwrap(), "drone1");
 
-        Ok(AuthData { users })
+        remove_file(path).unwrap();
     }
 }
@@ -1,36 +1,111 @@
-use broker::authentication::AuthData;
+use broker::authentication::UsersCommand;
 use logger::logger_handler::create_logger_handler;
 use mqtt::{
     config::{mqtt_config::Config, server_config::ServerConfig},
-    server::mqtt_server::MqttServer,
+    server::{
+        mqtt_server::MqttServer,
+        server_acl::ServerAcl,
+        server_admin::{send_admin_command, AdminCommand},
+        server_credentials::ServerCredentials,
+    },
 };
 
-use std::process::ExitCode;
+use signal_hook::{
+    consts::{SIGINT, SIGTERM},
+    iterator::Signals,
+};
+use std::{
+    env,
+    io::{self, ErrorKind},
+    process::ExitCode,
+    thread,
+};
 
 const CONFIG_PATH: &str = "broker/config/mqtt_server_config.txt";
-const AUTH_DATA_PATH: &str = "broker/config/broker_auth_data.txt";
 
 fn main() -> ExitCode {
     const CONFIGERROR: u8 = 3;
     const SERVER_LISTENERERROR: u8 = 4;
     const LOGGER_INITERROR: u8 = 5;
+    const USERS_COMMANDERROR: u8 = 6;
+    const ADMIN_COMMANDERROR: u8 = 7;
+    const SHUTDOWN_TIMEOUTERROR: u8 = 8;
 
-    let auth_data = match AuthData::from_file(String::from(AUTH_DATA_PATH)) {
-        Ok(data) => data,
+    let config = match ServerConfig::from_file(String::from(CONFIG_PATH)) {
+        Ok(conf) => conf,
         Err(e) => {
-            eprintln!("Error al obtener datos de autenticacion: {}", e);
+            eprintln!("Error al obtener configuracion del servidor: {}", e);
             return CONFIGERROR.into();
         }
     };
 
-    let config = match ServerConfig::from_file(String::from(CONFIG_PATH)) {
-        Ok(conf) => conf,
+    // broker admin ...: envia un comando al broker en ejecución
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("admin") {
+        let result = match &config.admin_socket_path {
+            Some(path) => {
+                AdminCommand::parse(&args[1..]).and_then(|_| send_admin_command(path, &args[1..]))
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "falta admin_socket_path en la configuracion",
+            )),
+        };
+        return match result {
+            Ok(message) => {
+                println!("{}", message);
+                0.into()
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                ADMIN_COMMANDERROR.into()
+            }
+        };
+    }
+
+    let credentials_path = match config.credentials_path.clone() {
+        Some(path) => path,
+        None => {
+            eprintln!("Error al obtener configuracion del servidor: falta credentials_path");
+            return CONFIGERROR.into();
+        }
+    };
+
+    // broker users ...: administra los usuarios sin iniciar el servidor
+    if args.first().map(String::as_str) == Some("users") {
+        let result = UsersCommand::parse(&args[1..])
+            .and_then(|command| command.run(&credentials_path, &mut io::stdin().lock()));
+        return match result {
+            Ok(message) => {
+                println!("{}", message);
+                0.into()
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                USERS_COMMANDERROR.into()
+            }
+        };
+    }
+
+    let credentials = match ServerCredentials::from_file(&credentials_path) {
+        Ok(credentials) => credentials,
         Err(e) => {
-            eprintln!("Error al obtener configuracion del servidor: {}", e);
+            eprintln!("Error al obtener datos de autenticacion: {}", e);
             return CONFIGERROR.into();
         }
     };
 
+    let acl = match &config.acl_path {
+        Some(acl_path) => match ServerAcl::from_file(acl_path) {
+            Ok(acl) => acl,
+            Err(e) => {
+                eprintln!("Error al obtener la ACL del servidor: {}", e);
+                return CONFIGERROR.into();
+            }
+        },
+        None => ServerAcl::default(),
+    };
+
     let log_path = config.general.log_path.to_string();
     let logger = match create_logger_handler(&log_path) {
         Ok(log) => {
@@ -49,11 +124,34 @@ fn main() -> ExitCode {
         }
     };
 
-    match MqttServer::new(config, auth_data.users).start_server(logger.get_logger()) {
+    let server = MqttServer::with_credentials(config, credentials).with_acl(acl);
+
+    // SIGINT / SIGTERM: el servidor deja de aceptar conexiones y se apaga ordenadamente
+    let mut signals = match Signals::new([SIGINT, SIGTERM]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            eprintln!("Error al registrar las señales de apagado: {}", e);
+            logger.close();
+            return SERVER_LISTENERERROR.into();
+        }
+    };
+    let shutdown_server = server.clone();
+    thread::spawn(move || {
+        if signals.forever().next().is_some() {
+            shutdown_server.shutdown();
+        }
+    });
+
+    match server.start_server(logger.get_logger()) {
         Ok(_) => {
             logger.close();
             0.into()
         }
+        Err(e) if e.kind() == ErrorKind::TimedOut => {
+            // Algun thread sigue usando el logger: no se espera a que termine de escribir
+            eprintln!("Server fails with error: {}", e);
+            SHUTDOWN_TIMEOUTERROR.into()
+        }
         Err(e) => {
             eprintln!("Server fails with error: {}", e);
             logger.close();
@@ -0,0 +1,23 @@
+#!/bin/bash
+
+# Uso: ./data/scripts/client_certs.sh <client_id>
+# El Common Name del certificado es el usuario con el que el broker identifica al cliente
+
+CERTS_FOLDER="data/certificates/"
+CA_NAME="ca-native"
+CLIENT_NAME=$1
+TIME="3600h"
+
+if [ -z "$CLIENT_NAME" ]; then
+    echo "Uso: make init_client_cert CLIENT=<client_id>"
+    exit 1
+fi
+
+echo "ingresa pass de la CA: 1234"
+step certificate create --kty=RSA --not-after=$TIME --profile leaf $CLIENT_NAME $CERTS_FOLDER$CLIENT_NAME.crt $CERTS_FOLDER$CLIENT_NAME.key --insecure --no-password --ca ./$CERTS_FOLDER$CA_NAME.crt --ca-key ./$CERTS_FOLDER$CA_NAME.key
+
+echo "verificacion de certificado"
+step certificate verify ./$CERTS_FOLDER$CLIENT_NAME.crt --roots ./$CERTS_FOLDER$CA_NAME.crt
+
+echo "formateando identidad del cliente - ingresa 1234: "
+openssl pkcs12 -export -out $CERTS_FOLDER$CLIENT_NAME.pfx -inkey $CERTS_FOLDER$CLIENT_NAME.key -in $CERTS_FOLDER$CLIENT_NAME.crt -certfile $CERTS_FOLDER$CA_NAME.crt
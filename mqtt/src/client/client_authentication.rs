@@ -0,0 +1,247 @@
+use std::sync::{
+    mpsc::{self, Receiver, Sender},
+    Arc, Mutex,
+};
+
+use crate::{
+    common::{
+        reason_codes::ReasonCode,
+        scram::{ScramClient, SCRAM_SHA_256},
+    },
+    mqtt_packets::properties::auth_properties::AuthProperties,
+};
+
+/// ## ClientAuthenticator
+///
+/// Método de autenticación extendida (Authentication Method) del lado del cliente.
+/// Cada intercambio, en el CONNECT o en una re-autenticación, usa una instancia nueva.
+///
+pub trait ClientAuthenticator: Send {
+    /// ## initial_data
+    ///
+    /// Authentication Data del CONNECT o del AUTH que inicia la re-autenticación.
+    ///
+    fn initial_data(&mut self) -> Option<Vec<u8>>;
+
+    /// ## step
+    ///
+    /// Responde los datos de un AUTH (Continue authentication) del servidor.
+    ///
+    fn step(&mut self, data: Option<Vec<u8>>) -> Result<Vec<u8>, ReasonCode>;
+
+    /// ## verify_success
+    ///
+    /// Verifica los datos finales que el servidor envía en el CONNACK o en el AUTH (Success).
+    ///
+    fn verify_success(&mut self, data: Option<Vec<u8>>) -> Result<(), ReasonCode>;
+}
+
+/// ## new_authenticator
+///
+/// Obtiene el autenticador de un Authentication Method.
+///
+/// ### Parámetros
+/// - method: Authentication Method.
+/// - username: Usuario con el que se autentica el cliente.
+/// - password: Contraseña del usuario.
+///
+/// ### Retorno
+/// El autenticador, o `None` si el cliente no soporta el método.
+///
+pub fn new_authenticator(
+    method: &str,
+    username: &str,
+    password: &str,
+) -> Option<Box<dyn ClientAuthenticator>> {
+    match method {
+        SCRAM_SHA_256 => Some(Box::new(ScramAuthenticator(ScramClient::new(
+            username, password,
+        )))),
+        _ => None,
+    }
+}
+
+/// ## ScramAuthenticator
+///
+/// SCRAM-SHA-256: la contraseña nunca se envía, el cliente prueba conocerla
+/// y verifica que el servidor conozca sus credenciales.
+///
+struct ScramAuthenticator(ScramClient);
+
+impl ClientAuthenticator for ScramAuthenticator {
+    fn initial_data(&mut self) -> Option<Vec<u8>> {
+        Some(self.0.client_first())
+    }
+
+    fn step(&mut self, data: Option<Vec<u8>>) -> Result<Vec<u8>, ReasonCode> {
+        self.0.client_final(&data.ok_or(ReasonCode::ProtocolError)?)
+    }
+
+    fn verify_success(&mut self, data: Option<Vec<u8>>) -> Result<(), ReasonCode> {
+        self.0
+            .verify_server_final(&data.ok_or(ReasonCode::NotAuthorized)?)
+    }
+}
+
+type PendingAuthentication = (Box<dyn ClientAuthenticator>, Sender<Result<(), ReasonCode>>);
+
+/// ## ClientAuthentication
+///
+/// Autenticación de un cliente MQTT. Es compartida entre los clones del cliente
+/// y su listener, que procesa los AUTH de las re-autenticaciones.
+///
+/// ### Atributos
+/// - method: Authentication Method negociado en el CONNECT.
+/// - pending: Re-autenticación en curso, junto al canal por el que se entrega su resultado.
+///
+#[derive(Clone, Default)]
+pub struct ClientAuthentication {
+    method: Option<String>,
+    pending: Arc<Mutex<Option<PendingAuthentication>>>,
+}
+
+impl ClientAuthentication {
+    pub fn new(method: Option<String>) -> Self {
+        ClientAuthentication {
+            method,
+            ..Default::default()
+        }
+    }
+
+    pub fn method(&self) -> Option<&str> {
+        self.method.as_deref()
+    }
+
+    /// ## start
+    ///
+    /// Registra una re-autenticación en curso.
+    ///
+    /// ### Retorno
+    /// Receptor por el que llega el resultado de la re-autenticación.
+    ///
+    pub fn start(
+        &self,
+        authenticator: Box<dyn ClientAuthenticator>,
+    ) -> Receiver<Result<(), ReasonCode>> {
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            *pending = Some((authenticator, sender));
+        }
+        receiver
+    }
+
+    /// ## handle_auth
+    ///
+    /// Procesa un AUTH recibido del servidor durante una re-autenticación.
+    ///
+    /// ### Retorno
+    /// - `Ok(Some(data))`: datos a responder en un AUTH (Continue authentication).
+    /// - `Ok(None)`: la re-autenticación terminó con éxito.
+    /// - `Err(reason_code)`: la re-autenticación falló y se debe cerrar la conexión.
+    ///
+    pub fn handle_auth(&self, auth: AuthProperties) -> Result<Option<Vec<u8>>, ReasonCode> {
+        let mut pending = match self.pending.lock() {
+            Ok(pending) => pending,
+            Err(_) => return Err(ReasonCode::UnspecifiedError),
+        };
+        let (mut authenticator, sender) = match pending.take() {
+            Some(authentication) => authentication,
+            None => return Err(ReasonCode::ProtocolError),
+        };
+
+        let result = if auth.authentication_method.as_deref() != self.method() {
+            Err(ReasonCode::BadAuthenticationMethod)
+        } else {
+            match ReasonCode::new(auth.reason_code) {
+                ReasonCode::ContinueAuthentication => {
+                    authenticator.step(auth.authentication_data).map(Some)
+                }
+                ReasonCode::Success => authenticator
+                    .verify_success(auth.authentication_data)
+                    .map(|_| None),
+                _ => Err(ReasonCode::ProtocolError),
+            }
+        };
+
+        match &result {
+            Ok(Some(_)) => *pending = Some((authenticator, sender)),
+            Ok(None) => {
+                let _ = sender.send(Ok(()));
+            }
+            Err(reason_code) => {
+                let _ = sender.send(Err(reason_code.clone()));
+            }
+        }
+        result
+    }
+
+    /// ## cancel
+    ///
+    /// Termina la re-autenticación en curso, si hay, con el reason code dado.
+    ///
+    pub fn cancel(&self, reason_code: ReasonCode) {
+        if let Ok(mut pending) = self.pending.lock() {
+            if let Some((_, sender)) = pending.take() {
+                let _ = sender.send(Err(reason_code));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::scram::{ScramCredentials, ScramServer};
+
+    use super::*;
+
+    fn auth(reason_code: ReasonCode, data: Vec<u8>) -> AuthProperties {
+        AuthProperties {
+            reason_code: reason_code.get_id(),
+            authentication_method: Some(SCRAM_SHA_256.to_string()),
+            authentication_data: Some(data),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_reauthentication() {
+        let credentials = ScramCredentials::generate("1234");
+        let authentication = ClientAuthentication::new(Some(SCRAM_SHA_256.to_string()));
+        assert!(new_authenticator("PLAIN", "monitoring", "1234").is_none());
+
+        let mut authenticator = new_authenticator(SCRAM_SHA_256, "monitoring", "1234").unwrap();
+        let client_first = authenticator.initial_data().unwrap();
+        let receiver = authentication.clone().start(authenticator);
+
+        let (server, server_first) =
+            ScramServer::server_first(&client_first, |_| Some(credentials.clone())).unwrap();
+        let client_final = authentication
+            .handle_auth(auth(ReasonCode::ContinueAuthentication, server_first))
+            .unwrap()
+            .unwrap();
+        assert!(receiver.try_recv().is_err());
+
+        let server_final = server.server_final(&client_final).unwrap();
+        assert_eq!(
+            authentication.handle_auth(auth(ReasonCode::Success, server_final.clone())),
+            Ok(None)
+        );
+        assert_eq!(receiver.recv().unwrap(), Ok(()));
+
+        // Sin re-autenticación en curso, un AUTH es un error de protocolo
+        assert_eq!(
+            authentication.handle_auth(auth(ReasonCode::Success, server_final)),
+            Err(ReasonCode::ProtocolError)
+        );
+    }
+
+    #[test]
+    fn test_cancel() {
+        let authentication = ClientAuthentication::new(Some(SCRAM_SHA_256.to_string()));
+        let authenticator = new_authenticator(SCRAM_SHA_256, "monitoring", "1234").unwrap();
+        let receiver = authentication.start(authenticator);
+
+        authentication.cancel(ReasonCode::NotAuthorized);
+        assert_eq!(receiver.recv().unwrap(), Err(ReasonCode::NotAuthorized));
+    }
+}
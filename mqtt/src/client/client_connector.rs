@@ -1,18 +1,72 @@
 use logger::logger_handler::Logger;
-use native_tls::{TlsConnector, TlsStream};
+use native_tls::{Certificate, Identity, TlsConnector, TlsStream};
 use std::error::Error;
+use std::fs;
 use std::net::TcpStream;
 use std::thread::sleep;
 
+/// ## build_tls_connector
+///
+/// Crea el conector TLS del cliente.
+/// Si se indica un certificado de CA, se agrega como raiz de confianza
+/// para validar el certificado del servidor.
+/// Si se indica una identidad, se presenta al servidor cuando este la pide.
+///
+/// ### Parametros
+/// - `ca_path`: ruta del certificado de la CA (formato PEM)
+/// - `identity`: ruta de la identidad del cliente (PKCS#12) y su contraseña
+///
+fn build_tls_connector(
+    ca_path: &Option<String>,
+    identity: Option<(&str, &str)>,
+) -> Result<TlsConnector, Box<dyn Error>> {
+    let mut builder = TlsConnector::builder();
+
+    if let Some(path) = ca_path {
+        let ca = fs::read(path)?;
+        builder.add_root_certificate(Certificate::from_pem(&ca)?);
+    }
+
+    if let Some((path, password)) = identity {
+        let identity = fs::read(path)?;
+        builder.identity(Identity::from_pkcs12(&identity, password)?);
+    }
+
+    Ok(builder.build()?)
+}
+
 // srv_name = certificated name of the address ip
 pub fn connect(
     addres: &str,
     srv_name: &str,
+    ca_path: &Option<String>,
+    keep_alive: &u16,
+    client_id: &String,
+    logger: &Logger,
+) -> Result<TlsStream<TcpStream>, Box<dyn Error>> {
+    connect_with_identity(
+        addres, srv_name, ca_path, None, keep_alive, client_id, logger,
+    )
+}
+
+/// ## connect_with_identity
+///
+/// Se conecta al servidor presentando un certificado de cliente (TLS mutuo).
+///
+/// ### Parametros
+/// - `identity`: ruta de la identidad del cliente (PKCS#12) y su contraseña.
+///   Sin identidad, equivale a `connect`.
+///
+pub fn connect_with_identity(
+    addres: &str,
+    srv_name: &str,
+    ca_path: &Option<String>,
+    identity: Option<(&str, &str)>,
     keep_alive: &u16,
     client_id: &String,
     logger: &Logger,
 ) -> Result<TlsStream<TcpStream>, Box<dyn Error>> {
-    let tls_connector = TlsConnector::new()?;
+    let tls_connector = build_tls_connector(ca_path, identity)?;
 
     let mut tcp_stream;
     let duration = *keep_alive as u64;
@@ -31,3 +85,19 @@ pub fn connect(
         }
     }
 }
+
+/// ## try_connect_with_identity
+///
+/// Igual que `connect_with_identity`, pero intenta conectarse una sola vez.
+/// Si el servidor no esta disponible se devuelve el error, sin reintentar.
+///
+pub fn try_connect_with_identity(
+    addres: &str,
+    srv_name: &str,
+    ca_path: &Option<String>,
+    identity: Option<(&str, &str)>,
+) -> Result<TlsStream<TcpStream>, Box<dyn Error>> {
+    let tls_connector = build_tls_connector(ca_path, identity)?;
+    let stream = TcpStream::connect(addres)?;
+    Ok(tls_connector.connect(srv_name, stream)?)
+}
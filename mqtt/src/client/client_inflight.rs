@@ -0,0 +1,233 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Condvar, Mutex,
+    },
+    time::Duration,
+};
+
+use super::client_message::MqttClientMessage;
+
+/// ## ClientInflight
+///
+/// Estado de las publicaciones en curso de un cliente MQTT.
+/// Es compartido entre los clones del cliente y su listener.
+///
+/// ### Atributos
+/// - acknowledgements: Publicaciones enviadas (QoS 1 y 2) que esperan su confirmación,
+///   junto al canal por el que se informa el reason code recibido.
+/// - window: Avisa cuando se confirma una publicación, a quienes esperan lugar para publicar.
+/// - awaiting_release: IDs de las publicaciones QoS 2 recibidas que esperan su PUBREL.
+/// - listener_running: Indica si hay un listener leyendo los paquetes del servidor.
+/// - undelivered_messages: Mensajes recibidos mientras no había listener.
+///
+#[derive(Clone, Default)]
+pub struct ClientInflight {
+    acknowledgements: Arc<Mutex<HashMap<u16, Sender<u8>>>>,
+    window: Arc<Condvar>,
+    awaiting_release: Arc<Mutex<HashSet<u16>>>,
+    listener_running: Arc<AtomicBool>,
+    undelivered_messages: Arc<Mutex<VecDeque<MqttClientMessage>>>,
+}
+
+impl ClientInflight {
+    /// ## register_publish
+    ///
+    /// Registra una publicación enviada que espera su confirmación.
+    /// Si ya hay `send_maximum` publicaciones sin confirmar, espera a que se confirme alguna.
+    ///
+    /// ### Parámetros
+    /// - packet_id: ID de la publicación.
+    /// - send_maximum: Receive Maximum del servidor.
+    /// - timeout: Tiempo máximo de espera.
+    ///
+    /// ### Retorno
+    /// Receptor por el que llega el reason code de la confirmación,
+    /// o `None` si no hubo lugar a tiempo.
+    ///
+    pub fn register_publish(
+        &self,
+        packet_id: u16,
+        send_maximum: u16,
+        timeout: Duration,
+    ) -> Option<Receiver<u8>> {
+        let acknowledgements = self.acknowledgements.lock().ok()?;
+        let (mut acknowledgements, wait) = self
+            .window
+            .wait_timeout_while(acknowledgements, timeout, |acknowledgements| {
+                acknowledgements.len() >= send_maximum as usize
+            })
+            .ok()?;
+        if wait.timed_out() {
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        acknowledgements.insert(packet_id, sender);
+        Some(receiver)
+    }
+
+    /// ## acknowledge
+    ///
+    /// Informa la confirmación (PUBACK o PUBCOMP) de una publicación enviada.
+    ///
+    /// ### Retorno
+    /// true si la publicación esperaba su confirmación.
+    ///
+    pub fn acknowledge(&self, packet_id: u16, reason_code: u8) -> bool {
+        let sender = match self.acknowledgements.lock() {
+            Ok(mut acknowledgements) => acknowledgements.remove(&packet_id),
+            Err(_) => None,
+        };
+        self.window.notify_all();
+        match sender {
+            Some(sender) => sender.send(reason_code).is_ok(),
+            None => false,
+        }
+    }
+
+    /// ## cancel
+    ///
+    /// Deja de esperar la confirmación de una publicación.
+    ///
+    pub fn cancel(&self, packet_id: u16) {
+        if let Ok(mut acknowledgements) = self.acknowledgements.lock() {
+            acknowledgements.remove(&packet_id);
+        }
+        self.window.notify_all();
+    }
+
+    /// ## receive_exactly_once
+    ///
+    /// Registra una publicación QoS 2 recibida del servidor.
+    ///
+    /// ### Retorno
+    /// true si la publicación es nueva, false si es un duplicado
+    /// (todavía no se recibió su PUBREL).
+    ///
+    pub fn receive_exactly_once(&self, packet_id: u16) -> bool {
+        match self.awaiting_release.lock() {
+            Ok(mut awaiting_release) => awaiting_release.insert(packet_id),
+            Err(_) => true,
+        }
+    }
+
+    /// ## unreleased_publishes
+    ///
+    /// Cantidad de publicaciones QoS 2 recibidas que esperan su PUBREL, sin contar
+    /// la del ID dado (un reenvío no es una publicación nueva).
+    ///
+    pub fn unreleased_publishes(&self, packet_id: u16) -> usize {
+        match self.awaiting_release.lock() {
+            Ok(awaiting_release) if !awaiting_release.contains(&packet_id) => {
+                awaiting_release.len()
+            }
+            _ => 0,
+        }
+    }
+
+    /// ## release_exactly_once
+    ///
+    /// Libera una publicación QoS 2 recibida al recibir su PUBREL.
+    ///
+    /// ### Retorno
+    /// true si el ID estaba registrado.
+    ///
+    pub fn release_exactly_once(&self, packet_id: u16) -> bool {
+        match self.awaiting_release.lock() {
+            Ok(mut awaiting_release) => awaiting_release.remove(&packet_id),
+            Err(_) => false,
+        }
+    }
+
+    pub fn set_listener_running(&self, running: bool) {
+        self.listener_running.store(running, Ordering::SeqCst);
+    }
+
+    pub fn is_listener_running(&self) -> bool {
+        self.listener_running.load(Ordering::SeqCst)
+    }
+
+    /// ## store_undelivered_message
+    ///
+    /// Guarda un mensaje recibido mientras no había listener,
+    /// para entregarlo cuando se inicie.
+    ///
+    pub fn store_undelivered_message(&self, message: MqttClientMessage) {
+        if let Ok(mut messages) = self.undelivered_messages.lock() {
+            messages.push_back(message);
+        }
+    }
+
+    /// ## take_undelivered_messages
+    ///
+    /// Obtiene los mensajes recibidos mientras no había listener.
+    ///
+    pub fn take_undelivered_messages(&self) -> Vec<MqttClientMessage> {
+        match self.undelivered_messages.lock() {
+            Ok(mut messages) => messages.drain(..).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_acknowledge_is_shared_between_clones() {
+        let inflight = ClientInflight::default();
+        let listener_side = inflight.clone();
+
+        let receiver = inflight
+            .register_publish(7, u16::MAX, Duration::from_secs(1))
+            .unwrap();
+        assert!(listener_side.acknowledge(7, 16));
+        assert_eq!(receiver.recv().unwrap(), 16);
+
+        // Una confirmacion repetida o desconocida se ignora
+        assert!(!listener_side.acknowledge(7, 0));
+        assert!(!listener_side.acknowledge(8, 0));
+    }
+
+    #[test]
+    fn test_exactly_once() {
+        let inflight = ClientInflight::default();
+
+        assert!(inflight.receive_exactly_once(3));
+        assert!(!inflight.clone().receive_exactly_once(3));
+        assert_eq!(inflight.unreleased_publishes(3), 0);
+        assert_eq!(inflight.unreleased_publishes(4), 1);
+        assert!(inflight.release_exactly_once(3));
+        assert!(!inflight.release_exactly_once(3));
+        assert!(inflight.receive_exactly_once(3));
+    }
+
+    #[test]
+    fn test_send_maximum() {
+        let inflight = ClientInflight::default();
+        let listener_side = inflight.clone();
+
+        let first = inflight.register_publish(1, 2, Duration::from_secs(1));
+        let second = inflight.register_publish(2, 2, Duration::from_secs(1));
+        assert!(first.is_some() && second.is_some());
+
+        // Sin lugar, la publicación no se registra
+        assert!(inflight
+            .register_publish(3, 2, Duration::from_millis(50))
+            .is_none());
+
+        // Al confirmarse una publicación se libera un lugar
+        let handle = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            listener_side.acknowledge(1, 0);
+        });
+        assert!(inflight
+            .register_publish(3, 2, Duration::from_secs(2))
+            .is_some());
+        handle.join().unwrap();
+    }
+}
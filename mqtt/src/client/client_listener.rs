@@ -1,22 +1,37 @@
 use std::{
-    io::Error,
-    net::TcpStream,
-    sync::mpsc::{self, Receiver, Sender},
+    io::{Error, ErrorKind},
+    sync::{
+        atomic::Ordering,
+        mpsc::{self, Receiver, Sender},
+    },
     thread::{self, JoinHandle},
 };
 
 use logger::logger_handler::Logger;
 
 use crate::{
-    common::reason_codes::ReasonCode,
+    common::{flags::flags_handler, mqtt_stream::MqttStream, reason_codes::ReasonCode},
+    config::mqtt_config::Config,
     logging::{actions::MqttActions, client_actions::MqttClientActions},
     mqtt_packets::{
         headers::fixed_header::PacketFixedHeader,
-        packet::generic_packet::{get_packet, PacketReceived},
+        packet::generic_packet::{get_packet, PacketReceived, Serialization},
+        packets::{
+            disconnect::Disconnect, puback::Puback, pubcomp::Pubcomp, publish::Publish,
+            pubrec::Pubrec, pubrel::Pubrel,
+        },
+        properties::{
+            disconnect_properties::DisconnectProperties, puback_properties::PubackProperties,
+            pubcomp_properties::PubcompProperties, pubrec_properties::PubrecProperties,
+            pubrel_properties::PubrelProperties,
+        },
     },
 };
 
-use super::{client_message::MqttClientMessage, mqtt_client::MqttClient};
+use super::{
+    client_message::MqttClientMessage,
+    mqtt_client::{send_auth_packet, MqttClient},
+};
 
 /// ## MqttClientListener
 ///
@@ -42,15 +57,23 @@ impl MqttClientListener {
     /// ### Retorno
     /// Resultado de la operación con el listener.
     ///
+    /// ### Consideraciones
+    /// - Los mensajes recibidos antes de iniciar el listener (por ejemplo, mientras
+    ///   se esperaba la confirmación de una publicación) se entregan primero
+    ///
     pub fn run(client: &mut MqttClient, logger: &Logger) -> Result<MqttClientListener, Error> {
         let client = client.clone();
 
         let (sender, receiver) = mpsc::channel();
+        client.inflight.set_listener_running(true);
+        for message in client.inflight.take_undelivered_messages() {
+            let _ = sender.send(message);
+        }
 
         //let log_path = client.config.general.log_path.to_string();
         let logger_cpy = logger.clone();
         let handler = thread::spawn(move || -> Result<(), Error> {
-            loop {
+            let result = loop {
                 match Self::listen_message(
                     &client,
                     client.stream.try_clone()?,
@@ -59,13 +82,17 @@ impl MqttClientListener {
                     &logger_cpy,
                 ) {
                     Ok(_) => {}
+                    // La conexion fue cerrada (por ejemplo, luego de un DISCONNECT)
+                    Err(e) if e.kind() == ErrorKind::UnexpectedEof => break Ok(()),
                     Err(e) => {
                         // Disconnect
                         // Handle session expity interval
-                        return Err(e);
+                        break Err(e);
                     }
                 };
-            }
+            };
+            client.inflight.set_listener_running(false);
+            result
         });
 
         Ok(MqttClientListener { receiver, handler })
@@ -86,7 +113,7 @@ impl MqttClientListener {
     ///
     pub fn listen_message(
         client: &MqttClient,
-        mut stream: TcpStream,
+        mut stream: MqttStream,
         sender: Sender<MqttClientMessage>,
         //log_path: &String,
         logger: &Logger,
@@ -95,6 +122,7 @@ impl MqttClientListener {
 
         let header = match PacketFixedHeader::read_from(&mut stream) {
             Ok(r) => r,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Err(e),
             Err(e) => {
                 logger.log_event(
                     &(ReasonCode::MalformedPacket.to_string()),
@@ -151,9 +179,13 @@ impl MqttClientListener {
     /// ### Retorno
     /// Resultado de la operación con el mensaje.
     ///
+    /// ### Consideraciones
+    /// - Si el paquete supera el Maximum Packet Size del cliente, o una publicación supera
+    ///   su Receive Maximum, se cierra la conexión con un DISCONNECT
+    ///
     pub fn packet_handler(
         client: &MqttClient,
-        mut stream: &mut TcpStream,
+        mut stream: &mut MqttStream,
         fixed_header: PacketFixedHeader,
         //log_path: &String,
         logger: &Logger,
@@ -161,31 +193,126 @@ impl MqttClientListener {
         //let logger_handler = create_logger_handler(log_path)?;
         //let logger = logger_handler.get_logger();
 
+        if let Err(reason_code) = client.flow_control.check_received_packet(&fixed_header) {
+            return Err(Self::close_connection(client, stream, reason_code, logger));
+        }
+
         let packet_recived = get_packet(
             &mut stream,
             fixed_header.get_package_type(),
             fixed_header.remaining_length,
         )?;
 
-        let mut data = Vec::new();
-        let mut topic = String::new();
-        let mut is_will_message = false;
+        let mut message = None;
         let action = match packet_recived {
-            PacketReceived::Publish(publish) => {
-                topic.clone_from(&publish.properties.topic_name);
-                data.clone_from(&publish.properties.application_message);
+            PacketReceived::Publish(mut publish) => {
+                Self::resolve_topic_alias(client, stream, &mut publish, logger)?;
+                let topic = publish.properties.topic_name.clone();
+
+                let packet_id = publish.properties.packet_identifier;
+                let qos = flags_handler::get_publish_qos_level(publish.fixed_header_flags);
+                if qos == 1 {
+                    Puback::new(PubackProperties {
+                        packet_id,
+                        puback_reason_code: ReasonCode::Success.get_id(),
+                        ..Default::default()
+                    })
+                    .send(stream)?;
+                    MqttClientActions::SendPuback(packet_id).log_action(
+                        &client.config.general.id,
+                        logger,
+                        &client.config.general.log_in_term,
+                    );
+                } else if qos == 2 {
+                    let unreleased = client.inflight.unreleased_publishes(packet_id);
+                    if let Err(reason_code) = client.flow_control.check_received_publish(unreleased)
+                    {
+                        return Err(Self::close_connection(client, stream, reason_code, logger));
+                    }
+                    let is_new = client.inflight.receive_exactly_once(packet_id);
 
-                is_will_message = publish.properties.is_will_message;
+                    Self::send_pubrec(client, stream, packet_id, logger)?;
+
+                    if !is_new {
+                        MqttClientActions::DiscardDuplicatePublish(topic, packet_id).log_action(
+                            &client.config.general.id,
+                            logger,
+                            &client.config.general.log_in_term,
+                        );
+                        return Ok(None);
+                    }
+                }
+
+                let is_will_message = publish.properties.is_will_message;
+                // Las respuestas a solicitudes pendientes se entregan a quien las espera
+                message = client
+                    .requests
+                    .deliver(MqttClientMessage::new(publish.properties));
                 if is_will_message {
-                    MqttClientActions::ReceiveWillMessage(topic.clone())
+                    MqttClientActions::ReceiveWillMessage(topic)
                 } else {
-                    MqttClientActions::ReceivePublish(topic.clone())
+                    MqttClientActions::ReceivePublish(topic)
                 }
             }
-            PacketReceived::Puback(puback) => MqttClientActions::AcknowledgePublish(
-                client.config.general.id.clone(),
-                puback.properties.puback_reason_code,
-            ),
+            PacketReceived::Puback(puback) => {
+                client.inflight.acknowledge(
+                    puback.properties.packet_id,
+                    puback.properties.puback_reason_code,
+                );
+                MqttClientActions::AcknowledgePublish(
+                    client.config.general.id.clone(),
+                    puback.properties.puback_reason_code,
+                )
+            }
+            PacketReceived::Pubrec(pubrec) => {
+                let packet_id = pubrec.properties.packet_id;
+                let reason_code = pubrec.properties.pubrec_reason_code;
+                MqttClientActions::AcknowledgePubrec(packet_id, reason_code).log_action(
+                    &client.config.general.id,
+                    logger,
+                    &client.config.general.log_in_term,
+                );
+
+                // Un PUBREC con error finaliza el flujo: no se envia PUBREL
+                if reason_code >= 0x80 {
+                    client.inflight.acknowledge(packet_id, reason_code);
+                    return Ok(None);
+                }
+
+                Pubrel::new(PubrelProperties {
+                    packet_id,
+                    pubrel_reason_code: ReasonCode::Success.get_id(),
+                    ..Default::default()
+                })
+                .send(stream)?;
+                MqttClientActions::SendPubrel(packet_id)
+            }
+            PacketReceived::Pubrel(pubrel) => {
+                let packet_id = pubrel.properties.packet_id;
+                let pubcomp_reason_code = if client.inflight.release_exactly_once(packet_id) {
+                    ReasonCode::Success.get_id()
+                } else {
+                    ReasonCode::PacketIdentifierNotFound.get_id()
+                };
+
+                Pubcomp::new(PubcompProperties {
+                    packet_id,
+                    pubcomp_reason_code,
+                    ..Default::default()
+                })
+                .send(stream)?;
+                MqttClientActions::SendPubcomp(packet_id)
+            }
+            PacketReceived::Pubcomp(pubcomp) => {
+                client.inflight.acknowledge(
+                    pubcomp.properties.packet_id,
+                    pubcomp.properties.pubcomp_reason_code,
+                );
+                MqttClientActions::AcknowledgePubcomp(
+                    pubcomp.properties.packet_id,
+                    pubcomp.properties.pubcomp_reason_code,
+                )
+            }
             PacketReceived::Suback(suback) => MqttClientActions::AcknowledgeSubscribe(
                 client.config.general.id.clone(),
                 suback.properties.reason_codes,
@@ -194,10 +321,34 @@ impl MqttClientListener {
                 client.config.general.id.clone(),
                 unsuback.properties.reason_codes,
             ),
-            PacketReceived::PingResp(_) => MqttClientActions::ReceivePinresp,
+            PacketReceived::PingResp(_) => {
+                client.ping_pending.store(false, Ordering::SeqCst);
+                MqttClientActions::ReceivePinresp
+            }
+            PacketReceived::Auth(auth) => {
+                match client.authentication.handle_auth(auth.properties) {
+                    Ok(Some(data)) => {
+                        send_auth_packet(
+                            stream,
+                            ReasonCode::ContinueAuthentication,
+                            client.authentication.method().map(String::from),
+                            Some(data),
+                        )?;
+                        MqttClientActions::SendAuthentication(client.config.general.id.clone())
+                    }
+                    Ok(None) => {
+                        MqttClientActions::Reauthenticated(client.config.general.id.clone())
+                    }
+                    Err(reason_code) => {
+                        return Err(Self::close_connection(client, stream, reason_code, logger))
+                    }
+                }
+            }
             PacketReceived::Disconnect(disconnect) => {
-                let reason_code = disconnect.properties.disconnect_reason_code;
-                MqttClientActions::ReceiveDisconnect(ReasonCode::new(reason_code))
+                let reason_code = ReasonCode::new(disconnect.properties.disconnect_reason_code);
+                // Una re-autenticación en curso termina con la desconexión
+                client.authentication.cancel(reason_code.clone());
+                MqttClientActions::ReceiveDisconnect(reason_code)
             }
             _ => {
                 logger.log_event(
@@ -217,22 +368,101 @@ impl MqttClientListener {
         //logger.close();
         //logger_handler.close();
 
-        if let MqttClientActions::ReceivePublish(_) = action {
-            return Ok(Some(MqttClientMessage {
-                topic,
-                data,
-                is_will_message,
-            }));
+        Ok(message)
+    }
+
+    /// ## send_pubrec
+    ///
+    /// Confirma la recepción de una publicación QoS 2.
+    ///
+    /// ### Parámetros
+    /// - stream: Stream de conexión con el servidor.
+    /// - packet_id: ID de la publicación.
+    ///
+    fn send_pubrec(
+        client: &MqttClient,
+        stream: &mut MqttStream,
+        packet_id: u16,
+        logger: &Logger,
+    ) -> Result<(), Error> {
+        Pubrec::new(PubrecProperties {
+            packet_id,
+            pubrec_reason_code: ReasonCode::Success.get_id(),
+            ..Default::default()
+        })
+        .send(stream)?;
+
+        MqttClientActions::SendPubrec(packet_id).log_action(
+            &client.config.general.id,
+            logger,
+            &client.config.general.log_in_term,
+        );
+        Ok(())
+    }
+
+    /// ## resolve_topic_alias
+    ///
+    /// Resuelve el Topic Alias de una publicación recibida. Si el alias es inválido
+    /// o desconocido, se cierra la conexión con un DISCONNECT.
+    ///
+    /// ### Parámetros
+    /// - stream: Stream de conexión con el servidor.
+    /// - publish: Publicación recibida.
+    ///
+    fn resolve_topic_alias(
+        client: &MqttClient,
+        stream: &mut MqttStream,
+        publish: &mut Publish,
+        logger: &Logger,
+    ) -> Result<(), Error> {
+        let resolved = match client.topic_aliases.lock() {
+            Ok(mut topic_aliases) => topic_aliases.resolve(publish),
+            Err(_) => return Err(Error::other("Cliente - Lock envenenado")),
+        };
+        match resolved {
+            Ok(()) => Ok(()),
+            Err(reason_code) => Err(Self::close_connection(client, stream, reason_code, logger)),
         }
+    }
 
-        if let MqttClientActions::ReceiveWillMessage(_) = action {
-            return Ok(Some(MqttClientMessage {
-                topic,
-                data,
-                is_will_message,
-            }));
+    /// ## close_connection
+    ///
+    /// Cierra la conexión con el servidor por un error del protocolo, enviando
+    /// un DISCONNECT con el reason code dado.
+    ///
+    /// ### Parámetros
+    /// - stream: Stream de conexión con el servidor.
+    /// - reason_code: Reason code del DISCONNECT.
+    ///
+    /// ### Retorno
+    /// Error con el que termina el listener.
+    ///
+    fn close_connection(
+        client: &MqttClient,
+        stream: &mut MqttStream,
+        reason_code: ReasonCode,
+        logger: &Logger,
+    ) -> Error {
+        let sent = Disconnect::new(DisconnectProperties {
+            id: client.config.general.id.clone(),
+            disconnect_reason_code: reason_code.get_id(),
+            ..Default::default()
+        })
+        .send(stream);
+        let _ = stream.shutdown();
+        if let Err(e) = sent {
+            return e;
         }
 
-        Ok(None)
+        MqttClientActions::SendDisconnect(
+            client.config.get_socket_address().to_string(),
+            reason_code.clone(),
+        )
+        .log_action(
+            &client.config.general.id,
+            logger,
+            &client.config.general.log_in_term,
+        );
+        Error::new(ErrorKind::InvalidData, reason_code.to_string())
     }
 }
@@ -1,3 +1,5 @@
+use crate::mqtt_packets::properties::publish_properties::PublishProperties;
+
 /// ## MqttClientMessage
 ///
 /// Estructura que representa un mensaje recibido por el cliente MQTT.
@@ -5,9 +7,46 @@
 /// ### Atributos
 /// - topic: Tópico del mensaje.
 /// - data: Datos del mensaje.
+/// - is_will_message: Si el mensaje es un mensaje de voluntad.
+/// - user_properties: Propiedades de usuario de la publicación, en el orden recibido.
+/// - content_type: Tipo de contenido de la publicación.
+/// - response_topic: Tópico en el que se espera una respuesta.
+/// - correlation_data: Datos para asociar la respuesta con la solicitud.
+/// - message_expiry_interval: Tiempo de vida restante del mensaje, en segundos.
+/// - subscription_identifier: Identificador de la suscripción por la que se recibió.
 ///
+#[derive(Debug, Default)]
 pub struct MqttClientMessage {
     pub topic: String,
     pub data: Vec<u8>,
     pub is_will_message: bool,
+    pub user_properties: Vec<(String, String)>,
+    pub content_type: Option<String>,
+    pub response_topic: Option<String>,
+    pub correlation_data: Option<Vec<u8>>,
+    pub message_expiry_interval: Option<u32>,
+    pub subscription_identifier: Option<u32>,
+}
+
+impl MqttClientMessage {
+    /// ### new
+    ///
+    /// Crea un mensaje a partir de las propiedades de una publicación recibida
+    ///
+    /// ### Parametros
+    /// - `properties`: propiedades de la publicación (con el tópico ya resuelto)
+    ///
+    pub fn new(properties: PublishProperties) -> Self {
+        MqttClientMessage {
+            topic: properties.topic_name,
+            data: properties.application_message,
+            is_will_message: properties.is_will_message,
+            user_properties: properties.user_properties,
+            content_type: properties.content_type,
+            response_topic: properties.response_topic,
+            correlation_data: properties.correlation_data,
+            message_expiry_interval: properties.message_expiry_interval,
+            subscription_identifier: properties.subscription_identifier,
+        }
+    }
 }
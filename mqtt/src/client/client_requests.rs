@@ -0,0 +1,185 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+};
+
+use super::client_message::MqttClientMessage;
+
+/// ## ClientRequests
+///
+/// Solicitudes (request/response) de un cliente MQTT que esperan su respuesta.
+/// Es compartido entre los clones del cliente y su listener.
+///
+/// ### Atributos
+/// - response_topic: Tópico en el que el cliente recibe las respuestas.
+/// - pending: Solicitudes enviadas, por Correlation Data, junto al canal por el que
+///   se entrega la respuesta.
+/// - last_correlation: Último número usado como Correlation Data.
+/// - subscribed: Indica si el cliente ya se suscribió al tópico de respuestas.
+///
+#[derive(Clone, Default)]
+pub struct ClientRequests {
+    response_topic: String,
+    pending: Arc<Mutex<HashMap<Vec<u8>, Sender<MqttClientMessage>>>>,
+    last_correlation: Arc<AtomicU64>,
+    subscribed: Arc<AtomicBool>,
+}
+
+impl ClientRequests {
+    /// ## new
+    ///
+    /// Crea el registro de solicitudes de un cliente.
+    ///
+    /// ### Parámetros
+    /// - response_information: Response Information del CONNACK, si el servidor la envió.
+    /// - client_id: ID del cliente.
+    ///
+    /// ### Consideraciones
+    /// - Si el servidor no envió Response Information, las respuestas se reciben
+    ///   en `{client_id}/response`
+    ///
+    pub fn new(response_information: Option<String>, client_id: &str) -> Self {
+        ClientRequests {
+            response_topic: response_information
+                .unwrap_or_else(|| format!("{}/response", client_id)),
+            ..Default::default()
+        }
+    }
+
+    pub fn response_topic(&self) -> &str {
+        &self.response_topic
+    }
+
+    /// ## needs_subscription
+    ///
+    /// Indica si todavía hay que suscribirse al tópico de respuestas.
+    /// Solo devuelve true una vez (ver `subscription_failed`).
+    ///
+    pub fn needs_subscription(&self) -> bool {
+        !self.subscribed.swap(true, Ordering::SeqCst)
+    }
+
+    /// ## subscription_failed
+    ///
+    /// Informa que no se pudo suscribir al tópico de respuestas, para reintentarlo
+    /// en la siguiente solicitud.
+    ///
+    pub fn subscription_failed(&self) {
+        self.subscribed.store(false, Ordering::SeqCst);
+    }
+
+    /// ## register_request
+    ///
+    /// Registra una solicitud que espera su respuesta.
+    ///
+    /// ### Retorno
+    /// Correlation Data de la solicitud y receptor por el que llega la respuesta.
+    ///
+    pub fn register_request(&self) -> (Vec<u8>, Receiver<MqttClientMessage>) {
+        let correlation_data = (self.last_correlation.fetch_add(1, Ordering::SeqCst) + 1)
+            .to_be_bytes()
+            .to_vec();
+        let (sender, receiver) = mpsc::channel();
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(correlation_data.clone(), sender);
+        }
+        (correlation_data, receiver)
+    }
+
+    /// ## cancel
+    ///
+    /// Deja de esperar la respuesta de una solicitud.
+    ///
+    pub fn cancel(&self, correlation_data: &[u8]) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(correlation_data);
+        }
+    }
+
+    /// ## deliver
+    ///
+    /// Entrega un mensaje recibido a la solicitud que lo espera, si es su respuesta.
+    ///
+    /// ### Retorno
+    /// El mensaje, si no es la respuesta de una solicitud pendiente.
+    ///
+    pub fn deliver(&self, message: MqttClientMessage) -> Option<MqttClientMessage> {
+        if message.topic != self.response_topic {
+            return Some(message);
+        }
+        let sender = match (&message.correlation_data, self.pending.lock()) {
+            (Some(correlation_data), Ok(mut pending)) => pending.remove(correlation_data),
+            _ => None,
+        };
+        match sender {
+            Some(sender) => {
+                let _ = sender.send(message);
+                None
+            }
+            None => Some(message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn response(topic: &str, correlation_data: Option<Vec<u8>>) -> MqttClientMessage {
+        MqttClientMessage {
+            topic: topic.to_string(),
+            correlation_data,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_deliver_matches_correlation_data() {
+        let requests = ClientRequests::new(None, "monitoring");
+        assert_eq!(requests.response_topic(), "monitoring/response");
+
+        let (first, first_receiver) = requests.register_request();
+        let (second, second_receiver) = requests.clone().register_request();
+        assert_ne!(first, second);
+
+        assert!(requests
+            .deliver(response("monitoring/response", Some(second.clone())))
+            .is_none());
+        assert_eq!(
+            second_receiver.recv().unwrap().correlation_data,
+            Some(second.clone())
+        );
+        assert!(first_receiver.try_recv().is_err());
+
+        // Los mensajes de otros topicos, repetidos o sin solicitud pendiente no se consumen
+        assert!(requests
+            .deliver(response("inc", Some(first.clone())))
+            .is_some());
+        assert!(requests
+            .deliver(response("monitoring/response", Some(second)))
+            .is_some());
+        assert!(requests
+            .deliver(response("monitoring/response", None))
+            .is_some());
+
+        requests.cancel(&first);
+        assert!(requests
+            .deliver(response("monitoring/response", Some(first)))
+            .is_some());
+    }
+
+    #[test]
+    fn test_subscribes_once() {
+        let requests = ClientRequests::new(Some("response/drone".to_string()), "drone");
+        assert_eq!(requests.response_topic(), "response/drone");
+
+        assert!(requests.needs_subscription());
+        assert!(!requests.clone().needs_subscription());
+        requests.subscription_failed();
+        assert!(requests.needs_subscription());
+    }
+}
@@ -1,9 +1,21 @@
-use std::{io::Error, net::TcpStream};
+use std::{
+    io::{Error, ErrorKind},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{Receiver, RecvTimeoutError},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use logger::logger_handler::{create_logger_handler, Logger};
 
 use crate::{
-    common::{authentication::serialize_username_password, reason_codes::ReasonCode},
+    common::{
+        flags::flags_handler, flow_control::FlowControl, mqtt_stream::MqttStream,
+        reason_codes::ReasonCode, topic_alias::TopicAliases,
+    },
     config::{client_config::ClientConfig, mqtt_config::Config},
     logging::{actions::MqttActions, client_actions::MqttClientActions},
     mqtt_packets::{
@@ -14,7 +26,7 @@ use crate::{
             pingreq::PingReq, publish::Publish, subscribe::Subscribe, unsubscribe::Unsubscribe,
         },
         properties::{
-            auth_properties, connect_payload::ConnectPayload,
+            auth_properties::AuthProperties, connect_payload::ConnectPayload,
             disconnect_properties::DisconnectProperties, publish_properties::PublishProperties,
             subscribe_properties::SubscribeProperties,
             unsubscribe_properties::UnsubscribeProperties,
@@ -22,8 +34,15 @@ use crate::{
     },
 };
 
-use super::client_connector::connect;
+use super::client_authentication::{new_authenticator, ClientAuthentication, ClientAuthenticator};
+use super::client_connector::{connect_with_identity, try_connect_with_identity};
+use super::client_inflight::ClientInflight;
 use super::client_listener::MqttClientListener;
+use super::client_message::MqttClientMessage;
+use super::client_requests::ClientRequests;
+
+/// Tiempo máximo de espera de la confirmación (PUBACK o PUBCOMP) de una publicación
+const ACKNOWLEDGE_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// ## MqttClient
 ///
@@ -32,7 +51,14 @@ use super::client_listener::MqttClientListener;
 /// ### Atributos
 /// - config: Configuración del cliente.
 /// - stream: Stream de conexión con el servidor.
-/// - current_packet_id: ID del paquete actual.
+/// - current_packet_id: ID del paquete actual (compartido entre los clones del cliente).
+/// - inflight: Publicaciones en curso (compartidas entre los clones del cliente y su listener).
+/// - keep_alive: Keep alive de la conexión (el pedido por el cliente o el Server Keep Alive del CONNACK).
+/// - ping_pending: Indica si se envió un PINGREQ que todavía no fue respondido.
+/// - topic_aliases: Tablas de Topic Alias de la conexión (compartidas entre los clones del cliente).
+/// - requests: Solicitudes que esperan su respuesta (compartidas entre los clones del cliente y su listener).
+/// - flow_control: Receive Maximum y Maximum Packet Size negociados en el CONNECT y el CONNACK.
+/// - authentication: Authentication Method negociado y re-autenticación en curso (compartidos entre los clones del cliente y su listener).
 ///
 /// ### Métodos
 /// - init: Inicializa un cliente MQTT.
@@ -40,6 +66,11 @@ use super::client_listener::MqttClientListener;
 /// - listen_message: Escucha los mensajes del servidor.
 /// - messages_handler: Maneja los mensajes recibidos.
 /// - publish: Publica un mensaje en un tópico.
+/// - publish_with_qos: Publica un mensaje en un tópico con un QoS determinado.
+/// - publish_with_properties: Publica un mensaje con propiedades propias.
+/// - reauthenticate: Vuelve a autenticar al cliente sin cerrar la conexión.
+/// - request: Envía una solicitud y espera su respuesta.
+/// - respond: Responde una solicitud recibida.
 /// - subscribe: Se suscribe a un tópico.
 /// - unsubscribe: Se desuscribe de un tópico.
 /// - disconnect: Se desconecta del servidor.
@@ -47,8 +78,15 @@ use super::client_listener::MqttClientListener;
 ///
 pub struct MqttClient {
     pub config: ClientConfig,
-    pub stream: TcpStream,
-    pub current_packet_id: u16,
+    pub stream: MqttStream,
+    pub current_packet_id: Arc<Mutex<u16>>,
+    pub inflight: ClientInflight,
+    pub keep_alive: u16,
+    pub ping_pending: Arc<AtomicBool>,
+    pub topic_aliases: Arc<Mutex<TopicAliases>>,
+    pub requests: ClientRequests,
+    pub flow_control: FlowControl,
+    pub authentication: ClientAuthentication,
 }
 
 /// ## receive_packet
@@ -61,7 +99,7 @@ pub struct MqttClient {
 /// ### Retorno
 /// Resultado de la operación con el paquete recibido.
 ///
-pub fn receive_packet(mut stream: &mut TcpStream) -> Result<PacketReceived, Error> {
+pub fn receive_packet(mut stream: &mut MqttStream) -> Result<PacketReceived, Error> {
     let fixed_header = PacketFixedHeader::read_from(&mut stream)?;
 
     get_packet(
@@ -74,28 +112,101 @@ pub fn receive_packet(mut stream: &mut TcpStream) -> Result<PacketReceived, Erro
 /// ## receive_connack_packet
 ///
 /// Función que recibe un paquete CONNACK del servidor.
+/// Si el cliente usa autenticación extendida, antes responde los AUTH del servidor.
 ///
 /// ### Parámetros
 /// - stream: Stream de conexión con el servidor.
+/// - authenticator: Autenticador del Authentication Method del CONNECT, si hay.
+/// - config: Configuración del cliente.
 ///
 /// ### Retorno
 /// Resultado de la operación con el paquete CONNACK recibido.
 ///
-fn receive_connack_packet(stream: &mut TcpStream) -> Result<Connack, Error> {
-    let packet_recived = receive_packet(stream)?;
-
-    match packet_recived {
-        PacketReceived::Connack(connack) => Ok(*connack),
-        _ => Err(Error::new(
-            std::io::ErrorKind::Other,
-            "ClientReceive - Paquete desconocido",
-        )),
+fn receive_connack_packet(
+    stream: &mut MqttStream,
+    authenticator: &mut Option<Box<dyn ClientAuthenticator>>,
+    config: &ClientConfig,
+    logger: &Logger,
+) -> Result<Connack, Error> {
+    loop {
+        let auth = match receive_packet(stream)? {
+            PacketReceived::Connack(connack) => return Ok(*connack),
+            PacketReceived::Auth(auth) => auth,
+            _ => {
+                return Err(Error::new(
+                    std::io::ErrorKind::Other,
+                    "ClientReceive - Paquete desconocido",
+                ))
+            }
+        };
+
+        let data = match (
+            ReasonCode::new(auth.properties.reason_code),
+            authenticator.as_mut(),
+        ) {
+            (ReasonCode::ContinueAuthentication, Some(authenticator)) => {
+                authenticator.step(auth.properties.authentication_data)
+            }
+            _ => Err(ReasonCode::ProtocolError),
+        };
+        let data = data.map_err(|reason_code| {
+            Error::new(ErrorKind::PermissionDenied, reason_code.to_string())
+        })?;
+        send_auth_packet(
+            stream,
+            ReasonCode::ContinueAuthentication,
+            config.connect_properties.authentication_method.clone(),
+            Some(data),
+        )?;
+        MqttClientActions::SendAuthentication(config.general.id.clone()).log_action(
+            &config.general.id,
+            logger,
+            &config.general.log_in_term,
+        );
     }
 }
 
-/// ## stablish_tcp_connection
+/// ## verify_connection
 ///
-/// Establece una conexión TCP con el servidor.
+/// Verifica que el servidor haya aceptado la autenticación del cliente y, si hubo
+/// autenticación extendida, los datos finales del servidor en el CONNACK.
+///
+/// ### Parámetros
+/// - connack: CONNACK recibido.
+/// - authenticator: Autenticador del Authentication Method del CONNECT, si hay.
+///
+/// ### Retorno
+/// Resultado de la operación (PermissionDenied si el cliente no se autenticó).
+///
+fn verify_connection(
+    connack: &Connack,
+    authenticator: Option<Box<dyn ClientAuthenticator>>,
+) -> Result<(), Error> {
+    let result = match (
+        ReasonCode::new(connack.properties.connect_reason_code),
+        authenticator,
+    ) {
+        (
+            reason_code @ (ReasonCode::NotAuthorized
+            | ReasonCode::BadUserNameOrPassword
+            | ReasonCode::BadAuthenticationMethod),
+            _,
+        ) => Err(reason_code),
+        (_, Some(mut authenticator)) => {
+            authenticator.verify_success(connack.properties.authentication_data.clone())
+        }
+        (_, None) => Ok(()),
+    };
+    result.map_err(|reason_code| Error::new(ErrorKind::PermissionDenied, reason_code.to_string()))
+}
+
+/// ## stablish_tls_connection
+///
+/// Establece una conexión TLS con el servidor.
+/// Todos los paquetes de la sesión viajan cifrados sobre esta conexión.
+/// Si se configura una identidad, el cliente presenta su certificado al servidor.
+/// Si el servidor no está disponible, se reintenta cada `keep_alive` segundos,
+/// salvo que la configuración tenga `connect_retry` en false.
 ///
 /// ### Parámetros
 /// - log_path: Ruta del archivo de log.
@@ -109,12 +220,29 @@ fn stablish_tls_connection(
     config: &ClientConfig,
     client_id: &String,
     logger: &Logger,
-) -> Result<TcpStream, Error> {
+) -> Result<MqttStream, Error> {
     let address = config.get_socket_address().to_string();
     let srv_name = &config.general.srv_name;
 
-    match connect(&address, srv_name, &config.keep_alive, client_id, logger) {
-        Ok(mut stream) => Ok(stream.get_mut().try_clone()?),
+    let identity = config
+        .identity_path
+        .as_deref()
+        .map(|path| (path, config.identity_pass.as_str()));
+
+    let connection = match config.connect_retry {
+        true => connect_with_identity(
+            &address,
+            srv_name,
+            &config.ca_path,
+            identity,
+            &config.keep_alive,
+            client_id,
+            logger,
+        ),
+        false => try_connect_with_identity(&address, srv_name, &config.ca_path, identity),
+    };
+    match connection {
+        Ok(stream) => MqttStream::new(stream),
         Err(e) => {
             logger.log_event(
                 &("Error al conectar con servidor: ".to_string() + &e.to_string()),
@@ -140,18 +268,25 @@ fn stablish_tls_connection(
 /// - stream: Stream de conexión con el servidor.
 /// - payload: Payload del paquete CONNECT.
 /// - config: Configuración del cliente.
+/// - authenticator: Autenticador del Authentication Method de la configuración, si hay.
 ///
 /// ### Retorno
-/// Resultado de la operación.
+/// Resultado de la operación con el CONNACK recibido.
 ///
 fn send_connect_packet(
     client_id: &String,
     logger: &Logger,
-    stream: &mut TcpStream,
+    stream: &mut MqttStream,
     payload: ConnectPayload,
     config: &ClientConfig,
-) -> Result<(), Error> {
-    match Connect::new(config.connect_properties.clone(), payload).send(stream) {
+    authenticator: &mut Option<Box<dyn ClientAuthenticator>>,
+) -> Result<Connack, Error> {
+    let mut properties = config.connect_properties.clone();
+    if let Some(authenticator) = authenticator.as_mut() {
+        properties.authentication_data = authenticator.initial_data();
+    }
+
+    match Connect::new(properties, payload).send(stream) {
         Ok(_) => (),
         Err(e) => {
             logger.log_event(
@@ -168,50 +303,48 @@ fn send_connect_packet(
         &config.general.log_in_term,
     );
 
-    match receive_connack_packet(stream) {
+    match receive_connack_packet(stream, authenticator, config, logger) {
         Ok(connack) => {
             MqttClientActions::Connection(
                 config.get_socket_address().to_string(),
                 connack.properties.connect_reason_code,
             )
             .log_action(client_id, logger, &config.general.log_in_term);
+            Ok(connack)
         }
         Err(e) => {
             logger.log_event(
                 &("Error al procesar connack: ".to_string() + &e.to_string()),
                 client_id,
             );
-            return Err(e);
+            Err(e)
         }
-    };
-
-    Ok(())
+    }
 }
 
-fn send_auth_packet(
-    stream: &mut TcpStream,
-    config: &ClientConfig,
-    logger: &Logger,
+/// ## send_auth_packet
+///
+/// Envía un paquete AUTH al servidor.
+///
+/// ### Parámetros
+/// - stream: Stream de conexión con el servidor.
+/// - reason_code: Continue authentication o Re-authenticate.
+/// - authentication_method: Authentication Method negociado en el CONNECT.
+/// - authentication_data: Datos del paso de autenticación.
+///
+pub fn send_auth_packet(
+    stream: &mut MqttStream,
+    reason_code: ReasonCode,
+    authentication_method: Option<String>,
+    authentication_data: Option<Vec<u8>>,
 ) -> Result<(), Error> {
-    let properties = auth_properties::AuthProperties {
-        reason_code: ReasonCode::Success.get_id(),
-        authentication_data: Some(serialize_username_password(
-            &config.general.id,
-            &config.general.password,
-        )),
+    Auth::new(AuthProperties {
+        reason_code: reason_code.get_id(),
+        authentication_method,
+        authentication_data,
         ..Default::default()
-    };
-
-    let auth_packet = Auth::new(properties);
-    auth_packet.send(stream)?;
-
-    MqttClientActions::SendAuthentication(config.general.id.clone()).log_action(
-        &config.general.id,
-        logger,
-        &config.general.log_in_term,
-    );
-
-    Ok(())
+    })
+    .send(stream)
 }
 
 impl MqttClient {
@@ -227,16 +360,29 @@ impl MqttClient {
     /// ### Retorno
     /// Resultado de la operación con el cliente MQTT.
     ///
+    /// ### Consideraciones
+    /// - Si la configuración tiene `authentication_method`, el cliente se autentica con
+    ///   su id y contraseña mediante ese método (por ejemplo `SCRAM-SHA-256`), sin enviar
+    ///   la contraseña. Si el servidor rechaza la autenticación se devuelve PermissionDenied
+    /// - Sin `authentication_method`, los flags `flag_username` y `flag_password` envían
+    ///   el id y la contraseña en el CONNECT
+    ///
     pub fn init(config: ClientConfig) -> Result<Self, Error> {
         let log_path = config.general.log_path.to_string();
         let client_id = config.general.id.to_string();
         let logger_handler = create_logger_handler(&log_path)?;
         let logger = logger_handler.get_logger();
 
+        let connect_flags = config.connect_properties.connect_flags;
         let payload = ConnectPayload {
             client_id: config.general.id.clone(),
             will_topic: config.will_topic.clone(),
             will_payload: config.will_payload.clone(),
+            will_delay_interval: config.will_delay_interval,
+            username: (flags_handler::get_connect_flag_username(connect_flags) == 1)
+                .then(|| client_id.clone()),
+            password: (flags_handler::get_connect_flag_password(connect_flags) == 1)
+                .then(|| config.general.password.as_bytes().to_vec()),
             ..Default::default()
         };
 
@@ -249,23 +395,140 @@ impl MqttClient {
             }
         };
 
-        send_connect_packet(&client_id, &logger, &mut stream, payload, &config)?;
+        let method = config.connect_properties.authentication_method.clone();
+        let mut authenticator = match &method {
+            Some(method) => match new_authenticator(method, &client_id, &config.general.password) {
+                Some(authenticator) => Some(authenticator),
+                None => {
+                    logger.close();
+                    logger_handler.close();
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        ReasonCode::BadAuthenticationMethod.to_string(),
+                    ));
+                }
+            },
+            None => None,
+        };
+
+        let connack = match send_connect_packet(
+            &client_id,
+            &logger,
+            &mut stream,
+            payload,
+            &config,
+            &mut authenticator,
+        ) {
+            Ok(connack) => connack,
+            Err(e) => {
+                let _ = stream.shutdown();
+                logger.close();
+                logger_handler.close();
+                return Err(e);
+            }
+        };
+
+        if let Err(e) = verify_connection(&connack, authenticator) {
+            logger.log_event(
+                &("Error de autenticacion: ".to_string() + &e.to_string()),
+                &client_id,
+            );
+            let _ = stream.shutdown();
+            logger.close();
+            logger_handler.close();
+            return Err(e);
+        }
+
+        let current_packet_id = Arc::new(Mutex::new(2));
+        let keep_alive = connack
+            .properties
+            .server_keep_alive
+            .unwrap_or(config.connect_properties.keep_alive);
+
+        // Cada parte usa a lo sumo la cantidad de Topic Alias que anunció la otra
+        let topic_aliases = TopicAliases::new(
+            config.connect_properties.topic_alias_maximum.unwrap_or(0),
+            connack.properties.topic_alias_maximum.unwrap_or(0),
+        );
 
-        send_auth_packet(&mut stream, &config, &logger)?;
+        // Las respuestas a las solicitudes se reciben en el Response Information del CONNACK
+        let requests = ClientRequests::new(connack.properties.response_information, &client_id);
 
-        let current_packet_id = 2;
+        // Cada parte respeta el Receive Maximum y el Maximum Packet Size que anunció la otra
+        let flow_control = FlowControl::new(
+            config.connect_properties.receive_maximum,
+            config.connect_properties.maximum_packet_size,
+            connack.properties.receive_maximum,
+            connack.properties.maximum_packet_size,
+        );
 
         let client = MqttClient {
             config,
             stream,
             current_packet_id,
+            inflight: ClientInflight::default(),
+            keep_alive,
+            ping_pending: Arc::new(AtomicBool::new(false)),
+            topic_aliases: Arc::new(Mutex::new(topic_aliases)),
+            requests,
+            flow_control,
+            authentication: ClientAuthentication::new(method),
         };
+        client.run_keep_alive();
 
         logger.close();
         logger_handler.close();
         Ok(client)
     }
 
+    /// ## run_keep_alive
+    ///
+    /// Inicia un thread que envía un PINGREQ cada `keep_alive` segundos.
+    ///
+    /// ### Consideraciones
+    /// - Si hay un listener corriendo y no se recibió el PINGRESP del PINGREQ anterior,
+    ///   se considera perdida la conexión y se la cierra
+    /// - El thread termina cuando no se puede escribir en la conexión (por ejemplo,
+    ///   luego de desconectarse)
+    ///
+    fn run_keep_alive(&self) {
+        if self.keep_alive == 0 {
+            return;
+        }
+
+        let mut client = self.clone();
+        let interval = Duration::from_secs(self.keep_alive as u64);
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            let action = if client.ping_pending.swap(true, Ordering::SeqCst)
+                && client.inflight.is_listener_running()
+            {
+                let _ = client.stream.shutdown();
+                MqttClientActions::PingrespNotReceived
+            } else if PingReq.send(&mut client.stream).is_ok() {
+                MqttClientActions::SendPinreq
+            } else {
+                return;
+            };
+
+            if let Ok(logger_handler) = create_logger_handler(&client.config.general.log_path) {
+                let logger = logger_handler.get_logger();
+                action.log_action(
+                    &client.config.general.id,
+                    &logger,
+                    &client.config.general.log_in_term,
+                );
+                logger.close();
+                logger_handler.close();
+            }
+
+            if let MqttClientActions::PingrespNotReceived = action {
+                return;
+            }
+        });
+    }
+
     /// ## run_listener
     ///
     /// Inicializa un listener para el cliente.
@@ -277,46 +540,451 @@ impl MqttClient {
         MqttClientListener::run(self, logger)
     }
 
+    /// ## reauthenticate
+    ///
+    /// Vuelve a autenticar al cliente con el Authentication Method negociado en el CONNECT,
+    /// sin cerrar la conexión.
+    ///
+    /// ### Retorno
+    /// Resultado de la operación (PermissionDenied si el servidor rechazó la autenticación).
+    ///
+    /// ### Consideraciones
+    /// - Los AUTH del servidor los procesa el listener, que debe estar corriendo
+    /// - Si la re-autenticación falla, se cierra la conexión
+    ///
+    pub fn reauthenticate(&mut self, logger: &Logger) -> Result<(), Error> {
+        let method = match self.authentication.method() {
+            Some(method) => method.to_string(),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "El cliente no negoció un Authentication Method",
+                ))
+            }
+        };
+        if !self.inflight.is_listener_running() {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "Se necesita un listener corriendo para re-autenticarse",
+            ));
+        }
+        let mut authenticator = match new_authenticator(
+            &method,
+            &self.config.general.id,
+            &self.config.general.password,
+        ) {
+            Some(authenticator) => authenticator,
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    ReasonCode::BadAuthenticationMethod.to_string(),
+                ))
+            }
+        };
+
+        let data = authenticator.initial_data();
+        let receiver = self.authentication.start(authenticator);
+        if let Err(e) = send_auth_packet(
+            &mut self.stream,
+            ReasonCode::ReAuthenticate,
+            Some(method),
+            data,
+        ) {
+            self.authentication.cancel(ReasonCode::UnspecifiedError);
+            return Err(e);
+        }
+        MqttClientActions::SendAuthentication(self.config.general.id.clone()).log_action(
+            &self.config.general.id,
+            logger,
+            &self.config.general.log_in_term,
+        );
+
+        match receiver.recv_timeout(ACKNOWLEDGE_TIMEOUT) {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(reason_code)) => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                reason_code.to_string(),
+            )),
+            Err(_) => {
+                self.authentication.cancel(ReasonCode::UnspecifiedError);
+                Err(Error::new(
+                    ErrorKind::TimedOut,
+                    "No se recibió la respuesta de la re-autenticación",
+                ))
+            }
+        }
+    }
+
     /// ## publish
     ///
-    /// Publica un mensaje en un tópico.
+    /// Publica un mensaje en un tópico, con el QoS de la configuración.
     ///
     /// ### Parámetros
     /// - message: Mensaje a publicar. (bytes)
     /// - topic: Tópico del mensaje.
     ///
     /// ### Retorno
-    /// Resultado de la operación.
+    /// Resultado de la operación con el reason code de la confirmación.
     ///
     pub fn publish(
         &mut self,
         message: Vec<u8>,
         topic: String,
         logger: &Logger,
-    ) -> Result<(), Error> {
-        self.current_packet_id += 1;
+    ) -> Result<ReasonCode, Error> {
+        let qos = self.config.pub_qos;
+        self.publish_with_qos(message, topic, qos, logger)
+    }
+
+    /// ## publish_with_qos
+    ///
+    /// Publica un mensaje en un tópico con un QoS determinado.
+    /// Con QoS 1 o 2 bloquea hasta recibir la confirmación del servidor.
+    ///
+    /// ### Parámetros
+    /// - message: Mensaje a publicar. (bytes)
+    /// - topic: Tópico del mensaje.
+    /// - qos: QoS de la publicación (0, 1 o 2).
+    ///
+    /// ### Retorno
+    /// Resultado de la operación con el reason code del PUBACK (QoS 1)
+    /// o del PUBCOMP (QoS 2). Con QoS 0 siempre es `Success`.
+    ///
+    /// ### Consideraciones
+    /// - Con QoS 2 el mensaje se entrega exactamente una vez: el listener
+    ///   responde el PUBREC del servidor con un PUBREL
+    /// - Si hay un listener corriendo, es él quien recibe la confirmación;
+    ///   si no, se leen los paquetes del servidor hasta recibirla y los mensajes
+    ///   recibidos mientras tanto se entregan al iniciar el listener
+    /// - Si el servidor acepta Topic Alias, el tópico se envía completo solo la primera vez
+    /// - Con QoS 1 o 2, si ya hay tantas publicaciones sin confirmar como el Receive Maximum
+    ///   del servidor, se espera a que se confirme alguna
+    /// - Una publicación mayor al Maximum Packet Size del servidor no se envía
+    ///
+    pub fn publish_with_qos(
+        &mut self,
+        message: Vec<u8>,
+        topic: String,
+        qos: u8,
+        logger: &Logger,
+    ) -> Result<ReasonCode, Error> {
         let properties = PublishProperties {
-            topic_name: topic.clone(),
-            packet_identifier: self.current_packet_id,
+            topic_name: topic,
             payload_format_indicator: Some(1),
             application_message: message,
             ..Default::default()
         };
+        self.publish_with_properties(properties, qos, logger)
+    }
+
+    /// ## publish_with_properties
+    ///
+    /// Publica un mensaje con propiedades propias (User Properties, Content Type,
+    /// Response Topic, Correlation Data, Message Expiry Interval) y un QoS determinado.
+    ///
+    /// ### Parámetros
+    /// - properties: Propiedades de la publicación, con el tópico y el mensaje.
+    /// - qos: QoS de la publicación (0, 1 o 2).
+    ///
+    /// ### Retorno
+    /// Resultado de la operación con el reason code de la confirmación (ver `publish_with_qos`).
+    ///
+    /// ### Consideraciones
+    /// - El packet identifier y el Topic Alias los asigna el cliente
+    /// - El Subscription Identifier no se envía: lo asigna el servidor a cada suscriptor
+    /// - Con QoS 1 o 2 se espera la confirmación como maximo `ACKNOWLEDGE_TIMEOUT`, la
+    ///   reciba el listener o, si no esta corriendo, la publicación misma
+    ///
+    pub fn publish_with_properties(
+        &mut self,
+        mut properties: PublishProperties,
+        qos: u8,
+        logger: &Logger,
+    ) -> Result<ReasonCode, Error> {
+        if qos > 2 {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "QoS de publicación inválido",
+            ));
+        }
 
-        Publish::new(
+        let packet_identifier = self.next_packet_id()?;
+        let topic = properties.topic_name.clone();
+        properties.packet_identifier = packet_identifier;
+        properties.topic_alias = None;
+        properties.subscription_identifier = None;
+
+        let mut publish = Publish::new(
             self.config.pub_dup_flag,
-            self.config.pub_qos,
+            qos,
             self.config.pub_retain,
             properties,
-        )
-        .send(&mut self.stream)?;
+        );
+        // Se verifica con el tópico completo: el Topic Alias solo puede achicar el paquete
+        if !self.flow_control.can_send_packet(publish.size_of()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                ReasonCode::PacketTooLarge.to_string(),
+            ));
+        }
+
+        let acknowledgement =
+            if qos > 0 {
+                match self.inflight.register_publish(
+                    packet_identifier,
+                    self.flow_control.send_maximum(),
+                    ACKNOWLEDGE_TIMEOUT,
+                ) {
+                    Some(acknowledgement) => Some(acknowledgement),
+                    None => return Err(Error::new(
+                        ErrorKind::TimedOut,
+                        "No se confirmaron publicaciones anteriores (Receive Maximum del servidor)",
+                    )),
+                }
+            } else {
+                None
+            };
+        // El Topic Alias se asigna y se envía bajo el mismo lock, para que un alias
+        // nunca llegue al servidor antes que la publicación que lo define
+        let sent = match self.topic_aliases.lock() {
+            Ok(mut topic_aliases) => {
+                topic_aliases.assign(&mut publish);
+                publish.send(&mut self.stream)
+            }
+            Err(_) => Err(Error::other("Cliente - Lock envenenado")),
+        };
+        if let Err(e) = sent {
+            self.inflight.cancel(packet_identifier);
+            return Err(e);
+        }
 
         MqttClientActions::SendPublish(topic).log_action(
             &self.config.general.id,
             logger,
             &self.config.general.log_in_term,
         );
-        Ok(())
+
+        let acknowledgement = match acknowledgement {
+            Some(acknowledgement) => acknowledgement,
+            None => return Ok(ReasonCode::Success),
+        };
+
+        let result = if self.inflight.is_listener_running() {
+            match acknowledgement.recv_timeout(ACKNOWLEDGE_TIMEOUT) {
+                Ok(reason_code) => Ok(ReasonCode::new(reason_code)),
+                Err(RecvTimeoutError::Timeout) => Err(Error::new(
+                    ErrorKind::TimedOut,
+                    "No se recibió la confirmación de la publicación",
+                )),
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(Error::other("Cliente - Confirmación cancelada"))
+                }
+            }
+        } else {
+            let result = self.read_acknowledgement(&acknowledgement, logger);
+            self.stream.set_read_timeout(None)?;
+            result
+        };
+
+        if result
+            .as_ref()
+            .is_err_and(|e| e.kind() == ErrorKind::TimedOut)
+        {
+            self.inflight.cancel(packet_identifier);
+            MqttClientActions::AcknowledgeNotReceived.log_action(
+                &self.config.general.id,
+                logger,
+                &self.config.general.log_in_term,
+            );
+        }
+        result
+    }
+
+    /// ## read_acknowledgement
+    ///
+    /// Lee los paquetes recibidos hasta obtener la confirmación de una publicación,
+    /// cuando no hay un listener que los lea.
+    ///
+    /// ### Parámetros
+    /// - acknowledgement: Canal por el que llega el reason code de la confirmación.
+    ///
+    /// ### Retorno
+    /// Resultado de la operación con el reason code de la confirmación, o
+    /// `ErrorKind::TimedOut` si no llega dentro de `ACKNOWLEDGE_TIMEOUT`.
+    ///
+    /// ### Consideraciones
+    /// - Las publicaciones recibidas mientras tanto se guardan para el listener
+    /// - Deja configurado el timeout de lectura del stream
+    ///
+    fn read_acknowledgement(
+        &mut self,
+        acknowledgement: &Receiver<u8>,
+        logger: &Logger,
+    ) -> Result<ReasonCode, Error> {
+        let deadline = Instant::now() + ACKNOWLEDGE_TIMEOUT;
+        loop {
+            if let Ok(reason_code) = acknowledgement.try_recv() {
+                return Ok(ReasonCode::new(reason_code));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(Error::new(
+                    ErrorKind::TimedOut,
+                    "No se recibió la confirmación de la publicación",
+                ));
+            }
+            self.stream.set_read_timeout(Some(remaining))?;
+
+            let mut stream = self.stream.clone();
+            let header = PacketFixedHeader::read_from(&mut stream)?;
+            if let Some(message) =
+                MqttClientListener::packet_handler(self, &mut stream, header, logger)?
+            {
+                self.inflight.store_undelivered_message(message);
+            }
+        }
+    }
+
+    /// ## request
+    ///
+    /// Envía una solicitud a un tópico y espera su respuesta (request/response).
+    ///
+    /// ### Parámetros
+    /// - topic: Tópico de la solicitud.
+    /// - payload: Mensaje de la solicitud. (bytes)
+    /// - timeout: Tiempo máximo de espera de la respuesta.
+    ///
+    /// ### Retorno
+    /// Resultado de la operación con la respuesta recibida.
+    ///
+    /// ### Consideraciones
+    /// - Necesita un listener corriendo, que es quien recibe la respuesta
+    /// - La primera solicitud suscribe al cliente a su tópico de respuestas
+    /// - La respuesta se asocia a la solicitud por su Correlation Data, y no se
+    ///   entrega al receptor del listener
+    ///
+    pub fn request(
+        &mut self,
+        topic: String,
+        payload: Vec<u8>,
+        timeout: Duration,
+        logger: &Logger,
+    ) -> Result<MqttClientMessage, Error> {
+        if !self.inflight.is_listener_running() {
+            return Err(Error::new(
+                ErrorKind::NotConnected,
+                "Se necesita un listener para recibir la respuesta",
+            ));
+        }
+
+        let response_topic = self.requests.response_topic().to_string();
+        if self.requests.needs_subscription() {
+            if let Err(e) = self.subscribe(vec![&response_topic], logger) {
+                self.requests.subscription_failed();
+                return Err(e);
+            }
+        }
+
+        let (correlation_data, response) = self.requests.register_request();
+        let properties = PublishProperties {
+            topic_name: topic.clone(),
+            payload_format_indicator: Some(1),
+            response_topic: Some(response_topic),
+            correlation_data: Some(correlation_data.clone()),
+            application_message: payload,
+            ..Default::default()
+        };
+        let qos = self.config.pub_qos;
+        if let Err(e) = self.publish_with_properties(properties, qos, logger) {
+            self.requests.cancel(&correlation_data);
+            return Err(e);
+        }
+        MqttClientActions::SendRequest(topic.clone()).log_action(
+            &self.config.general.id,
+            logger,
+            &self.config.general.log_in_term,
+        );
+
+        match response.recv_timeout(timeout) {
+            Ok(message) => {
+                MqttClientActions::ReceiveResponse(topic).log_action(
+                    &self.config.general.id,
+                    logger,
+                    &self.config.general.log_in_term,
+                );
+                Ok(message)
+            }
+            Err(_) => {
+                self.requests.cancel(&correlation_data);
+                MqttClientActions::ResponseNotReceived(topic).log_action(
+                    &self.config.general.id,
+                    logger,
+                    &self.config.general.log_in_term,
+                );
+                Err(Error::new(
+                    ErrorKind::TimedOut,
+                    "No se recibió la respuesta de la solicitud",
+                ))
+            }
+        }
+    }
+
+    /// ## respond
+    ///
+    /// Responde una solicitud recibida: publica el mensaje que devuelve el handler
+    /// en el Response Topic de la solicitud, con su Correlation Data.
+    ///
+    /// ### Parámetros
+    /// - request: Mensaje recibido.
+    /// - handler: Función que arma la respuesta a partir de la solicitud.
+    ///
+    /// ### Retorno
+    /// Resultado de la operación con el reason code de la confirmación de la respuesta,
+    /// o `None` si el mensaje no es una solicitud (no tiene Response Topic).
+    ///
+    pub fn respond<F>(
+        &mut self,
+        request: &MqttClientMessage,
+        handler: F,
+        logger: &Logger,
+    ) -> Result<Option<ReasonCode>, Error>
+    where
+        F: FnOnce(&MqttClientMessage) -> Vec<u8>,
+    {
+        let response_topic = match &request.response_topic {
+            Some(response_topic) => response_topic.clone(),
+            None => return Ok(None),
+        };
+
+        let properties = PublishProperties {
+            topic_name: response_topic.clone(),
+            payload_format_indicator: Some(1),
+            correlation_data: request.correlation_data.clone(),
+            application_message: handler(request),
+            ..Default::default()
+        };
+        let qos = self.config.pub_qos;
+        let reason_code = self.publish_with_properties(properties, qos, logger)?;
+        MqttClientActions::SendResponse(response_topic).log_action(
+            &self.config.general.id,
+            logger,
+            &self.config.general.log_in_term,
+        );
+        Ok(Some(reason_code))
+    }
+
+    /// ## next_packet_id
+    ///
+    /// Obtiene el siguiente ID de paquete (distinto de 0).
+    ///
+    fn next_packet_id(&self) -> Result<u16, Error> {
+        let mut current_packet_id = self
+            .current_packet_id
+            .lock()
+            .map_err(|_| Error::other("Cliente - Lock envenenado"))?;
+        *current_packet_id = current_packet_id.wrapping_add(1).max(1);
+        Ok(*current_packet_id)
     }
 
     /// ## subscribe
@@ -330,8 +998,45 @@ impl MqttClient {
     /// Resultado de la operación.
     ///
     pub fn subscribe(&mut self, topics: Vec<&str>, logger: &Logger) -> Result<(), Error> {
+        self.send_subscribe(topics, None, logger)
+    }
+
+    /// ## subscribe_with_identifier
+    ///
+    /// Se suscribe a un tópico con un Subscription Identifier.
+    ///
+    /// ### Parámetros
+    /// - topics: Lista de tópicos a los que se suscribe.
+    /// - subscription_identifier: Identificador (distinto de 0) que el servidor incluye
+    ///   en las publicaciones recibidas por estas suscripciones.
+    ///
+    /// ### Retorno
+    /// Resultado de la operación.
+    ///
+    pub fn subscribe_with_identifier(
+        &mut self,
+        topics: Vec<&str>,
+        subscription_identifier: u32,
+        logger: &Logger,
+    ) -> Result<(), Error> {
+        if subscription_identifier == 0 {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Subscription Identifier inválido",
+            ));
+        }
+        self.send_subscribe(topics, Some(subscription_identifier), logger)
+    }
+
+    fn send_subscribe(
+        &mut self,
+        topics: Vec<&str>,
+        subscription_identifier: Option<u32>,
+        logger: &Logger,
+    ) -> Result<(), Error> {
         let mut properties = SubscribeProperties {
             packet_identifier: 0,
+            subscription_identifier,
             ..Default::default()
         };
 
@@ -429,11 +1134,13 @@ impl MqttClient {
             disconnect_reason_code,
             session_expiry_interval: None,
             reason_string: None,
-            user_property: None,
+            user_properties: Vec::new(),
             server_reference: None,
         };
 
         Disconnect::new(properties).send(&mut self.stream)?;
+        // Luego de enviar el DISCONNECT se cierra la conexion de red
+        let _ = self.stream.shutdown();
 
         MqttClientActions::SendDisconnect(
             self.config.get_socket_address().to_string(),
@@ -474,8 +1181,15 @@ impl Clone for MqttClient {
     fn clone(&self) -> Self {
         MqttClient {
             config: self.config.clone(),
-            stream: self.stream.try_clone().unwrap(),
-            current_packet_id: self.current_packet_id,
+            stream: self.stream.clone(),
+            current_packet_id: Arc::clone(&self.current_packet_id),
+            inflight: self.inflight.clone(),
+            keep_alive: self.keep_alive,
+            ping_pending: Arc::clone(&self.ping_pending),
+            topic_aliases: Arc::clone(&self.topic_aliases),
+            requests: self.requests.clone(),
+            flow_control: self.flow_control,
+            authentication: self.authentication.clone(),
         }
     }
 }
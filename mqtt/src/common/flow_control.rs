@@ -0,0 +1,165 @@
+use crate::{
+    common::reason_codes::ReasonCode, mqtt_packets::headers::fixed_header::PacketFixedHeader,
+};
+
+/// Receive Maximum de una parte que no lo informa
+pub const DEFAULT_RECEIVE_MAXIMUM: u16 = u16::MAX;
+
+/// ## FlowControl
+///
+/// Limites de flujo de una conexion, en ambos sentidos
+///
+/// ### Atributos
+/// - `receive_maximum`: Receive Maximum anunciado a la otra parte (publicaciones QoS 1 y 2
+///   sin confirmar que se aceptan)
+/// - `maximum_packet_size`: Maximum Packet Size anunciado a la otra parte
+/// - `send_maximum`: Receive Maximum anunciado por la otra parte
+/// - `send_maximum_packet_size`: Maximum Packet Size anunciado por la otra parte
+///
+/// ### Consideraciones
+/// - Un Receive Maximum o Maximum Packet Size de 0 no es valido y se ignora
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlowControl {
+    receive_maximum: u16,
+    maximum_packet_size: Option<u32>,
+    send_maximum: u16,
+    send_maximum_packet_size: Option<u32>,
+}
+
+impl Default for FlowControl {
+    fn default() -> Self {
+        FlowControl::new(None, None, None, None)
+    }
+}
+
+impl FlowControl {
+    /// ### new
+    ///
+    /// Crea los limites de flujo de una conexion
+    ///
+    /// ### Parametros
+    /// - `receive_maximum`: Receive Maximum anunciado a la otra parte
+    /// - `maximum_packet_size`: Maximum Packet Size anunciado a la otra parte
+    /// - `send_maximum`: Receive Maximum anunciado por la otra parte
+    /// - `send_maximum_packet_size`: Maximum Packet Size anunciado por la otra parte
+    ///
+    pub fn new(
+        receive_maximum: Option<u16>,
+        maximum_packet_size: Option<u32>,
+        send_maximum: Option<u16>,
+        send_maximum_packet_size: Option<u32>,
+    ) -> Self {
+        FlowControl {
+            receive_maximum: receive_maximum
+                .filter(|maximum| *maximum > 0)
+                .unwrap_or(DEFAULT_RECEIVE_MAXIMUM),
+            maximum_packet_size: maximum_packet_size.filter(|maximum| *maximum > 0),
+            send_maximum: send_maximum
+                .filter(|maximum| *maximum > 0)
+                .unwrap_or(DEFAULT_RECEIVE_MAXIMUM),
+            send_maximum_packet_size: send_maximum_packet_size.filter(|maximum| *maximum > 0),
+        }
+    }
+
+    pub fn receive_maximum(&self) -> u16 {
+        self.receive_maximum
+    }
+
+    pub fn send_maximum(&self) -> u16 {
+        self.send_maximum
+    }
+
+    /// ### check_received_packet
+    ///
+    /// Verifica que un paquete recibido no supere el Maximum Packet Size anunciado
+    ///
+    /// ### Parametros
+    /// - `fixed_header`: encabezado fijo del paquete recibido
+    ///
+    /// ### Retorno
+    /// - `Result<(), ReasonCode>`: `PacketTooLarge` si el paquete supera el maximo
+    ///
+    pub fn check_received_packet(
+        &self,
+        fixed_header: &PacketFixedHeader,
+    ) -> Result<(), ReasonCode> {
+        let size = fixed_header.size_of() as u64 + fixed_header.remaining_length as u64;
+        match self.maximum_packet_size {
+            Some(maximum) if size > maximum as u64 => Err(ReasonCode::PacketTooLarge),
+            _ => Ok(()),
+        }
+    }
+
+    /// ### check_received_publish
+    ///
+    /// Verifica que una publicacion QoS 1 o 2 recibida no supere el Receive Maximum anunciado
+    ///
+    /// ### Parametros
+    /// - `unacknowledged`: publicaciones recibidas que todavia no se confirmaron, sin contar
+    ///   la nueva
+    ///
+    /// ### Retorno
+    /// - `Result<(), ReasonCode>`: `ReceiveMaximumExceeded` si la publicacion supera el maximo
+    ///
+    pub fn check_received_publish(&self, unacknowledged: usize) -> Result<(), ReasonCode> {
+        if unacknowledged >= self.receive_maximum as usize {
+            return Err(ReasonCode::ReceiveMaximumExceeded);
+        }
+        Ok(())
+    }
+
+    /// ### can_send_packet
+    ///
+    /// Indica si un paquete de `size` bytes respeta el Maximum Packet Size de la otra parte
+    ///
+    pub fn can_send_packet(&self, size: usize) -> bool {
+        match self.send_maximum_packet_size {
+            Some(maximum) => size as u64 <= maximum as u64,
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mqtt_packets::headers::fixed_header::PUBLISH_PACKET;
+
+    use super::*;
+
+    #[test]
+    fn test_maximum_packet_size() {
+        let flow_control = FlowControl::new(None, Some(100), None, Some(50));
+
+        // 1 byte de tipo + 1 byte de remaining length
+        let fixed_header = PacketFixedHeader::new(PUBLISH_PACKET, 98);
+        assert_eq!(flow_control.check_received_packet(&fixed_header), Ok(()));
+        let fixed_header = PacketFixedHeader::new(PUBLISH_PACKET, 99);
+        assert_eq!(
+            flow_control.check_received_packet(&fixed_header),
+            Err(ReasonCode::PacketTooLarge)
+        );
+
+        assert!(flow_control.can_send_packet(50));
+        assert!(!flow_control.can_send_packet(51));
+
+        // Sin Maximum Packet Size no hay limite
+        let flow_control = FlowControl::default();
+        let fixed_header = PacketFixedHeader::new(PUBLISH_PACKET, 1 << 20);
+        assert_eq!(flow_control.check_received_packet(&fixed_header), Ok(()));
+        assert!(flow_control.can_send_packet(usize::MAX));
+    }
+
+    #[test]
+    fn test_receive_maximum() {
+        let flow_control = FlowControl::new(Some(2), None, Some(0), None);
+        assert_eq!(flow_control.check_received_publish(1), Ok(()));
+        assert_eq!(
+            flow_control.check_received_publish(2),
+            Err(ReasonCode::ReceiveMaximumExceeded)
+        );
+
+        // Un Receive Maximum de 0 se ignora
+        assert_eq!(flow_control.send_maximum(), DEFAULT_RECEIVE_MAXIMUM);
+    }
+}
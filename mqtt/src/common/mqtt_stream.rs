@@ -0,0 +1,473 @@
+use std::{
+    io::{Error, ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use native_tls::TlsStream;
+use openssl::ssl::SslStream;
+
+/// Tiempo maximo que una lectura retiene el stream esperando el resto de un registro
+/// TLS o un frame WebSocket incompleto, antes de liberarlo para las escrituras
+const READ_LOCK_TIMEOUT: Duration = Duration::from_millis(10);
+
+/// ## Transport
+///
+/// Trait que representa el medio de transporte sobre el que viajan los paquetes MQTT
+///
+/// ### Metodos
+/// - `tcp_stream`: Devuelve el socket TCP subyacente
+/// - `has_buffered_data`: Indica si hay datos leidos del socket que todavia no se entregaron
+///
+pub trait Transport: Read + Write + Send {
+    /// ## tcp_stream
+    ///
+    /// Devuelve el socket TCP subyacente,
+    /// utilizado para configurar timeouts y cerrar la conexion
+    ///
+    fn tcp_stream(&self) -> &TcpStream;
+
+    /// ## has_buffered_data
+    ///
+    /// Indica si el transporte tiene datos ya leidos del socket que una lectura
+    /// puede devolver sin esperar al socket (por ejemplo, el resto de un registro TLS)
+    ///
+    fn has_buffered_data(&self) -> bool {
+        false
+    }
+}
+
+impl Transport for TcpStream {
+    fn tcp_stream(&self) -> &TcpStream {
+        self
+    }
+}
+
+impl Transport for TlsStream<TcpStream> {
+    fn tcp_stream(&self) -> &TcpStream {
+        self.get_ref()
+    }
+
+    fn has_buffered_data(&self) -> bool {
+        self.buffered_read_size().unwrap_or(0) > 0
+    }
+}
+
+impl Transport for SslStream<TcpStream> {
+    fn tcp_stream(&self) -> &TcpStream {
+        self.get_ref()
+    }
+
+    fn has_buffered_data(&self) -> bool {
+        self.ssl().pending() > 0
+    }
+}
+
+/// ## TrafficCounters
+///
+/// Contadores de los bytes recibidos y enviados por uno o mas streams.
+/// Sus clones comparten los mismos contadores.
+///
+#[derive(Clone, Default)]
+pub struct TrafficCounters {
+    received: Arc<AtomicU64>,
+    sent: Arc<AtomicU64>,
+}
+
+impl TrafficCounters {
+    /// ## bytes_received
+    ///
+    /// Total de bytes leidos por los streams que comparten los contadores.
+    ///
+    pub fn bytes_received(&self) -> u64 {
+        self.received.load(Ordering::Relaxed)
+    }
+
+    /// ## bytes_sent
+    ///
+    /// Total de bytes escritos por los streams que comparten los contadores.
+    ///
+    pub fn bytes_sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+}
+
+/// ## MqttStream
+///
+/// Stream compartido de una conexion MQTT.
+/// Encapsula el transporte (por ejemplo un `TlsStream`) para que
+/// pueda ser utilizado desde varios threads a la vez: el que lee los
+/// paquetes entrantes y los que envian paquetes a la conexion.
+///
+/// ### Consideraciones
+/// - Una lectura espera datos en el socket sin retener el stream, de modo que
+///   una lectura bloqueada no impide enviar paquetes. Solo lo retiene para leer
+///   los datos ya disponibles, como maximo `READ_LOCK_TIMEOUT` si llega un registro
+///   TLS o un frame WebSocket incompleto
+/// - Las lecturas desde distintos threads se serializan con `reader`: el timeout del
+///   socket solo cambia mientras se espera con ese lock tomado, por lo que una lectura
+///   nunca retiene el stream con el timeout de la espera de otra
+/// - Cada `write_all` se realiza de forma atomica, por lo que dos
+///   paquetes enviados desde threads distintos no se intercalan
+/// - Si se configura un timeout de lectura, una lectura que no recibe
+///   datos en ese tiempo falla con `ErrorKind::TimedOut`
+/// - Los bytes leidos y escritos se suman a sus `TrafficCounters`
+///
+#[derive(Clone)]
+pub struct MqttStream {
+    transport: Arc<Mutex<Box<dyn Transport>>>,
+    socket: Arc<TcpStream>,
+    reader: Arc<Mutex<()>>,
+    read_timeout: Arc<Mutex<Option<Duration>>>,
+    traffic: TrafficCounters,
+}
+
+impl MqttStream {
+    /// ## new
+    ///
+    /// Crea un stream compartido a partir de un transporte ya establecido
+    /// (por ejemplo, luego del handshake TLS)
+    ///
+    /// ### Parametros
+    /// - `transport`: transporte de la conexion
+    ///
+    /// ### Retorno
+    /// - `Result<MqttStream, Error>`: stream compartido
+    ///
+    pub fn new<T: Transport + 'static>(transport: T) -> Result<Self, Error> {
+        let socket = transport.tcp_stream().try_clone()?;
+        socket.set_read_timeout(Some(READ_LOCK_TIMEOUT))?;
+
+        Ok(MqttStream {
+            transport: Arc::new(Mutex::new(Box::new(transport))),
+            socket: Arc::new(socket),
+            reader: Arc::new(Mutex::new(())),
+            read_timeout: Arc::new(Mutex::new(None)),
+            traffic: TrafficCounters::default(),
+        })
+    }
+
+    /// ## with_traffic_counters
+    ///
+    /// Suma el trafico del stream a contadores compartidos con otros streams
+    ///
+    /// ### Parametros
+    /// - `traffic`: contadores de bytes recibidos y enviados
+    ///
+    pub fn with_traffic_counters(mut self, traffic: TrafficCounters) -> Self {
+        self.traffic = traffic;
+        self
+    }
+
+    /// ## set_read_timeout
+    ///
+    /// Configura el tiempo maximo que una lectura espera datos.
+    /// Es compartido por todas las referencias al stream.
+    ///
+    /// ### Parametros
+    /// - `timeout`: tiempo maximo de espera (`None` espera indefinidamente)
+    ///
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        let mut read_timeout = self
+            .read_timeout
+            .lock()
+            .map_err(|_| Error::other("Stream - Lock envenenado"))?;
+        *read_timeout = timeout;
+        Ok(())
+    }
+
+    /// ## set_write_timeout
+    ///
+    /// Configura el tiempo maximo que una escritura espera a que el otro extremo
+    /// reciba los datos. Es compartido por todas las referencias al stream.
+    ///
+    /// ### Parametros
+    /// - `timeout`: tiempo maximo de espera (`None` espera indefinidamente)
+    ///
+    pub fn set_write_timeout(&self, timeout: Option<Duration>) -> Result<(), Error> {
+        self.socket.set_write_timeout(timeout)
+    }
+
+    fn read_timeout(&self) -> Result<Option<Duration>, Error> {
+        self.read_timeout
+            .lock()
+            .map(|timeout| *timeout)
+            .map_err(|_| Error::other("Stream - Lock envenenado"))
+    }
+
+    /// ## try_clone
+    ///
+    /// Devuelve otra referencia al mismo stream
+    ///
+    pub fn try_clone(&self) -> Result<Self, Error> {
+        Ok(self.clone())
+    }
+
+    /// ## is_same_connection
+    ///
+    /// Indica si ambos streams referencian a la misma conexion
+    ///
+    pub fn is_same_connection(&self, other: &MqttStream) -> bool {
+        Arc::ptr_eq(&self.transport, &other.transport)
+    }
+
+    /// ## shutdown
+    ///
+    /// Cierra la conexion en ambos sentidos
+    ///
+    pub fn shutdown(&self) -> Result<(), Error> {
+        self.socket.shutdown(Shutdown::Both)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Box<dyn Transport>>, Error> {
+        self.transport
+            .lock()
+            .map_err(|_| Error::other("Stream - Lock envenenado"))
+    }
+
+    /// ## wait_for_data
+    ///
+    /// Espera, sin retener el stream, a que lleguen datos al socket
+    ///
+    /// ### Parametros
+    /// - `deadline`: momento en el que vence el timeout de lectura (`None`: sin limite)
+    ///
+    /// ### Consideraciones
+    /// - Tambien retorna si se cierra la conexion, para que la lectura lo informe
+    /// - Cambia el timeout del socket, compartido por todas las referencias al stream:
+    ///   solo se llama con el lock `reader` tomado
+    ///
+    fn wait_for_data(&self, deadline: Option<Instant>) -> Result<(), Error> {
+        let timed_out = || Error::new(ErrorKind::TimedOut, "Stream - No se recibieron datos");
+        let timeout = match deadline {
+            Some(deadline) => match deadline.checked_duration_since(Instant::now()) {
+                Some(timeout) if !timeout.is_zero() => Some(timeout),
+                _ => return Err(timed_out()),
+            },
+            None => None,
+        };
+
+        self.socket.set_read_timeout(timeout)?;
+        let result = self.socket.peek(&mut [0u8; 1]);
+        self.socket.set_read_timeout(Some(READ_LOCK_TIMEOUT))?;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                Err(timed_out())
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Read for MqttStream {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        let _reader = self
+            .reader
+            .lock()
+            .map_err(|_| Error::other("Stream - Lock envenenado"))?;
+        let deadline = self.read_timeout()?.map(|timeout| Instant::now() + timeout);
+        loop {
+            let has_buffered_data = self.lock()?.has_buffered_data();
+            if !has_buffered_data {
+                self.wait_for_data(deadline)?;
+            }
+
+            let result = self.lock()?.read(buf);
+            match result {
+                // Llego un registro TLS o un frame WebSocket incompleto, o solo datos
+                // de control: se vuelve a esperar sin retener el stream
+                Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+                Ok(read) => {
+                    self.traffic
+                        .received
+                        .fetch_add(read as u64, Ordering::Relaxed);
+                    return Ok(read);
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Write for MqttStream {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        let written = self.lock()?.write(buf)?;
+        self.traffic
+            .sent
+            .fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> Result<(), Error> {
+        self.lock()?.write_all(buf)?;
+        self.traffic
+            .sent
+            .fetch_add(buf.len() as u64, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.lock()?.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_read_does_not_block_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = TcpStream::connect(address).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        let mut client = MqttStream::new(client).unwrap();
+        let mut server = MqttStream::new(server).unwrap();
+
+        // Un thread queda leyendo del stream del cliente sin recibir datos
+        let mut reader = client.clone();
+        let handle = thread::spawn(move || {
+            let mut buf = [0u8; 4];
+            reader.read_exact(&mut buf).unwrap();
+            buf
+        });
+
+        thread::sleep(Duration::from_millis(50));
+
+        // Mientras tanto, el cliente puede seguir escribiendo
+        client.write_all(&[1, 2, 3, 4]).unwrap();
+        let mut buf = [0u8; 4];
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2, 3, 4]);
+
+        server.write_all(&[5, 6, 7, 8]).unwrap();
+        assert_eq!(handle.join().unwrap(), [5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn test_concurrent_reads_do_not_block_writes() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = TcpStream::connect(address).unwrap();
+        let (server, _) = listener.accept().unwrap();
+
+        let mut client = MqttStream::new(client).unwrap();
+        let mut server = MqttStream::new(server).unwrap();
+
+        // Dos threads quedan leyendo del mismo stream sin recibir datos
+        let readers = (0..2)
+            .map(|_| {
+                let mut reader = client.clone();
+                thread::spawn(move || {
+                    let mut buf = [0u8; 4];
+                    reader.read_exact(&mut buf).unwrap();
+                    buf
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..10 {
+            client.write_all(&[1, 2, 3, 4]).unwrap();
+            let mut buf = [0u8; 4];
+            server.read_exact(&mut buf).unwrap();
+            assert_eq!(buf, [1, 2, 3, 4]);
+        }
+
+        // Entre ambas lecturas se reciben todos los datos
+        server.write_all(&[5, 5, 5, 5, 6, 6, 6, 6]).unwrap();
+        let mut received = readers
+            .into_iter()
+            .flat_map(|reader| reader.join().unwrap())
+            .collect::<Vec<u8>>();
+        received.sort();
+        assert_eq!(received, vec![5, 5, 5, 5, 6, 6, 6, 6]);
+    }
+
+    #[test]
+    fn test_shutdown_ends_blocked_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = MqttStream::new(TcpStream::connect(address).unwrap()).unwrap();
+        let (_server, _) = listener.accept().unwrap();
+
+        // Una lectura sin timeout espera datos hasta que se cierra la conexion
+        let mut reader = client.clone();
+        let handle = thread::spawn(move || reader.read(&mut [0u8; 4]));
+
+        thread::sleep(Duration::from_millis(50));
+        assert!(!handle.is_finished());
+
+        client.shutdown().unwrap();
+        assert_eq!(handle.join().unwrap().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_same_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = MqttStream::new(TcpStream::connect(address).unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let server = MqttStream::new(server).unwrap();
+
+        assert!(client.is_same_connection(&client.try_clone().unwrap()));
+        assert!(!client.is_same_connection(&server));
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let mut client = MqttStream::new(TcpStream::connect(address).unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let mut server = MqttStream::new(server).unwrap();
+
+        server
+            .try_clone()
+            .unwrap()
+            .set_read_timeout(Some(Duration::from_millis(50)))
+            .unwrap();
+
+        let mut buf = [0u8; 1];
+        let error = server.read_exact(&mut buf).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+
+        // Los datos recibidos antes del timeout se leen normalmente
+        client.write_all(&[9]).unwrap();
+        server.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [9]);
+    }
+
+    #[test]
+    fn test_traffic_counters_are_shared() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let mut client = MqttStream::new(TcpStream::connect(address).unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let traffic = TrafficCounters::default();
+        let mut server = MqttStream::new(server)
+            .unwrap()
+            .with_traffic_counters(traffic.clone());
+
+        client.write_all(&[1, 2, 3]).unwrap();
+        let mut buf = [0u8; 3];
+        server.read_exact(&mut buf).unwrap();
+        server.clone().write_all(&[4, 5]).unwrap();
+
+        assert_eq!(traffic.bytes_received(), 3);
+        assert_eq!(traffic.bytes_sent(), 2);
+    }
+}
@@ -87,6 +87,9 @@ use std::fmt::Display;
 /// #### 145 - 0x91 - Packet Identifier in use
 /// The Packet Identifier is already in use. This might indicate a mismatch in the Session State between the Client and Server.
 ///
+/// #### 146 - 0x92 - Packet Identifier not found
+/// The Packet Identifier is not known. This is not an error during recovery, but at other times indicates a mismatch between the Session State on the Client and Server.
+///
 /// #### 147 - 0x93 - Receive Maximum exceeded
 /// The Client or Server has received more than Receive Maximum publication for which it has not sent PUBACK.
 ///
@@ -135,6 +138,7 @@ use std::fmt::Display;
 /// #### 162 - 0xA2 - Wildcard subscriptions not supported
 /// The Server does not support Wildcard Subscriptions; the subscription is not accepted.
 ///
+#[derive(Clone, Debug, PartialEq)]
 pub enum ReasonCode {
     Success,
     NormalDisconnection,
@@ -164,6 +168,7 @@ pub enum ReasonCode {
     TopicFilterInvalid,
     TopicNameInvalid,
     PacketIdentifierInUse,
+    PacketIdentifierNotFound,
     ReceiveMaximumExceeded,
     TopicAliasInvalid,
     PacketTooLarge,
@@ -220,6 +225,7 @@ impl ReasonCode {
             ReasonCode::TopicFilterInvalid => 143, // SUBACK, UNSUBACK, DISCONNECT
             ReasonCode::TopicNameInvalid => 144, // CONNACK, PUBACK, DISCONNECT
             ReasonCode::PacketIdentifierInUse => 145, // PUBACK, SUBACK, UNSUBACK
+            ReasonCode::PacketIdentifierNotFound => 146, // PUBREL, PUBCOMP
             ReasonCode::ReceiveMaximumExceeded => 147, // DISCONNECT
             ReasonCode::TopicAliasInvalid => 148, // DISCONNECT
             ReasonCode::PacketTooLarge => 149, // CONNACK, DISCONNECT
@@ -276,6 +282,7 @@ impl ReasonCode {
             143 => ReasonCode::TopicFilterInvalid,
             144 => ReasonCode::TopicNameInvalid,
             145 => ReasonCode::PacketIdentifierInUse,
+            146 => ReasonCode::PacketIdentifierNotFound,
             147 => ReasonCode::ReceiveMaximumExceeded,
             148 => ReasonCode::TopicAliasInvalid,
             149 => ReasonCode::PacketTooLarge,
@@ -424,6 +431,9 @@ impl Display for ReasonCode {
             ReasonCode::PacketIdentifierInUse => {
                 write!(f, "{} - Packet identifier in use", self.get_id())
             }
+            ReasonCode::PacketIdentifierNotFound => {
+                write!(f, "{} - Packet identifier not found", self.get_id())
+            }
             ReasonCode::ReceiveMaximumExceeded => {
                 write!(f, "{} - Receive maximum exceeded", self.get_id())
             }
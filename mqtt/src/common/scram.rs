@@ -0,0 +1,422 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{Hmac, Mac};
+use rand::{thread_rng, RngCore};
+use sha2::{Digest, Sha256};
+
+use crate::common::reason_codes::ReasonCode;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Nombre del metodo en la propiedad Authentication Method
+pub const SCRAM_SHA_256: &str = "SCRAM-SHA-256";
+
+/// Iteraciones de PBKDF2 con las que se derivan las credenciales nuevas (minimo de RFC 7677)
+pub const DEFAULT_ITERATIONS: u32 = 4096;
+
+/// Channel binding "n,," codificado en base64, unico soportado
+const CHANNEL_BINDING: &str = "biws";
+const GS2_HEADER: &str = "n,,";
+const NONCE_LEN: usize = 18;
+const SALT_LEN: usize = 16;
+
+/// ## ScramCredentials
+///
+/// Credenciales SCRAM-SHA-256 de un usuario. No contienen la contraseña: a partir de
+/// ellas se puede verificar una prueba del cliente, pero no hacerse pasar por él
+///
+/// ### Atributos
+/// - `salt`: salt con el que se derivó la contraseña
+/// - `iterations`: iteraciones de PBKDF2
+/// - `stored_key`: H(ClientKey)
+/// - `server_key`: HMAC(SaltedPassword, "Server Key")
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScramCredentials {
+    pub salt: Vec<u8>,
+    pub iterations: u32,
+    pub stored_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+}
+
+impl ScramCredentials {
+    /// ### new
+    ///
+    /// Deriva las credenciales de una contraseña
+    ///
+    /// ### Parametros
+    /// - `password`: contraseña del usuario
+    /// - `salt`: salt del usuario
+    /// - `iterations`: iteraciones de PBKDF2
+    ///
+    pub fn new(password: &str, salt: Vec<u8>, iterations: u32) -> Self {
+        let salted_password = salted_password(password, &salt, iterations);
+        let client_key = hmac(&salted_password, b"Client Key");
+        ScramCredentials {
+            stored_key: Sha256::digest(client_key).to_vec(),
+            server_key: hmac(&salted_password, b"Server Key"),
+            salt,
+            iterations,
+        }
+    }
+
+    /// ### generate
+    ///
+    /// Deriva las credenciales de una contraseña con un salt aleatorio
+    ///
+    pub fn generate(password: &str) -> Self {
+        ScramCredentials::new(password, random_bytes(SALT_LEN), DEFAULT_ITERATIONS)
+    }
+
+    /// ### verify_password
+    ///
+    /// Verifica una contraseña recibida en texto plano (por ejemplo, en el campo
+    /// Password del CONNECT) derivandola con el salt y las iteraciones del usuario
+    ///
+    pub fn verify_password(&self, password: &str) -> bool {
+        ScramCredentials::new(password, self.salt.clone(), self.iterations).stored_key
+            == self.stored_key
+    }
+}
+
+/// ## ScramClient
+///
+/// Intercambio SCRAM-SHA-256 (RFC 5802 y RFC 7677) del lado del cliente
+///
+/// ### Consideraciones
+/// - No se aplica SASLprep a la contraseña, que se usa tal cual en UTF-8
+/// - No se soporta channel binding
+///
+pub struct ScramClient {
+    password: String,
+    client_nonce: String,
+    client_first_bare: String,
+    server_signature: Option<Vec<u8>>,
+}
+
+impl ScramClient {
+    pub fn new(username: &str, password: &str) -> Self {
+        ScramClient::with_nonce(username, password, STANDARD.encode(random_bytes(NONCE_LEN)))
+    }
+
+    fn with_nonce(username: &str, password: &str, client_nonce: String) -> Self {
+        ScramClient {
+            password: password.to_string(),
+            client_first_bare: format!("n={},r={}", escape_username(username), client_nonce),
+            client_nonce,
+            server_signature: None,
+        }
+    }
+
+    /// ### client_first
+    ///
+    /// Mensaje inicial del cliente (`n,,n=usuario,r=nonce`)
+    ///
+    pub fn client_first(&self) -> Vec<u8> {
+        format!("{}{}", GS2_HEADER, self.client_first_bare).into_bytes()
+    }
+
+    /// ### client_final
+    ///
+    /// Responde el desafio del servidor con la prueba de que el cliente conoce la contraseña
+    ///
+    /// ### Parametros
+    /// - `server_first`: mensaje del servidor (`r=nonce,s=salt,i=iteraciones`)
+    ///
+    /// ### Retorno
+    /// - `Result<Vec<u8>, ReasonCode>`: mensaje final del cliente (`c=biws,r=nonce,p=prueba`),
+    ///   o `MalformedPacket` si el desafio no es valido
+    ///
+    pub fn client_final(&mut self, server_first: &[u8]) -> Result<Vec<u8>, ReasonCode> {
+        let server_first = std::str::from_utf8(server_first).map_err(|_| malformed())?;
+        let nonce = attribute(server_first, 'r')?;
+        let salt = STANDARD
+            .decode(attribute(server_first, 's')?)
+            .map_err(|_| malformed())?;
+        let iterations = attribute(server_first, 'i')?
+            .parse::<u32>()
+            .map_err(|_| malformed())?;
+        // El nonce del servidor debe extender el del cliente
+        if !nonce.starts_with(&self.client_nonce) || nonce.len() == self.client_nonce.len() {
+            return Err(malformed());
+        }
+
+        let without_proof = format!("c={},r={}", CHANNEL_BINDING, nonce);
+        let auth_message = format!(
+            "{},{},{}",
+            self.client_first_bare, server_first, without_proof
+        );
+        let salted_password = salted_password(&self.password, &salt, iterations);
+        let client_key = hmac(&salted_password, b"Client Key");
+        let stored_key = Sha256::digest(&client_key);
+        let client_signature = hmac(&stored_key, auth_message.as_bytes());
+        let server_key = hmac(&salted_password, b"Server Key");
+        self.server_signature = Some(hmac(&server_key, auth_message.as_bytes()));
+
+        let proof = xor(&client_key, &client_signature);
+        Ok(format!("{},p={}", without_proof, STANDARD.encode(proof)).into_bytes())
+    }
+
+    /// ### verify_server_final
+    ///
+    /// Verifica la firma del servidor, que prueba que conoce las credenciales del usuario
+    ///
+    /// ### Parametros
+    /// - `server_final`: mensaje final del servidor (`v=firma`)
+    ///
+    /// ### Retorno
+    /// - `Result<(), ReasonCode>`: `NotAuthorized` si la firma no es la esperada
+    ///
+    pub fn verify_server_final(&self, server_final: &[u8]) -> Result<(), ReasonCode> {
+        let server_final = std::str::from_utf8(server_final).map_err(|_| malformed())?;
+        let signature = STANDARD
+            .decode(attribute(server_final, 'v')?)
+            .map_err(|_| malformed())?;
+        match &self.server_signature {
+            Some(expected) if *expected == signature => Ok(()),
+            _ => Err(ReasonCode::NotAuthorized),
+        }
+    }
+}
+
+/// ## ScramServer
+///
+/// Intercambio SCRAM-SHA-256 del lado del servidor
+///
+/// ### Consideraciones
+/// - Un usuario desconocido recibe un desafio con un salt aleatorio, y falla recien al
+///   verificar la prueba, para no revelar que usuarios existen
+///
+pub struct ScramServer {
+    username: String,
+    credentials: Option<ScramCredentials>,
+    auth_message: String,
+    nonce: String,
+}
+
+impl ScramServer {
+    /// ### server_first
+    ///
+    /// Procesa el mensaje inicial del cliente y arma el desafio
+    ///
+    /// ### Parametros
+    /// - `client_first`: mensaje inicial del cliente
+    /// - `lookup`: obtiene las credenciales de un usuario
+    ///
+    /// ### Retorno
+    /// - `Result<(ScramServer, Vec<u8>), ReasonCode>`: estado del intercambio y desafio a enviar
+    ///
+    pub fn server_first<F>(client_first: &[u8], lookup: F) -> Result<(Self, Vec<u8>), ReasonCode>
+    where
+        F: Fn(&str) -> Option<ScramCredentials>,
+    {
+        ScramServer::with_nonce(
+            client_first,
+            lookup,
+            STANDARD.encode(random_bytes(NONCE_LEN)),
+        )
+    }
+
+    fn with_nonce<F>(
+        client_first: &[u8],
+        lookup: F,
+        server_nonce: String,
+    ) -> Result<(Self, Vec<u8>), ReasonCode>
+    where
+        F: Fn(&str) -> Option<ScramCredentials>,
+    {
+        let client_first = std::str::from_utf8(client_first).map_err(|_| malformed())?;
+        let client_first_bare = client_first
+            .strip_prefix(GS2_HEADER)
+            .ok_or(ReasonCode::BadAuthenticationMethod)?;
+        let username = unescape_username(attribute(client_first_bare, 'n')?)?;
+        let client_nonce = attribute(client_first_bare, 'r')?;
+
+        let credentials = lookup(&username);
+        let (salt, iterations) = match &credentials {
+            Some(credentials) => (credentials.salt.clone(), credentials.iterations),
+            None => (random_bytes(SALT_LEN), DEFAULT_ITERATIONS),
+        };
+        let nonce = format!("{}{}", client_nonce, server_nonce);
+        let server_first = format!("r={},s={},i={}", nonce, STANDARD.encode(salt), iterations);
+
+        let server = ScramServer {
+            username,
+            credentials,
+            auth_message: format!("{},{}", client_first_bare, server_first),
+            nonce,
+        };
+        Ok((server, server_first.into_bytes()))
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// ### server_final
+    ///
+    /// Verifica la prueba del cliente
+    ///
+    /// ### Parametros
+    /// - `client_final`: mensaje final del cliente
+    ///
+    /// ### Retorno
+    /// - `Result<Vec<u8>, ReasonCode>`: mensaje final del servidor con su firma, o
+    ///   `BadUserNameOrPassword` si la prueba no es valida
+    ///
+    pub fn server_final(&self, client_final: &[u8]) -> Result<Vec<u8>, ReasonCode> {
+        let client_final = std::str::from_utf8(client_final).map_err(|_| malformed())?;
+        let (without_proof, proof) = client_final.rsplit_once(",p=").ok_or_else(malformed)?;
+        if attribute(without_proof, 'c')? != CHANNEL_BINDING
+            || attribute(without_proof, 'r')? != self.nonce
+        {
+            return Err(ReasonCode::BadUserNameOrPassword);
+        }
+        let proof = STANDARD.decode(proof).map_err(|_| malformed())?;
+
+        let credentials = self
+            .credentials
+            .as_ref()
+            .ok_or(ReasonCode::BadUserNameOrPassword)?;
+        let auth_message = format!("{},{}", self.auth_message, without_proof);
+        let client_signature = hmac(&credentials.stored_key, auth_message.as_bytes());
+        if proof.len() != client_signature.len() {
+            return Err(ReasonCode::BadUserNameOrPassword);
+        }
+        let client_key = xor(&proof, &client_signature);
+        if Sha256::digest(client_key).as_slice() != credentials.stored_key.as_slice() {
+            return Err(ReasonCode::BadUserNameOrPassword);
+        }
+
+        let server_signature = hmac(&credentials.server_key, auth_message.as_bytes());
+        Ok(format!("v={}", STANDARD.encode(server_signature)).into_bytes())
+    }
+}
+
+fn malformed() -> ReasonCode {
+    ReasonCode::MalformedPacket
+}
+
+/// Obtiene el valor de un atributo (`clave=valor`) de un mensaje SCRAM
+fn attribute(message: &str, key: char) -> Result<&str, ReasonCode> {
+    message
+        .split(',')
+        .find_map(|field| {
+            let mut chars = field.chars();
+            match (chars.next(), chars.next()) {
+                (Some(k), Some('=')) if k == key => Some(&field[2..]),
+                _ => None,
+            }
+        })
+        .ok_or_else(malformed)
+}
+
+fn escape_username(username: &str) -> String {
+    username.replace('=', "=3D").replace(',', "=2C")
+}
+
+fn unescape_username(username: &str) -> Result<String, ReasonCode> {
+    let unescaped = username.replace("=2C", ",").replace("=3D", "=");
+    if unescaped.matches('=').count() != username.matches("=3D").count() {
+        return Err(malformed());
+    }
+    Ok(unescaped)
+}
+
+fn salted_password(password: &str, salt: &[u8], iterations: u32) -> Vec<u8> {
+    let mut salted_password = [0; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(password.as_bytes(), salt, iterations, &mut salted_password);
+    salted_password.to_vec()
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC acepta claves de cualquier largo");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn xor(a: &[u8], b: &[u8]) -> Vec<u8> {
+    a.iter().zip(b).map(|(a, b)| a ^ b).collect()
+}
+
+fn random_bytes(len: usize) -> Vec<u8> {
+    let mut bytes = vec![0; len];
+    thread_rng().fill_bytes(&mut bytes);
+    bytes
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Ejemplo de la seccion 3 de RFC 7677
+    const CLIENT_NONCE: &str = "rOprNGfwEbeRWgbNEkqO";
+    const SERVER_NONCE: &str = "%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0";
+    const SALT: &str = "W22ZaJ0SNY7soEsUEjb6gQ==";
+    const CLIENT_FINAL: &str = "c=biws,r=rOprNGfwEbeRWgbNEkqO%hvYDpWUa2RaTCAfuxFIlj)hNlF$k0,p=dHzbZapWIk4jUhN+Ute9ytag9zjfMHgsqmmiz7AndVQ=";
+    const SERVER_FINAL: &str = "v=6rriTRBi23WpRR/wtup+mMhUZUn/dB5nLTJRsjl95G4=";
+
+    fn credentials(password: &str) -> ScramCredentials {
+        ScramCredentials::new(password, STANDARD.decode(SALT).unwrap(), 4096)
+    }
+
+    #[test]
+    fn test_rfc_7677_exchange() {
+        let mut client = ScramClient::with_nonce("user", "pencil", CLIENT_NONCE.to_string());
+        assert_eq!(
+            client.client_first(),
+            b"n,,n=user,r=rOprNGfwEbeRWgbNEkqO".to_vec()
+        );
+
+        let lookup = |user: &str| (user == "user").then(|| credentials("pencil"));
+        let (server, server_first) =
+            ScramServer::with_nonce(&client.client_first(), lookup, SERVER_NONCE.to_string())
+                .unwrap();
+        assert_eq!(server.username(), "user");
+        assert_eq!(
+            String::from_utf8(server_first.clone()).unwrap(),
+            format!("r={}{},s={},i=4096", CLIENT_NONCE, SERVER_NONCE, SALT)
+        );
+
+        let client_final = client.client_final(&server_first).unwrap();
+        assert_eq!(client_final, CLIENT_FINAL.as_bytes().to_vec());
+
+        let server_final = server.server_final(&client_final).unwrap();
+        assert_eq!(server_final, SERVER_FINAL.as_bytes().to_vec());
+        assert_eq!(client.verify_server_final(&server_final), Ok(()));
+    }
+
+    #[test]
+    fn test_wrong_password_and_unknown_user() {
+        let lookup = |user: &str| (user == "monitoring").then(|| credentials("1234"));
+        assert!(credentials("1234").verify_password("1234"));
+        assert!(!credentials("1234").verify_password("4321"));
+
+        for (user, password) in [("monitoring", "4321"), ("intruso", "1234")] {
+            let mut client = ScramClient::new(user, password);
+            let (server, server_first) =
+                ScramServer::server_first(&client.client_first(), lookup).unwrap();
+            let client_final = client.client_final(&server_first).unwrap();
+            assert_eq!(
+                server.server_final(&client_final),
+                Err(ReasonCode::BadUserNameOrPassword)
+            );
+        }
+
+        // El cliente rechaza una firma que no corresponde a su intercambio
+        let mut client = ScramClient::new("monitoring", "1234");
+        let (_, server_first) = ScramServer::server_first(&client.client_first(), lookup).unwrap();
+        client.client_final(&server_first).unwrap();
+        assert_eq!(
+            client.verify_server_final(SERVER_FINAL.as_bytes()),
+            Err(ReasonCode::NotAuthorized)
+        );
+    }
+
+    #[test]
+    fn test_username_escaping() {
+        let client = ScramClient::new("drone,1=a", "1234");
+        let lookup = |user: &str| (user == "drone,1=a").then(|| credentials("1234"));
+        let (server, _) = ScramServer::server_first(&client.client_first(), lookup).unwrap();
+        assert_eq!(server.username(), "drone,1=a");
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use crate::{common::reason_codes::ReasonCode, mqtt_packets::packets::publish::Publish};
+
+/// Topic Alias Maximum que anuncia el servidor si no se configura otro
+pub const DEFAULT_TOPIC_ALIAS_MAXIMUM: u16 = 10;
+
+/// ## TopicAliases
+///
+/// Tablas de Topic Alias de una conexion, en ambos sentidos
+///
+/// ### Atributos
+/// - `receive_maximum`: Topic Alias Maximum anunciado a la otra parte (aliases que se aceptan)
+/// - `send_maximum`: Topic Alias Maximum anunciado por la otra parte (aliases que se pueden usar)
+/// - `received`: topico asociado a cada alias recibido
+/// - `sent`: alias asignado a cada topico enviado
+///
+/// ### Consideraciones
+/// - Los aliases solo son validos durante la conexion: una nueva conexion empieza con
+///   tablas vacias
+///
+#[derive(Debug, Default)]
+pub struct TopicAliases {
+    receive_maximum: u16,
+    send_maximum: u16,
+    received: HashMap<u16, String>,
+    sent: HashMap<String, u16>,
+}
+
+impl TopicAliases {
+    /// ### new
+    ///
+    /// Crea las tablas de Topic Alias de una conexion
+    ///
+    /// ### Parametros
+    /// - `receive_maximum`: Topic Alias Maximum anunciado a la otra parte
+    /// - `send_maximum`: Topic Alias Maximum anunciado por la otra parte
+    ///
+    pub fn new(receive_maximum: u16, send_maximum: u16) -> Self {
+        TopicAliases {
+            receive_maximum,
+            send_maximum,
+            ..Default::default()
+        }
+    }
+
+    /// ### resolve
+    ///
+    /// Resuelve el Topic Alias de una publicacion recibida: si trae un topico, se asocia
+    /// al alias; si no, se completa el topico con el asociado al alias
+    ///
+    /// ### Parametros
+    /// - `publish`: publicacion recibida (se le quita el Topic Alias)
+    ///
+    /// ### Retorno
+    /// - `Result<(), ReasonCode>`: reason code con el que debe cerrarse la conexion si
+    ///   el alias es invalido
+    ///
+    /// ### Consideraciones
+    /// - Un alias 0 o mayor al Topic Alias Maximum es `TopicAliasInvalid`
+    /// - Un topico vacio sin alias, o con un alias desconocido, es `ProtocolError`
+    ///
+    pub fn resolve(&mut self, publish: &mut Publish) -> Result<(), ReasonCode> {
+        let topic_alias = match publish.properties.topic_alias.take() {
+            Some(topic_alias) => topic_alias,
+            None if publish.properties.topic_name.is_empty() => {
+                return Err(ReasonCode::ProtocolError)
+            }
+            None => return Ok(()),
+        };
+
+        if topic_alias == 0 || topic_alias > self.receive_maximum {
+            return Err(ReasonCode::TopicAliasInvalid);
+        }
+
+        if publish.properties.topic_name.is_empty() {
+            match self.received.get(&topic_alias) {
+                Some(topic) => publish.properties.topic_name.clone_from(topic),
+                None => return Err(ReasonCode::ProtocolError),
+            }
+        } else {
+            self.received
+                .insert(topic_alias, publish.properties.topic_name.clone());
+        }
+        Ok(())
+    }
+
+    /// ### assign
+    ///
+    /// Asigna un Topic Alias a una publicacion a enviar: si su topico ya tiene alias,
+    /// se envia solo el alias; si no y quedan aliases disponibles, se envia el topico
+    /// junto a un nuevo alias
+    ///
+    /// ### Parametros
+    /// - `publish`: publicacion a enviar
+    ///
+    pub fn assign(&mut self, publish: &mut Publish) {
+        if let Some(topic_alias) = self.sent.get(&publish.properties.topic_name) {
+            publish.properties.topic_alias = Some(*topic_alias);
+            publish.properties.topic_name = String::new();
+        } else if self.sent.len() < self.send_maximum as usize {
+            let topic_alias = self.sent.len() as u16 + 1;
+            self.sent
+                .insert(publish.properties.topic_name.clone(), topic_alias);
+            publish.properties.topic_alias = Some(topic_alias);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mqtt_packets::properties::publish_properties::PublishProperties;
+
+    use super::*;
+
+    fn publish(topic: &str, topic_alias: Option<u16>) -> Publish {
+        let properties = PublishProperties {
+            topic_name: topic.to_string(),
+            topic_alias,
+            ..Default::default()
+        };
+        Publish::new(0, 0, 0, properties)
+    }
+
+    #[test]
+    fn test_assign_and_resolve() {
+        let mut sender = TopicAliases::new(0, 2);
+        let mut receiver = TopicAliases::new(2, 0);
+
+        for (topic, alias, sent_topic) in [
+            ("drone", Some(1), "drone"),
+            ("drone", Some(1), ""),
+            ("camaras", Some(2), "camaras"),
+            ("inc", None, "inc"),
+            ("camaras", Some(2), ""),
+        ] {
+            let mut message = publish(topic, None);
+            sender.assign(&mut message);
+            assert_eq!(message.properties.topic_alias, alias);
+            assert_eq!(message.properties.topic_name, sent_topic);
+
+            assert!(receiver.resolve(&mut message).is_ok());
+            assert_eq!(message.properties.topic_name, topic);
+            assert_eq!(message.properties.topic_alias, None);
+        }
+    }
+
+    #[test]
+    fn test_invalid_aliases() {
+        let mut receiver = TopicAliases::new(2, 0);
+
+        assert!(matches!(
+            receiver.resolve(&mut publish("drone", Some(0))),
+            Err(ReasonCode::TopicAliasInvalid)
+        ));
+        assert!(matches!(
+            receiver.resolve(&mut publish("drone", Some(3))),
+            Err(ReasonCode::TopicAliasInvalid)
+        ));
+        assert!(matches!(
+            receiver.resolve(&mut publish("", Some(1))),
+            Err(ReasonCode::ProtocolError)
+        ));
+        assert!(matches!(
+            receiver.resolve(&mut publish("", None)),
+            Err(ReasonCode::ProtocolError)
+        ));
+
+        // Sin Topic Alias Maximum no se aceptan aliases
+        let mut receiver = TopicAliases::default();
+        assert!(matches!(
+            receiver.resolve(&mut publish("drone", Some(1))),
+            Err(ReasonCode::TopicAliasInvalid)
+        ));
+    }
+}
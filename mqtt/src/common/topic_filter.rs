@@ -1,4 +1,4 @@
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default)]
 
 /// ## TopicFilter
 ///
@@ -7,15 +7,21 @@
 /// ### Atributos
 /// - `topic_filter`: Filtro de topicos
 /// - `subscription_options`: Opciones de suscripción
+/// - `subscription_identifier`: Subscription Identifier del SUBSCRIBE que la creó
 ///
 /// ### Consideraciones
 /// - El filtro de topicos debe ser un string
 /// - Las opciones de suscripción deben ser un byte
 /// - Cada Topic Filter debe ser seguido por el Subscriptions Options Byte
+/// - El Subscription Identifier no forma parte del filtro en el SUBSCRIBE, es una
+///   propiedad del paquete que se copia a cada filtro al leerlo
+/// - Al persistirse, el filtro incluye los 4 bytes de su Subscription Identifier. Los
+///   archivos de sesiones escritos antes de persistirlo se leen con `from_v0_be_bytes`
 ///
 pub struct TopicFilter {
     pub topic_filter: String,
     pub subscription_options: u8,
+    pub subscription_identifier: Option<u32>,
 }
 
 impl TopicFilter {
@@ -33,6 +39,8 @@ impl TopicFilter {
         bytes.extend_from_slice(&len.to_be_bytes());
         bytes.extend_from_slice(self.topic_filter.as_bytes());
         bytes.push(self.subscription_options);
+        // 0: la subscripción no tiene Subscription Identifier
+        bytes.extend_from_slice(&self.subscription_identifier.unwrap_or(0).to_be_bytes());
 
         bytes
     }
@@ -45,21 +53,265 @@ impl TopicFilter {
     /// - `bytes`: Vector de bytes
     ///
     /// ### Retorno
-    /// - `TopicFilter`: Filtro de topicos creado
+    /// - `Option<TopicFilter>`: Filtro de topicos creado, o `None` si los bytes estan incompletos
     ///
-    pub fn from_be_bytes(bytes: Vec<u8>) -> Self {
-        let mut index = 0;
+    pub fn from_be_bytes(bytes: Vec<u8>) -> Option<Self> {
+        let topic_filter_len = *bytes.first()? as usize;
+        let topic_filter = String::from_utf8(bytes.get(1..topic_filter_len)?.to_vec()).ok()?;
+        let index = topic_filter_len;
+        let subscription_options = *bytes.get(index)?;
+        let subscription_identifier =
+            match u32::from_be_bytes(bytes.get(index + 1..index + 5)?.try_into().ok()?) {
+                0 => None,
+                subscription_identifier => Some(subscription_identifier),
+            };
 
-        let topic_filter_len = bytes[index];
-        index += 1;
-        let topic_filter =
-            String::from_utf8(bytes[index..topic_filter_len as usize].to_vec()).unwrap();
-        index += topic_filter.len();
-        let subscription_options = bytes[index];
+        Some(TopicFilter {
+            topic_filter,
+            subscription_options,
+            subscription_identifier,
+        })
+    }
+
+    /// ### from_v0_be_bytes
+    ///
+    /// Convierte en un filtro de topicos los bytes persistidos antes de incluirse el
+    /// Subscription Identifier (archivos de sesiones sin encabezado)
+    ///
+    /// ### Retorno
+    /// - `Option<(TopicFilter, usize)>`: Filtro de topicos, sin Subscription Identifier, y
+    ///   la cantidad de bytes que ocupaba; o `None` si los bytes estan incompletos
+    ///
+    pub fn from_v0_be_bytes(bytes: Vec<u8>) -> Option<(Self, usize)> {
+        let topic_filter_len = *bytes.first()? as usize;
+        let topic_filter = String::from_utf8(bytes.get(1..topic_filter_len)?.to_vec()).ok()?;
+        let subscription_options = *bytes.get(topic_filter_len)?;
 
-        TopicFilter {
+        let topic_filter = TopicFilter {
             topic_filter,
             subscription_options,
+            subscription_identifier: None,
+        };
+        Some((topic_filter, topic_filter_len + 1))
+    }
+
+    /// ### is_valid
+    ///
+    /// Indica si el filtro de topicos es valido
+    ///
+    /// ### Retorno
+    /// - `bool`: true si el filtro es valido
+    ///
+    pub fn is_valid(&self) -> bool {
+        match split_shared_subscription(&self.topic_filter) {
+            Some((share_name, topic_filter)) => {
+                !share_name.is_empty()
+                    && !share_name.contains(SINGLE_LEVEL_WILDCARD)
+                    && !share_name.contains(MULTI_LEVEL_WILDCARD)
+                    && is_valid_topic_filter(topic_filter)
+            }
+            None => is_valid_topic_filter(&self.topic_filter),
+        }
+    }
+
+    /// ### share_name
+    ///
+    /// Devuelve el nombre del grupo de una subscripción compartida
+    /// (`$share/{grupo}/{filtro}`)
+    ///
+    /// ### Retorno
+    /// - `Option<&str>`: nombre del grupo, o `None` si la subscripción no es compartida
+    ///
+    pub fn share_name(&self) -> Option<&str> {
+        split_shared_subscription(&self.topic_filter).map(|(share_name, _)| share_name)
+    }
+
+    /// ### matches
+    ///
+    /// Indica si un topico coincide con el filtro de topicos
+    ///
+    /// ### Parametros
+    /// - `topic_name`: Nombre del topico
+    ///
+    /// ### Retorno
+    /// - `bool`: true si el topico coincide con el filtro
+    ///
+    pub fn matches(&self, topic_name: &str) -> bool {
+        match split_shared_subscription(&self.topic_filter) {
+            Some((_, topic_filter)) => topic_matches(topic_filter, topic_name),
+            None => topic_matches(&self.topic_filter, topic_name),
+        }
+    }
+}
+
+/// Separador de niveles de un topico
+const LEVEL_SEPARATOR: char = '/';
+
+/// Wildcard que reemplaza a un unico nivel
+const SINGLE_LEVEL_WILDCARD: &str = "+";
+
+/// Wildcard que reemplaza a cualquier cantidad de niveles (debe ser el ultimo nivel)
+const MULTI_LEVEL_WILDCARD: &str = "#";
+
+/// Prefijo de las subscripciones compartidas (`$share/{grupo}/{filtro}`)
+const SHARED_SUBSCRIPTION_PREFIX: &str = "$share";
+
+/// ### split_shared_subscription
+///
+/// Separa una subscripción compartida en el nombre de su grupo y su filtro de topicos
+///
+/// ### Parametros
+/// - `topic_filter`: Filtro de topicos
+///
+/// ### Retorno
+/// - `Option<(&str, &str)>`: nombre del grupo y filtro de topicos, o `None` si el filtro
+///   no comienza con `$share/`
+///
+/// ### Consideraciones
+/// - Si el filtro no tiene un nivel despues del nombre del grupo, el filtro devuelto es vacio
+///   (y por lo tanto invalido)
+///
+pub fn split_shared_subscription(topic_filter: &str) -> Option<(&str, &str)> {
+    let mut levels = topic_filter.splitn(3, LEVEL_SEPARATOR);
+    if levels.next() != Some(SHARED_SUBSCRIPTION_PREFIX) {
+        return None;
+    }
+    let share_name = levels.next()?;
+    Some((share_name, levels.next().unwrap_or_default()))
+}
+
+/// ### is_valid_topic_filter
+///
+/// Valida un filtro de topicos
+///
+/// ### Parametros
+/// - `topic_filter`: Filtro de topicos
+///
+/// ### Retorno
+/// - `bool`: true si el filtro es valido
+///
+/// ### Consideraciones
+/// - Debe tener al menos un caracter y no puede contener el caracter nulo
+/// - `+` debe ocupar un nivel completo
+/// - `#` debe ocupar un nivel completo y ser el ultimo nivel del filtro
+///
+pub fn is_valid_topic_filter(topic_filter: &str) -> bool {
+    if topic_filter.is_empty() || topic_filter.contains('\0') {
+        return false;
+    }
+
+    let levels = topic_filter.split(LEVEL_SEPARATOR).collect::<Vec<&str>>();
+    let last = levels.len() - 1;
+
+    levels.iter().enumerate().all(|(i, level)| {
+        if *level == MULTI_LEVEL_WILDCARD {
+            return i == last;
+        }
+        *level == SINGLE_LEVEL_WILDCARD || !(level.contains('+') || level.contains('#'))
+    })
+}
+
+/// ### is_valid_topic_name
+///
+/// Valida el nombre de un topico de publicacion
+///
+/// ### Parametros
+/// - `topic_name`: Nombre del topico
+///
+/// ### Retorno
+/// - `bool`: true si el nombre es valido
+///
+/// ### Consideraciones
+/// - Debe tener al menos un caracter
+/// - No puede contener wildcards ni el caracter nulo
+///
+pub fn is_valid_topic_name(topic_name: &str) -> bool {
+    !topic_name.is_empty()
+        && !topic_name.contains('\0')
+        && !topic_name.contains('+')
+        && !topic_name.contains('#')
+}
+
+/// ### topic_matches
+///
+/// Determina si un nombre de topico coincide con un filtro de topicos
+///
+/// ### Parametros
+/// - `topic_filter`: Filtro de topicos (puede contener wildcards)
+/// - `topic_name`: Nombre del topico
+///
+/// ### Retorno
+/// - `bool`: true si el topico coincide con el filtro
+///
+/// ### Consideraciones
+/// - Los topicos que comienzan con `$` no coinciden con filtros
+///   que comienzan con un wildcard
+/// - `#` tambien coincide con el nivel padre (`a/#` coincide con `a`)
+///
+pub fn topic_matches(topic_filter: &str, topic_name: &str) -> bool {
+    if !is_valid_topic_filter(topic_filter) || !is_valid_topic_name(topic_name) {
+        return false;
+    }
+
+    if topic_name.starts_with('$')
+        && (topic_filter.starts_with(SINGLE_LEVEL_WILDCARD)
+            || topic_filter.starts_with(MULTI_LEVEL_WILDCARD))
+    {
+        return false;
+    }
+
+    let mut filter_levels = topic_filter.split(LEVEL_SEPARATOR);
+    let mut name_levels = topic_name.split(LEVEL_SEPARATOR);
+
+    loop {
+        match (filter_levels.next(), name_levels.next()) {
+            (Some(MULTI_LEVEL_WILDCARD), _) => return true,
+            (Some(SINGLE_LEVEL_WILDCARD), Some(_)) => {}
+            (Some(filter), Some(name)) if filter == name => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// ### filter_covers
+///
+/// Determina si un filtro de topicos abarca a otro, es decir, si todo topico que
+/// coincide con `requested` tambien coincide con `topic_filter`
+///
+/// ### Parametros
+/// - `topic_filter`: Filtro de topicos que debe abarcar al otro
+/// - `requested`: Filtro de topicos a verificar (por ejemplo, el de una subscripción)
+///
+/// ### Consideraciones
+/// - Un wildcard de `requested` solo queda abarcado por un wildcard igual o mas amplio
+///   de `topic_filter` (`+` por `+` o `#`, y `#` solo por `#`)
+/// - Igual que en `topic_matches`, un filtro que comienza con un wildcard no abarca
+///   a los topicos que comienzan con `$`
+///
+pub fn filter_covers(topic_filter: &str, requested: &str) -> bool {
+    if !is_valid_topic_filter(topic_filter) || !is_valid_topic_filter(requested) {
+        return false;
+    }
+
+    if requested.starts_with('$')
+        && (topic_filter.starts_with(SINGLE_LEVEL_WILDCARD)
+            || topic_filter.starts_with(MULTI_LEVEL_WILDCARD))
+    {
+        return false;
+    }
+
+    let mut filter_levels = topic_filter.split(LEVEL_SEPARATOR);
+    let mut requested_levels = requested.split(LEVEL_SEPARATOR);
+
+    loop {
+        match (filter_levels.next(), requested_levels.next()) {
+            (Some(MULTI_LEVEL_WILDCARD), _) => return true,
+            (Some(_), Some(MULTI_LEVEL_WILDCARD)) => return false,
+            (Some(SINGLE_LEVEL_WILDCARD), Some(_)) => {}
+            (Some(filter), Some(level)) if filter == level => {}
+            (None, None) => return true,
+            _ => return false,
         }
     }
 }
@@ -73,15 +325,190 @@ mod test {
         let topic_filter = TopicFilter {
             topic_filter: "topic".to_string(),
             subscription_options: 0,
+            subscription_identifier: Some(7),
         };
 
         let bytes = topic_filter.as_bytes();
-        let deserialized = TopicFilter::from_be_bytes(bytes);
+        let deserialized = TopicFilter::from_be_bytes(bytes).unwrap();
 
         assert_eq!(topic_filter.topic_filter, deserialized.topic_filter);
         assert_eq!(
             topic_filter.subscription_options,
             deserialized.subscription_options
         );
+        assert_eq!(deserialized.subscription_identifier, Some(7));
+
+        let without_identifier = TopicFilter {
+            topic_filter: "topic".to_string(),
+            subscription_options: 0,
+            subscription_identifier: None,
+        };
+        let deserialized = TopicFilter::from_be_bytes(without_identifier.as_bytes()).unwrap();
+        assert_eq!(deserialized.subscription_identifier, None);
+
+        // Un filtro truncado no se puede leer
+        let bytes = topic_filter.as_bytes();
+        for len in 0..bytes.len() {
+            assert!(TopicFilter::from_be_bytes(bytes[..len].to_vec()).is_none());
+        }
+
+        // Un filtro persistido sin Subscription Identifier (version 0)
+        let v0_bytes = bytes[..bytes.len() - 4].to_vec();
+        let (deserialized, size) = TopicFilter::from_v0_be_bytes(v0_bytes.clone()).unwrap();
+        assert_eq!(deserialized.topic_filter, topic_filter.topic_filter);
+        assert_eq!(deserialized.subscription_identifier, None);
+        assert_eq!(size, v0_bytes.len());
+        assert!(TopicFilter::from_v0_be_bytes(v0_bytes[..size - 1].to_vec()).is_none());
+    }
+
+    #[test]
+    fn test_valid_topic_filters() {
+        for filter in [
+            "drone/1/state",
+            "drone/+/state",
+            "inc/#",
+            "#",
+            "+",
+            "+/+",
+            "/+",
+            "sport/tennis/+/#",
+        ] {
+            assert!(is_valid_topic_filter(filter), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_invalid_topic_filters() {
+        for filter in [
+            "",
+            "inc/#/state",
+            "inc#",
+            "inc/a#",
+            "drone+/state",
+            "drone/+a",
+            "##",
+            "a\0b",
+        ] {
+            assert!(!is_valid_topic_filter(filter), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn test_topic_names() {
+        assert!(is_valid_topic_name("drone/1/state"));
+        assert!(is_valid_topic_name("$SYS/broker"));
+        assert!(!is_valid_topic_name(""));
+        assert!(!is_valid_topic_name("drone/+/state"));
+        assert!(!is_valid_topic_name("inc/#"));
+    }
+
+    #[test]
+    fn test_exact_match() {
+        assert!(topic_matches("drone/1/state", "drone/1/state"));
+        assert!(!topic_matches("drone/1/state", "drone/2/state"));
+        assert!(!topic_matches("drone/1", "drone/1/state"));
+        assert!(!topic_matches("drone/1/state", "drone/1"));
+    }
+
+    #[test]
+    fn test_single_level_wildcard() {
+        assert!(topic_matches("drone/+/state", "drone/1/state"));
+        assert!(topic_matches("drone/+/state", "drone//state"));
+        assert!(!topic_matches("drone/+/state", "drone/1/2/state"));
+        assert!(!topic_matches("drone/+", "drone"));
+        assert!(topic_matches("+", "drone"));
+        assert!(!topic_matches("+", "drone/1"));
+        assert!(topic_matches("/+", "/drone"));
+    }
+
+    #[test]
+    fn test_multi_level_wildcard() {
+        assert!(topic_matches("inc/#", "inc"));
+        assert!(topic_matches("inc/#", "inc/1"));
+        assert!(topic_matches("inc/#", "inc/1/resolved"));
+        assert!(!topic_matches("inc/#", "incidents"));
+        assert!(topic_matches("#", "drone/1/state"));
+        assert!(topic_matches("drone/+/#", "drone/1/state/battery"));
+    }
+
+    #[test]
+    fn test_dollar_topics() {
+        assert!(!topic_matches("#", "$SYS/broker/clients"));
+        assert!(!topic_matches("+/broker/clients", "$SYS/broker/clients"));
+        assert!(topic_matches("$SYS/#", "$SYS/broker/clients"));
+        assert!(topic_matches("$SYS/+/clients", "$SYS/broker/clients"));
+    }
+
+    #[test]
+    fn test_filter_covers() {
+        assert!(filter_covers("inc", "inc"));
+        assert!(filter_covers("drone/+/state", "drone/1/state"));
+        assert!(filter_covers("drone/+/state", "drone/+/state"));
+        assert!(filter_covers("drone/#", "drone/+/state"));
+        assert!(filter_covers("drone/#", "drone/#"));
+        assert!(filter_covers("drone/#", "drone"));
+        assert!(filter_covers("#", "inc/+"));
+        assert!(!filter_covers("drone/1/state", "drone/+/state"));
+        assert!(!filter_covers("drone/+/state", "drone/#"));
+        assert!(!filter_covers("drone/+", "drone/1/state"));
+        assert!(!filter_covers("inc", "camaras"));
+        assert!(!filter_covers("#", "$SYS/#"));
+        assert!(filter_covers("$SYS/#", "$SYS/broker/+"));
+        assert!(!filter_covers("inc/#/a", "inc"));
+    }
+
+    #[test]
+    fn test_invalid_filter_never_matches() {
+        assert!(!topic_matches("inc/#/state", "inc/1/state"));
+        assert!(!topic_matches("drone/+", "drone/+"));
+    }
+
+    #[test]
+    fn test_topic_filter_matches() {
+        let topic_filter = TopicFilter {
+            topic_filter: "drone/+/state".to_string(),
+            subscription_options: 0,
+            subscription_identifier: None,
+        };
+
+        assert!(topic_filter.is_valid());
+        assert!(topic_filter.matches("drone/7/state"));
+        assert!(!topic_filter.matches("cam/7/state"));
+    }
+
+    #[test]
+    fn test_shared_subscriptions() {
+        let shared = TopicFilter {
+            topic_filter: "$share/camssystem/inc/#".to_string(),
+            subscription_options: 0,
+            subscription_identifier: None,
+        };
+
+        assert!(shared.is_valid());
+        assert_eq!(shared.share_name(), Some("camssystem"));
+        assert!(shared.matches("inc"));
+        assert!(shared.matches("inc/1"));
+        assert!(!shared.matches("$share/camssystem/inc"));
+
+        let not_shared = TopicFilter {
+            topic_filter: "inc/#".to_string(),
+            subscription_options: 0,
+            subscription_identifier: None,
+        };
+        assert_eq!(not_shared.share_name(), None);
+
+        for filter in [
+            "$share/camssystem",
+            "$share//inc",
+            "$share/cams+/inc",
+            "$share/a/inc/#/b",
+        ] {
+            let invalid = TopicFilter {
+                topic_filter: filter.to_string(),
+                subscription_options: 0,
+                subscription_identifier: None,
+            };
+            assert!(!invalid.is_valid(), "{}", filter);
+        }
     }
 }
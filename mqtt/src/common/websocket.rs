@@ -0,0 +1,580 @@
+use std::{
+    io::{Error, ErrorKind, Read, Write},
+    net::{Shutdown, TcpStream},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::Rng;
+use sha1::{Digest, Sha1};
+
+use super::mqtt_stream::Transport;
+
+/// Subprotocolo WebSocket con el que se transportan paquetes MQTT
+pub const MQTT_SUBPROTOCOL: &str = "mqtt";
+
+/// Valor que se concatena a la clave del cliente para calcular `Sec-WebSocket-Accept` (RFC 6455)
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Tamaño maximo de los encabezados HTTP del handshake
+const MAX_HANDSHAKE_LEN: usize = 8192;
+
+/// Tamaño maximo del payload de un frame (el de un paquete MQTT mas su encabezado fijo)
+const MAX_FRAME_PAYLOAD: u64 = 268_435_455 + 5;
+
+/// Plazo para recibir el pedido de conexion del cliente
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tamaño de cada lectura del transporte
+const READ_CHUNK: usize = 4096;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xA;
+
+/// Codigo de cierre para datos que no son binarios (RFC 6455, 7.4.1)
+const CLOSE_UNSUPPORTED_DATA: u16 = 1003;
+
+/// ## Frame
+///
+/// Frame WebSocket decodificado
+///
+#[derive(Debug, PartialEq)]
+struct Frame {
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// ## WebSocketTransport
+///
+/// Transporte que envia y recibe los paquetes MQTT en frames binarios de una
+/// conexion WebSocket (subprotocolo `mqtt`)
+///
+/// ### Atributos
+/// - `inner`: transporte sobre el que se establecio la conexion WebSocket (TCP o TLS)
+/// - `is_client`: si los frames enviados se enmascaran (el cliente debe hacerlo)
+/// - `incoming`: bytes recibidos que todavia no forman un frame completo
+/// - `payload`: datos de los frames binarios recibidos que todavia no se leyeron
+/// - `closed`: si se recibió el frame de cierre
+///
+/// ### Consideraciones
+/// - Los frames se decodifican de a partes, por lo que una lectura interrumpida por
+///   el timeout del socket no pierde datos
+/// - Cada escritura se envia en un frame binario. Un paquete MQTT puede ocupar varios frames
+/// - Los PING se responden automaticamente y un frame de cierre se lee como fin de la conexion
+///
+pub struct WebSocketTransport<T: Transport> {
+    inner: T,
+    is_client: bool,
+    incoming: Vec<u8>,
+    payload: Vec<u8>,
+    closed: bool,
+}
+
+impl<T: Transport> WebSocketTransport<T> {
+    fn new(inner: T, is_client: bool) -> Self {
+        WebSocketTransport {
+            inner,
+            is_client,
+            incoming: Vec::new(),
+            payload: Vec::new(),
+            closed: false,
+        }
+    }
+
+    /// ### accept
+    ///
+    /// Realiza el handshake de una conexion WebSocket entrante
+    ///
+    /// ### Parametros
+    /// - `inner`: transporte de la conexion
+    /// - `path`: ruta en la que se aceptan conexiones (ej. `/mqtt`)
+    ///
+    /// ### Consideraciones
+    /// - El cliente debe ofrecer el subprotocolo `mqtt`; si no, se rechaza la conexion
+    /// - Si el cliente no envia el pedido dentro de `HANDSHAKE_TIMEOUT`, se rechaza la conexion
+    /// - Al rechazar la conexion se responde el error HTTP y se cierra el socket
+    ///
+    pub fn accept(mut inner: T, path: &str) -> Result<Self, Error> {
+        inner
+            .tcp_stream()
+            .set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let request = read_http_head(&mut inner)?;
+        let mut lines = request.lines();
+        let request_line = lines.next().unwrap_or_default();
+        let headers = parse_headers(lines);
+
+        let mut parts = request_line.split_whitespace();
+        let rejection = match (parts.next(), parts.next()) {
+            (Some("GET"), Some(request_path)) if request_path == path => {
+                validate_upgrade_request(&headers)
+            }
+            (Some("GET"), _) => Err(("404 Not Found", "ruta invalida")),
+            _ => Err(("400 Bad Request", "se esperaba un GET")),
+        };
+        let key = match rejection {
+            Ok(key) => key,
+            Err((status, reason)) => {
+                let response = format!(
+                    "HTTP/1.1 {}\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+                    status
+                );
+                let _ = inner.write_all(response.as_bytes());
+                let _ = inner.tcp_stream().shutdown(Shutdown::Both);
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("WebSocket - Handshake rechazado: {}", reason),
+                ));
+            }
+        };
+
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\nSec-WebSocket-Protocol: {}\r\n\r\n",
+            accept_key(&key),
+            MQTT_SUBPROTOCOL
+        );
+        inner.write_all(response.as_bytes())?;
+        inner.flush()?;
+        Ok(Self::new(inner, false))
+    }
+
+    /// ### connect
+    ///
+    /// Realiza el handshake de una conexion WebSocket saliente, pidiendo el subprotocolo `mqtt`
+    ///
+    /// ### Parametros
+    /// - `inner`: transporte de la conexion
+    /// - `host`: valor del encabezado `Host`
+    /// - `path`: ruta del servidor (ej. `/mqtt`)
+    ///
+    pub fn connect(mut inner: T, host: &str, path: &str) -> Result<Self, Error> {
+        let key = STANDARD.encode(rand::thread_rng().gen::<[u8; 16]>());
+        let request = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\
+             Sec-WebSocket-Protocol: {}\r\n\r\n",
+            path, host, key, MQTT_SUBPROTOCOL
+        );
+        inner.write_all(request.as_bytes())?;
+        inner.flush()?;
+
+        let response = read_http_head(&mut inner)?;
+        let mut lines = response.lines();
+        let status_line = lines.next().unwrap_or_default();
+        let headers = parse_headers(lines);
+        if status_line.split_whitespace().nth(1) != Some("101") {
+            return Err(Error::new(
+                ErrorKind::ConnectionRefused,
+                format!("WebSocket - Handshake rechazado: {}", status_line),
+            ));
+        }
+        if header(&headers, "sec-websocket-accept") != Some(accept_key(&key).as_str())
+            || header(&headers, "sec-websocket-protocol") != Some(MQTT_SUBPROTOCOL)
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "WebSocket - Respuesta del handshake invalida",
+            ));
+        }
+        Ok(Self::new(inner, true))
+    }
+
+    /// ### send_frame
+    ///
+    /// Envia un frame, enmascarado si el transporte es un cliente
+    ///
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), Error> {
+        let mask = match self.is_client {
+            true => Some(rand::thread_rng().gen::<[u8; 4]>()),
+            false => None,
+        };
+        self.inner.write_all(&encode_frame(opcode, payload, mask))
+    }
+
+    /// ### handle_frame
+    ///
+    /// Procesa un frame recibido: los datos binarios quedan disponibles para leer
+    /// y los frames de control se responden
+    ///
+    fn handle_frame(&mut self, frame: Frame) -> Result<(), Error> {
+        match frame.opcode {
+            OPCODE_BINARY | OPCODE_CONTINUATION => self.payload.extend(frame.payload),
+            OPCODE_PING => self.send_frame(OPCODE_PONG, &frame.payload)?,
+            OPCODE_PONG => {}
+            OPCODE_CLOSE => {
+                // Se responde el cierre con el mismo codigo
+                let code = frame.payload.get(..2).unwrap_or_default().to_vec();
+                let _ = self.send_frame(OPCODE_CLOSE, &code);
+                self.closed = true;
+            }
+            OPCODE_TEXT => {
+                let _ = self.send_frame(OPCODE_CLOSE, &CLOSE_UNSUPPORTED_DATA.to_be_bytes());
+                self.closed = true;
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    "WebSocket - Los paquetes MQTT deben enviarse en frames binarios",
+                ));
+            }
+            opcode => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("WebSocket - Opcode desconocido {}", opcode),
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: Transport> Read for WebSocketTransport<T> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+        loop {
+            if !self.payload.is_empty() {
+                let read = buf.len().min(self.payload.len());
+                buf[..read].copy_from_slice(&self.payload[..read]);
+                self.payload.drain(..read);
+                return Ok(read);
+            }
+            if self.closed {
+                return Ok(0);
+            }
+
+            // Los frames del cliente deben estar enmascarados y los del servidor no
+            if let Some((frame, used)) = decode_frame(&self.incoming, !self.is_client)? {
+                self.incoming.drain(..used);
+                self.handle_frame(frame)?;
+                continue;
+            }
+
+            let mut chunk = [0; READ_CHUNK];
+            let read = self.inner.read(&mut chunk)?;
+            if read == 0 {
+                return Ok(0);
+            }
+            self.incoming.extend_from_slice(&chunk[..read]);
+        }
+    }
+}
+
+impl<T: Transport> Write for WebSocketTransport<T> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+        self.send_frame(OPCODE_BINARY, buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Error> {
+        self.inner.flush()
+    }
+}
+
+impl<T: Transport> Transport for WebSocketTransport<T> {
+    fn tcp_stream(&self) -> &TcpStream {
+        self.inner.tcp_stream()
+    }
+
+    fn has_buffered_data(&self) -> bool {
+        !self.payload.is_empty()
+            || self.closed
+            || !matches!(decode_frame(&self.incoming, !self.is_client), Ok(None))
+            || self.inner.has_buffered_data()
+    }
+}
+
+/// ### accept_key
+///
+/// Calcula el `Sec-WebSocket-Accept` que corresponde a la clave del cliente
+///
+pub fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(WEBSOCKET_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+/// ### validate_upgrade_request
+///
+/// Verifica los encabezados de un pedido de conexion WebSocket
+///
+/// ### Retorno
+/// - `Result<String, (&str, &str)>`: clave del cliente, o el estado HTTP y el motivo del rechazo
+///
+fn validate_upgrade_request(
+    headers: &[(String, String)],
+) -> Result<String, (&'static str, &'static str)> {
+    let contains_token = |name: &str, token: &str| {
+        header(headers, name).is_some_and(|value| {
+            value
+                .split(',')
+                .any(|item| item.trim().eq_ignore_ascii_case(token))
+        })
+    };
+
+    if !contains_token("upgrade", "websocket") || !contains_token("connection", "upgrade") {
+        return Err(("400 Bad Request", "no es un pedido de WebSocket"));
+    }
+    if header(headers, "sec-websocket-version") != Some("13") {
+        return Err(("426 Upgrade Required", "version de WebSocket no soportada"));
+    }
+    if !contains_token("sec-websocket-protocol", MQTT_SUBPROTOCOL) {
+        return Err(("400 Bad Request", "no se ofrecio el subprotocolo mqtt"));
+    }
+    match header(headers, "sec-websocket-key") {
+        Some(key) if !key.is_empty() => Ok(key.to_string()),
+        _ => Err(("400 Bad Request", "falta Sec-WebSocket-Key")),
+    }
+}
+
+/// ### read_http_head
+///
+/// Lee los encabezados HTTP del handshake, sin consumir los frames que le siguen
+///
+fn read_http_head(stream: &mut impl Read) -> Result<String, Error> {
+    let mut head = Vec::new();
+    let mut byte = [0; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_HANDSHAKE_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "WebSocket - Encabezados del handshake demasiado grandes",
+            ));
+        }
+        if stream.read(&mut byte)? == 0 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "WebSocket - Conexion cerrada durante el handshake",
+            ));
+        }
+        head.push(byte[0]);
+    }
+    String::from_utf8(head).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidData,
+            "WebSocket - Encabezados del handshake invalidos",
+        )
+    })
+}
+
+fn parse_headers<'a>(lines: impl Iterator<Item = &'a str>) -> Vec<(String, String)> {
+    lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect()
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(header, _)| header == name)
+        .map(|(_, value)| value.as_str())
+}
+
+/// ### encode_frame
+///
+/// Codifica un frame completo (FIN) con el opcode y payload indicados
+///
+fn encode_frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut bytes = vec![0x80 | opcode];
+    let mask_bit = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => bytes.push(mask_bit | len as u8),
+        len if len <= u16::MAX as usize => {
+            bytes.push(mask_bit | 126);
+            bytes.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            bytes.push(mask_bit | 127);
+            bytes.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            bytes.extend_from_slice(&mask);
+            bytes.extend(
+                payload
+                    .iter()
+                    .enumerate()
+                    .map(|(i, byte)| byte ^ mask[i % 4]),
+            );
+        }
+        None => bytes.extend_from_slice(payload),
+    }
+    bytes
+}
+
+/// ### decode_frame
+///
+/// Decodifica el primer frame de los bytes recibidos
+///
+/// ### Parametros
+/// - `bytes`: bytes recibidos
+/// - `expect_masked`: si el frame debe estar enmascarado (frames enviados por un cliente)
+///
+/// ### Retorno
+/// - `Result<Option<(Frame, usize)>, Error>`: frame y cantidad de bytes que ocupa,
+///   `None` si todavia no se recibió completo, o error si es invalido
+///
+fn decode_frame(bytes: &[u8], expect_masked: bool) -> Result<Option<(Frame, usize)>, Error> {
+    if bytes.len() < 2 {
+        return Ok(None);
+    }
+    let opcode = bytes[0] & 0x0F;
+    let masked = bytes[1] & 0x80 != 0;
+    if bytes[0] & 0x70 != 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "WebSocket - Frame con bits reservados",
+        ));
+    }
+    if masked != expect_masked {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "WebSocket - Enmascaramiento del frame invalido",
+        ));
+    }
+
+    let mut index = 2;
+    let payload_len = match bytes[1] & 0x7F {
+        126 => {
+            let Some(len) = bytes.get(index..index + 2) else {
+                return Ok(None);
+            };
+            index += 2;
+            u16::from_be_bytes([len[0], len[1]]) as u64
+        }
+        127 => {
+            let Some(len) = bytes.get(index..index + 8) else {
+                return Ok(None);
+            };
+            index += 8;
+            let mut len_bytes = [0; 8];
+            len_bytes.copy_from_slice(len);
+            u64::from_be_bytes(len_bytes)
+        }
+        len => len as u64,
+    };
+    if payload_len > MAX_FRAME_PAYLOAD {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "WebSocket - Frame demasiado grande",
+        ));
+    }
+
+    let mask = if masked {
+        let Some(mask) = bytes.get(index..index + 4) else {
+            return Ok(None);
+        };
+        index += 4;
+        Some([mask[0], mask[1], mask[2], mask[3]])
+    } else {
+        None
+    };
+
+    let end = index + payload_len as usize;
+    let Some(payload) = bytes.get(index..end) else {
+        return Ok(None);
+    };
+    let payload = match mask {
+        Some(mask) => payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4])
+            .collect(),
+        None => payload.to_vec(),
+    };
+    Ok(Some((Frame { opcode, payload }, end)))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::TcpListener, thread};
+
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // Ejemplo de la RFC 6455
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_frames() {
+        for len in [0, 125, 126, 65535, 65536] {
+            let payload = (0..len).map(|i| i as u8).collect::<Vec<u8>>();
+            for mask in [None, Some([1, 2, 3, 4])] {
+                let bytes = encode_frame(OPCODE_BINARY, &payload, mask);
+
+                // Un frame incompleto todavia no se decodifica
+                assert!(decode_frame(&bytes[..bytes.len() - 1], mask.is_some())
+                    .unwrap()
+                    .is_none());
+                assert!(decode_frame(&bytes, mask.is_none()).is_err());
+
+                let (frame, used) = decode_frame(&bytes, mask.is_some()).unwrap().unwrap();
+                assert_eq!(used, bytes.len());
+                assert_eq!(frame.opcode, OPCODE_BINARY);
+                assert_eq!(frame.payload, payload);
+            }
+        }
+    }
+
+    #[test]
+    fn test_handshake_and_data() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut transport = WebSocketTransport::accept(stream, "/mqtt").unwrap();
+            let mut data = [0; 5];
+            transport.read_exact(&mut data).unwrap();
+            transport.write_all(&data).unwrap();
+            // El cliente cierra la conexion
+            assert_eq!(transport.read(&mut data).unwrap(), 0);
+        });
+
+        let stream = TcpStream::connect(address).unwrap();
+        let mut client = WebSocketTransport::connect(stream, "localhost", "/mqtt").unwrap();
+        // Un PING del cliente se responde sin interferir con los datos
+        client.send_frame(OPCODE_PING, b"ping").unwrap();
+        client.write_all(b"he").unwrap();
+        client.write_all(b"llo").unwrap();
+
+        let mut data = [0; 5];
+        client.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"hello");
+        client
+            .send_frame(OPCODE_CLOSE, &1000_u16.to_be_bytes())
+            .unwrap();
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_handshake_requires_mqtt_subprotocol() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            WebSocketTransport::accept(stream, "/mqtt").is_err()
+        });
+
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream
+            .write_all(
+                b"GET /mqtt HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                  Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                  Sec-WebSocket-Version: 13\r\n\r\n",
+            )
+            .unwrap();
+        let response = read_http_head(&mut stream).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"));
+        assert!(server.join().unwrap());
+    }
+}
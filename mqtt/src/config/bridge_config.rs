@@ -0,0 +1,279 @@
+use std::{
+    io::{Error, ErrorKind},
+    net::SocketAddr,
+};
+
+use crate::common::topic_filter::{is_valid_topic_filter, topic_matches};
+
+use super::client_config::ClientConfig;
+use super::mqtt_config::Config;
+
+/// Segundos de espera antes del primer reintento de conexion si no se configura otro
+const DEFAULT_RECONNECT_MIN_DELAY: u64 = 1;
+
+/// Espera maxima (en segundos) entre reintentos de conexion si no se configura otra
+const DEFAULT_RECONNECT_MAX_DELAY: u64 = 30;
+
+/// ## BridgeDirection
+///
+/// Sentido en el que un puente reenvia los mensajes de un patron de topicos
+///
+/// ### Variantes
+/// - `In`: del servidor remoto al local (`in`)
+/// - `Out`: del servidor local al remoto (`out`)
+/// - `Both`: en ambos sentidos (`both`)
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BridgeDirection {
+    In,
+    Out,
+    Both,
+}
+
+impl BridgeDirection {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "in" => Some(BridgeDirection::In),
+            "out" => Some(BridgeDirection::Out),
+            "both" => Some(BridgeDirection::Both),
+            _ => None,
+        }
+    }
+
+    pub fn is_incoming(&self) -> bool {
+        matches!(self, BridgeDirection::In | BridgeDirection::Both)
+    }
+
+    pub fn is_outgoing(&self) -> bool {
+        matches!(self, BridgeDirection::Out | BridgeDirection::Both)
+    }
+}
+
+/// ## BridgeTopic
+///
+/// Patron de topicos que un puente reenvia entre el servidor local y el remoto
+///
+/// ### Atributos
+/// - `direction`: sentido en el que se reenvian los mensajes
+/// - `pattern`: filtro de topicos, sin los prefijos
+/// - `local_prefix`: prefijo de los topicos en el servidor local
+/// - `remote_prefix`: prefijo de los topicos en el servidor remoto
+///
+/// ### Consideraciones
+/// - Se configura con `<sentido>:<patron>[:<prefijo local>:<prefijo remoto>]`, por ejemplo
+///   `both:incidents/#` o `in:incidents/#:north/:` (los incidentes del servidor remoto se
+///   publican localmente en `north/incidents/...`)
+/// - Al reenviar un mensaje se reemplaza el prefijo de un servidor por el del otro
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct BridgeTopic {
+    pub direction: BridgeDirection,
+    pub pattern: String,
+    pub local_prefix: String,
+    pub remote_prefix: String,
+}
+
+impl BridgeTopic {
+    /// ### from_rule
+    ///
+    /// Lee un patron de topicos desde su regla
+    ///
+    /// ### Parametros
+    /// - `rule`: regla del patron (ej. `both:incidents/#`)
+    ///
+    pub fn from_rule(rule: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid parameter: Bridge topic '{}' ({})", rule, reason),
+            )
+        };
+
+        let parts = rule.split(':').collect::<Vec<&str>>();
+        let (direction, pattern, local_prefix, remote_prefix) = match parts[..] {
+            [direction, pattern] => (direction, pattern, "", ""),
+            [direction, pattern, local_prefix, remote_prefix] => {
+                (direction, pattern, local_prefix, remote_prefix)
+            }
+            _ => return Err(invalid("cantidad de campos invalida")),
+        };
+        let direction =
+            BridgeDirection::from_name(direction).ok_or_else(|| invalid("sentido desconocido"))?;
+
+        let topic = BridgeTopic {
+            direction,
+            pattern: pattern.to_string(),
+            local_prefix: local_prefix.to_string(),
+            remote_prefix: remote_prefix.to_string(),
+        };
+        if !is_valid_topic_filter(&topic.local_filter())
+            || !is_valid_topic_filter(&topic.remote_filter())
+        {
+            return Err(invalid("patron invalido"));
+        }
+        Ok(topic)
+    }
+
+    /// ### local_filter
+    ///
+    /// Filtro de los topicos del patron en el servidor local
+    ///
+    pub fn local_filter(&self) -> String {
+        self.local_prefix.clone() + &self.pattern
+    }
+
+    /// ### remote_filter
+    ///
+    /// Filtro de los topicos del patron en el servidor remoto
+    ///
+    pub fn remote_filter(&self) -> String {
+        self.remote_prefix.clone() + &self.pattern
+    }
+
+    /// ### to_remote
+    ///
+    /// Topico con el que se reenvia al servidor remoto un mensaje publicado localmente
+    ///
+    /// ### Retorno
+    /// - `Option<String>`: topico remoto, si el patron reenvia el topico en ese sentido
+    ///
+    pub fn to_remote(&self, topic: &str) -> Option<String> {
+        if !self.direction.is_outgoing() || !topic_matches(&self.local_filter(), topic) {
+            return None;
+        }
+        let suffix = topic.strip_prefix(&self.local_prefix)?;
+        Some(self.remote_prefix.clone() + suffix)
+    }
+
+    /// ### to_local
+    ///
+    /// Topico con el que se publica localmente un mensaje recibido del servidor remoto
+    ///
+    /// ### Retorno
+    /// - `Option<String>`: topico local, si el patron reenvia el topico en ese sentido
+    ///
+    pub fn to_local(&self, topic: &str) -> Option<String> {
+        if !self.direction.is_incoming() || !topic_matches(&self.remote_filter(), topic) {
+            return None;
+        }
+        let suffix = topic.strip_prefix(&self.remote_prefix)?;
+        Some(self.local_prefix.clone() + suffix)
+    }
+}
+
+/// ## BridgeConfig
+///
+/// Configuracion de un puente que conecta el servidor con un servidor MQTT remoto
+///
+/// ### Atributos
+/// - `client`: configuracion del cliente con el que se conecta al servidor remoto
+/// - `topics`: patrones de topicos que se reenvian, uno por cada parametro `topic`
+///   (ver `BridgeTopic`)
+/// - `reconnect_min_delay`: segundos de espera antes del primer reintento de conexion
+///   (por defecto 1)
+/// - `reconnect_max_delay`: espera maxima (en segundos) entre reintentos, que se
+///   duplica en cada intento fallido (por defecto 30)
+///
+/// ### Consideraciones
+/// - El archivo tiene los mismos parametros que el de un cliente, mas los del puente
+/// - Las subscripciones en el servidor remoto siempre usan No Local, para no recibir
+///   los mensajes que el propio puente publica
+/// - El cliente no reintenta la conexion por su cuenta: los reintentos los maneja el puente
+///
+#[derive(Clone)]
+pub struct BridgeConfig {
+    pub client: ClientConfig,
+    pub topics: Vec<BridgeTopic>,
+    pub reconnect_min_delay: u64,
+    pub reconnect_max_delay: u64,
+}
+
+impl BridgeConfig {
+    /// ### remote_filters
+    ///
+    /// Filtros a los que se subscribe el puente en el servidor remoto
+    ///
+    pub fn remote_filters(&self) -> Vec<String> {
+        self.topics
+            .iter()
+            .filter(|topic| topic.direction.is_incoming())
+            .map(|topic| topic.remote_filter())
+            .collect()
+    }
+
+    /// ### to_remote
+    ///
+    /// Topico remoto de un mensaje publicado localmente, segun el primer patron que lo reenvia
+    ///
+    pub fn to_remote(&self, topic: &str) -> Option<String> {
+        self.topics.iter().find_map(|t| t.to_remote(topic))
+    }
+
+    /// ### to_local
+    ///
+    /// Topico local de un mensaje recibido del servidor remoto, segun el primer patron que lo reenvia
+    ///
+    pub fn to_local(&self, topic: &str) -> Option<String> {
+        self.topics.iter().find_map(|t| t.to_local(topic))
+    }
+}
+
+impl Config for BridgeConfig {
+    fn get_socket_address(&self) -> SocketAddr {
+        self.client.get_socket_address()
+    }
+
+    fn set_params(params: &[(String, String)]) -> Result<Self, Error> {
+        let mut topics = Vec::new();
+        let mut reconnect_min_delay = DEFAULT_RECONNECT_MIN_DELAY;
+        let mut reconnect_max_delay = DEFAULT_RECONNECT_MAX_DELAY;
+        let mut client_params = Vec::new();
+
+        for param in params.iter() {
+            match param.0.as_str() {
+                "topic" => topics.push(BridgeTopic::from_rule(&param.1)?),
+                "reconnect_min_delay" => {
+                    reconnect_min_delay = match param.1.parse::<u64>() {
+                        Ok(value) if value > 0 => value,
+                        _ => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "Invalid parameter: Reconnect Min Delay",
+                            ))
+                        }
+                    }
+                }
+                "reconnect_max_delay" => {
+                    reconnect_max_delay = match param.1.parse::<u64>() {
+                        Ok(value) if value > 0 => value,
+                        _ => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "Invalid parameter: Reconnect Max Delay",
+                            ))
+                        }
+                    }
+                }
+                _ => client_params.push(param.clone()),
+            }
+        }
+
+        if topics.is_empty() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Invalid parameter: Bridge without topics",
+            ));
+        }
+
+        let mut client = ClientConfig::set_params(&client_params)?;
+        client.sub_no_local = true;
+        client.connect_retry = false;
+
+        Ok(BridgeConfig {
+            client,
+            topics,
+            reconnect_min_delay,
+            reconnect_max_delay: reconnect_max_delay.max(reconnect_min_delay),
+        })
+    }
+}
@@ -21,6 +21,13 @@ use super::mqtt_config::{Config, MqttConfig};
 /// - `sub_no_local`: flag de no local
 /// - `sub_retain_as_published`: flag de retencion de publicacion
 /// - `sub_retain_handling`: manejo de retencion de publicacion
+/// - `will_delay_interval`: segundos que el servidor demora la publicacion del mensaje de voluntad
+/// - `ca_path`: certificado de la CA con el que se valida al servidor (opcional)
+/// - `identity_path`: identidad del cliente (PKCS#12) que se presenta al servidor en el
+///   handshake TLS, si este exige certificados de clientes (opcional)
+/// - `identity_pass`: contraseña de la identidad del cliente
+/// - `connect_retry`: si se reintenta la conexion mientras el servidor no esta disponible
+///   (por defecto `true`)
 ///
 /// ### Implementa
 /// - `Clone`: clonar la configuracion
@@ -42,6 +49,13 @@ pub struct ClientConfig {
     // Will Message
     pub will_topic: Option<String>,
     pub will_payload: Option<Vec<u8>>,
+    pub will_delay_interval: Option<u32>,
+
+    // TLS
+    pub ca_path: Option<String>,
+    pub identity_path: Option<String>,
+    pub identity_pass: String,
+    pub connect_retry: bool,
 }
 
 impl Clone for ClientConfig {
@@ -59,6 +73,11 @@ impl Clone for ClientConfig {
             sub_retain_handling: self.sub_retain_handling,
             will_topic: self.will_topic.clone(),
             will_payload: self.will_payload.clone(),
+            will_delay_interval: self.will_delay_interval,
+            ca_path: self.ca_path.clone(),
+            identity_path: self.identity_path.clone(),
+            identity_pass: self.identity_pass.clone(),
+            connect_retry: self.connect_retry,
         }
     }
 }
@@ -82,6 +101,11 @@ impl Config for ClientConfig {
         let mut sub_no_local = false;
         let mut sub_retain_as_published = false;
         let mut sub_retain_handling = 0;
+        let mut will_delay_interval = None;
+        let mut ca_path = None;
+        let mut identity_path = None;
+        let mut identity_pass = String::new();
+        let mut connect_retry = true;
 
         for param in params.iter() {
             match param.0.as_str() {
@@ -321,6 +345,31 @@ impl Config for ClientConfig {
                         }
                     }
                 }
+                "will_delay_interval" => {
+                    will_delay_interval = match param.1.parse::<u32>() {
+                        Ok(p) => Some(p),
+                        Err(_) => {
+                            return Err(Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "Invalid parameter: Will Delay Interval",
+                            ))
+                        }
+                    }
+                }
+                "ca_path" => ca_path = Some(param.1.clone()),
+                "identity_path" => identity_path = Some(param.1.clone()),
+                "identity_pass" => identity_pass.clone_from(&param.1),
+                "connect_retry" => {
+                    connect_retry = match param.1.parse::<bool>() {
+                        Ok(p) => p,
+                        Err(_) => {
+                            return Err(Error::new(
+                                std::io::ErrorKind::InvalidData,
+                                "Invalid parameter: Connect Retry",
+                            ))
+                        }
+                    }
+                }
 
                 "id" | "password" | "ip" | "port" | "log_path" | "log_in_terminal"
                 | "domain_name" | "cert_path" | "cert_pass" => {}
@@ -347,6 +396,11 @@ impl Config for ClientConfig {
             sub_retain_handling,
             will_topic: None,
             will_payload: None,
+            will_delay_interval,
+            ca_path,
+            identity_path,
+            identity_pass,
+            connect_retry,
         })
     }
 }
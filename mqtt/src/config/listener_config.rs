@@ -0,0 +1,198 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+    net::SocketAddr,
+};
+
+/// Ruta en la que se aceptan conexiones WebSocket si no se configura otra
+pub const DEFAULT_WEBSOCKET_PATH: &str = "/mqtt";
+
+/// ## ListenerProtocol
+///
+/// Protocolo con el que un listener acepta conexiones
+///
+/// ### Variantes
+/// - `Tcp`: MQTT sobre TCP, sin cifrar (`tcp://`)
+/// - `Tls`: MQTT sobre TLS (`tls://`)
+/// - `WebSocket`: MQTT sobre WebSocket, sin cifrar (`ws://`)
+/// - `SecureWebSocket`: MQTT sobre WebSocket con TLS (`wss://`)
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ListenerProtocol {
+    Tcp,
+    Tls,
+    WebSocket,
+    SecureWebSocket,
+}
+
+impl ListenerProtocol {
+    pub fn from_scheme(scheme: &str) -> Option<Self> {
+        match scheme {
+            "tcp" | "mqtt" => Some(ListenerProtocol::Tcp),
+            "tls" | "mqtts" => Some(ListenerProtocol::Tls),
+            "ws" => Some(ListenerProtocol::WebSocket),
+            "wss" => Some(ListenerProtocol::SecureWebSocket),
+            _ => None,
+        }
+    }
+
+    pub fn scheme(&self) -> &str {
+        match self {
+            ListenerProtocol::Tcp => "tcp",
+            ListenerProtocol::Tls => "tls",
+            ListenerProtocol::WebSocket => "ws",
+            ListenerProtocol::SecureWebSocket => "wss",
+        }
+    }
+
+    /// ### uses_tls
+    ///
+    /// Indica si las conexiones realizan un handshake TLS
+    ///
+    pub fn uses_tls(&self) -> bool {
+        matches!(
+            self,
+            ListenerProtocol::Tls | ListenerProtocol::SecureWebSocket
+        )
+    }
+
+    /// ### uses_websocket
+    ///
+    /// Indica si las conexiones realizan un handshake WebSocket
+    ///
+    pub fn uses_websocket(&self) -> bool {
+        matches!(
+            self,
+            ListenerProtocol::WebSocket | ListenerProtocol::SecureWebSocket
+        )
+    }
+}
+
+/// ## ListenerConfig
+///
+/// Configuracion de uno de los listeners en los que el servidor acepta conexiones
+///
+/// ### Atributos
+/// - `protocol`: protocolo de las conexiones
+/// - `address`: direccion en la que escucha
+/// - `websocket_path`: ruta del handshake WebSocket (solo `ws` y `wss`)
+/// - `cert_path`: identidad TLS (PKCS#12) del listener. Si no se indica, se usa la del servidor
+/// - `cert_pass`: contraseña de la identidad TLS del listener
+/// - `client_ca_path`: CA de los certificados de los clientes. Si no se indica, se usa la del servidor
+/// - `allow_anonymous`: si se aceptan conexiones sin autenticación. Si no se indica,
+///   se usa el `allow_anonymous` del servidor
+///
+/// ### Consideraciones
+/// - Se configura con una URL: `<protocolo>://<ip>:<puerto>[<ruta>][?<opcion>=<valor>&...]`,
+///   con las opciones `cert_path`, `cert_pass`, `client_ca_path` y `allow_anonymous`.
+///   Por ejemplo `tls://0.0.0.0:8883?allow_anonymous=false` o `ws://0.0.0.0:8080/mqtt`
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct ListenerConfig {
+    pub protocol: ListenerProtocol,
+    pub address: SocketAddr,
+    pub websocket_path: String,
+    pub cert_path: Option<String>,
+    pub cert_pass: Option<String>,
+    pub client_ca_path: Option<String>,
+    pub allow_anonymous: Option<bool>,
+}
+
+impl ListenerConfig {
+    pub fn new(protocol: ListenerProtocol, address: SocketAddr) -> Self {
+        ListenerConfig {
+            protocol,
+            address,
+            websocket_path: DEFAULT_WEBSOCKET_PATH.to_string(),
+            cert_path: None,
+            cert_pass: None,
+            client_ca_path: None,
+            allow_anonymous: None,
+        }
+    }
+
+    pub fn with_websocket_path(mut self, websocket_path: &str) -> Self {
+        self.websocket_path = websocket_path.to_string();
+        self
+    }
+
+    /// ### from_url
+    ///
+    /// Lee la configuracion de un listener desde su URL
+    ///
+    /// ### Parametros
+    /// - `url`: URL del listener (ej. `tls://0.0.0.0:8883?allow_anonymous=false`)
+    ///
+    pub fn from_url(url: &str) -> Result<Self, Error> {
+        let invalid = |reason: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid parameter: Listener '{}' ({})", url, reason),
+            )
+        };
+
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| invalid("falta el protocolo"))?;
+        let protocol = ListenerProtocol::from_scheme(scheme)
+            .ok_or_else(|| invalid("protocolo desconocido"))?;
+        let (location, options) = match rest.split_once('?') {
+            Some((location, options)) => (location, Some(options)),
+            None => (rest, None),
+        };
+        let (address, path) = match location.find('/') {
+            Some(index) => location.split_at(index),
+            None => (location, ""),
+        };
+        let address = address
+            .parse::<SocketAddr>()
+            .map_err(|_| invalid("direccion invalida"))?;
+
+        let mut listener = ListenerConfig::new(protocol, address);
+        if !path.is_empty() {
+            if !protocol.uses_websocket() {
+                return Err(invalid("solo los listeners WebSocket tienen ruta"));
+            }
+            listener.websocket_path = path.to_string();
+        }
+
+        for option in options.unwrap_or_default().split('&') {
+            if option.is_empty() {
+                continue;
+            }
+            let (name, value) = option
+                .split_once('=')
+                .ok_or_else(|| invalid("opcion sin valor"))?;
+            match name {
+                "cert_path" => listener.cert_path = Some(value.to_string()),
+                "cert_pass" => listener.cert_pass = Some(value.to_string()),
+                "client_ca_path" => listener.client_ca_path = Some(value.to_string()),
+                "allow_anonymous" => {
+                    let allow_anonymous = value
+                        .parse::<bool>()
+                        .map_err(|_| invalid("allow_anonymous invalido"))?;
+                    listener.allow_anonymous = Some(allow_anonymous);
+                }
+                _ => return Err(invalid("opcion desconocida")),
+            }
+        }
+
+        let has_tls_options = listener.cert_path.is_some()
+            || listener.cert_pass.is_some()
+            || listener.client_ca_path.is_some();
+        if has_tls_options && !protocol.uses_tls() {
+            return Err(invalid("opciones TLS en un listener sin TLS"));
+        }
+        Ok(listener)
+    }
+}
+
+impl fmt::Display for ListenerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}://{}", self.protocol.scheme(), self.address)?;
+        if self.protocol.uses_websocket() {
+            write!(f, "{}", self.websocket_path)?;
+        }
+        Ok(())
+    }
+}
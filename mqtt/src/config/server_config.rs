@@ -1,7 +1,17 @@
 use std::{io::Error, net::SocketAddr};
 
+use crate::server::queued_message::QueueOverflowPolicy;
+
+use super::bridge_config::BridgeConfig;
+use super::listener_config::{ListenerConfig, ListenerProtocol, DEFAULT_WEBSOCKET_PATH};
 use super::mqtt_config::{Config, MqttConfig};
 
+/// Intervalo (en segundos) con el que se publican los topicos `$SYS` si no se configura otro
+const DEFAULT_SYS_INTERVAL: u64 = 10;
+
+/// Plazo (en segundos) para apagar el servidor si no se configura otro
+const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 5;
+
 /// ## ServerConfig
 ///
 /// Estructura que representa la configuracion
@@ -10,6 +20,35 @@ use super::mqtt_config::{Config, MqttConfig};
 /// ### Atributos
 /// - `general`: configuracion general del servidor
 /// - `maximum_threads`: cantidad maxima de threads
+/// - `db_path`: archivo donde se persisten las sesiones
+/// - `keep_alive`: Server Keep Alive, reemplaza al keep alive pedido por los clientes
+/// - `maximum_session_expiry_interval`: Session Expiry Interval maximo que acepta el servidor
+/// - `topic_alias_maximum`: cantidad de Topic Alias que acepta el servidor en cada conexion
+/// - `receive_maximum`: publicaciones QoS 1 y 2 sin confirmar que acepta el servidor de cada cliente
+/// - `maximum_packet_size`: tamaño maximo (en bytes) de los paquetes que acepta el servidor
+/// - `max_queued_messages`: cantidad maxima de mensajes en la cola de cada sesión
+/// - `queue_overflow_policy`: mensaje que se descarta con la cola llena (`drop_oldest` o `reject`)
+/// - `allow_anonymous`: si se aceptan conexiones sin Authentication Method (por defecto `true`)
+/// - `credentials_path`: archivo con las credenciales de los usuarios, que se recarga al modificarse
+/// - `acl_path`: archivo con la lista de control de acceso a los topicos (sin ACL se permite todo)
+/// - `client_ca_path`: certificados de las CA que firman los certificados de los clientes.
+///   Si se indica, los clientes deben presentar un certificado en el handshake TLS
+/// - `sys_interval`: segundos entre cada publicacion de las estadisticas en los topicos `$SYS`
+///   (por defecto 10, con 0 no se publican)
+/// - `admin_socket_path`: socket Unix en el que el servidor recibe comandos de administración
+///   (sin socket no se reciben comandos)
+/// - `shutdown_timeout`: segundos que tiene el servidor para desconectar a los clientes y
+///   persistir su estado al apagarse (por defecto 5)
+/// - `websocket_port`: puerto en el que se aceptan conexiones MQTT sobre WebSocket
+///   (sin puerto no se aceptan)
+/// - `websocket_path`: ruta del handshake WebSocket (por defecto `/mqtt`)
+/// - `websocket_tls`: si las conexiones WebSocket usan TLS, con el mismo certificado
+///   que el listener principal (por defecto `false`)
+/// - `listeners`: listeners en los que se aceptan conexiones, uno por cada parametro
+///   `listener` (ver `ListenerConfig`). Si no se indica ninguno, se escucha con TLS en
+///   `ip`/`port` y, si se indica `websocket_port`, con WebSocket en ese puerto
+/// - `bridges`: puentes con servidores remotos, uno por cada parametro `bridge` con la
+///   ruta del archivo de configuracion del puente (ver `BridgeConfig`)
 ///
 /// ### Implementa
 /// - `Clone`: clonar la configuracion
@@ -18,6 +57,25 @@ use super::mqtt_config::{Config, MqttConfig};
 pub struct ServerConfig {
     pub general: MqttConfig,
     pub db_path: Option<String>,
+    pub keep_alive: Option<u16>,
+    pub maximum_session_expiry_interval: Option<u32>,
+    pub topic_alias_maximum: Option<u16>,
+    pub receive_maximum: Option<u16>,
+    pub maximum_packet_size: Option<u32>,
+    pub max_queued_messages: Option<usize>,
+    pub queue_overflow_policy: QueueOverflowPolicy,
+    pub allow_anonymous: bool,
+    pub credentials_path: Option<String>,
+    pub acl_path: Option<String>,
+    pub client_ca_path: Option<String>,
+    pub sys_interval: u64,
+    pub admin_socket_path: Option<String>,
+    pub shutdown_timeout: u64,
+    pub websocket_port: Option<u16>,
+    pub websocket_path: String,
+    pub websocket_tls: bool,
+    pub listeners: Vec<ListenerConfig>,
+    pub bridges: Vec<BridgeConfig>,
 }
 
 impl Clone for ServerConfig {
@@ -25,10 +83,40 @@ impl Clone for ServerConfig {
         ServerConfig {
             general: self.general.clone(),
             db_path: self.db_path.clone(),
+            keep_alive: self.keep_alive,
+            maximum_session_expiry_interval: self.maximum_session_expiry_interval,
+            topic_alias_maximum: self.topic_alias_maximum,
+            receive_maximum: self.receive_maximum,
+            maximum_packet_size: self.maximum_packet_size,
+            max_queued_messages: self.max_queued_messages,
+            queue_overflow_policy: self.queue_overflow_policy,
+            allow_anonymous: self.allow_anonymous,
+            credentials_path: self.credentials_path.clone(),
+            acl_path: self.acl_path.clone(),
+            client_ca_path: self.client_ca_path.clone(),
+            sys_interval: self.sys_interval,
+            admin_socket_path: self.admin_socket_path.clone(),
+            shutdown_timeout: self.shutdown_timeout,
+            websocket_port: self.websocket_port,
+            websocket_path: self.websocket_path.clone(),
+            websocket_tls: self.websocket_tls,
+            listeners: self.listeners.clone(),
+            bridges: self.bridges.clone(),
         }
     }
 }
 
+impl ServerConfig {
+    /// ### get_websocket_address
+    ///
+    /// Direccion en la que se aceptan conexiones WebSocket, si se configuro un puerto
+    ///
+    pub fn get_websocket_address(&self) -> Option<SocketAddr> {
+        self.websocket_port
+            .map(|port| SocketAddr::new(self.general.ip, port))
+    }
+}
+
 impl Config for ServerConfig {
     fn get_socket_address(&self) -> SocketAddr {
         self.general.get_socket_address()
@@ -50,6 +138,279 @@ impl Config for ServerConfig {
             }
         }
 
-        Ok(ServerConfig { general, db_path })
+        let mut keep_alive = None;
+        if let Some(param) = params.iter().find(|param| param.0.as_str() == "keep_alive") {
+            keep_alive = match param.1.parse::<u16>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Keep Alive",
+                    ))
+                }
+            };
+        }
+
+        let mut maximum_session_expiry_interval = None;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "maximum_session_expiry_interval")
+        {
+            maximum_session_expiry_interval = match param.1.parse::<u32>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Maximum Session Expiry Interval",
+                    ))
+                }
+            };
+        }
+
+        let mut topic_alias_maximum = None;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "topic_alias_maximum")
+        {
+            topic_alias_maximum = match param.1.parse::<u16>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Topic Alias Maximum",
+                    ))
+                }
+            };
+        }
+
+        let mut receive_maximum = None;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "receive_maximum")
+        {
+            receive_maximum = match param.1.parse::<u16>() {
+                Ok(value) if value > 0 => Some(value),
+                _ => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Receive Maximum",
+                    ))
+                }
+            };
+        }
+
+        let mut maximum_packet_size = None;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "maximum_packet_size")
+        {
+            maximum_packet_size = match param.1.parse::<u32>() {
+                Ok(value) if value > 0 => Some(value),
+                _ => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Maximum Packet Size",
+                    ))
+                }
+            };
+        }
+
+        let mut max_queued_messages = None;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "max_queued_messages")
+        {
+            max_queued_messages = match param.1.parse::<usize>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Max Queued Messages",
+                    ))
+                }
+            };
+        }
+
+        let mut queue_overflow_policy = QueueOverflowPolicy::default();
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "queue_overflow_policy")
+        {
+            queue_overflow_policy = match QueueOverflowPolicy::from_name(&param.1) {
+                Some(policy) => policy,
+                None => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Queue Overflow Policy",
+                    ))
+                }
+            };
+        }
+
+        let mut allow_anonymous = true;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "allow_anonymous")
+        {
+            allow_anonymous = match param.1.parse::<bool>() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Allow Anonymous",
+                    ))
+                }
+            };
+        }
+
+        let credentials_path = params
+            .iter()
+            .find(|param| param.0.as_str() == "credentials_path")
+            .map(|param| param.1.clone());
+
+        let acl_path = params
+            .iter()
+            .find(|param| param.0.as_str() == "acl_path")
+            .map(|param| param.1.clone());
+
+        let client_ca_path = params
+            .iter()
+            .find(|param| param.0.as_str() == "client_ca_path")
+            .map(|param| param.1.clone());
+
+        let admin_socket_path = params
+            .iter()
+            .find(|param| param.0.as_str() == "admin_socket_path")
+            .map(|param| param.1.clone());
+
+        let mut sys_interval = DEFAULT_SYS_INTERVAL;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "sys_interval")
+        {
+            sys_interval = match param.1.parse::<u64>() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: SYS Interval",
+                    ))
+                }
+            };
+        }
+
+        let mut shutdown_timeout = DEFAULT_SHUTDOWN_TIMEOUT;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "shutdown_timeout")
+        {
+            shutdown_timeout = match param.1.parse::<u64>() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: Shutdown Timeout",
+                    ))
+                }
+            };
+        }
+
+        let mut websocket_port = None;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "websocket_port")
+        {
+            websocket_port = match param.1.parse::<u16>() {
+                Ok(value) => Some(value),
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: WebSocket Port",
+                    ))
+                }
+            };
+        }
+
+        let mut websocket_path = DEFAULT_WEBSOCKET_PATH.to_string();
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "websocket_path")
+        {
+            if !param.1.starts_with('/') {
+                return Err(Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "Invalid parameter: WebSocket Path",
+                ));
+            }
+            websocket_path = param.1.clone();
+        }
+
+        let mut websocket_tls = false;
+        if let Some(param) = params
+            .iter()
+            .find(|param| param.0.as_str() == "websocket_tls")
+        {
+            websocket_tls = match param.1.parse::<bool>() {
+                Ok(value) => value,
+                Err(_) => {
+                    return Err(Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        "Invalid parameter: WebSocket TLS",
+                    ))
+                }
+            };
+        }
+
+        let mut listeners = params
+            .iter()
+            .filter(|param| param.0.as_str() == "listener")
+            .map(|param| ListenerConfig::from_url(&param.1))
+            .collect::<Result<Vec<ListenerConfig>, Error>>()?;
+        if listeners.is_empty() {
+            listeners.push(ListenerConfig::new(
+                ListenerProtocol::Tls,
+                general.get_socket_address(),
+            ));
+            if let Some(port) = websocket_port {
+                let protocol = match websocket_tls {
+                    true => ListenerProtocol::SecureWebSocket,
+                    false => ListenerProtocol::WebSocket,
+                };
+                listeners.push(
+                    ListenerConfig::new(protocol, SocketAddr::new(general.ip, port))
+                        .with_websocket_path(&websocket_path),
+                );
+            }
+        }
+
+        let bridges = params
+            .iter()
+            .filter(|param| param.0.as_str() == "bridge")
+            .map(|param| BridgeConfig::from_file(param.1.clone()))
+            .collect::<Result<Vec<BridgeConfig>, Error>>()?;
+
+        Ok(ServerConfig {
+            general,
+            db_path,
+            keep_alive,
+            maximum_session_expiry_interval,
+            topic_alias_maximum,
+            receive_maximum,
+            maximum_packet_size,
+            max_queued_messages,
+            queue_overflow_policy,
+            allow_anonymous,
+            credentials_path,
+            acl_path,
+            client_ca_path,
+            sys_interval,
+            admin_socket_path,
+            shutdown_timeout,
+            websocket_port,
+            websocket_path,
+            websocket_tls,
+            listeners,
+            bridges,
+        })
     }
 }
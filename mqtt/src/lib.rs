@@ -1,18 +1,32 @@
 pub mod client {
+    pub mod client_authentication;
     pub mod client_connector;
+    pub mod client_inflight;
     pub mod client_listener;
     pub mod client_message;
+    pub mod client_requests;
     pub mod mqtt_client;
 }
 
 pub mod server {
     pub mod acknowledge_handler;
+    pub mod inflight_message;
     pub mod mqtt_server;
+    pub mod queued_message;
+    pub mod retained_messages;
+    pub mod server_acl;
+    pub mod server_admin;
+    pub mod server_authentication;
+    pub mod server_bridge;
     pub mod server_connector;
+    pub mod server_credentials;
     pub mod server_handlers;
     pub mod server_network;
+    pub mod server_persistence;
     pub mod server_register;
     pub mod server_session;
+    pub mod server_shutdown;
+    pub mod server_stats;
     pub mod will_message;
 }
 
@@ -23,7 +37,9 @@ pub mod logging {
 }
 
 pub mod config {
+    pub mod bridge_config;
     pub mod client_config;
+    pub mod listener_config;
     pub mod mqtt_config;
     pub mod server_config;
 }
@@ -41,7 +57,10 @@ pub mod mqtt_packets {
         pub mod pingreq;
         pub mod pingresp;
         pub mod puback;
+        pub mod pubcomp;
         pub mod publish;
+        pub mod pubrec;
+        pub mod pubrel;
         pub mod suback;
         pub mod subscribe;
         pub mod unsuback;
@@ -54,7 +73,10 @@ pub mod mqtt_packets {
         pub mod connect_properties;
         pub mod disconnect_properties;
         pub mod puback_properties;
+        pub mod pubcomp_properties;
         pub mod publish_properties;
+        pub mod pubrec_properties;
+        pub mod pubrel_properties;
         pub mod suback_properties;
         pub mod subscribe_properties;
         pub mod unsuback_properties;
@@ -67,10 +89,14 @@ pub mod mqtt_packets {
 }
 
 pub mod common {
-    pub mod authentication;
     pub mod data_types;
     pub mod flags;
+    pub mod flow_control;
+    pub mod mqtt_stream;
     pub mod reason_codes;
+    pub mod scram;
+    pub mod topic_alias;
     pub mod topic_filter;
     pub mod utils;
+    pub mod websocket;
 }
@@ -13,6 +13,8 @@ use super::actions::MqttActions;
 ///
 /// ### Variantes
 /// - `Connection`: Conexion establecida
+/// - `SendAuthentication`: Cliente envia un AUTH para autenticarse
+/// - `Reauthenticated`: Cliente terminó una re-autenticación con éxito
 /// - `ReceivePublish`: Cliente recibe un mensaje
 /// - `ReceiveDisconnect`: Cliente recibe un mensaje de desconexión
 /// - `ReceivePinresp`: Cliente recibe respuesta de ping
@@ -25,10 +27,23 @@ use super::actions::MqttActions;
 /// - `AcknowledgePublish`: Cliente recibe confirmación de envio de mensaje
 /// - `AcknowledgeSubscribe`: Cliente recibe confirmación de subscripción
 /// - `AcknowledgeUnsubscribe`: Cliente recibe confirmación de desubscripción
+/// - `AcknowledgePubrec`: Cliente recibe confirmación de recepción de una publicación QoS 2
+/// - `SendPubrel`: Cliente libera una publicación QoS 2
+/// - `AcknowledgePubcomp`: Cliente recibe confirmación final de una publicación QoS 2
+/// - `SendPubrec`: Cliente confirma la recepción de una publicación QoS 2
+/// - `SendPubcomp`: Cliente completa una publicación QoS 2 recibida
+/// - `DiscardDuplicatePublish`: Cliente descarta una publicación QoS 2 duplicada
+/// - `SendPuback`: Cliente confirma la recepción de una publicación QoS 1
+/// - `PingrespNotReceived`: Cliente no recibió respuesta de ping y cierra la conexión
+/// - `SendRequest`: Cliente envia una solicitud (request/response)
+/// - `ReceiveResponse`: Cliente recibe la respuesta de una solicitud
+/// - `ResponseNotReceived`: Cliente no recibió la respuesta de una solicitud a tiempo
+/// - `SendResponse`: Cliente responde una solicitud
 ///
 pub enum MqttClientActions {
     Connection(String, u8),
     SendAuthentication(String),
+    Reauthenticated(String),
     ReceivePublish(String),
     ReceiveDisconnect(ReasonCode),
     ReceivePinresp,
@@ -43,6 +58,18 @@ pub enum MqttClientActions {
     AcknowledgeSubscribe(String, Vec<u8>),
     AcknowledgeUnsubscribe(String, Vec<u8>),
     AcknowledgeNotReceived,
+    AcknowledgePubrec(u16, u8),
+    SendPubrel(u16),
+    AcknowledgePubcomp(u16, u8),
+    SendPubrec(u16),
+    SendPubcomp(u16),
+    DiscardDuplicatePublish(String, u16),
+    SendPuback(u16),
+    PingrespNotReceived,
+    SendRequest(String),
+    ReceiveResponse(String),
+    ResponseNotReceived(String),
+    SendResponse(String),
 }
 
 impl fmt::Display for MqttClientActions {
@@ -59,6 +86,9 @@ impl fmt::Display for MqttClientActions {
             MqttClientActions::SendAuthentication(user) => {
                 write!(f, "AUTH - Cliente intenta autenticarse como '{}'", user)
             }
+            MqttClientActions::Reauthenticated(user) => {
+                write!(f, "AUTH - Cliente se re-autenticó como '{}'", user)
+            }
             MqttClientActions::ReceivePublish(topic) => write!(
                 f,
                 "PUBLISH - Cliente recibió un mensaje proveniente del topic: '{}'",
@@ -163,6 +193,83 @@ impl fmt::Display for MqttClientActions {
                 write!(f, "PINGRESP - Cliente recibió respuesta de ping")
             }
             MqttClientActions::SendPinreq => write!(f, "PINGREQ - Cliente envió ping"),
+            MqttClientActions::AcknowledgePubrec(packet_id, code) => {
+                let reason_code = ReasonCode::new(*code);
+                write!(
+                    f,
+                    "PUBREC - Cliente recibió confirmación de recepción de la publicación {} - reason code: [{}]",
+                    packet_id, reason_code
+                )
+            }
+            MqttClientActions::SendPubrel(packet_id) => {
+                write!(f, "PUBREL - Cliente liberó la publicación {}", packet_id)
+            }
+            MqttClientActions::AcknowledgePubcomp(packet_id, code) => {
+                let reason_code = ReasonCode::new(*code);
+                write!(
+                    f,
+                    "PUBCOMP - Cliente recibió confirmación final de la publicación {} - reason code: [{}]",
+                    packet_id, reason_code
+                )
+            }
+            MqttClientActions::SendPubrec(packet_id) => {
+                write!(
+                    f,
+                    "PUBREC - Cliente confirmó la recepción de la publicación {}",
+                    packet_id
+                )
+            }
+            MqttClientActions::SendPubcomp(packet_id) => {
+                write!(f, "PUBCOMP - Cliente completó la publicación {}", packet_id)
+            }
+            MqttClientActions::DiscardDuplicatePublish(topic, packet_id) => {
+                write!(
+                    f,
+                    "PUBLISH - Cliente descartó la publicación duplicada {} del topico '{}'",
+                    packet_id, topic
+                )
+            }
+            MqttClientActions::SendPuback(packet_id) => {
+                write!(
+                    f,
+                    "PUBACK - Cliente confirmó la recepción de la publicación {}",
+                    packet_id
+                )
+            }
+            MqttClientActions::PingrespNotReceived => {
+                write!(
+                    f,
+                    "PINGRESP - Cliente no recibió respuesta de ping, cerrando la conexión"
+                )
+            }
+            MqttClientActions::SendRequest(topic) => {
+                write!(
+                    f,
+                    "PUBLISH - Cliente envió una solicitud al topico '{}'",
+                    topic
+                )
+            }
+            MqttClientActions::ReceiveResponse(topic) => {
+                write!(
+                    f,
+                    "PUBLISH - Cliente recibió la respuesta a su solicitud al topico '{}'",
+                    topic
+                )
+            }
+            MqttClientActions::ResponseNotReceived(topic) => {
+                write!(
+                    f,
+                    "PUBLISH - Cliente no recibió a tiempo la respuesta a su solicitud al topico '{}'",
+                    topic
+                )
+            }
+            MqttClientActions::SendResponse(topic) => {
+                write!(
+                    f,
+                    "PUBLISH - Cliente respondió una solicitud en el topico '{}'",
+                    topic
+                )
+            }
         }
     }
 }
@@ -3,6 +3,7 @@ use std::fmt;
 use crate::{
     common::{reason_codes::ReasonCode, topic_filter::TopicFilter},
     logging::actions::add_topics_names,
+    server::queued_message::QueueOverflowPolicy,
 };
 
 use super::actions::MqttActions;
@@ -13,6 +14,11 @@ use super::actions::MqttActions;
 ///
 /// ### Variantes
 /// - `Connection`: Conexion establecida
+/// - `ValidAuthentication`: Un cliente se autenticó como un usuario
+/// - `InvalidAuthentication`: Un cliente no pudo autenticarse
+/// - `ContinueAuthentication`: Servidor continua el intercambio de autenticación de un cliente
+/// - `SendAuth`: Servidor envia un AUTH a un cliente
+/// - `RefuseConnection`: Servidor rechaza la conexión de un cliente en el CONNACK
 /// - `ReceivePublish`: Servidor recibe un mensaje
 /// - `ReceiveSubscribe`: Servidor recibe subscripción
 /// - `ReceiveUnsubscribe`: Servidor recibe desubscripción
@@ -25,11 +31,52 @@ use super::actions::MqttActions;
 /// - `SendSuback`: Servidor envia confirmación de subscripción
 /// - `SendUnsuback`: Servidor envia confirmación de desubscripción
 /// - `CloseServer`: Servidor apaga
+/// - `RetainMessage`: Servidor retiene el mensaje de un topico
+/// - `ClearRetainedMessage`: Servidor elimina el mensaje retenido de un topico
+/// - `SendRetainedMessages`: Servidor envia mensajes retenidos a un cliente
+/// - `SendPubrec`: Servidor confirma la recepción de una publicación QoS 2
+/// - `DiscardDuplicatePublish`: Servidor descarta una publicación QoS 2 duplicada
+/// - `SendPubrel`: Servidor libera una publicación QoS 2 enviada a un cliente
+/// - `SendPubcomp`: Servidor completa una publicación QoS 2 recibida de un cliente
+/// - `ReceivePuback`: Servidor recibe la confirmación de una publicación QoS 1
+/// - `ReceivePubcomp`: Servidor recibe la confirmación final de una publicación QoS 2
+/// - `ResendInflightMessage`: Servidor reenvía una publicación QoS 2 sin confirmar
+/// - `CloseInactiveConnection`: Servidor cierra la conexión de un cliente que superó su keep alive
+/// - `CloseProtocolError`: Servidor cierra la conexión de un cliente que no respetó el protocolo
+/// - `CloseUnauthenticated`: Servidor cierra la conexión de un cliente que no pudo re-autenticarse
+/// - `ConnectionLost`: Se perdió la conexión con un cliente sin recibir su DISCONNECT
+/// - `SessionExpired`: La sesión de un cliente superó su Session Expiry Interval
+/// - `DelayWillMessage`: Servidor demora la publicación del mensaje de voluntad de un cliente
+/// - `DiscardWillMessage`: Servidor descarta el mensaje de voluntad de un cliente
+/// - `RedispatchSharedMessage`: Servidor redistribuye una publicación de una subscripción
+///   compartida que un cliente desconectado no recibió
+/// - `ReloadCredentials`: Servidor recarga el archivo de credenciales de los usuarios
+/// - `InvalidCredentials`: Servidor no pudo recargar el archivo de credenciales
+/// - `PublishNotAuthorized`: Servidor descarta una publicación que la ACL no permite al cliente
+/// - `SubscribeNotAuthorized`: Servidor rechaza subscripciones que la ACL no permite al cliente
+/// - `UnsubscribeNotAuthorized`: Servidor rechaza desubscripciones de la sesión de otro cliente
+/// - `PublishStats`: Servidor publica sus estadisticas en los topicos `$SYS`
+/// - `AdminCommand`: Servidor ejecuta un comando de administración
+/// - `ForceDisconnect`: Servidor desconecta a un cliente por pedido de un administrador
+/// - `StartShutdown`: Servidor deja de aceptar conexiones para apagarse
+/// - `ShutdownTimeout`: Servidor no termina de apagarse dentro del plazo configurado
+/// - `RecoverCorruptDatabase`: Servidor aparta un archivo persistido corrupto y continua sin su contenido
+/// - `PersistenceFailed`: Servidor no pudo persistir sus sesiones o mensajes retenidos
+/// - `StartListener`: Servidor comienza a aceptar conexiones en uno de sus listeners
+/// - `BridgeConnected`: Un puente se conecta al servidor remoto
+/// - `BridgeConnectionFailed`: Un puente no pudo conectarse al servidor remoto y reintentará
+/// - `BridgeDisconnected`: Un puente pierde la conexión con el servidor remoto
+/// - `BridgeDiscardMessages`: Un puente descarta los mensajes publicados mientras estaba desconectado
+/// - `BridgeForward`: Un puente reenvia al servidor remoto un mensaje publicado localmente
+/// - `BridgeReceive`: Un puente publica localmente un mensaje del servidor remoto
 ///
 pub enum MqttServerActions {
     Connection(String),
     ValidAuthentication(String),
     InvalidAuthentication(String),
+    ContinueAuthentication(String),
+    SendAuth(String, ReasonCode),
+    RefuseConnection(String, ReasonCode),
     SendDisconnect(ReasonCode),
     SendWillMessage(String, Vec<String>),
     NoSendWillMessage(),
@@ -51,6 +98,46 @@ pub enum MqttServerActions {
     DisconnectSession(String),
     SendToQueueSession(String),
     SendPendingMessage(String),
+    RetainMessage(String),
+    ClearRetainedMessage(String),
+    SendRetainedMessages(String, Vec<String>),
+    SendPubrec(String, u16),
+    DiscardDuplicatePublish(String, u16),
+    SendPubrel(String, u16),
+    SendPubcomp(String, u16),
+    ReceivePuback(String, u16),
+    ReceivePubcomp(String, u16),
+    ResendInflightMessage(String, u16),
+    CloseInactiveConnection(String),
+    CloseProtocolError(String),
+    CloseUnauthenticated(String),
+    ConnectionLost(String),
+    SessionExpired(String),
+    DelayWillMessage(String, u32),
+    DiscardWillMessage(String),
+    RedispatchSharedMessage(String, String),
+    DiscardExpiredMessage(String, String),
+    QueueFull(String, String, QueueOverflowPolicy),
+    DiscardPacketTooLarge(String, String),
+    ReloadCredentials(usize),
+    InvalidCredentials(String),
+    PublishNotAuthorized(String, String),
+    SubscribeNotAuthorized(String, Vec<String>),
+    UnsubscribeNotAuthorized(String, Vec<String>),
+    PublishStats(usize, Vec<String>),
+    AdminCommand(String),
+    ForceDisconnect(String, ReasonCode),
+    StartShutdown(u64),
+    ShutdownTimeout(u64),
+    RecoverCorruptDatabase(String),
+    PersistenceFailed(String),
+    StartListener(String),
+    BridgeConnected(String),
+    BridgeConnectionFailed(String, String, u64),
+    BridgeDisconnected(String),
+    BridgeDiscardMessages(String, usize),
+    BridgeForward(String, String),
+    BridgeReceive(String, String, Vec<String>),
 }
 
 impl fmt::Display for MqttServerActions {
@@ -73,6 +160,23 @@ impl fmt::Display for MqttServerActions {
                     user
                 )
             }
+            MqttServerActions::ContinueAuthentication(id) => {
+                write!(f, "AUTH - Servidor continua la autenticación de '{}'", id)
+            }
+            MqttServerActions::SendAuth(id, reason_code) => {
+                write!(
+                    f,
+                    "AUTH - Servidor envió AUTH a '{}' - reason code: [{}]",
+                    id, reason_code
+                )
+            }
+            MqttServerActions::RefuseConnection(id, reason_code) => {
+                write!(
+                    f,
+                    "CONNACK - Servidor rechaza la conexion de '{}' - reason code: [{}]",
+                    id, reason_code
+                )
+            }
 
             MqttServerActions::ReceivePublish(topic) => {
                 write!(
@@ -194,6 +298,275 @@ impl fmt::Display for MqttServerActions {
                     id
                 )
             }
+            MqttServerActions::RetainMessage(topic) => {
+                write!(
+                    f,
+                    "RETAIN - Servidor retiene mensaje del topico '{}'",
+                    topic
+                )
+            }
+            MqttServerActions::ClearRetainedMessage(topic) => {
+                write!(
+                    f,
+                    "RETAIN - Servidor elimina mensaje retenido del topico '{}'",
+                    topic
+                )
+            }
+            MqttServerActions::SendRetainedMessages(id, topics) => {
+                write!(
+                    f,
+                    "RETAIN - Servidor envió mensajes retenidos al cliente '{}' de los topicos {:?}",
+                    id, topics
+                )
+            }
+            MqttServerActions::SendPubrec(id, packet_id) => {
+                write!(
+                    f,
+                    "PUBREC - Servidor envió recepción de la publicación {} del cliente '{}'",
+                    packet_id, id
+                )
+            }
+            MqttServerActions::DiscardDuplicatePublish(id, packet_id) => {
+                write!(
+                    f,
+                    "PUBLISH - Servidor descarta la publicación duplicada {} del cliente '{}'",
+                    packet_id, id
+                )
+            }
+            MqttServerActions::SendPubrel(id, packet_id) => {
+                write!(
+                    f,
+                    "PUBREL - Servidor liberó la publicación {} enviada al cliente '{}'",
+                    packet_id, id
+                )
+            }
+            MqttServerActions::SendPubcomp(id, packet_id) => {
+                write!(
+                    f,
+                    "PUBCOMP - Servidor completó la publicación {} del cliente '{}'",
+                    packet_id, id
+                )
+            }
+            MqttServerActions::ReceivePuback(id, packet_id) => {
+                write!(
+                    f,
+                    "PUBACK - Cliente '{}' confirmó la publicación {}",
+                    id, packet_id
+                )
+            }
+            MqttServerActions::ReceivePubcomp(id, packet_id) => {
+                write!(
+                    f,
+                    "PUBCOMP - Cliente '{}' completó la publicación {}",
+                    id, packet_id
+                )
+            }
+            MqttServerActions::ResendInflightMessage(id, packet_id) => {
+                write!(
+                    f,
+                    "SESSION - Servidor reenvía la publicación sin confirmar {} a '{}'",
+                    packet_id, id
+                )
+            }
+            MqttServerActions::CloseInactiveConnection(id) => {
+                write!(
+                    f,
+                    "KEEP ALIVE - Servidor cierra la conexión de '{}' por inactividad",
+                    id
+                )
+            }
+            MqttServerActions::CloseProtocolError(id) => {
+                write!(
+                    f,
+                    "DISCONNECT - Servidor cierra la conexión de '{}' por un error de protocolo",
+                    id
+                )
+            }
+            MqttServerActions::CloseUnauthenticated(id) => {
+                write!(
+                    f,
+                    "DISCONNECT - Servidor cierra la conexión de '{}' por fallar su re-autenticación",
+                    id
+                )
+            }
+            MqttServerActions::ConnectionLost(id) => {
+                write!(f, "DISCONNECT - Se perdió la conexión con '{}'", id)
+            }
+            MqttServerActions::SessionExpired(id) => {
+                write!(f, "SESSION - La sesión de '{}' expiró", id)
+            }
+            MqttServerActions::DelayWillMessage(id, delay) => {
+                write!(
+                    f,
+                    "DISCONNECT - Servidor publicará el mensaje de voluntad de '{}' en {} segundos",
+                    id, delay
+                )
+            }
+            MqttServerActions::DiscardWillMessage(id) => {
+                write!(
+                    f,
+                    "DISCONNECT - Servidor descarta el mensaje de voluntad de '{}'",
+                    id
+                )
+            }
+            MqttServerActions::RedispatchSharedMessage(id, topic_filter) => {
+                write!(
+                    f,
+                    "PUBLISH - Servidor redistribuye en '{}' una publicación no recibida por '{}'",
+                    topic_filter, id
+                )
+            }
+            MqttServerActions::DiscardExpiredMessage(id, topic) => {
+                write!(
+                    f,
+                    "SESSION - Servidor descarta un mensaje vencido del topico '{}' en cola de '{}'",
+                    topic, id
+                )
+            }
+            MqttServerActions::DiscardPacketTooLarge(id, topic) => {
+                write!(
+                    f,
+                    "PUBLISH - Servidor descarta un mensaje del topico '{}' que supera el Maximum Packet Size de '{}'",
+                    topic, id
+                )
+            }
+            MqttServerActions::QueueFull(id, topic, policy) => {
+                let discarded = match policy {
+                    QueueOverflowPolicy::DropOldest => "el mensaje mas antiguo",
+                    QueueOverflowPolicy::Reject => "el mensaje nuevo",
+                };
+                write!(
+                    f,
+                    "SESSION - Cola de '{}' llena: servidor descarta {} del topico '{}'",
+                    id, discarded, topic
+                )
+            }
+            MqttServerActions::ReloadCredentials(users) => {
+                write!(
+                    f,
+                    "AUTH - Servidor recarga las credenciales de {} usuarios",
+                    users
+                )
+            }
+            MqttServerActions::InvalidCredentials(error) => {
+                write!(
+                    f,
+                    "AUTH - Servidor conserva las credenciales anteriores: {}",
+                    error
+                )
+            }
+            MqttServerActions::PublishNotAuthorized(id, topic) => {
+                write!(
+                    f,
+                    "PUBLISH - Servidor descarta la publicación de '{}' en el topico '{}': no autorizada",
+                    id, topic
+                )
+            }
+            MqttServerActions::SubscribeNotAuthorized(id, topic_filters) => {
+                write!(
+                    f,
+                    "SUBSCRIBE - Servidor rechaza las subscripciones no autorizadas de '{}' a los topicos: [ {} ]",
+                    id,
+                    topic_filters.join(" - ")
+                )
+            }
+            MqttServerActions::UnsubscribeNotAuthorized(id, topic_filters) => {
+                write!(
+                    f,
+                    "UNSUBSCRIBE - Servidor rechaza las desubscripciones de '{}' de otra sesión: [ {} ]",
+                    id,
+                    topic_filters.join(" - ")
+                )
+            }
+            MqttServerActions::PublishStats(topics, receivers) => {
+                write!(
+                    f,
+                    "SYS - Servidor publica {} estadisticas a los clientes: [ {} ]",
+                    topics,
+                    receivers.join(" - ")
+                )
+            }
+            MqttServerActions::AdminCommand(command) => {
+                write!(
+                    f,
+                    "ADMIN - Servidor ejecuta el comando de administración '{}'",
+                    command
+                )
+            }
+            MqttServerActions::ForceDisconnect(id, reason_code) => {
+                write!(
+                    f,
+                    "ADMIN - Servidor desconecta al cliente '{}' con reason code {}",
+                    id, reason_code
+                )
+            }
+            MqttServerActions::StartShutdown(timeout) => {
+                write!(
+                    f,
+                    "SHUTDOWN - Servidor deja de aceptar conexiones, tiene {} segundos para apagarse",
+                    timeout
+                )
+            }
+            MqttServerActions::ShutdownTimeout(timeout) => {
+                write!(
+                    f,
+                    "SHUTDOWN - Servidor no termino de apagarse en {} segundos",
+                    timeout
+                )
+            }
+            MqttServerActions::RecoverCorruptDatabase(error) => {
+                write!(f, "RECOVER - {}", error)
+            }
+            MqttServerActions::PersistenceFailed(error) => {
+                write!(f, "PERSIST - {}", error)
+            }
+            MqttServerActions::StartListener(listener) => {
+                write!(f, "LISTENER - Servidor acepta conexiones en {}", listener)
+            }
+            MqttServerActions::BridgeConnected(remote) => {
+                write!(
+                    f,
+                    "BRIDGE - Servidor conectado al servidor remoto {}",
+                    remote
+                )
+            }
+            MqttServerActions::BridgeConnectionFailed(remote, error, delay) => {
+                write!(
+                    f,
+                    "BRIDGE - Servidor no pudo conectarse al servidor remoto {} ({}), reintenta en {} segundos",
+                    remote, error, delay
+                )
+            }
+            MqttServerActions::BridgeDisconnected(remote) => {
+                write!(
+                    f,
+                    "BRIDGE - Servidor pierde la conexión con el servidor remoto {}",
+                    remote
+                )
+            }
+            MqttServerActions::BridgeDiscardMessages(remote, messages) => {
+                write!(
+                    f,
+                    "BRIDGE - Servidor descarta {} mensajes para el servidor remoto {}: no estaba conectado",
+                    messages, remote
+                )
+            }
+            MqttServerActions::BridgeForward(topic, remote) => {
+                write!(
+                    f,
+                    "BRIDGE - Servidor reenvia el mensaje del topico '{}' al servidor remoto {}",
+                    topic, remote
+                )
+            }
+            MqttServerActions::BridgeReceive(topic, remote, receivers) => {
+                write!(
+                    f,
+                    "BRIDGE - Servidor publica el mensaje del topico '{}' recibido de {} a los clientes: [ {} ]",
+                    topic,
+                    remote,
+                    receivers.join(" - ")
+                )
+            }
         }
     }
 }
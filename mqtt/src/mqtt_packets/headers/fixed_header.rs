@@ -12,6 +12,9 @@ pub const CONNECT_PACKET: u8 = 0x10;
 pub const CONNACK_PACKET: u8 = 0x20;
 pub const PUBLISH_PACKET: u8 = 0x30;
 pub const PUBACK_PACKET: u8 = 0x40;
+pub const PUBREC_PACKET: u8 = 0x50;
+pub const PUBREL_PACKET: u8 = 0x60;
+pub const PUBCOMP_PACKET: u8 = 0x70;
 pub const SUBSCRIBE_PACKET: u8 = 0x80;
 pub const SUBACK_PACKET: u8 = 0x90;
 pub const UNSUBSCRIBE_PACKET: u8 = 0xA0;
@@ -48,7 +51,10 @@ impl PacketFixedHeader {
     ///
     pub fn new(packet_type_header: u8, remaining_length: u32) -> Self {
         let mut packet_type = packet_type_header;
-        if packet_type == UNSUBSCRIBE_PACKET || packet_type == SUBSCRIBE_PACKET {
+        if packet_type == UNSUBSCRIBE_PACKET
+            || packet_type == SUBSCRIBE_PACKET
+            || packet_type == PUBREL_PACKET
+        {
             packet_type |= 1 << 1;
         }
 
@@ -115,8 +121,11 @@ impl PacketFixedHeader {
         match self.get_packet_type() {
             CONNECT_PACKET => PacketType::ConnectType,
             CONNACK_PACKET => PacketType::ConnackType,
-            PUBLISH_PACKET => PacketType::PublishType,
+            PUBLISH_PACKET => PacketType::PublishType(self.packet_type & 0x0F),
             PUBACK_PACKET => PacketType::PubackType,
+            PUBREC_PACKET => PacketType::PubrecType,
+            PUBREL_PACKET => PacketType::PubrelType,
+            PUBCOMP_PACKET => PacketType::PubcompType,
             SUBSCRIBE_PACKET => PacketType::SubscribeType,
             SUBACK_PACKET => PacketType::SubackType,
             UNSUBSCRIBE_PACKET => PacketType::Unsubscribe,
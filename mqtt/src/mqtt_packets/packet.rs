@@ -1,13 +1,14 @@
 pub mod generic_packet {
-    use std::{
-        io::{Error, Read, Write},
-        net::TcpStream,
-    };
+    use std::io::{Error, Read, Write};
 
-    use crate::mqtt_packets::packets::{
-        auth::Auth, connack::Connack, connect::Connect, disconnect::Disconnect, pingreq::PingReq,
-        pingresp::PingResp, puback::Puback, publish::Publish, suback::Suback, subscribe::Subscribe,
-        unsuback::Unsuback, unsubscribe::Unsubscribe,
+    use crate::{
+        common::mqtt_stream::MqttStream,
+        mqtt_packets::packets::{
+            auth::Auth, connack::Connack, connect::Connect, disconnect::Disconnect,
+            pingreq::PingReq, pingresp::PingResp, puback::Puback, pubcomp::Pubcomp,
+            publish::Publish, pubrec::Pubrec, pubrel::Pubrel, suback::Suback, subscribe::Subscribe,
+            unsuback::Unsuback, unsubscribe::Unsubscribe,
+        },
     };
 
     /// ## PacketType
@@ -15,11 +16,16 @@ pub mod generic_packet {
     /// Enum que representa los tipos de paquetes
     /// del protocolo MQTT
     ///
+    /// `PublishType` incluye los flags del fixed header (DUP, QoS y RETAIN)
+    ///
     pub enum PacketType {
         ConnectType,
         ConnackType,
-        PublishType,
+        PublishType(u8),
         PubackType,
+        PubrecType,
+        PubrelType,
+        PubcompType,
         SubscribeType,
         SubackType,
         Unsubscribe,
@@ -40,6 +46,9 @@ pub mod generic_packet {
         Connack(Box<Connack>),
         Publish(Box<Publish>),
         Puback(Box<Puback>),
+        Pubrec(Box<Pubrec>),
+        Pubrel(Box<Pubrel>),
+        Pubcomp(Box<Pubcomp>),
         Subscribe(Box<Subscribe>),
         Suback(Box<Suback>),
         Unsubscribe(Box<Unsubscribe>),
@@ -111,7 +120,10 @@ pub mod generic_packet {
 
         /// ## send
         ///
-        /// Envia el paquete a traves del stream
+        /// Envia el paquete a traves del stream.
+        /// El paquete se escribe en una unica operacion,
+        /// para que no se intercale con otros paquetes
+        /// enviados por la misma conexion
         ///
         /// ### Parametros
         /// - `stream`: stream de bytes
@@ -120,8 +132,10 @@ pub mod generic_packet {
         /// - `Result<(), Error>`:
         ///   - Ok: envio correcto
         ///   - Err: error de envio (std::io::Error)
-        fn send(&self, stream: &mut TcpStream) -> Result<(), Error> {
-            self.write_to(stream)?;
+        fn send(&self, stream: &mut MqttStream) -> Result<(), Error> {
+            let mut bytes = Vec::new();
+            self.write_to(&mut bytes)?;
+            stream.write_all(&bytes)?;
             Ok(())
         }
     }
@@ -148,8 +162,15 @@ pub mod generic_packet {
         match package_type {
             PacketType::ConnectType => pack_bytes::<Connect>(stream, remaining_length),
             PacketType::ConnackType => pack_bytes::<Connack>(stream, remaining_length),
-            PacketType::PublishType => pack_bytes::<Publish>(stream, remaining_length),
+            PacketType::PublishType(flags) => {
+                let mut publish = Publish::read_from(stream, remaining_length)?;
+                publish.fixed_header_flags = flags;
+                Ok(Publish::packed_package(publish))
+            }
             PacketType::PubackType => pack_bytes::<Puback>(stream, remaining_length),
+            PacketType::PubrecType => pack_bytes::<Pubrec>(stream, remaining_length),
+            PacketType::PubrelType => pack_bytes::<Pubrel>(stream, remaining_length),
+            PacketType::PubcompType => pack_bytes::<Pubcomp>(stream, remaining_length),
             PacketType::SubscribeType => pack_bytes::<Subscribe>(stream, remaining_length),
             PacketType::SubackType => pack_bytes::<Suback>(stream, remaining_length),
             PacketType::Unsubscribe => pack_bytes::<Unsubscribe>(stream, remaining_length),
@@ -97,7 +97,7 @@ mod test {
             authentication_method: Some("method".to_string()),
             authentication_data: Some(authentication_data),
             reason_string: Some("string".to_string()),
-            user_property: Some(("key".to_string(), "value".to_string())),
+            user_properties: vec![("key".to_string(), "value".to_string())],
         };
 
         let auth = Auth::new(properties);
@@ -137,7 +137,7 @@ mod test {
             panic!("Invalid property");
         }
 
-        if let Some(value) = props.user_property {
+        if let Some(value) = props.user_properties.first() {
             assert_eq!(value.0, "key".to_string());
             assert_eq!(value.1, "value".to_string());
         } else {
@@ -155,7 +155,7 @@ mod test {
             authentication_method: Some("passkey".to_string()),
             authentication_data: Some(authentication_data),
             reason_string: Some("reason str".to_string()),
-            user_property: Some(("newkey".to_string(), "newvalue".to_string())),
+            user_properties: vec![("newkey".to_string(), "newvalue".to_string())],
         };
 
         let auth = Auth::new(properties);
@@ -192,7 +192,7 @@ mod test {
             panic!("Invalid property");
         }
 
-        if let Some(value) = props.user_property {
+        if let Some(value) = props.user_properties.first() {
             assert_eq!(value.0, "newkey".to_string());
             assert_eq!(value.1, "newvalue".to_string());
         } else {
@@ -226,6 +226,6 @@ mod test {
         assert_eq!(props.authentication_method, None);
         assert_eq!(props.authentication_data, None);
         assert_eq!(props.reason_string, None);
-        assert_eq!(props.user_property, None);
+        assert!(props.user_properties.is_empty());
     }
 }
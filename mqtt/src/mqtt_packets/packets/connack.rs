@@ -150,7 +150,7 @@ mod test {
             wildcard_subscription_available: Some(0),
             subscription_identifiers_available: Some(0),
             shared_subscription_available: Some(0),
-            user_property: Some(("test_key".to_string(), "test_value".to_string())),
+            user_properties: vec![("test_key".to_string(), "test_value".to_string())],
             maximum_packet_size: Some(0),
         };
 
@@ -264,7 +264,7 @@ mod test {
             panic!("Invalid Shared Subscription Available");
         }
 
-        if let Some(value) = props.user_property {
+        if let Some(value) = props.user_properties.first() {
             assert_eq!(value.0, "test_key");
             assert_eq!(value.1, "test_value");
         } else {
@@ -319,7 +319,7 @@ mod test {
         assert_eq!(connack.properties.wildcard_subscription_available, None);
         assert_eq!(connack.properties.subscription_identifiers_available, None);
         assert_eq!(connack.properties.shared_subscription_available, None);
-        assert_eq!(connack.properties.user_property, None);
+        assert!(connack.properties.user_properties.is_empty());
         assert_eq!(connack.properties.maximum_packet_size, None);
     }
 }
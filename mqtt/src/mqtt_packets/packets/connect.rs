@@ -173,7 +173,7 @@ mod test {
             request_response_information: Some(0),
             receive_maximum: Some(0),
             topic_alias_maximum: Some(10),
-            user_property: Some(("test_key".to_string(), "test_value".to_string())),
+            user_properties: vec![("test_key".to_string(), "test_value".to_string())],
             maximum_packet_size: Some(20),
         };
 
@@ -192,7 +192,7 @@ mod test {
             content_type: Some("content".to_string()),
             response_topic: Some("response".to_string()),
             correlation_data: Some(correlation_data),
-            user_property: Some(("key".to_string(), "value".to_string())),
+            user_properties: vec![("key".to_string(), "value".to_string())],
 
             will_topic: Some("topic".to_string()),
             will_payload: Some(will_message),
@@ -292,7 +292,7 @@ mod test {
             panic!("Invalid property");
         }
 
-        if let Some(value) = props.user_property {
+        if let Some(value) = props.user_properties.first() {
             assert_eq!(value.0, "test_key".to_string());
             assert_eq!(value.1, "test_value".to_string());
         } else {
@@ -345,7 +345,7 @@ mod test {
             panic!("Invalid property");
         }
 
-        if let Some(value) = payload_props.user_property {
+        if let Some(value) = payload_props.user_properties.first() {
             assert_eq!(value.0, "key".to_string());
             assert_eq!(value.1, "value".to_string());
         } else {
@@ -447,7 +447,7 @@ mod test {
         assert_eq!(new_connect.properties.request_response_information, None);
         assert_eq!(new_connect.properties.receive_maximum, None);
         assert_eq!(new_connect.properties.topic_alias_maximum, None);
-        assert_eq!(new_connect.properties.user_property, None);
+        assert!(new_connect.properties.user_properties.is_empty());
         assert_eq!(new_connect.properties.maximum_packet_size, None);
 
         assert_eq!(new_connect.payload.client_id, "test2".to_string());
@@ -458,11 +458,43 @@ mod test {
         assert_eq!(new_connect.payload.content_type, None);
         assert_eq!(new_connect.payload.response_topic, None);
         assert_eq!(new_connect.payload.correlation_data, None);
-        assert_eq!(new_connect.payload.user_property, None);
+        assert!(new_connect.payload.user_properties.is_empty());
 
         assert_eq!(new_connect.payload.will_topic, None);
         assert_eq!(new_connect.payload.will_payload, None);
         assert_eq!(new_connect.payload.username, None);
         assert_eq!(new_connect.payload.password, None);
     }
+
+    #[test]
+    fn test_connect_username_without_will() {
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            connect_flags: 0xC0,
+            keep_alive: 10,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: "drone1".to_string(),
+            username: Some("drone1".to_string()),
+            password: Some(b"1234".to_vec()),
+            ..Default::default()
+        };
+
+        let mut buffer: Vec<u8> = Vec::new();
+        Connect::new(properties, payload)
+            .write_to(&mut buffer)
+            .unwrap();
+
+        let mut buffer = buffer.as_slice();
+        let fixed_header = PacketFixedHeader::read_from(&mut buffer).unwrap();
+        let connect = Connect::read_from(&mut buffer, fixed_header.remaining_length).unwrap();
+
+        assert!(buffer.is_empty());
+        assert_eq!(connect.payload.will_topic, None);
+        assert_eq!(connect.payload.will_payload, None);
+        assert_eq!(connect.payload.username, Some("drone1".to_string()));
+        assert_eq!(connect.payload.password, Some(b"1234".to_vec()));
+    }
 }
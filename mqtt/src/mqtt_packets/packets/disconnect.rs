@@ -79,7 +79,7 @@ mod test {
             disconnect_reason_code: 0,
             session_expiry_interval: Some(10),
             reason_string: Some("reason".to_string()),
-            user_property: Some(("name".to_string(), "value".to_string())),
+            user_properties: vec![("name".to_string(), "value".to_string())],
             server_reference: Some("server".to_string()),
         });
 
@@ -111,7 +111,7 @@ mod test {
             panic!("Invalid property");
         }
 
-        if let Some(value) = props.user_property {
+        if let Some(value) = props.user_properties.first() {
             assert_eq!(value.0, "name".to_string());
             assert_eq!(value.1, "value".to_string());
         } else {
@@ -151,7 +151,7 @@ mod test {
 
         assert_eq!(disconnect.properties.session_expiry_interval, None);
         assert_eq!(disconnect.properties.reason_string, None);
-        assert_eq!(disconnect.properties.user_property, None);
+        assert!(disconnect.properties.user_properties.is_empty());
         assert_eq!(disconnect.properties.server_reference, None);
     }
 }
@@ -113,7 +113,7 @@ mod test {
             packet_id: 1,
             puback_reason_code: 0,
             reason_string: Some("reason".to_string()),
-            user_property: Some(("name".to_string(), "value".to_string())),
+            user_properties: vec![("name".to_string(), "value".to_string())],
         };
         let puback = Puback::new(properties);
 
@@ -139,7 +139,7 @@ mod test {
             panic!("Invalid Reason String");
         }
 
-        if let Some(value) = props.user_property {
+        if let Some(value) = props.user_properties.first() {
             assert_eq!(value.0, "name");
             assert_eq!(value.1, "value");
         } else {
@@ -172,6 +172,6 @@ mod test {
         assert_eq!(puback.properties.puback_reason_code, 0);
 
         assert_eq!(puback.properties.reason_string, None);
-        assert_eq!(puback.properties.user_property, None);
+        assert!(puback.properties.user_properties.is_empty());
     }
 }
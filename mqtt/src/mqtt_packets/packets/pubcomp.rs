@@ -0,0 +1,165 @@
+use std::io::{Error, Read, Write};
+
+use crate::mqtt_packets::{
+    headers::fixed_header::{PacketFixedHeader, PUBCOMP_PACKET},
+    packet::generic_packet::{PacketReceived, Serialization},
+    packet_properties::PacketProperties,
+    properties::pubcomp_properties::PubcompProperties,
+};
+
+/// ## PUBCOMP PACKET
+///
+/// The PUBCOMP packet is the response to a PUBREL packet. It is the fourth and final packet of the QoS 2
+/// protocol exchange.
+///
+/// ### FIXED HEADER
+///
+/// FIRST BYTE:
+/// 4 most significant bits: MQTT Control Packet type
+/// PUBCOMP: 0111
+///
+/// 4 less significant bits: Flags
+/// 0000: Reserved
+///
+/// SECOND BYTE ONWARDS:
+/// Remaining Length
+/// This is the length of the Variable Header. It is encoded as a Variable Byte Integer.
+///
+/// ### VARIABLE HEADER
+///
+/// The Variable Header of the PUBCOMP packet contains the following fields in the order: Packet Identifier from the
+/// PUBREL packet that is being acknowledged, PUBCOMP Reason Code, and Properties.
+///
+/// BYTE 1: Most Significant Byte of the Packet Identifier (MSB)
+/// BYTE 2: Least Significant Byte of the Packet Identifier (LSB)
+/// BYTE 3: PUBCOMP Reason Code
+///
+/// 0 - Success - Packet Identifier released. Publication of QoS 2 message is complete.
+/// 146 - Packet Identifier not found - The Packet Identifier is not known.
+///
+/// #### Properties
+///
+/// 1. Property Length
+///    BYTE 4: Property Length (Variable Byte Integer)
+///
+/// 2. Reason String
+///    ID: 31 (0x1F)
+///    BYTE 5: Reason String (UTF-8 Encoded String)
+///
+/// 3. User Property
+///    ID: 38 (0x26)
+///    BYTE n: User Property (UTF-8 string pair)
+///
+/// ### PAYLOAD
+/// The PUBCOMP packet has no Payload.
+///
+pub struct Pubcomp {
+    pub properties: PubcompProperties,
+}
+
+impl Serialization for Pubcomp {
+    fn read_from(stream: &mut dyn Read, remaining_length: u32) -> Result<Self, std::io::Error> {
+        let mut aux_buffer = vec![0; remaining_length as usize];
+        stream.read_exact(&mut aux_buffer)?;
+        let mut buffer = aux_buffer.as_slice();
+
+        let properties = PubcompProperties::read_from(&mut buffer)?;
+
+        Ok(Pubcomp { properties })
+    }
+
+    fn write_to(&self, stream: &mut dyn Write) -> Result<(), Error> {
+        let properties_bytes = self.properties.as_bytes()?;
+        let remaining_length = self.properties.size_of();
+        let fixed_header = PacketFixedHeader::new(PUBCOMP_PACKET, remaining_length);
+        let fixed_header_bytes = fixed_header.as_bytes();
+
+        stream.write_all(&fixed_header_bytes)?;
+        stream.write_all(&properties_bytes)?;
+
+        Ok(())
+    }
+
+    fn packed_package(package: Pubcomp) -> PacketReceived {
+        PacketReceived::Pubcomp(Box::new(package))
+    }
+}
+
+impl Pubcomp {
+    pub fn new(properties: PubcompProperties) -> Self {
+        Pubcomp { properties }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pubcomp() {
+        let properties = PubcompProperties {
+            packet_id: 1,
+            pubcomp_reason_code: 0,
+            reason_string: Some("reason".to_string()),
+            user_properties: vec![("name".to_string(), "value".to_string())],
+        };
+        let pubcomp = Pubcomp::new(properties);
+
+        // ESCRIBE EL PACKET EN EL BUFFER
+        let mut buf = Vec::new();
+        pubcomp.write_to(&mut buf).unwrap();
+
+        // LEE EL PACKET DEL BUFFER
+        let mut buf = buf.as_slice();
+        let pubcomp_fixed_header = PacketFixedHeader::read_from(&mut buf).unwrap();
+
+        let pubcomp = Pubcomp::read_from(&mut buf, pubcomp_fixed_header.remaining_length).unwrap();
+
+        assert_eq!(pubcomp_fixed_header.get_packet_type(), PUBCOMP_PACKET);
+        assert_eq!(pubcomp.properties.packet_id, 1);
+        assert_eq!(pubcomp.properties.pubcomp_reason_code, 0);
+
+        let props = pubcomp.properties;
+
+        if let Some(value) = props.reason_string {
+            assert_eq!(value, "reason");
+        } else {
+            panic!("Invalid Reason String");
+        }
+
+        if let Some(value) = props.user_properties.first() {
+            assert_eq!(value.0, "name");
+            assert_eq!(value.1, "value");
+        } else {
+            panic!("Invalid User Property");
+        }
+    }
+
+    #[test]
+    fn test_pubcomp_no_properties() {
+        let properties = PubcompProperties {
+            packet_id: 2,
+            ..Default::default()
+        };
+
+        let pubcomp = Pubcomp::new(properties);
+
+        // ESCRIBE EL PACKET EN EL BUFFER
+        let mut buf = Vec::new();
+        pubcomp.write_to(&mut buf).unwrap();
+
+        // LEE EL PACKET DEL BUFFER
+        let mut buf = buf.as_slice();
+        let pubcomp_fixed_header = PacketFixedHeader::read_from(&mut buf).unwrap();
+        //let pubcomp_fixed_header = PacketFixedHeader::read_from_buffer(&mut buf).unwrap();
+
+        let pubcomp = Pubcomp::read_from(&mut buf, pubcomp_fixed_header.remaining_length).unwrap();
+
+        assert_eq!(pubcomp_fixed_header.get_packet_type(), PUBCOMP_PACKET);
+        assert_eq!(pubcomp.properties.packet_id, 2);
+        assert_eq!(pubcomp.properties.pubcomp_reason_code, 0);
+
+        assert_eq!(pubcomp.properties.reason_string, None);
+        assert!(pubcomp.properties.user_properties.is_empty());
+    }
+}
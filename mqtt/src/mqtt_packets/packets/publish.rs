@@ -166,15 +166,21 @@ impl Publish {
 
     pub fn from_be_bytes(buffer: Vec<u8>) -> Result<Publish, Error> {
         let mut index = 0;
-        let fixed_header = PacketFixedHeader::read_from(&mut &buffer[index..]).unwrap();
+        let fixed_header = PacketFixedHeader::read_from(&mut &buffer[index..])?;
         index += fixed_header.size_of();
 
         let limit = fixed_header.remaining_length as usize + index;
+        let properties_bytes = buffer.get(index..limit).ok_or_else(|| {
+            Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                "Publish - Paquete incompleto",
+            )
+        })?;
 
-        let properties = PublishProperties::read_from(&mut &buffer[index..limit])?;
+        let properties = PublishProperties::read_from(&mut &properties_bytes[..])?;
 
         Ok(Publish {
-            fixed_header_flags: 0,
+            fixed_header_flags: fixed_header.packet_type & 0x0F,
             properties,
         })
     }
@@ -220,7 +226,7 @@ mod test {
             topic_alias: Some(0),
             response_topic: Some("response".to_string()),
             correlation_data: Some(correlation_data),
-            user_property: Some(("test_key".to_string(), "test_value".to_string())),
+            user_properties: vec![("test_key".to_string(), "test_value".to_string())],
             subscription_identifier: Some(0),
             content_type: Some("type".to_string()),
             application_message,
@@ -289,7 +295,7 @@ mod test {
             panic!("Error");
         }
 
-        if let Some(value) = props.user_property {
+        if let Some(value) = props.user_properties.first() {
             assert_eq!(value.0, "test_key");
             assert_eq!(value.1, "test_value");
         } else {
@@ -355,7 +361,7 @@ mod test {
         assert_eq!(publish.properties.topic_alias, None);
         assert_eq!(publish.properties.response_topic, None);
         assert_eq!(publish.properties.correlation_data, None);
-        assert_eq!(publish.properties.user_property, None);
+        assert!(publish.properties.user_properties.is_empty());
         assert_eq!(publish.properties.subscription_identifier, None);
         assert_eq!(publish.properties.content_type, None);
 
@@ -382,6 +388,7 @@ mod test {
 
         let publish = Publish::from_be_bytes(bytes.unwrap()).unwrap();
 
+        assert_eq!(msg.fixed_header_flags, publish.fixed_header_flags);
         assert_eq!(msg.properties.size_of(), publish.properties.size_of());
         assert_eq!(msg.properties.topic_name, publish.properties.topic_name);
         assert_eq!(
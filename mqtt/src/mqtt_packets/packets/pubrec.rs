@@ -0,0 +1,172 @@
+use std::io::{Error, Read, Write};
+
+use crate::mqtt_packets::{
+    headers::fixed_header::{PacketFixedHeader, PUBREC_PACKET},
+    packet::generic_packet::{PacketReceived, Serialization},
+    packet_properties::PacketProperties,
+    properties::pubrec_properties::PubrecProperties,
+};
+
+/// ## PUBREC PACKET
+///
+/// The PUBREC packet is the response to a PUBLISH packet with QoS 2. It is the second packet of the QoS 2
+/// protocol exchange.
+///
+/// ### FIXED HEADER
+///
+/// FIRST BYTE:
+/// 4 most significant bits: MQTT Control Packet type
+/// PUBREC: 0101
+///
+/// 4 less significant bits: Flags
+/// 0000: Reserved
+///
+/// SECOND BYTE ONWARDS:
+/// Remaining Length
+/// This is the length of the Variable Header. It is encoded as a Variable Byte Integer.
+///
+/// ### VARIABLE HEADER
+///
+/// The Variable Header of the PUBREC packet contains the following fields in the order: Packet Identifier from the
+/// PUBLISH packet that is being acknowledged, PUBREC Reason Code, and Properties.
+///
+/// BYTE 1: Most Significant Byte of the Packet Identifier (MSB)
+/// BYTE 2: Least Significant Byte of the Packet Identifier (LSB)
+/// BYTE 3: PUBREC Reason Code
+///
+/// 0 - Success - The message is accepted. Publication of the QoS 2 message proceeds.
+/// 16 - No Matching Subscribers - The message is accepted but there are no subscribers.
+/// 128 - Unspecified Error - The receiver does not accept the publish.
+/// 131 - Implementation Specific Error - The PUBLISH is valid but the receiver is not willing to accept it.
+/// 135 - Not Authorized - The PUBLISH is not authorized.
+/// 144 - Topic Name Invalid - The Topic Name is not malformed, but is not accepted.
+/// 145 - Packet Identifier In Use - The Packet Identifier is already in use.
+/// 151 - Quota Exceeded - An implementation or administrative imposed limit has been exceeded.
+/// 153 - Payload Format Invalid - The payload format does not match the one specified in the Payload Format Indicator.
+///
+/// #### Properties
+///
+/// 1. Property Length
+///    BYTE 4: Property Length (Variable Byte Integer)
+///
+/// 2. Reason String
+///    ID: 31 (0x1F)
+///    BYTE 5: Reason String (UTF-8 Encoded String)
+///
+/// 3. User Property
+///    ID: 38 (0x26)
+///    BYTE n: User Property (UTF-8 string pair)
+///
+/// ### PAYLOAD
+/// The PUBREC packet has no Payload.
+///
+pub struct Pubrec {
+    pub properties: PubrecProperties,
+}
+
+impl Serialization for Pubrec {
+    fn read_from(stream: &mut dyn Read, remaining_length: u32) -> Result<Self, std::io::Error> {
+        let mut aux_buffer = vec![0; remaining_length as usize];
+        stream.read_exact(&mut aux_buffer)?;
+        let mut buffer = aux_buffer.as_slice();
+
+        let properties = PubrecProperties::read_from(&mut buffer)?;
+
+        Ok(Pubrec { properties })
+    }
+
+    fn write_to(&self, stream: &mut dyn Write) -> Result<(), Error> {
+        let properties_bytes = self.properties.as_bytes()?;
+        let remaining_length = self.properties.size_of();
+        let fixed_header = PacketFixedHeader::new(PUBREC_PACKET, remaining_length);
+        let fixed_header_bytes = fixed_header.as_bytes();
+
+        stream.write_all(&fixed_header_bytes)?;
+        stream.write_all(&properties_bytes)?;
+
+        Ok(())
+    }
+
+    fn packed_package(package: Pubrec) -> PacketReceived {
+        PacketReceived::Pubrec(Box::new(package))
+    }
+}
+
+impl Pubrec {
+    pub fn new(properties: PubrecProperties) -> Self {
+        Pubrec { properties }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pubrec() {
+        let properties = PubrecProperties {
+            packet_id: 1,
+            pubrec_reason_code: 0,
+            reason_string: Some("reason".to_string()),
+            user_properties: vec![("name".to_string(), "value".to_string())],
+        };
+        let pubrec = Pubrec::new(properties);
+
+        // ESCRIBE EL PACKET EN EL BUFFER
+        let mut buf = Vec::new();
+        pubrec.write_to(&mut buf).unwrap();
+
+        // LEE EL PACKET DEL BUFFER
+        let mut buf = buf.as_slice();
+        let pubrec_fixed_header = PacketFixedHeader::read_from(&mut buf).unwrap();
+
+        let pubrec = Pubrec::read_from(&mut buf, pubrec_fixed_header.remaining_length).unwrap();
+
+        assert_eq!(pubrec_fixed_header.get_packet_type(), PUBREC_PACKET);
+        assert_eq!(pubrec.properties.packet_id, 1);
+        assert_eq!(pubrec.properties.pubrec_reason_code, 0);
+
+        let props = pubrec.properties;
+
+        if let Some(value) = props.reason_string {
+            assert_eq!(value, "reason");
+        } else {
+            panic!("Invalid Reason String");
+        }
+
+        if let Some(value) = props.user_properties.first() {
+            assert_eq!(value.0, "name");
+            assert_eq!(value.1, "value");
+        } else {
+            panic!("Invalid User Property");
+        }
+    }
+
+    #[test]
+    fn test_pubrec_no_properties() {
+        let properties = PubrecProperties {
+            packet_id: 2,
+            ..Default::default()
+        };
+
+        let pubrec = Pubrec::new(properties);
+
+        // ESCRIBE EL PACKET EN EL BUFFER
+        let mut buf = Vec::new();
+        pubrec.write_to(&mut buf).unwrap();
+
+        // LEE EL PACKET DEL BUFFER
+        let mut buf = buf.as_slice();
+        let pubrec_fixed_header = PacketFixedHeader::read_from(&mut buf).unwrap();
+        //let pubrec_fixed_header = PacketFixedHeader::read_from_buffer(&mut buf).unwrap();
+
+        let pubrec = Pubrec::read_from(&mut buf, pubrec_fixed_header.remaining_length).unwrap();
+
+        assert_eq!(pubrec_fixed_header.get_packet_type(), PUBREC_PACKET);
+        assert_eq!(pubrec.properties.packet_id, 2);
+        assert_eq!(pubrec.properties.pubrec_reason_code, 0);
+
+        assert_eq!(pubrec.properties.reason_string, None);
+        assert!(pubrec.properties.user_properties.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+use std::io::{Error, Read, Write};
+
+use crate::mqtt_packets::{
+    headers::fixed_header::{PacketFixedHeader, PUBREL_PACKET},
+    packet::generic_packet::{PacketReceived, Serialization},
+    packet_properties::PacketProperties,
+    properties::pubrel_properties::PubrelProperties,
+};
+
+/// ## PUBREL PACKET
+///
+/// The PUBREL packet is the response to a PUBREC packet. It is the third packet of the QoS 2 protocol exchange.
+///
+/// ### FIXED HEADER
+///
+/// FIRST BYTE:
+/// 4 most significant bits: MQTT Control Packet type
+/// PUBREL: 0110
+///
+/// 4 less significant bits: Flags
+/// 0010: Reserved. Any other value must be treated as malformed
+///
+/// SECOND BYTE ONWARDS:
+/// Remaining Length
+/// This is the length of the Variable Header. It is encoded as a Variable Byte Integer.
+///
+/// ### VARIABLE HEADER
+///
+/// The Variable Header of the PUBREL packet contains the following fields in the order: Packet Identifier from the
+/// PUBREC packet that is being acknowledged, PUBREL Reason Code, and Properties.
+///
+/// BYTE 1: Most Significant Byte of the Packet Identifier (MSB)
+/// BYTE 2: Least Significant Byte of the Packet Identifier (LSB)
+/// BYTE 3: PUBREL Reason Code
+///
+/// 0 - Success - Message released.
+/// 146 - Packet Identifier not found - The Packet Identifier is not known.
+///
+/// #### Properties
+///
+/// 1. Property Length
+///    BYTE 4: Property Length (Variable Byte Integer)
+///
+/// 2. Reason String
+///    ID: 31 (0x1F)
+///    BYTE 5: Reason String (UTF-8 Encoded String)
+///
+/// 3. User Property
+///    ID: 38 (0x26)
+///    BYTE n: User Property (UTF-8 string pair)
+///
+/// ### PAYLOAD
+/// The PUBREL packet has no Payload.
+///
+pub struct Pubrel {
+    pub properties: PubrelProperties,
+}
+
+impl Serialization for Pubrel {
+    fn read_from(stream: &mut dyn Read, remaining_length: u32) -> Result<Self, std::io::Error> {
+        let mut aux_buffer = vec![0; remaining_length as usize];
+        stream.read_exact(&mut aux_buffer)?;
+        let mut buffer = aux_buffer.as_slice();
+
+        let properties = PubrelProperties::read_from(&mut buffer)?;
+
+        Ok(Pubrel { properties })
+    }
+
+    fn write_to(&self, stream: &mut dyn Write) -> Result<(), Error> {
+        let properties_bytes = self.properties.as_bytes()?;
+        let remaining_length = self.properties.size_of();
+        let fixed_header = PacketFixedHeader::new(PUBREL_PACKET, remaining_length);
+        let fixed_header_bytes = fixed_header.as_bytes();
+
+        stream.write_all(&fixed_header_bytes)?;
+        stream.write_all(&properties_bytes)?;
+
+        Ok(())
+    }
+
+    fn packed_package(package: Pubrel) -> PacketReceived {
+        PacketReceived::Pubrel(Box::new(package))
+    }
+}
+
+impl Pubrel {
+    pub fn new(properties: PubrelProperties) -> Self {
+        Pubrel { properties }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pubrel() {
+        let properties = PubrelProperties {
+            packet_id: 1,
+            pubrel_reason_code: 0,
+            reason_string: Some("reason".to_string()),
+            user_properties: vec![("name".to_string(), "value".to_string())],
+        };
+        let pubrel = Pubrel::new(properties);
+
+        // ESCRIBE EL PACKET EN EL BUFFER
+        let mut buf = Vec::new();
+        pubrel.write_to(&mut buf).unwrap();
+
+        // Los flags reservados de PUBREL deben ser 0010
+        assert_eq!(buf[0], PUBREL_PACKET | 0b0010);
+
+        // LEE EL PACKET DEL BUFFER
+        let mut buf = buf.as_slice();
+        let pubrel_fixed_header = PacketFixedHeader::read_from(&mut buf).unwrap();
+
+        let pubrel = Pubrel::read_from(&mut buf, pubrel_fixed_header.remaining_length).unwrap();
+
+        assert_eq!(pubrel_fixed_header.get_packet_type(), PUBREL_PACKET);
+        assert_eq!(pubrel.properties.packet_id, 1);
+        assert_eq!(pubrel.properties.pubrel_reason_code, 0);
+
+        let props = pubrel.properties;
+
+        if let Some(value) = props.reason_string {
+            assert_eq!(value, "reason");
+        } else {
+            panic!("Invalid Reason String");
+        }
+
+        if let Some(value) = props.user_properties.first() {
+            assert_eq!(value.0, "name");
+            assert_eq!(value.1, "value");
+        } else {
+            panic!("Invalid User Property");
+        }
+    }
+
+    #[test]
+    fn test_pubrel_no_properties() {
+        let properties = PubrelProperties {
+            packet_id: 2,
+            ..Default::default()
+        };
+
+        let pubrel = Pubrel::new(properties);
+
+        // ESCRIBE EL PACKET EN EL BUFFER
+        let mut buf = Vec::new();
+        pubrel.write_to(&mut buf).unwrap();
+
+        // LEE EL PACKET DEL BUFFER
+        let mut buf = buf.as_slice();
+        let pubrel_fixed_header = PacketFixedHeader::read_from(&mut buf).unwrap();
+        //let pubrel_fixed_header = PacketFixedHeader::read_from_buffer(&mut buf).unwrap();
+
+        let pubrel = Pubrel::read_from(&mut buf, pubrel_fixed_header.remaining_length).unwrap();
+
+        assert_eq!(pubrel_fixed_header.get_packet_type(), PUBREL_PACKET);
+        assert_eq!(pubrel.properties.packet_id, 2);
+        assert_eq!(pubrel.properties.pubrel_reason_code, 0);
+
+        assert_eq!(pubrel.properties.reason_string, None);
+        assert!(pubrel.properties.user_properties.is_empty());
+    }
+}
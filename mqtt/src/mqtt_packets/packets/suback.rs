@@ -113,7 +113,7 @@ mod test {
         let properties = SubackProperties {
             packet_identifier: 1,
             reason_string: Some("reason_string".to_string()),
-            user_property: Some(("test_key".to_string(), "test_value".to_string())),
+            user_properties: vec![("test_key".to_string(), "test_value".to_string())],
 
             // Payload
             reason_codes: vec![
@@ -145,7 +145,7 @@ mod test {
             panic!("Error");
         }
 
-        if let Some(user_property) = &suback.properties.user_property {
+        if let Some(user_property) = suback.properties.user_properties.first() {
             assert_eq!(user_property.0, "test_key");
             assert_eq!(user_property.1, "test_value");
         } else {
@@ -177,6 +177,6 @@ mod test {
         assert_eq!(suback.properties.packet_identifier, 1);
         assert_eq!(suback.properties.reason_codes, Vec::new());
         assert_eq!(suback.properties.reason_string, None);
-        assert_eq!(suback.properties.user_property, None);
+        assert!(suback.properties.user_properties.is_empty());
     }
 }
@@ -112,7 +112,7 @@ mod test {
         let mut properties = SubscribeProperties {
             packet_identifier: 1,
             subscription_identifier: Some(1),
-            user_property: Some(("key".to_string(), "value".to_string())),
+            user_properties: vec![("key".to_string(), "value".to_string())],
             ..Default::default()
         };
 
@@ -137,8 +137,8 @@ mod test {
         assert_eq!(subscribe.properties.packet_identifier, 1);
         assert_eq!(subscribe.properties.subscription_identifier, Some(1));
         assert_eq!(
-            subscribe.properties.user_property,
-            Some(("key".to_string(), "value".to_string()))
+            subscribe.properties.user_properties,
+            vec![("key".to_string(), "value".to_string())]
         );
 
         assert_eq!(subscribe.properties.topic_filters.len(), 1);
@@ -172,7 +172,7 @@ mod test {
         let mut properties = SubscribeProperties {
             packet_identifier: 1,
             subscription_identifier: Some(1),
-            user_property: Some(("key".to_string(), "value".to_string())),
+            user_properties: vec![("key".to_string(), "value".to_string())],
             ..Default::default()
         };
 
@@ -197,8 +197,8 @@ mod test {
         assert_eq!(subscribe.properties.packet_identifier, 1);
         assert_eq!(subscribe.properties.subscription_identifier, Some(1));
         assert_eq!(
-            subscribe.properties.user_property,
-            Some(("key".to_string(), "value".to_string()))
+            subscribe.properties.user_properties,
+            vec![("key".to_string(), "value".to_string())]
         );
 
         assert_eq!(subscribe.properties.topic_filters.len(), 3);
@@ -277,7 +277,7 @@ mod test {
         let mut properties = SubscribeProperties {
             packet_identifier: 1,
             subscription_identifier: None,
-            user_property: None,
+            user_properties: Vec::new(),
             ..Default::default()
         };
 
@@ -300,7 +300,7 @@ mod test {
 
         assert_eq!(subscribe.properties.packet_identifier, 1);
         assert_eq!(subscribe.properties.subscription_identifier, None);
-        assert_eq!(subscribe.properties.user_property, None);
+        assert!(subscribe.properties.user_properties.is_empty());
 
         assert_eq!(subscribe.properties.topic_filters.len(), 1);
 
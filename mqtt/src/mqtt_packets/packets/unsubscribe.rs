@@ -96,7 +96,7 @@ mod test {
     fn test_unsubscribe_to_one_topic() {
         let properties = UnsubscribeProperties {
             packet_identifier: 1,
-            user_property: None,
+            user_properties: Vec::new(),
             topic_filters: vec!["topic".to_string()],
         };
 
@@ -123,7 +123,7 @@ mod test {
     fn test_unsubscribe_to_multiple_topics() {
         let properties = UnsubscribeProperties {
             packet_identifier: 1,
-            user_property: None,
+            user_properties: Vec::new(),
             topic_filters: vec![
                 "topic1".to_string(),
                 "topic2".to_string(),
@@ -18,7 +18,7 @@ pub struct AuthProperties {
     pub authentication_method: Option<String>,
     pub authentication_data: Option<Vec<u8>>,
     pub reason_string: Option<String>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
 }
 
 impl Clone for AuthProperties {
@@ -28,7 +28,7 @@ impl Clone for AuthProperties {
             authentication_method: self.authentication_method.clone(),
             authentication_data: self.authentication_data.clone(),
             reason_string: self.reason_string.clone(),
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
         }
     }
 }
@@ -55,13 +55,13 @@ impl PacketProperties for AuthProperties {
             variable_props.add_utf8_string_property(REASON_STRING, reason_string)?;
         };
 
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
-        };
+        }
 
         Ok(variable_props)
     }
@@ -83,7 +83,7 @@ impl PacketProperties for AuthProperties {
         let mut authentication_method = None;
         let mut authentication_data = None;
         let mut reason_string = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
 
         for property in &variable_header_properties.properties {
             match property.id() {
@@ -97,7 +97,9 @@ impl PacketProperties for AuthProperties {
                     reason_string = property.value_string();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 _ => {}
             }
@@ -108,7 +110,7 @@ impl PacketProperties for AuthProperties {
             authentication_method,
             authentication_data,
             reason_string,
-            user_property,
+            user_properties,
         })
     }
 }
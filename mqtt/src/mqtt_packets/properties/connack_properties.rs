@@ -27,7 +27,7 @@ pub struct ConnackProperties {
     pub wildcard_subscription_available: Option<u8>,
     pub subscription_identifiers_available: Option<u8>,
     pub shared_subscription_available: Option<u8>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
     pub maximum_packet_size: Option<u32>,
 }
 
@@ -51,7 +51,7 @@ impl Clone for ConnackProperties {
             wildcard_subscription_available: self.wildcard_subscription_available,
             subscription_identifiers_available: self.subscription_identifiers_available,
             shared_subscription_available: self.shared_subscription_available,
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
             maximum_packet_size: self.maximum_packet_size,
         }
     }
@@ -113,13 +113,13 @@ impl PacketProperties for ConnackProperties {
         if let Some(value) = self.shared_subscription_available {
             variable_props.add_u8_property(SHARED_SUBSCRIPTION_AVAILABLE, value)?;
         }
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
-        };
+        }
 
         if let Some(value) = self.maximum_packet_size {
             variable_props.add_u32_property(MAXIMUM_PACKET_SIZE, value)?;
@@ -160,7 +160,7 @@ impl PacketProperties for ConnackProperties {
         let mut wildcard_subscription_available = None;
         let mut subscription_identifiers_available = None;
         let mut shared_subscription_available = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
         let mut maximum_packet_size = None;
 
         for property in &variable_header_properties.properties {
@@ -211,7 +211,9 @@ impl PacketProperties for ConnackProperties {
                     shared_subscription_available = property.value_u8();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 MAXIMUM_PACKET_SIZE => {
                     maximum_packet_size = property.value_u32();
@@ -238,7 +240,7 @@ impl PacketProperties for ConnackProperties {
             wildcard_subscription_available,
             subscription_identifiers_available,
             shared_subscription_available,
-            user_property,
+            user_properties,
             maximum_packet_size,
         })
     }
@@ -18,7 +18,7 @@ pub struct ConnectPayload {
     pub content_type: Option<String>,
     pub response_topic: Option<String>,
     pub correlation_data: Option<Vec<u8>>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
     // Campos opcionales
     pub will_topic: Option<String>,
     pub will_payload: Option<Vec<u8>>,
@@ -36,7 +36,7 @@ impl Clone for ConnectPayload {
             content_type: self.content_type.clone(),
             response_topic: self.response_topic.clone(),
             correlation_data: self.correlation_data.clone(),
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
             will_topic: self.will_topic.clone(),
             will_payload: self.will_payload.clone(),
             username: self.username.clone(),
@@ -45,22 +45,42 @@ impl Clone for ConnectPayload {
     }
 }
 
+impl ConnectPayload {
+    /// ### optional_fields
+    ///
+    /// Devuelve los campos opcionales del payload (Will Topic, Will Payload, User Name y
+    /// Password) que se deben escribir, en orden
+    ///
+    /// ### Consideraciones
+    /// - Los campos se leen por posición, por lo que un campo ausente que precede a uno
+    ///   presente se escribe con longitud 0 (por ejemplo, un User Name sin Will Topic)
+    ///
+    fn optional_fields(&self) -> Vec<&[u8]> {
+        let fields = [
+            self.will_topic.as_ref().map(|topic| topic.as_bytes()),
+            self.will_payload.as_deref(),
+            self.username.as_ref().map(|username| username.as_bytes()),
+            self.password.as_deref(),
+        ];
+        let present = fields
+            .iter()
+            .rposition(|field| field.is_some())
+            .map_or(0, |last| last + 1);
+
+        fields[..present]
+            .iter()
+            .map(|field| field.unwrap_or_default())
+            .collect()
+    }
+}
+
 impl PacketProperties for ConnectPayload {
     fn size_of(&self) -> u32 {
         let payload_props = self.as_variable_header_properties().unwrap();
         let mut payload_fields = std::mem::size_of::<u16>() + self.client_id.len();
 
-        if let Some(will_topic) = &self.will_topic {
-            payload_fields += std::mem::size_of::<u16>() + will_topic.len();
-        }
-        if let Some(will_payload) = &self.will_payload {
-            payload_fields += std::mem::size_of::<u16>() + will_payload.len();
-        }
-        if let Some(username) = &self.username {
-            payload_fields += std::mem::size_of::<u16>() + username.len();
-        }
-        if let Some(password) = &self.password {
-            payload_fields += std::mem::size_of::<u16>() + password.len();
+        for field in self.optional_fields() {
+            payload_fields += std::mem::size_of::<u16>() + field.len();
         }
 
         payload_fields as u32 + payload_props.size_of()
@@ -93,13 +113,13 @@ impl PacketProperties for ConnectPayload {
             payload_props.add_binary_data_property(CORRELATION_DATA, correlation_data)?;
         }
 
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             payload_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
-        };
+        }
 
         Ok(payload_props)
     }
@@ -114,21 +134,9 @@ impl PacketProperties for ConnectPayload {
 
         bytes.extend_from_slice(&variable_header_properties.as_bytes());
 
-        if let Some(will_topic) = self.will_topic.clone() {
-            bytes.extend_from_slice(&(will_topic.len() as u16).to_be_bytes());
-            bytes.extend_from_slice(will_topic.as_bytes());
-        }
-        if let Some(will_payload) = self.will_payload.clone() {
-            bytes.extend_from_slice(&(will_payload.len() as u16).to_be_bytes());
-            bytes.extend_from_slice(&will_payload);
-        }
-        if let Some(username) = self.username.clone() {
-            bytes.extend_from_slice(&(username.len() as u16).to_be_bytes());
-            bytes.extend_from_slice(username.as_bytes());
-        }
-        if let Some(password) = self.password.clone() {
-            bytes.extend_from_slice(&(password.len() as u16).to_be_bytes());
-            bytes.extend_from_slice(&password);
+        for field in self.optional_fields() {
+            bytes.extend_from_slice(&(field.len() as u16).to_be_bytes());
+            bytes.extend_from_slice(field);
         }
 
         Ok(bytes)
@@ -145,7 +153,7 @@ impl PacketProperties for ConnectPayload {
         let mut content_type = None;
         let mut response_topic = None;
         let mut correlation_data = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
 
         for property in &variable_header_properties.properties {
             match property.id() {
@@ -168,7 +176,9 @@ impl PacketProperties for ConnectPayload {
                     correlation_data = property.value_binary_data();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 _ => {}
             }
@@ -208,7 +218,7 @@ impl PacketProperties for ConnectPayload {
             content_type,
             response_topic,
             correlation_data,
-            user_property,
+            user_properties,
 
             will_topic,
             will_payload,
@@ -21,7 +21,7 @@ pub struct ConnectProperties {
     pub request_response_information: Option<u8>,
     pub receive_maximum: Option<u16>,
     pub topic_alias_maximum: Option<u16>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
     pub maximum_packet_size: Option<u32>,
 }
 
@@ -39,7 +39,7 @@ impl Clone for ConnectProperties {
             request_response_information: self.request_response_information,
             receive_maximum: self.receive_maximum,
             topic_alias_maximum: self.topic_alias_maximum,
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
             maximum_packet_size: self.maximum_packet_size,
         }
     }
@@ -85,13 +85,13 @@ impl PacketProperties for ConnectProperties {
             variable_props.add_u16_property(TOPIC_ALIAS_MAXIMUM, topic_alias_maximum)?;
         };
 
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
-        };
+        }
 
         if let Some(maximum_packet_size) = self.maximum_packet_size {
             variable_props.add_u32_property(MAXIMUM_PACKET_SIZE, maximum_packet_size)?;
@@ -131,7 +131,7 @@ impl PacketProperties for ConnectProperties {
         let mut request_response_information = None;
         let mut receive_maximum = None;
         let mut topic_alias_maximum = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
         let mut maximum_packet_size = None;
 
         for property in &variable_header_properties.properties {
@@ -158,7 +158,9 @@ impl PacketProperties for ConnectProperties {
                     topic_alias_maximum = property.value_u16();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 MAXIMUM_PACKET_SIZE => {
                     maximum_packet_size = property.value_u32();
@@ -179,7 +181,7 @@ impl PacketProperties for ConnectProperties {
             request_response_information,
             receive_maximum,
             topic_alias_maximum,
-            user_property,
+            user_properties,
             maximum_packet_size,
         })
     }
@@ -16,7 +16,7 @@ pub struct DisconnectProperties {
     pub disconnect_reason_code: u8,
     pub session_expiry_interval: Option<u32>,
     pub reason_string: Option<String>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
     pub server_reference: Option<String>,
 }
 
@@ -27,7 +27,7 @@ impl Clone for DisconnectProperties {
             disconnect_reason_code: self.disconnect_reason_code,
             session_expiry_interval: self.session_expiry_interval,
             reason_string: self.reason_string.clone(),
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
             server_reference: self.server_reference.clone(),
         }
     }
@@ -51,13 +51,13 @@ impl PacketProperties for DisconnectProperties {
         if let Some(reason_string) = self.reason_string.clone() {
             variable_props.add_utf8_string_property(REASON_STRING, reason_string)?;
         };
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
-        };
+        }
 
         if let Some(server_reference) = self.server_reference.clone() {
             variable_props.add_utf8_string_property(SERVER_REFERENCE, server_reference)?;
@@ -88,7 +88,7 @@ impl PacketProperties for DisconnectProperties {
 
         let mut session_expiry_interval = None;
         let mut reason_string = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
         let mut server_reference = None;
 
         for property in &variable_header_properties.properties {
@@ -100,7 +100,9 @@ impl PacketProperties for DisconnectProperties {
                     reason_string = property.value_string();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 SERVER_REFERENCE => {
                     server_reference = property.value_string();
@@ -114,7 +116,7 @@ impl PacketProperties for DisconnectProperties {
             disconnect_reason_code,
             session_expiry_interval,
             reason_string,
-            user_property,
+            user_properties,
             server_reference,
         })
     }
@@ -13,7 +13,7 @@ pub struct PubackProperties {
     pub packet_id: u16,
     pub puback_reason_code: u8,
     pub reason_string: Option<String>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
 }
 
 impl Clone for PubackProperties {
@@ -22,7 +22,7 @@ impl Clone for PubackProperties {
             packet_id: self.packet_id,
             puback_reason_code: self.puback_reason_code,
             reason_string: self.reason_string.clone(),
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
         }
     }
 }
@@ -42,13 +42,13 @@ impl PacketProperties for PubackProperties {
             variable_props.add_utf8_string_property(REASON_STRING, value)?;
         }
 
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
-        };
+        }
         Ok(variable_props)
     }
 
@@ -69,7 +69,7 @@ impl PacketProperties for PubackProperties {
         let variable_header_properties = VariableHeaderProperties::read_from(stream)?;
 
         let mut reason_string = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
 
         for property in &variable_header_properties.properties {
             match property.id() {
@@ -77,7 +77,9 @@ impl PacketProperties for PubackProperties {
                     reason_string = property.value_string();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 _ => {}
             }
@@ -87,7 +89,7 @@ impl PacketProperties for PubackProperties {
             packet_id,
             puback_reason_code,
             reason_string,
-            user_property,
+            user_properties,
         })
     }
 }
@@ -17,7 +17,7 @@ pub struct PublishProperties {
     pub topic_alias: Option<u16>,
     pub response_topic: Option<String>,
     pub correlation_data: Option<Vec<u8>>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
     pub subscription_identifier: Option<u32>,
     pub content_type: Option<String>,
 
@@ -35,7 +35,7 @@ impl Clone for PublishProperties {
             topic_alias: self.topic_alias,
             response_topic: self.response_topic.clone(),
             correlation_data: self.correlation_data.clone(),
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
             subscription_identifier: self.subscription_identifier,
             content_type: self.content_type.clone(),
 
@@ -80,11 +80,11 @@ impl PacketProperties for PublishProperties {
             variable_props.add_binary_data_property(CORRELATION_DATA, correlation_data)?;
         }
 
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
         }
 
@@ -140,7 +140,7 @@ impl PacketProperties for PublishProperties {
         let mut topic_alias = None;
         let mut response_topic = None;
         let mut correlation_data = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
         let mut subscription_identifier = None;
         let mut content_type = None;
 
@@ -162,7 +162,9 @@ impl PacketProperties for PublishProperties {
                     correlation_data = property.value_binary_data();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 SUBSCRIPTION_IDENTIFIER => {
                     subscription_identifier = property.value_variable_byte_integer();
@@ -187,7 +189,7 @@ impl PacketProperties for PublishProperties {
             topic_alias,
             response_topic,
             correlation_data,
-            user_property,
+            user_properties,
             subscription_identifier,
             content_type,
             application_message,
@@ -210,7 +212,11 @@ mod tests {
             topic_alias: Some(1),
             response_topic: Some("response".to_string()),
             correlation_data: Some(vec![1, 2, 3]),
-            user_property: Some(("key".to_string(), "value".to_string())),
+            user_properties: vec![
+                ("key".to_string(), "value".to_string()),
+                ("key".to_string(), "other value".to_string()),
+                ("trace".to_string(), "1".to_string()),
+            ],
             subscription_identifier: Some(1),
             content_type: Some("content".to_string()),
             application_message: vec![1, 2, 3],
@@ -243,7 +249,10 @@ mod tests {
             publish_properties.correlation_data,
             deserialized.correlation_data
         );
-        assert_eq!(publish_properties.user_property, deserialized.user_property);
+        assert_eq!(
+            publish_properties.user_properties,
+            deserialized.user_properties
+        );
         assert_eq!(
             publish_properties.subscription_identifier,
             deserialized.subscription_identifier
@@ -0,0 +1,95 @@
+use std::io::{Error, Read};
+
+use crate::{
+    common::data_types::data_representation::*,
+    mqtt_packets::{
+        headers::variable_header_properties::VariableHeaderProperties,
+        packet_properties::PacketProperties, packet_property::*,
+    },
+};
+
+#[derive(Default)]
+pub struct PubrelProperties {
+    pub packet_id: u16,
+    pub pubrel_reason_code: u8,
+    pub reason_string: Option<String>,
+    pub user_properties: Vec<(String, String)>,
+}
+
+impl Clone for PubrelProperties {
+    fn clone(&self) -> Self {
+        PubrelProperties {
+            packet_id: self.packet_id,
+            pubrel_reason_code: self.pubrel_reason_code,
+            reason_string: self.reason_string.clone(),
+            user_properties: self.user_properties.clone(),
+        }
+    }
+}
+
+impl PacketProperties for PubrelProperties {
+    fn size_of(&self) -> u32 {
+        let variable_props = self.as_variable_header_properties().unwrap();
+        let fixed_props_size = std::mem::size_of::<u16>() + std::mem::size_of::<u8>();
+
+        fixed_props_size as u32 + variable_props.size_of()
+    }
+
+    fn as_variable_header_properties(&self) -> Result<VariableHeaderProperties, Error> {
+        let mut variable_props = VariableHeaderProperties::default();
+
+        if let Some(value) = self.reason_string.clone() {
+            variable_props.add_utf8_string_property(REASON_STRING, value)?;
+        }
+
+        for (key, value) in self.user_properties.iter() {
+            variable_props.add_utf8_pair_string_property(
+                USER_PROPERTY,
+                key.clone(),
+                value.clone(),
+            )?;
+        }
+        Ok(variable_props)
+    }
+
+    fn as_bytes(&self) -> Result<Vec<u8>, Error> {
+        let mut bytes: Vec<u8> = Vec::new();
+        let variable_header_properties = self.as_variable_header_properties()?;
+
+        bytes.extend_from_slice(&self.packet_id.to_be_bytes());
+        bytes.push(self.pubrel_reason_code);
+        bytes.extend_from_slice(&variable_header_properties.as_bytes());
+
+        Ok(bytes)
+    }
+
+    fn read_from(stream: &mut dyn Read) -> Result<Self, Error> {
+        let packet_id = read_two_byte_integer(stream)?;
+        let pubrel_reason_code = read_byte(stream)?;
+        let variable_header_properties = VariableHeaderProperties::read_from(stream)?;
+
+        let mut reason_string = None;
+        let mut user_properties = Vec::new();
+
+        for property in &variable_header_properties.properties {
+            match property.id() {
+                REASON_STRING => {
+                    reason_string = property.value_string();
+                }
+                USER_PROPERTY => {
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(PubrelProperties {
+            packet_id,
+            pubrel_reason_code,
+            reason_string,
+            user_properties,
+        })
+    }
+}
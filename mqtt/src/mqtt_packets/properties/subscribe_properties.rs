@@ -11,7 +11,7 @@ use std::io::{Error, Read};
 pub struct SubscribeProperties {
     pub packet_identifier: u16,
     pub subscription_identifier: Option<u32>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
 
     pub topic_filters: Vec<TopicFilter>,
 }
@@ -21,7 +21,7 @@ impl Clone for SubscribeProperties {
         SubscribeProperties {
             packet_identifier: self.packet_identifier,
             subscription_identifier: self.subscription_identifier,
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
             topic_filters: self.topic_filters.clone(),
         }
     }
@@ -50,11 +50,11 @@ impl PacketProperties for SubscribeProperties {
             )?;
         }
 
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
         }
         Ok(variable_props)
@@ -84,7 +84,7 @@ impl PacketProperties for SubscribeProperties {
         let variable_header_properties = VariableHeaderProperties::read_from(stream)?;
 
         let mut subscription_identifier = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
 
         for property in &variable_header_properties.properties {
             match property.id() {
@@ -92,7 +92,9 @@ impl PacketProperties for SubscribeProperties {
                     subscription_identifier = property.value_variable_byte_integer();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 _ => {}
             }
@@ -108,6 +110,7 @@ impl PacketProperties for SubscribeProperties {
             topic_filters.push(TopicFilter {
                 topic_filter,
                 subscription_options,
+                subscription_identifier,
             });
             i += 1;
         }
@@ -115,7 +118,7 @@ impl PacketProperties for SubscribeProperties {
         Ok(SubscribeProperties {
             packet_identifier,
             subscription_identifier,
-            user_property,
+            user_properties,
             topic_filters,
         })
     }
@@ -169,6 +172,7 @@ impl SubscribeProperties {
         self.topic_filters.push(TopicFilter {
             topic_filter,
             subscription_options,
+            subscription_identifier: None,
         });
     }
 }
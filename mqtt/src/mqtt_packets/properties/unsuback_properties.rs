@@ -12,7 +12,7 @@ use crate::{
 pub struct UnsubackProperties {
     pub packet_identifier: u16,
     pub reason_string: Option<String>,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
 
     pub reason_codes: Vec<u8>, //Payload
 }
@@ -22,7 +22,7 @@ impl Clone for UnsubackProperties {
         UnsubackProperties {
             packet_identifier: self.packet_identifier,
             reason_string: self.reason_string.clone(),
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
 
             reason_codes: self.reason_codes.clone(),
         }
@@ -48,11 +48,11 @@ impl PacketProperties for UnsubackProperties {
         if let Some(value) = self.reason_string.clone() {
             variable_props.add_utf8_string_property(REASON_STRING, value)?;
         }
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
         }
 
@@ -80,7 +80,7 @@ impl PacketProperties for UnsubackProperties {
         let variable_header_properties = VariableHeaderProperties::read_from(stream)?;
 
         let mut reason_string = None;
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
 
         for property in &variable_header_properties.properties {
             match property.id() {
@@ -88,7 +88,9 @@ impl PacketProperties for UnsubackProperties {
                     reason_string = property.value_string();
                 }
                 USER_PROPERTY => {
-                    user_property = property.value_string_pair();
+                    if let Some(user_property) = property.value_string_pair() {
+                        user_properties.push(user_property);
+                    }
                 }
                 _ => {}
             }
@@ -106,7 +108,7 @@ impl PacketProperties for UnsubackProperties {
         Ok(UnsubackProperties {
             packet_identifier,
             reason_string,
-            user_property,
+            user_properties,
             reason_codes,
         })
     }
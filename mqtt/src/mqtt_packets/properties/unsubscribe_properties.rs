@@ -11,7 +11,7 @@ use crate::{
 #[derive(Default)]
 pub struct UnsubscribeProperties {
     pub packet_identifier: u16,
-    pub user_property: Option<(String, String)>,
+    pub user_properties: Vec<(String, String)>,
 
     pub topic_filters: Vec<String>, // Payload
 }
@@ -20,7 +20,7 @@ impl Clone for UnsubscribeProperties {
     fn clone(&self) -> Self {
         UnsubscribeProperties {
             packet_identifier: self.packet_identifier,
-            user_property: self.user_property.clone(),
+            user_properties: self.user_properties.clone(),
             topic_filters: self.topic_filters.clone(),
         }
     }
@@ -42,11 +42,11 @@ impl PacketProperties for UnsubscribeProperties {
     fn as_variable_header_properties(&self) -> Result<VariableHeaderProperties, Error> {
         let mut variable_props = VariableHeaderProperties::default();
 
-        if let Some(user_property) = self.user_property.clone() {
+        for (key, value) in self.user_properties.iter() {
             variable_props.add_utf8_pair_string_property(
                 USER_PROPERTY,
-                user_property.0,
-                user_property.1,
+                key.clone(),
+                value.clone(),
             )?;
         }
         Ok(variable_props)
@@ -76,11 +76,13 @@ impl PacketProperties for UnsubscribeProperties {
         let packet_identifier = read_two_byte_integer(stream)?;
         let variable_header_properties = VariableHeaderProperties::read_from(stream)?;
 
-        let mut user_property = None;
+        let mut user_properties = Vec::new();
 
         for property in &variable_header_properties.properties {
             if property.id() == USER_PROPERTY {
-                user_property = property.value_string_pair();
+                if let Some(user_property) = property.value_string_pair() {
+                    user_properties.push(user_property);
+                }
             }
         }
 
@@ -94,7 +96,7 @@ impl PacketProperties for UnsubscribeProperties {
 
         Ok(UnsubscribeProperties {
             packet_identifier,
-            user_property,
+            user_properties,
             topic_filters,
         })
     }
@@ -1,24 +1,39 @@
-use std::{io::Error, net::TcpStream};
+use std::{io::Error, time::Duration};
+
+use logger::logger_handler::Logger;
 
 use crate::{
-    common::{flags::flags_handler, reason_codes::ReasonCode},
+    common::{
+        flags::flags_handler,
+        flow_control::FlowControl,
+        mqtt_stream::MqttStream,
+        reason_codes::ReasonCode,
+        topic_alias::{TopicAliases, DEFAULT_TOPIC_ALIAS_MAXIMUM},
+        topic_filter::is_valid_topic_name,
+    },
+    logging::actions::MqttActions,
     mqtt_packets::{
         packets::{
             connect::Connect, publish::Publish, subscribe::Subscribe, unsubscribe::Unsubscribe,
         },
         properties::{
             connack_properties::ConnackProperties, puback_properties::PubackProperties,
-            suback_properties::SubackProperties, unsuback_properties::UnsubackProperties,
+            pubrec_properties::PubrecProperties, suback_properties::SubackProperties,
+            unsuback_properties::UnsubackProperties,
         },
     },
 };
 
 use super::mqtt_server::MqttServer;
 
+/// Prefijo de la Response Information que se envia a los clientes que la piden
+const RESPONSE_INFORMATION_PREFIX: &str = "response";
+
 pub fn determinate_connect_acknowledge(
     server: &mut MqttServer,
-    connect: Connect,
-    stream_connection: TcpStream,
+    mut connect: Connect,
+    stream_connection: MqttStream,
+    logger: &Logger,
 ) -> Result<ConnackProperties, Error> {
     // Si no recibe ninguna conexión en cierta cantidad de tiempo debe cortar la conexión (timer!)
 
@@ -27,10 +42,10 @@ pub fn determinate_connect_acknowledge(
     // Si will flag == 1, will retain puede ser 0 o 1. En caso de ser 1, el servidor debe almacenar el mensaje y enviarlo a los suscriptores en caso de que el cliente se desconecte
     // (si will retain == 0, debe enviarse como un normal message, si will retain == 1, debe enviarse como un Retained Message)
     // - Username y password flags determinan que hayan respectivos username y password en el payload del CONNECT
-    // - Keep Alive: El tiempo en segundos que el cliente espera entre dos mensajes de control. Si el servidor no recibe un mensaje de control en ese tiempo, debe cerrar la conexion
+    // - Keep Alive: El tiempo en segundos que el cliente espera entre dos mensajes de control.
     // Si keep alive != 0, el cliente debe enviar un PINGREQ packet al servidor en ese tiempo.
-    // Si el servidor no recibe en x1.5 veces el tiempo de keep alive un MQTT Control Packet, debe cerrar la Network Connection como si haya fallado
-    // Si el server envia un Server Keep Alive en el CONNACK packet, se debe usar ese valor
+    // Si el servidor no recibe en x1.5 veces el tiempo de keep alive un MQTT Control Packet, cierra la Network Connection como si haya fallado
+    // (ver `keep_alive_timeout`). Si el servidor tiene configurado un Server Keep Alive, lo envia en el CONNACK y se usa ese valor
 
     // Se inicia la sesion de la conexion entre el cliente y el servidor.
     // El cliente y el servidor deben asociar el estado con el Client Identifier
@@ -40,67 +55,221 @@ pub fn determinate_connect_acknowledge(
     // let connack_properties = server.determinate_connack_properties(&connect);
 
     let mut connack_properties = ConnackProperties {
-        connect_reason_code: determinate_reason_code(server, &connect),
+        connect_reason_code: determinate_reason_code(&connect),
+        wildcard_subscription_available: Some(1),
+        shared_subscription_available: Some(1),
+        subscription_identifiers_available: Some(1),
+        topic_alias_maximum: Some(
+            server
+                .config
+                .topic_alias_maximum
+                .unwrap_or(DEFAULT_TOPIC_ALIAS_MAXIMUM),
+        ),
+        server_keep_alive: server.config.keep_alive,
+        receive_maximum: server.config.receive_maximum,
+        maximum_packet_size: server.config.maximum_packet_size,
         ..Default::default()
     };
 
+    let keep_alive = server
+        .config
+        .keep_alive
+        .unwrap_or(connect.properties.keep_alive);
+    stream_connection.set_read_timeout(keep_alive_timeout(keep_alive))?;
+
+    // Session Expiry Interval: si supera el maximo del servidor, se usa el maximo y se informa en el CONNACK
+    if let Some(maximum) = server.config.maximum_session_expiry_interval {
+        if connect.properties.session_expiry_interval.unwrap_or(0) > maximum {
+            connect.properties.session_expiry_interval = Some(maximum);
+            connack_properties.session_expiry_interval = Some(maximum);
+        }
+    }
+
+    // Request Response Information: si es 1, se informa en el CONNACK un topico propio del
+    // cliente a partir del cual armar los Response Topic de sus solicitudes
+    if connect.properties.request_response_information == Some(1) {
+        connack_properties.response_information = Some(format!(
+            "{}/{}",
+            RESPONSE_INFORMATION_PREFIX, connect.payload.client_id
+        ));
+    }
+
     // Clean start: si es 1, el cliente y servidor deben descartar cualquier session state asociado con el Client Identifier. Session Present flag in connack = 0
     // Clean Start: si es 0, el cliente y servidor deben mantener el session state asociado con el Client Identifier.
     // En caso de que no exista dicha sesion, hay que crearla
+    // Si la sesion descartada tenia un mensaje de voluntad demorado, se publica ya que la sesion terminó
     if flags_handler::get_connect_flag_clean_start(connect.properties.connect_flags) == 1 {
-        server.register.clean_session(&connect.payload.client_id);
+        if let Some(will_message) = server.register.clean_session(&connect.payload.client_id) {
+            server
+                .register
+                .publish_will(&server.network, &connect.payload.client_id, will_message)
+                .log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+        }
     }
     // - Will Flag: si es 1, un Will Message debe ser almacenado en el servidor y asociado a la sesion.
     // El will message esta compuesto de will properties, will topic y will payload fields del payload del CONNECT packet.
     // El will message debe ser publicado despues de que una network connection se cierra y la sesion expira, o el willdelay interval haya pasado
     // El will message debe ser borrado en caso de que el servidor reciba un DISCONNECT packet con reason code 0x00, o una nueva Network Connection con Clean Start = 1
     // con el mismo client identifier. Tambien debe ser borrado de la session state en caso de que ya haya sido publicado
-    server
-        .network
-        .connections
-        .insert(connect.payload.client_id.clone(), stream_connection);
+    // - Topic Alias Maximum: cada parte usa a lo sumo la cantidad de aliases que anuncio la otra
+    let topic_aliases = TopicAliases::new(
+        connack_properties.topic_alias_maximum.unwrap_or(0),
+        connect.properties.topic_alias_maximum.unwrap_or(0),
+    );
+    // - Receive Maximum y Maximum Packet Size: cada parte respeta los limites que anuncio la otra
+    let flow_control = FlowControl::new(
+        connack_properties.receive_maximum,
+        connack_properties.maximum_packet_size,
+        connect.properties.receive_maximum,
+        connect.properties.maximum_packet_size,
+    );
+    server.network.add_connection(
+        connect.payload.client_id.clone(),
+        stream_connection,
+        topic_aliases,
+        flow_control,
+    );
     connack_properties.connect_acknowledge_flags = server.register.open_session(connect);
 
     Ok(connack_properties)
 }
 
+/// ### keep_alive_timeout
+///
+/// Tiempo maximo sin recibir paquetes de un cliente antes de cerrar su conexion
+///
+/// ### Parametros
+/// - `keep_alive`: keep alive de la conexion (en segundos)
+///
+/// ### Retorno
+/// - `Option<Duration>`: 1.5 veces el keep alive, o `None` si el keep alive es 0 (deshabilitado)
+///
+pub fn keep_alive_timeout(keep_alive: u16) -> Option<Duration> {
+    if keep_alive == 0 {
+        return None;
+    }
+    Some(Duration::from_millis(keep_alive as u64 * 1500))
+}
+
 /// ### determinate_publish_acknowledge
 ///
 /// Determina la respuesta a un paquete de publicación
 ///
 /// ### Parametros
 /// - `publish`: Paquete de publicación
+/// - `authorized`: si la ACL permite al cliente publicar en el topico
+/// - `has_subscribers`: si la publicación tiene suscriptores
 ///
 /// ### Retorno
 /// - `Result<PubackProperties, Error>`: Resultado de la operación
 ///     
-pub fn determinate_publish_acknowledge(publish: Publish) -> Result<PubackProperties, Error> {
+pub fn determinate_publish_acknowledge(
+    publish: Publish,
+    authorized: bool,
+    has_subscribers: bool,
+) -> Result<PubackProperties, Error> {
+    let puback_reason_code = if !is_valid_topic_name(&publish.properties.topic_name) {
+        ReasonCode::TopicNameInvalid.get_id()
+    } else if !authorized {
+        ReasonCode::NotAuthorized.get_id()
+    } else if !has_subscribers {
+        ReasonCode::NoMatchingSubscribers.get_id()
+    } else {
+        ReasonCode::Success.get_id()
+    };
+
     let puback_properties = PubackProperties {
         packet_id: publish.properties.packet_identifier,
-        puback_reason_code: ReasonCode::Success.get_id(),
+        puback_reason_code,
         ..Default::default()
     };
 
     Ok(puback_properties)
 }
 
+/// ### determinate_publish_received
+///
+/// Determina la respuesta a un paquete de publicación QoS 2
+///
+/// ### Parametros
+/// - `publish`: Paquete de publicación
+/// - `authorized`: si la ACL permite al cliente publicar en el topico
+/// - `has_subscribers`: si la publicación tiene suscriptores
+///
+/// ### Retorno
+/// - `Result<PubrecProperties, Error>`: Resultado de la operación
+///
+pub fn determinate_publish_received(
+    publish: Publish,
+    authorized: bool,
+    has_subscribers: bool,
+) -> Result<PubrecProperties, Error> {
+    let pubrec_reason_code = if !is_valid_topic_name(&publish.properties.topic_name) {
+        ReasonCode::TopicNameInvalid.get_id()
+    } else if !authorized {
+        ReasonCode::NotAuthorized.get_id()
+    } else if !has_subscribers {
+        ReasonCode::NoMatchingSubscribers.get_id()
+    } else {
+        ReasonCode::Success.get_id()
+    };
+
+    let pubrec_properties = PubrecProperties {
+        packet_id: publish.properties.packet_identifier,
+        pubrec_reason_code,
+        ..Default::default()
+    };
+
+    Ok(pubrec_properties)
+}
+
 /// ### determinate_subscribe_acknowledge
 ///
 /// Determina la respuesta a un paquete de subscripción
 ///
 /// ### Parametros
 /// - `subscribe`: Paquete de subscripción
+/// - `authorized`: si la ACL permite al cliente cada subscripción, en el mismo orden que los filtros
 ///
 /// ### Retorno
 /// - `Result<SubackProperties, Error>`: Resultado de la operación
 ///
-pub fn determinate_subscribe_acknowledge(subscribe: Subscribe) -> Result<SubackProperties, Error> {
+/// ### Consideraciones
+/// - Se devuelve un reason code por cada filtro de topicos, en el mismo orden
+/// - Los filtros invalidos se responden con `TopicFilterInvalid`
+/// - Los filtros que la ACL no permite se responden con `NotAuthorized`
+///
+pub fn determinate_subscribe_acknowledge(
+    subscribe: Subscribe,
+    authorized: &[bool],
+) -> Result<SubackProperties, Error> {
+    let reason_codes = subscribe
+        .properties
+        .topic_filters
+        .iter()
+        .zip(authorized)
+        .map(|(topic_filter, authorized)| {
+            if !topic_filter.is_valid() {
+                return ReasonCode::TopicFilterInvalid.get_id();
+            }
+            if !authorized {
+                return ReasonCode::NotAuthorized.get_id();
+            }
+            match flags_handler::get_subscribe_max_qos(topic_filter.subscription_options) {
+                0 => ReasonCode::GrantedQoS0.get_id(),
+                1 => ReasonCode::GrantedQoS1.get_id(),
+                _ => ReasonCode::GrantedQoS2.get_id(),
+            }
+        })
+        .collect();
+
     let suback_properties = SubackProperties {
         packet_identifier: subscribe.properties.packet_identifier,
-        reason_codes: vec![
-            ReasonCode::Success.get_id(),
-            ReasonCode::NotAuthorized.get_id(),
-        ],
+        reason_codes,
         ..Default::default()
     };
 
@@ -113,16 +282,24 @@ pub fn determinate_subscribe_acknowledge(subscribe: Subscribe) -> Result<SubackP
 ///
 /// ### Parametros
 /// - `unsubscribe`: Paquete de desubscripción
+/// - `authorized`: si el cliente puede desubscribir la sesión de los filtros
 ///
 /// ### Retorno
-/// - `Result<UnsubackProperties, Error>`: Resultado de la operación
+/// - `Result<UnsubackProperties, Error>`: Resultado de la operación, con un reason code
+///   por cada filtro
 ///
 pub fn determinate_unsubscribe_acknowledge(
     unsubscribe: Unsubscribe,
+    authorized: bool,
 ) -> Result<UnsubackProperties, Error> {
+    let reason_code = if authorized {
+        ReasonCode::Success.get_id()
+    } else {
+        ReasonCode::NotAuthorized.get_id()
+    };
     let unsuback_properties = UnsubackProperties {
         packet_identifier: unsubscribe.properties.packet_identifier,
-        reason_codes: vec![ReasonCode::Success.get_id()],
+        reason_codes: vec![reason_code; unsubscribe.properties.topic_filters.len().max(1)],
         ..Default::default()
     };
 
@@ -139,12 +316,11 @@ pub fn determinate_unsubscribe_acknowledge(
 /// ### Retorno
 /// - `u8`: Resultado de la operación
 ///
-pub fn determinate_reason_code(server: &MqttServer, connect_packet: &Connect) -> u8 {
-    // Si ya se recibió un CONNECT packet, se debe procesar como un Protocol Error (reason code 130) y cerrar la conexion.
-    if server.connect_received {
-        return ReasonCode::ProtocolError.get_id();
-    }
-
+/// ### Consideraciones
+/// - Un segundo CONNECT en la misma conexion no llega hasta aqui: el estado de la
+///   conexion lo procesa como Protocol Error (reason code 130) y cierra la conexion
+///
+pub fn determinate_reason_code(connect_packet: &Connect) -> u8 {
     // Protocol Name: "MQTT" - En caso de ser diferente, debe procesarlo como  Unsupported Protocol Version (reason code 132) y cerrar la conexion.
     // Protocol Version: 5 - En caso de ser diferente, debe procesarlo como  Unsupported Protocol Version (reason code 132) y cerrar la conexion.
     if connect_packet.properties.protocol_name != *"MQTT"
@@ -0,0 +1,146 @@
+use crate::{common::flags::flags_handler, mqtt_packets::packets::publish::Publish};
+
+/// ## InflightState
+///
+/// Estado de una publicacion saliente que todavia no fue confirmada por el cliente
+///
+/// ### Variantes
+/// - `AwaitingPuback`: se envio el PUBLISH (QoS 1) y se espera el PUBACK
+/// - `AwaitingPubrec`: se envio el PUBLISH (QoS 2) y se espera el PUBREC
+/// - `AwaitingPubcomp`: se envio el PUBREL y se espera el PUBCOMP
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum InflightState {
+    AwaitingPuback,
+    AwaitingPubrec,
+    AwaitingPubcomp,
+}
+
+impl InflightState {
+    fn get_id(&self) -> u8 {
+        match self {
+            InflightState::AwaitingPubrec => 0,
+            InflightState::AwaitingPubcomp => 1,
+            InflightState::AwaitingPuback => 2,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<InflightState> {
+        match id {
+            0 => Some(InflightState::AwaitingPubrec),
+            1 => Some(InflightState::AwaitingPubcomp),
+            2 => Some(InflightState::AwaitingPuback),
+            _ => None,
+        }
+    }
+}
+
+/// ## InflightMessage
+///
+/// Publicacion (QoS 1 o 2) enviada a un cliente que todavia no completo su flujo de confirmacion
+///
+/// ### Atributos
+/// - `state`: estado del flujo de confirmacion
+/// - `message`: publicacion enviada (con el packet identifier asignado por el servidor)
+///
+#[derive(Clone, Debug)]
+pub struct InflightMessage {
+    pub state: InflightState,
+    pub message: Publish,
+}
+
+impl InflightMessage {
+    /// ### new
+    ///
+    /// Crea un mensaje en vuelo a la espera de su primera confirmacion
+    /// (PUBACK para QoS 1, PUBREC para QoS 2)
+    ///
+    /// #### Parametros
+    /// - `message`: publicacion enviada
+    ///
+    pub fn new(message: Publish) -> Self {
+        let state = if flags_handler::get_publish_qos_level(message.fixed_header_flags) == 1 {
+            InflightState::AwaitingPuback
+        } else {
+            InflightState::AwaitingPubrec
+        };
+        InflightMessage { state, message }
+    }
+
+    /// ### packet_id
+    ///
+    /// Devuelve el packet identifier de la publicacion
+    ///
+    pub fn packet_id(&self) -> u16 {
+        self.message.properties.packet_identifier
+    }
+
+    pub fn size_of(&self) -> usize {
+        1 + self.message.size_of()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![self.state.get_id()];
+        if let Ok(message) = self.message.as_bytes() {
+            bytes.extend_from_slice(message.as_ref());
+        }
+        bytes
+    }
+
+    pub fn from_be_bytes(bytes: Vec<u8>) -> Option<InflightMessage> {
+        let state = InflightState::from_id(*bytes.first()?)?;
+        let message = Publish::from_be_bytes(bytes.get(1..)?.to_vec()).ok()?;
+
+        Some(InflightMessage { state, message })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mqtt_packets::properties::publish_properties::PublishProperties;
+
+    use super::*;
+
+    #[test]
+    fn test_serialization() {
+        let properties = PublishProperties {
+            topic_name: "incidents".to_string(),
+            packet_identifier: 7,
+            payload_format_indicator: Some(1),
+            application_message: b"incident".to_vec(),
+            ..Default::default()
+        };
+        let mut inflight = InflightMessage::new(Publish::new(0, 2, 0, properties));
+        inflight.state = InflightState::AwaitingPubcomp;
+
+        let bytes = inflight.as_bytes();
+        assert_eq!(bytes.len(), inflight.size_of());
+
+        let recovered = InflightMessage::from_be_bytes(bytes).unwrap();
+        assert_eq!(recovered.state, InflightState::AwaitingPubcomp);
+        assert_eq!(recovered.packet_id(), 7);
+        assert_eq!(
+            flags_handler::get_publish_qos_level(recovered.message.fixed_header_flags),
+            2
+        );
+        assert_eq!(
+            recovered.message.properties.application_message,
+            b"incident".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_initial_state_depends_on_qos() {
+        let properties = PublishProperties {
+            topic_name: "drone".to_string(),
+            packet_identifier: 1,
+            ..Default::default()
+        };
+
+        let at_least_once = InflightMessage::new(Publish::new(0, 1, 0, properties.clone()));
+        let exactly_once = InflightMessage::new(Publish::new(0, 2, 0, properties));
+
+        assert_eq!(at_least_once.state, InflightState::AwaitingPuback);
+        assert_eq!(exactly_once.state, InflightState::AwaitingPubrec);
+    }
+}
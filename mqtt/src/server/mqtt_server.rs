@@ -1,12 +1,18 @@
-use std::io::Error;
-use std::net::TcpStream;
-use std::sync::mpsc::{self, Receiver, Sender};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::fs;
+use std::io::{Error, ErrorKind};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
 
 use logger::logger_handler::Logger;
 
-use crate::config::{mqtt_config::Config, server_config::ServerConfig};
+use crate::common::websocket::WebSocketTransport;
+use crate::common::{flow_control::FlowControl, mqtt_stream::MqttStream, reason_codes::ReasonCode};
+use crate::config::{
+    listener_config::{ListenerConfig, ListenerProtocol},
+    server_config::ServerConfig,
+};
 use crate::logging::actions::MqttActions;
 use crate::logging::server_actions::MqttServerActions;
 use crate::mqtt_packets::headers::fixed_header::PacketFixedHeader;
@@ -18,8 +24,42 @@ use super::server_handlers::{
     connect_handler, disconnect_handler, publish_handler, subscribe_handler, unsubscribe_handler,
 };
 
+use super::retained_messages::RetainedMessages;
+use super::server_acl::ServerAcl;
+use super::server_admin::AdminSocket;
+use super::server_authentication::ConnectionAuthentication;
+use super::server_bridge::{MqttBridge, ServerBridges};
+use super::server_credentials::ServerCredentials;
 use super::server_network::ServerNetwork;
 use super::server_register::SessionRegister;
+use super::server_shutdown::ShutdownSignal;
+use super::server_stats::{ServerStats, StatsReport};
+
+/// Intervalo con el que se revisan las sesiones expiradas y los mensajes de voluntad demorados
+const SESSION_REAPER_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Intervalo con el que se persisten las sesiones que cambiaron sin confirmarse a un cliente
+/// (desconexiones, entregas, colas de publicaciones QoS 0): es lo que puede perderse si el
+/// servidor se cae
+const SESSION_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Intervalo con el que se revisa si cambió el archivo de credenciales
+const CREDENTIALS_RELOAD_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Intervalo con el que el listener revisa si se pidió el cierre del servidor
+/// mientras no recibe conexiones
+pub const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Handshake de una conexion entrante (TLS, WebSocket) que establece el stream
+/// de la sesion y el sujeto del certificado del cliente, si presentó uno
+type ConnectionHandshake =
+    dyn Fn(TcpStream) -> Result<(MqttStream, Option<String>), Error> + Send + Sync;
+
+/// Conexiones abiertas de un listener: el thread que las procesa y su socket
+type OpenConnections = Vec<(JoinHandle<()>, TcpStream)>;
+
+/// Thread de un listener, que al terminar devuelve sus conexiones abiertas
+type ListenerHandle = JoinHandle<Result<OpenConnections, Error>>;
 
 /// ## MqttServer
 ///
@@ -27,61 +67,113 @@ use super::server_register::SessionRegister;
 ///
 /// ### Atributos
 /// - `config`: Configuración del servidor
-/// - `sessions`: Sesiones del servidor
-/// - `connect_received`: Bandera que indica si se recibió un paquete de conexión
+/// - `register`: Sesiones del servidor
+/// - `retained_messages`: Mensajes retenidos por topico
+/// - `network`: Conexiones activas de los clientes
+/// - `credentials`: Credenciales de los usuarios habilitados
+/// - `acl`: Lista de control de acceso a los topicos
+/// - `stats`: Contadores de la actividad del servidor
+/// - `shutdown_signal`: Señal con la que se pide el cierre del servidor
+/// - `bridges`: Puentes que reenvian las publicaciones a servidores remotos
 ///
+/// ### Consideraciones
+/// - Cada conexion procesa sus paquetes en su propio thread, con un clon del servidor.
+///   Los clones comparten el registro de sesiones, los mensajes retenidos y las conexiones
+///
+#[derive(Clone)]
 pub struct MqttServer {
     pub config: ServerConfig,
     pub register: SessionRegister,
+    pub retained_messages: RetainedMessages,
     pub network: ServerNetwork,
-    pub connect_received: bool,
-    pub users: Vec<String>,
+    pub credentials: ServerCredentials,
+    pub acl: ServerAcl,
+    pub stats: ServerStats,
+    pub shutdown_signal: ShutdownSignal,
+    pub bridges: ServerBridges,
 }
 
-impl Clone for MqttServer {
-    fn clone(&self) -> Self {
-        MqttServer {
-            config: self.config.clone(),
-            register: self.register.clone(),
-            network: self.network.clone(),
-            connect_received: self.connect_received,
-            users: self.users.clone(),
+/// ## ConnectionEvent
+///
+/// Evento de una conexion que debe procesar el servidor
+///
+/// ### Variantes
+/// - `Packet`: Paquete recibido del cliente
+/// - `KeepAliveTimeout`: El cliente no envió paquetes durante 1.5 veces su keep alive
+/// - `ConnectionLost`: La conexion se cerró o falló sin recibir un DISCONNECT
+/// - `PacketTooLarge`: El cliente envió un paquete mayor al Maximum Packet Size del servidor
+///
+pub enum ConnectionEvent {
+    Packet(PacketReceived),
+    KeepAliveTimeout,
+    ConnectionLost,
+    PacketTooLarge,
+}
+
+/// ## ConnectionState
+///
+/// Estado de una conexion con un cliente
+///
+/// ### Variantes
+/// - `AwaitingConnect`: Se espera el CONNECT, que debe ser el primer paquete de la conexion
+/// - `Authenticating`: Se recibió el CONNECT y se intercambian paquetes AUTH antes del CONNACK
+/// - `Connected`: Se aceptó el CONNECT del cliente, con el id del cliente de la conexion
+/// - `Closed`: La conexion fue cerrada y no se leen mas paquetes
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConnectionState {
+    AwaitingConnect,
+    Authenticating,
+    Connected(String),
+    Closed,
+}
+
+impl ConnectionState {
+    /// ### client_id
+    ///
+    /// Id del cliente de la conexion, si ya fue aceptada
+    ///
+    pub fn client_id(&self) -> Option<&str> {
+        match self {
+            ConnectionState::Connected(client_id) => Some(client_id),
+            _ => None,
         }
     }
 }
 
 /// ## message_catcher
 ///
-/// Función que maneja los paquetes recibidos por el servidor
+/// Función que lee el siguiente paquete de una conexión
 ///
 /// ### Parametros
 /// - `stream`: Stream de la conexión
-/// - `sender`: Sender del servidor (envia los mensajes para que sean procesados)
+/// - `flow_control`: Limites de flujo que anunció el servidor
 ///
 /// ### Retorno
-/// - `Result<(), Error>`: Resultado de la operación
+/// - `ConnectionEvent`: Evento a procesar
 ///
-pub fn message_catcher(
-    mut stream: TcpStream,
-    sender: Arc<Mutex<Sender<(PacketReceived, TcpStream)>>>,
-) -> Result<(), Error> {
-    let sender = sender.lock().unwrap().clone();
-
-    let fixed_header = PacketFixedHeader::read_from(&mut stream)?;
+/// ### Consideraciones
+/// - Si vence el keep alive de la conexion se devuelve `ConnectionEvent::KeepAliveTimeout`
+/// - Un paquete mayor al Maximum Packet Size no se lee: se devuelve `ConnectionEvent::PacketTooLarge`
+/// - Cualquier otro error de lectura se considera una falla de la conexion
+///
+pub fn message_catcher(stream: &mut MqttStream, flow_control: &FlowControl) -> ConnectionEvent {
+    let fixed_header = match PacketFixedHeader::read_from(stream) {
+        Ok(fixed_header) => fixed_header,
+        Err(e) if e.kind() == ErrorKind::TimedOut => return ConnectionEvent::KeepAliveTimeout,
+        Err(_) => return ConnectionEvent::ConnectionLost,
+    };
+    if flow_control.check_received_packet(&fixed_header).is_err() {
+        return ConnectionEvent::PacketTooLarge;
+    }
 
     match get_packet(
-        &mut stream,
+        stream,
         fixed_header.get_package_type(),
         fixed_header.remaining_length,
     ) {
-        Ok(pack) => match sender.send((pack, stream)) {
-            Ok(_) => Ok(()),
-            Err(_) => Err(Error::new(
-                std::io::ErrorKind::Other,
-                "Server - Error al enviar el paquete",
-            )),
-        },
-        Err(e) => Err(e),
+        Ok(pack) => ConnectionEvent::Packet(pack),
+        Err(_) => ConnectionEvent::ConnectionLost,
     }
 }
 
@@ -90,42 +182,82 @@ pub fn message_catcher(
 /// Función que maneja las conexiones de los clientes
 ///
 /// ### Parametros
-/// - `listener`: Listener del servidor
-/// - `sender`: Sender del servidor (envia los mensajes para que sean procesados)
+/// - `server`: Servidor (cada conexion recibe un clon)
+/// - `listener`: Listener en el que se aceptan las conexiones
+/// - `handshake`: Handshake que establece el stream de cada conexion
+/// - `allow_anonymous`: si el listener acepta conexiones sin autenticación
+/// - `logger`: Logger del servidor
+///
+/// ### Consideraciones
+/// - Todos los paquetes de la sesion viajan sobre el stream
+///   establecido en el handshake (encapsulado en un `MqttStream`)
+/// - El handshake y el procesamiento de los paquetes se realizan en el thread
+///   de la conexion, por lo que un cliente lento no demora a los demas
+/// - Deja de aceptar conexiones cuando se pide el cierre del servidor, devolviendo
+///   las conexiones que siguen abiertas (el thread que las procesa y su socket)
 ///
 fn client_handler(
-    //listener: TcpListener,
-    server_connector: TlsServerConnector,
-    sender: Arc<Mutex<Sender<(PacketReceived, TcpStream)>>>,
-) -> Result<(), Error> {
-    let listener = server_connector.get_listener().unwrap();
-    let srv_cpy = Arc::new(server_connector);
-
-    for client_stream in listener.incoming() {
-        let client_stream = match client_stream {
+    server: &MqttServer,
+    listener: TcpListener,
+    handshake: Arc<ConnectionHandshake>,
+    allow_anonymous: bool,
+    logger: &Logger,
+) -> Result<OpenConnections, Error> {
+    listener.set_nonblocking(true)?;
+    let mut connections: OpenConnections = Vec::new();
+
+    while !server.shutdown_signal.is_triggered() {
+        let client_stream = match listener.accept() {
+            Ok((client_stream, _)) => client_stream,
+            Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                server.shutdown_signal.wait(ACCEPT_POLL_INTERVAL);
+                continue;
+            }
             Err(e) => return Err(e),
-            Ok(client_stream) => client_stream,
         };
-
-        let srv = srv_cpy.clone();
-        //let srv = srv_cpy.clone();
-        let mut stream = srv
-            .accept_tls_connection(client_stream)
-            .expect("error al aceptar conexion");
-
-        let stream = stream.get_mut();
-
-        let stream = stream.try_clone()?;
-        let sender_clone = Arc::clone(&sender);
-        thread::spawn(move || -> Result<(), Error> {
-            loop {
-                // Manejo de paquetes, cuando se recibe un paquete se envia al procesador de mensajes
-                message_catcher(stream.try_clone()?, sender_clone.clone())?;
-            }
+        client_stream.set_nonblocking(false)?;
+        let socket = client_stream.try_clone()?;
+        connections.retain(|(connection, _)| !connection.is_finished());
+
+        let handshake = handshake.clone();
+        let connection_server = server.clone();
+        let logger = logger.clone();
+        let connection = thread::spawn(move || {
+            let traffic = connection_server.stats.traffic();
+            let (stream, certificate_subject) = match handshake(client_stream) {
+                Ok((stream, subject)) => (stream.with_traffic_counters(traffic), subject),
+                Err(e) => {
+                    logger.log_event(
+                        &("Error al aceptar conexion: ".to_string() + &e.to_string()),
+                        &connection_server.config.general.id,
+                    );
+                    logger.close();
+                    return;
+                }
+            };
+            let authentication = ConnectionAuthentication {
+                certificate_subject,
+                allow_anonymous,
+                ..Default::default()
+            };
+            connection_server.handle_connection(stream, authentication, &logger);
+            logger.close();
         });
+        connections.push((connection, socket));
     }
 
-    Ok(())
+    Ok(connections)
+}
+
+/// ## close_connections
+///
+/// Cierra los sockets de las conexiones y espera a que terminen sus threads
+///
+fn close_connections(connections: OpenConnections) {
+    for (connection, socket) in connections {
+        let _ = socket.shutdown(Shutdown::Both);
+        let _ = connection.join();
+    }
 }
 
 impl MqttServer {
@@ -135,20 +267,64 @@ impl MqttServer {
     ///
     /// ### Parametros
     /// - `config`: Configuración del servidor
+    /// - `users`: Usuarios habilitados, que se autentican con la contraseña de la configuración
     ///
     pub fn new(config: ServerConfig, users: Vec<String>) -> Self {
-        let register = SessionRegister::new(config.db_path.clone());
+        let credentials = ServerCredentials::from_password(&users, &config.general.password);
+        MqttServer::with_credentials(config, credentials)
+    }
+
+    /// ### with_credentials
+    ///
+    /// Crea un nuevo servidor MQTT con las credenciales de sus usuarios
+    ///
+    /// ### Parametros
+    /// - `config`: Configuración del servidor
+    /// - `credentials`: Credenciales de los usuarios habilitados
+    ///
+    /// ### Consideraciones
+    /// - Si las credenciales se cargaron de un archivo, el servidor lo recarga cuando se modifica
+    ///
+    pub fn with_credentials(config: ServerConfig, credentials: ServerCredentials) -> Self {
+        let register = SessionRegister::new(config.db_path.clone())
+            .with_queue_limit(config.max_queued_messages, config.queue_overflow_policy);
+        let retained_messages = RetainedMessages::new(&config.db_path);
         let network = ServerNetwork::default();
 
         MqttServer {
             config,
             register,
+            retained_messages,
             network,
-            connect_received: false,
-            users,
+            credentials,
+            acl: ServerAcl::default(),
+            stats: ServerStats::default(),
+            shutdown_signal: ShutdownSignal::default(),
+            bridges: ServerBridges::default(),
         }
     }
 
+    /// ### with_acl
+    ///
+    /// Restringe los topicos en los que cada cliente puede publicar y subscribirse
+    ///
+    /// ### Parametros
+    /// - `acl`: Lista de control de acceso a los topicos
+    ///
+    pub fn with_acl(mut self, acl: ServerAcl) -> Self {
+        self.acl = acl;
+        self
+    }
+
+    /// ### shutdown
+    ///
+    /// Pide el cierre del servidor. Puede llamarse desde cualquier clon del servidor,
+    /// por ejemplo al recibir una señal del sistema
+    ///
+    pub fn shutdown(&self) {
+        self.shutdown_signal.trigger();
+    }
+
     /// ### start_server
     ///
     /// Inicia el servidor MQTT
@@ -156,67 +332,182 @@ impl MqttServer {
     /// ### Retorno
     /// - `Result<(), Error>`: Resultado de la operación
     ///
+    /// ### Consideraciones
+    /// - Atiende conexiones hasta que se pide el cierre del servidor con `shutdown`.
+    ///   Entonces desconecta a los clientes, persiste su estado y cierra el logger
+    ///   dentro del plazo `shutdown_timeout`. Si no lo logra, devuelve `ErrorKind::TimedOut`
+    ///
     pub fn start_server(self, logger: Logger) -> Result<(), Error> {
         let id = self.config.general.id.clone();
         let logger_cpy = logger.clone();
 
+        for error in [
+            self.register.load_error(),
+            self.retained_messages.load_error(),
+        ]
+        .into_iter()
+        .flatten()
+        {
+            MqttServerActions::RecoverCorruptDatabase(error.clone()).log_action(
+                &self.config.general.id,
+                &logger,
+                &self.config.general.log_in_term,
+            );
+        }
         self.register.log_sessions(
             &self.config.general.id,
             &self.config.general.log_in_term,
             &logger,
         );
 
-        let address = self.config.get_socket_address().to_string();
-        let cert_path = self.config.general.cert_path.clone();
-        let cert_pass = self.config.general.cert_pass.clone();
-        let server_connector =
-            match TlsServerConnector::initialize(&cert_path, &cert_pass, &address) {
-                Ok(srv) => srv,
+        // Cada listener acepta conexiones en su propio thread, y cada conexion procesa
+        // sus paquetes en su propio thread
+        let mut listeners = Vec::new();
+        for listener in &self.config.listeners {
+            match self.start_listener(listener, &logger) {
+                Ok(handle) => listeners.push(handle),
                 Err(e) => {
                     logger_cpy.log_event(
-                        &("Error al conectar con servidor: ".to_string() + &e.to_string()),
-                        &self.config.general.id,
+                        &format!("Error al iniciar el listener {}: {}", listener, e),
+                        &id,
                     );
+                    // Se detienen los listeners iniciados antes de cerrar el logger
+                    self.shutdown();
+                    for handle in listeners {
+                        if let Ok(Ok(connections)) = handle.join() {
+                            close_connections(connections);
+                        }
+                    }
                     logger_cpy.close();
                     logger.close();
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("TLS error de conexion: {}", e),
-                    ));
+                    return Err(e);
                 }
-            };
-
-        //let listener = server_connector.get_listener()?;
-
-        let (sender, receiver) = mpsc::channel();
-
-        let sender = Arc::new(Mutex::new(sender));
-
-        let receiver = Arc::new(Mutex::new(receiver));
+            }
+        }
 
-        // Iniciando el procesador de mesages que recibe el servidor
-        self.server_listener_messages(Arc::clone(&receiver), logger_cpy.clone());
+        let mut workers = vec![
+            self.session_reaper(logger.clone()),
+            self.session_flusher(logger.clone()),
+        ];
+        workers.extend(self.credentials_watcher(logger.clone()));
+        workers.extend(self.stats_publisher(logger.clone()));
+        workers.extend(self.admin_listener(logger.clone()));
+        for bridge in &self.config.bridges {
+            workers.push(MqttBridge::start(&self, bridge.clone(), logger.clone()));
+        }
 
-        // Iniciando el listener de conexiones que recibe el servidor dentro de un thread pool
-        // agregar tls connector
-        client_handler(server_connector, Arc::clone(&sender))?;
+        // Se atienden conexiones hasta que se pide el cierre o falla un listener
+        while !self.shutdown_signal.wait(ACCEPT_POLL_INTERVAL) {
+            if listeners.iter().any(|handle| handle.is_finished()) {
+                break;
+            }
+        }
+        self.shutdown();
+
+        let mut connections = Vec::new();
+        let mut listener_error = None;
+        for handle in listeners {
+            match handle.join() {
+                Ok(Ok(listener_connections)) => connections.extend(listener_connections),
+                Ok(Err(e)) => {
+                    logger.log_event(
+                        &("Error en un listener: ".to_string() + &e.to_string()),
+                        &id,
+                    );
+                    listener_error = Some(e);
+                }
+                Err(_) => {}
+            }
+        }
 
         logger.log_event(
             &("Cerrando servidor ... no se reciben mas paquetes".to_string()),
             &id,
         );
-
         logger_cpy.close();
-        logger.close();
-        Err(Error::new(
-            std::io::ErrorKind::Other,
-            "No se pudo recibir el paquete",
-        ))
+        let result = self.shutdown_gracefully(workers, connections, logger);
+        match listener_error {
+            Some(e) => Err(e),
+            None => result,
+        }
+    }
+
+    /// ### shutdown_gracefully
+    ///
+    /// Apaga el servidor una vez que dejó de aceptar conexiones
+    ///
+    /// ### Parametros
+    /// - `workers`: threads del servidor, que terminan al pedirse el cierre
+    /// - `connections`: conexiones abiertas, que se cierran despues de desconectar a los clientes
+    ///
+    /// ### Consideraciones
+    /// - Tambien se cierran las conexiones que no llegaron a enviar el CONNECT
+    /// - El cierre se realiza en otro thread, para no esperar mas del plazo configurado.
+    ///   Si se vence el plazo, ese thread continua pero el servidor no lo espera
+    ///
+    fn shutdown_gracefully(
+        &self,
+        workers: Vec<JoinHandle<()>>,
+        connections: OpenConnections,
+        logger: Logger,
+    ) -> Result<(), Error> {
+        let timeout = self.config.shutdown_timeout;
+        MqttServerActions::StartShutdown(timeout).log_action(
+            &self.config.general.id,
+            &logger,
+            &self.config.general.log_in_term,
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let mut server = self.clone();
+        let shutdown_logger = logger.clone();
+        thread::spawn(move || {
+            server.disconnect(&shutdown_logger);
+            close_connections(connections);
+            for worker in workers {
+                let _ = worker.join();
+            }
+            shutdown_logger.close();
+            let _ = sender.send(());
+        });
+
+        match receiver.recv_timeout(Duration::from_secs(timeout)) {
+            Ok(()) => {
+                logger.close();
+                Ok(())
+            }
+            Err(_) => {
+                MqttServerActions::ShutdownTimeout(timeout).log_action(
+                    &self.config.general.id,
+                    &logger,
+                    &self.config.general.log_in_term,
+                );
+                logger.close();
+                Err(Error::new(
+                    ErrorKind::TimedOut,
+                    "No se pudo apagar el servidor dentro del plazo",
+                ))
+            }
+        }
     }
 
+    /// ### disconnect
+    ///
+    /// Desconecta a todos los clientes con el reason code `ServerShuttingDown` y
+    /// persiste las sesiones y los mensajes retenidos
+    ///
     pub fn disconnect(&mut self, logger: &Logger) {
         self.register
-            .disconnect_all_sessions(&mut self.network, &mut self.config, logger);
+            .disconnect_all_sessions(&self.network, &self.config, logger);
+        for result in [self.register.flush(), self.retained_messages.flush()] {
+            if let Err(e) = result {
+                MqttServerActions::PersistenceFailed(e.to_string()).log_action(
+                    &self.config.general.id,
+                    logger,
+                    &self.config.general.log_in_term,
+                );
+            }
+        }
 
         MqttServerActions::CloseServer.log_action(
             &self.config.general.id,
@@ -225,38 +516,487 @@ impl MqttServer {
         );
     }
 
-    /// ### process_messages
+    /// ### start_listener
+    ///
+    /// Inicia un listener, que acepta conexiones con su protocolo en su propio thread
+    ///
+    /// ### Parametros
+    /// - `listener`: Configuración del listener
+    ///
+    /// ### Retorno
+    /// - `Result<ListenerHandle, Error>`: thread del listener, que termina al pedirse
+    ///   el cierre del servidor devolviendo sus conexiones abiertas
+    ///
+    /// ### Consideraciones
+    /// - Las conexiones de todos los listeners comparten el registro de sesiones y los handlers
+    /// - Los listeners TLS sin identidad o CA de clientes propias usan las del servidor
+    /// - Con `wss` se realiza el handshake TLS antes del handshake WebSocket
+    ///
+    fn start_listener(
+        &self,
+        listener: &ListenerConfig,
+        logger: &Logger,
+    ) -> Result<ListenerHandle, Error> {
+        let path = listener.websocket_path.clone();
+        let (tcp_listener, handshake): (TcpListener, Arc<ConnectionHandshake>) = match listener
+            .protocol
+        {
+            ListenerProtocol::Tcp => (
+                TcpListener::bind(listener.address)?,
+                Arc::new(|client_stream| Ok((MqttStream::new(client_stream)?, None))),
+            ),
+            ListenerProtocol::WebSocket => (
+                TcpListener::bind(listener.address)?,
+                Arc::new(move |client_stream| {
+                    let transport = WebSocketTransport::accept(client_stream, &path)?;
+                    Ok((MqttStream::new(transport)?, None))
+                }),
+            ),
+            ListenerProtocol::Tls | ListenerProtocol::SecureWebSocket => {
+                let connector = TlsServerConnector::initialize(
+                    listener
+                        .cert_path
+                        .as_deref()
+                        .unwrap_or(&self.config.general.cert_path),
+                    listener
+                        .cert_pass
+                        .as_deref()
+                        .unwrap_or(&self.config.general.cert_pass),
+                    listener
+                        .client_ca_path
+                        .as_deref()
+                        .or(self.config.client_ca_path.as_deref()),
+                    &listener.address.to_string(),
+                )
+                .map_err(|e| Error::other(format!("TLS error de conexion: {}", e)))?;
+                let tcp_listener = connector.get_listener()?;
+                let websocket = listener.protocol.uses_websocket();
+                let handshake: Arc<ConnectionHandshake> = Arc::new(move |client_stream| {
+                    let (tls_stream, subject) = connector.accept_tls_connection(client_stream)?;
+                    let stream = match websocket {
+                        true => MqttStream::new(WebSocketTransport::accept(tls_stream, &path)?)?,
+                        false => MqttStream::new(tls_stream)?,
+                    };
+                    Ok((stream, subject))
+                });
+                (tcp_listener, handshake)
+            }
+        };
+        let allow_anonymous = listener
+            .allow_anonymous
+            .unwrap_or(self.config.allow_anonymous);
+
+        MqttServerActions::StartListener(listener.to_string()).log_action(
+            &self.config.general.id,
+            logger,
+            &self.config.general.log_in_term,
+        );
+        let server = self.clone();
+        let logger = logger.clone();
+        Ok(thread::spawn(move || {
+            let connections =
+                client_handler(&server, tcp_listener, handshake, allow_anonymous, &logger);
+            logger.close();
+            connections
+        }))
+    }
+
+    /// ### session_reaper
+    ///
+    /// Inicia el thread que publica los mensajes de voluntad demorados y
+    /// elimina las sesiones que superaron su Session Expiry Interval
+    ///
+    fn session_reaper(&self, logger: Logger) -> JoinHandle<()> {
+        let server = self.clone();
+        thread::spawn(move || {
+            while !server.shutdown_signal.wait(SESSION_REAPER_INTERVAL) {
+                server.register.expire_sessions(
+                    &server.network,
+                    &server.config.general.id,
+                    &server.config.general.log_in_term,
+                    &logger,
+                );
+            }
+        })
+    }
+
+    /// ### session_flusher
+    ///
+    /// Inicia el thread que persiste periodicamente las sesiones que cambiaron
+    ///
+    /// ### Consideraciones
+    /// - Los cambios que se le confirman a un cliente se persisten antes de enviar la
+    ///   confirmación, sin esperar a este thread
+    /// - Las sesiones se persisten por ultima vez al desconectar a los clientes (`disconnect`)
+    ///
+    fn session_flusher(&self, logger: Logger) -> JoinHandle<()> {
+        let server = self.clone();
+        thread::spawn(move || {
+            while !server.shutdown_signal.wait(SESSION_FLUSH_INTERVAL) {
+                if let Err(e) = server.register.flush() {
+                    MqttServerActions::PersistenceFailed(e.to_string()).log_action(
+                        &server.config.general.id,
+                        &logger,
+                        &server.config.general.log_in_term,
+                    );
+                }
+            }
+        })
+    }
+
+    /// ### credentials_watcher
+    ///
+    /// Inicia el thread que recarga el archivo de credenciales cuando se modifica,
+    /// sin reiniciar el servidor
+    ///
+    /// ### Consideraciones
+    /// - Las conexiones ya autenticadas se mantienen; los cambios aplican a las
+    ///   nuevas conexiones y re-autenticaciones
+    ///
+    fn credentials_watcher(&self, logger: Logger) -> Option<JoinHandle<()>> {
+        self.credentials.path()?;
+        let server = self.clone();
+        Some(thread::spawn(move || {
+            while !server.shutdown_signal.wait(CREDENTIALS_RELOAD_INTERVAL) {
+                let action = match server.credentials.reload_if_modified() {
+                    Ok(None) => continue,
+                    Ok(Some(users)) => MqttServerActions::ReloadCredentials(users),
+                    Err(e) => MqttServerActions::InvalidCredentials(e.to_string()),
+                };
+                action.log_action(
+                    &server.config.general.id,
+                    &logger,
+                    &server.config.general.log_in_term,
+                );
+            }
+        }))
+    }
+
+    /// ### stats_publisher
     ///
-    /// Procesa los mensajes recibidos por el servidor
+    /// Inicia el thread que publica periodicamente las estadisticas del servidor
+    /// en los topicos `$SYS/broker/...`
+    ///
+    /// ### Consideraciones
+    /// - Las tasas por segundo se calculan sobre el intervalo desde la publicacion anterior
+    /// - Con `sys_interval` 0 no se publican estadisticas
+    ///
+    fn stats_publisher(&self, logger: Logger) -> Option<JoinHandle<()>> {
+        if self.config.sys_interval == 0 {
+            return None;
+        }
+        let mut server = self.clone();
+        let interval = Duration::from_secs(self.config.sys_interval);
+        Some(thread::spawn(move || {
+            let mut previous = server.stats.snapshot();
+            while !server.shutdown_signal.wait(interval) {
+                let (connected_sessions, disconnected_sessions) = server.register.count_sessions();
+                let report = StatsReport {
+                    current: server.stats.snapshot(),
+                    previous,
+                    connected_sessions,
+                    disconnected_sessions,
+                    retained_messages: server.retained_messages.len(),
+                    queued_messages: server.register.queued_messages(),
+                };
+                previous = report.current.clone();
+
+                let messages = report.messages();
+                let topics = messages.len();
+                let mut receivers = Vec::new();
+                for (topic, payload) in messages {
+                    for id in publish_handler::publish_server_message(
+                        &mut server,
+                        topic,
+                        payload.into_bytes(),
+                        &logger,
+                    ) {
+                        if !receivers.contains(&id) {
+                            receivers.push(id);
+                        }
+                    }
+                }
+                MqttServerActions::PublishStats(topics, receivers).log_action(
+                    &server.config.general.id,
+                    &logger,
+                    &server.config.general.log_in_term,
+                );
+            }
+        }))
+    }
+
+    /// ### admin_listener
+    ///
+    /// Inicia el thread que atiende los comandos de administración del socket configurado
+    ///
+    /// ### Consideraciones
+    /// - Si no se puede crear el socket, el servidor continua sin recibir comandos
+    /// - Al apagarse el servidor se elimina el archivo del socket
+    ///
+    fn admin_listener(&self, logger: Logger) -> Option<JoinHandle<()>> {
+        let path = self.config.admin_socket_path.clone()?;
+        let admin_socket = match AdminSocket::bind(&path) {
+            Ok(admin_socket) => admin_socket,
+            Err(e) => {
+                logger.log_event(
+                    &("Error al crear el socket de administración: ".to_string() + &e.to_string()),
+                    &self.config.general.id,
+                );
+                return None;
+            }
+        };
+        let server = self.clone();
+        Some(thread::spawn(move || {
+            admin_socket.run(server, logger);
+            let _ = fs::remove_file(path);
+        }))
+    }
+
+    /// ### handle_connection
+    ///
+    /// Procesa los paquetes de una conexion hasta que se cierra
     ///
     /// ### Parametros
-    /// - `receiver`: Receiver de los mensajes
+    /// - `stream`: Stream de la conexión
+    /// - `authentication`: Autenticación de la conexión, con lo establecido en el handshake
     ///
-    fn process_messages(
+    fn handle_connection(
+        mut self,
+        stream: MqttStream,
+        mut authentication: ConnectionAuthentication,
+        logger: &Logger,
+    ) {
+        let mut state = ConnectionState::AwaitingConnect;
+        let flow_control = FlowControl::new(
+            self.config.receive_maximum,
+            self.config.maximum_packet_size,
+            None,
+            None,
+        );
+
+        while state != ConnectionState::Closed {
+            let event = message_catcher(&mut stream.clone(), &flow_control);
+
+            // Una conexion que se cierra antes de ser aceptada no tiene sesion
+            if let ConnectionState::AwaitingConnect | ConnectionState::Authenticating = state {
+                if let ConnectionEvent::ConnectionLost
+                | ConnectionEvent::PacketTooLarge
+                | ConnectionEvent::KeepAliveTimeout = event
+                {
+                    let _ = stream.shutdown();
+                    break;
+                }
+            }
+
+            match self.process_event(
+                &mut state,
+                &mut authentication,
+                event,
+                stream.clone(),
+                logger,
+            ) {
+                Ok(action) => action.log_action(
+                    &self.config.general.id,
+                    logger,
+                    &self.config.general.log_in_term,
+                ),
+                Err(e) => logger.log_event(
+                    &("Error al procesar el mensaje: ".to_string() + &e.to_string()),
+                    &self.config.general.id,
+                ),
+            }
+        }
+
+        // La conexion cerrada (por el cliente, por inactividad o por un administrador)
+        // deja de estar asociada al cliente
+        self.network
+            .remove_connection(&authentication.client_id, &stream);
+    }
+
+    /// ### process_event
+    ///
+    /// Procesa un evento de la conexion segun su estado
+    ///
+    /// ### Parametros
+    /// - `state`: Estado de la conexion (se actualiza segun el evento)
+    /// - `authentication`: Estado de la autenticación de la conexion
+    /// - `event`: Evento a procesar
+    /// - `stream`: Stream de la conexión
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    /// ### Consideraciones
+    /// - El primer paquete debe ser un CONNECT, y no puede recibirse un segundo CONNECT.
+    ///   En ambos casos se cierra la conexion con Protocol Error
+    /// - Mientras dura la autenticación extendida del CONNECT solo se aceptan paquetes AUTH
+    /// - El Topic Alias de las publicaciones se resuelve antes de procesarlas; si es invalido
+    ///   se cierra la conexion
+    /// - Un paquete mayor al Maximum Packet Size, o una publicacion que supera el Receive Maximum
+    ///   del servidor, cierra la conexion
+    ///
+    fn process_event(
+        &mut self,
+        state: &mut ConnectionState,
+        authentication: &mut ConnectionAuthentication,
+        event: ConnectionEvent,
+        stream: MqttStream,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        // Los paquetes de una conexion aceptada se procesan con el id de su cliente,
+        // sin buscar la conexion entre las del servidor
+        let client_id = state.client_id().unwrap_or_default().to_string();
+        let pack = match event {
+            ConnectionEvent::Packet(pack) => pack,
+            ConnectionEvent::KeepAliveTimeout => {
+                *state = ConnectionState::Closed;
+                return disconnect_handler::close_inactive_connection(
+                    self, &client_id, stream, logger,
+                );
+            }
+            ConnectionEvent::ConnectionLost => {
+                *state = ConnectionState::Closed;
+                return disconnect_handler::close_lost_connection(self, &client_id, stream, logger);
+            }
+            ConnectionEvent::PacketTooLarge => {
+                *state = ConnectionState::Closed;
+                return disconnect_handler::close_flow_control_violation(
+                    self,
+                    &client_id,
+                    stream,
+                    ReasonCode::PacketTooLarge,
+                    logger,
+                );
+            }
+        };
+
+        match (&*state, pack) {
+            (ConnectionState::AwaitingConnect, PacketReceived::Connect(connect_pack)) => {
+                connect_handler::receive_connect(
+                    self,
+                    state,
+                    authentication,
+                    stream,
+                    *connect_pack,
+                    logger,
+                )
+            }
+            (
+                ConnectionState::Authenticating | ConnectionState::Connected(_),
+                PacketReceived::Auth(auth_pack),
+            ) => connect_handler::receive_auth(
+                self,
+                state,
+                authentication,
+                stream,
+                *auth_pack,
+                logger,
+            ),
+            (
+                ConnectionState::AwaitingConnect
+                | ConnectionState::Authenticating
+                | ConnectionState::Closed,
+                _,
+            )
+            | (ConnectionState::Connected(_), PacketReceived::Connect(_)) => {
+                *state = ConnectionState::Closed;
+                disconnect_handler::close_protocol_error(self, &client_id, stream, logger)
+            }
+            (_, PacketReceived::Publish(mut pub_packet)) => {
+                if let Err(reason_code) = self
+                    .network
+                    .resolve_topic_alias(&client_id, &mut pub_packet)
+                {
+                    *state = ConnectionState::Closed;
+                    return disconnect_handler::close_invalid_topic_alias(
+                        self,
+                        &client_id,
+                        stream,
+                        reason_code,
+                        logger,
+                    );
+                }
+                if let Err(reason_code) =
+                    publish_handler::check_receive_maximum(self, &client_id, &pub_packet)
+                {
+                    *state = ConnectionState::Closed;
+                    return disconnect_handler::close_flow_control_violation(
+                        self,
+                        &client_id,
+                        stream,
+                        reason_code,
+                        logger,
+                    );
+                }
+                self.process_packet(
+                    &client_id,
+                    stream,
+                    PacketReceived::Publish(pub_packet),
+                    logger,
+                )
+            }
+            (_, PacketReceived::Disconnect(disconnect_pack)) => {
+                *state = ConnectionState::Closed;
+                let action = disconnect_handler::receive_disconnect(
+                    self,
+                    &client_id,
+                    &stream,
+                    *disconnect_pack,
+                    logger,
+                );
+                let _ = stream.shutdown();
+                action
+            }
+            (_, pack) => self.process_packet(&client_id, stream, pack, logger),
+        }
+    }
+
+    /// ### process_packet
+    ///
+    /// Procesa un paquete recibido de un cliente conectado
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `pack`: Paquete recibido
+    ///
+    fn process_packet(
         &mut self,
-        receiver: Arc<Mutex<Receiver<(PacketReceived, TcpStream)>>>,
+        client_id: &str,
+        mut stream: MqttStream,
+        pack: PacketReceived,
         logger: &Logger,
     ) -> Result<MqttServerActions, Error> {
-        let (pack, mut stream) = receiver.lock().unwrap().recv().unwrap();
         match pack {
-            PacketReceived::Connect(connect_pack) => {
-                connect_handler::stablish_connection(self, stream, *connect_pack, logger)
+            PacketReceived::Publish(pub_packet) => publish_handler::resend_publish_to_subscribers(
+                self,
+                client_id,
+                stream,
+                *pub_packet,
+                logger,
+            ),
+            PacketReceived::Pubrec(pubrec_packet) => {
+                publish_handler::release_publish(self, client_id, stream, *pubrec_packet)
             }
-            PacketReceived::Auth(auth_packet) => {
-                connect_handler::authenticate_client(self, *auth_packet)
+            PacketReceived::Pubrel(pubrel_packet) => {
+                publish_handler::complete_publish(self, client_id, stream, *pubrel_packet)
             }
-            PacketReceived::Disconnect(disconnect_pack) => {
-                disconnect_handler::receive_disconnect(self, *disconnect_pack, logger)
+            PacketReceived::Puback(puback_packet) => {
+                publish_handler::receive_puback(self, client_id, *puback_packet, logger)
             }
-            PacketReceived::Publish(pub_packet) => {
-                publish_handler::resend_publish_to_subscribers(self, stream, *pub_packet, logger)
+            PacketReceived::Pubcomp(pubcomp_packet) => {
+                publish_handler::receive_pubcomp(self, client_id, *pubcomp_packet, logger)
             }
             PacketReceived::Subscribe(sub_packet) => {
-                subscribe_handler::add_subscriptions(self, stream, *sub_packet, logger)
-            }
-            PacketReceived::Unsubscribe(unsub_packet) => {
-                unsubscribe_handler::remove_subscriptions(self, stream, *unsub_packet, logger)
+                subscribe_handler::add_subscriptions(self, client_id, stream, *sub_packet, logger)
             }
+            PacketReceived::Unsubscribe(unsub_packet) => unsubscribe_handler::remove_subscriptions(
+                self,
+                client_id,
+                stream,
+                *unsub_packet,
+                logger,
+            ),
             PacketReceived::PingReq(_) => {
                 MqttServerActions::ReceivePingReq.log_action(
                     &self.config.general.id,
@@ -272,45 +1012,4 @@ impl MqttServer {
             )),
         }
     }
-
-    /// ### server_listener_messages
-    ///
-    /// Inicia el listener de mensajes del servidor
-    ///
-    /// ### Parametros
-    /// - `receiver`: Receiver de los mensajes (procesados por el client_handler)
-    /// - `log_path`: Path del log
-    ///
-    fn server_listener_messages(
-        mut self,
-        receiver: Arc<Mutex<Receiver<(PacketReceived, TcpStream)>>>,
-        logger: Logger,
-    ) {
-        let logger_cpy = logger.clone();
-        thread::spawn(move || -> Result<(), Error> {
-            let log_2 = logger_cpy.clone();
-            loop {
-                let log = log_2.clone();
-                match self.process_messages(Arc::clone(&receiver), &log) {
-                    Ok(a) => {
-                        a.log_action(
-                            &self.config.general.id,
-                            &log,
-                            &self.config.general.log_in_term,
-                        );
-                    }
-                    Err(e) => {
-                        logger_cpy.log_event(
-                            &("Error al procesar el mensaje: ".to_string() + &e.to_string()),
-                            &self.config.general.id,
-                        );
-                        logger_cpy.close();
-                        return Err(e);
-                    }
-                };
-                log.close();
-            }
-        });
-        logger.close();
-    }
 }
@@ -0,0 +1,188 @@
+use crate::mqtt_packets::packets::publish::Publish;
+
+/// ## QueueOverflowPolicy
+///
+/// Que hacer cuando la cola de una sesión alcanza su cantidad maxima de mensajes
+///
+/// ### Variantes
+/// - `DropOldest`: se descarta el mensaje mas antiguo de la cola y se encola el nuevo
+/// - `Reject`: se descarta el mensaje nuevo
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum QueueOverflowPolicy {
+    #[default]
+    DropOldest,
+    Reject,
+}
+
+impl QueueOverflowPolicy {
+    /// ### from_name
+    ///
+    /// Obtiene la politica a partir de su nombre en la configuracion
+    /// (`drop_oldest` o `reject`)
+    ///
+    pub fn from_name(name: &str) -> Option<QueueOverflowPolicy> {
+        match name {
+            "drop_oldest" => Some(QueueOverflowPolicy::DropOldest),
+            "reject" => Some(QueueOverflowPolicy::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// ## QueuedMessage
+///
+/// Publicacion en la cola de una sesión, a la espera de poder enviarse al cliente
+///
+/// ### Atributos
+/// - `message`: publicacion encolada
+/// - `expires_at`: momento (segundos desde UNIX EPOCH) en que vence su Message Expiry Interval
+///
+#[derive(Clone, Debug)]
+pub struct QueuedMessage {
+    pub message: Publish,
+    pub expires_at: Option<u64>,
+}
+
+impl QueuedMessage {
+    /// ### new
+    ///
+    /// Encola una publicacion, calculando su vencimiento a partir de su Message Expiry Interval
+    ///
+    /// #### Parametros
+    /// - `message`: publicacion a encolar
+    /// - `now`: momento actual (segundos desde UNIX EPOCH)
+    ///
+    pub fn new(message: Publish, now: u64) -> Self {
+        let expires_at = message
+            .properties
+            .message_expiry_interval
+            .map(|interval| now + interval as u64);
+        QueuedMessage {
+            message,
+            expires_at,
+        }
+    }
+
+    /// ### is_expired
+    ///
+    /// Indica si la publicacion supero su Message Expiry Interval
+    ///
+    /// #### Consideraciones
+    /// - Los momentos se truncan a segundos: la publicacion vence recien cuando pasó un
+    ///   segundo completo mas, para que nunca se descarte antes de tiempo
+    ///
+    pub fn is_expired(&self, now: u64) -> bool {
+        match self.expires_at {
+            Some(expires_at) => now > expires_at,
+            None => false,
+        }
+    }
+
+    /// ### into_message
+    ///
+    /// Devuelve la publicacion a enviar, con el Message Expiry Interval reemplazado
+    /// por el tiempo que le queda de vida
+    ///
+    pub fn into_message(self, now: u64) -> Publish {
+        let mut message = self.message;
+        if let Some(expires_at) = self.expires_at {
+            let remaining = expires_at.saturating_sub(now).max(1);
+            message.properties.message_expiry_interval = Some(remaining as u32);
+        }
+        message
+    }
+
+    pub fn size_of(&self) -> usize {
+        8 + self.message.size_of()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        // 0: la publicacion no tiene Message Expiry Interval
+        let mut bytes = self.expires_at.unwrap_or(0).to_be_bytes().to_vec();
+        if let Ok(message) = self.message.as_bytes() {
+            bytes.extend_from_slice(message.as_ref());
+        }
+        bytes
+    }
+
+    pub fn from_be_bytes(bytes: Vec<u8>) -> Option<QueuedMessage> {
+        let mut expires_at = [0; 8];
+        expires_at.copy_from_slice(bytes.get(..8)?);
+        let expires_at = match u64::from_be_bytes(expires_at) {
+            0 => None,
+            expires_at => Some(expires_at),
+        };
+        let message = Publish::from_be_bytes(bytes.get(8..)?.to_vec()).ok()?;
+
+        Some(QueuedMessage {
+            message,
+            expires_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mqtt_packets::properties::publish_properties::PublishProperties;
+
+    use super::*;
+
+    fn publish(message_expiry_interval: Option<u32>) -> Publish {
+        let properties = PublishProperties {
+            topic_name: "inc".to_string(),
+            packet_identifier: 3,
+            message_expiry_interval,
+            application_message: b"incident".to_vec(),
+            ..Default::default()
+        };
+        Publish::new(0, 1, 0, properties)
+    }
+
+    #[test]
+    fn test_expiry() {
+        let queued = QueuedMessage::new(publish(Some(60)), 1000);
+        assert_eq!(queued.expires_at, Some(1060));
+        assert!(!queued.is_expired(1060));
+        assert!(queued.is_expired(1061));
+
+        // Al enviarse, el Message Expiry Interval es el tiempo restante
+        let message = queued.into_message(1045);
+        assert_eq!(message.properties.message_expiry_interval, Some(15));
+
+        let queued = QueuedMessage::new(publish(None), 1000);
+        assert!(!queued.is_expired(u64::MAX / 2));
+        let message = queued.into_message(5000);
+        assert_eq!(message.properties.message_expiry_interval, None);
+    }
+
+    #[test]
+    fn test_serialization() {
+        for interval in [Some(30), None] {
+            let queued = QueuedMessage::new(publish(interval), 1000);
+
+            let bytes = queued.as_bytes();
+            assert_eq!(bytes.len(), queued.size_of());
+
+            let recovered = QueuedMessage::from_be_bytes(bytes).unwrap();
+            assert_eq!(recovered.expires_at, queued.expires_at);
+            assert_eq!(
+                recovered.message.properties.application_message,
+                b"incident".to_vec()
+            );
+        }
+    }
+
+    #[test]
+    fn test_overflow_policy_names() {
+        assert_eq!(
+            QueueOverflowPolicy::from_name("drop_oldest"),
+            Some(QueueOverflowPolicy::DropOldest)
+        );
+        assert_eq!(
+            QueueOverflowPolicy::from_name("reject"),
+            Some(QueueOverflowPolicy::Reject)
+        );
+        assert_eq!(QueueOverflowPolicy::from_name("drop_newest"), None);
+    }
+}
@@ -0,0 +1,447 @@
+use std::{
+    collections::HashMap,
+    io::Error,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    common::{
+        flags::flags_handler,
+        topic_filter::{topic_matches, TopicFilter},
+    },
+    mqtt_packets::packets::publish::Publish,
+};
+
+use super::server_persistence::PersistentFile;
+
+/// Extension del archivo de mensajes retenidos (junto al archivo de sesiones)
+const RETAINED_FILE_EXTENSION: &str = ".retained";
+
+/// ## RetainedMessages
+///
+/// Almacen de mensajes retenidos del servidor.
+/// Guarda, por cada topico, el ultimo mensaje publicado con el flag RETAIN
+///
+/// ### Atributos
+/// - `messages`: mensajes retenidos (clave: nombre del topico)
+/// - `db_path`: ruta del archivo donde se persisten los mensajes
+/// - `load_error`: descripción del error si el archivo estaba corrupto al iniciar
+///
+/// ### Consideraciones
+/// - Se persiste en un archivo junto al `db_path` del registro de sesiones, escrito con
+///   `PersistentFile`: si al iniciar esta corrupto, se aparta y se comienza sin mensajes
+/// - Un mensaje retenido con payload vacio elimina el mensaje retenido del topico
+/// - Es compartido por todas las conexiones del servidor (sus clones referencian los mismos mensajes)
+///
+#[derive(Clone, Default)]
+pub struct RetainedMessages {
+    messages: Arc<Mutex<HashMap<String, Publish>>>,
+    db_path: Option<String>,
+    load_error: Option<String>,
+}
+
+impl RetainedMessages {
+    fn messages(&self) -> MutexGuard<'_, HashMap<String, Publish>> {
+        self.messages.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn as_bytes(messages: &HashMap<String, Publish>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+
+        let messages_len = messages.len() as u32;
+        bytes.extend_from_slice(messages_len.to_be_bytes().as_ref());
+
+        for message in messages.values() {
+            if let Ok(message_bytes) = message.as_bytes() {
+                bytes.extend_from_slice(message_bytes.as_ref());
+            }
+        }
+        bytes
+    }
+
+    /// ### messages_from_be_bytes
+    ///
+    /// Decodifica los mensajes persistidos
+    ///
+    /// ### Retorno
+    /// - `Option<HashMap<String, Publish>>`: mensajes, o `None` si los bytes estan
+    ///   incompletos o son invalidos
+    ///
+    fn messages_from_be_bytes(bytes: Vec<u8>) -> Option<HashMap<String, Publish>> {
+        let mut messages = HashMap::new();
+
+        let mut index = 0;
+        let messages_len = u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?);
+        index += 4;
+
+        for _ in 0..messages_len {
+            let message = Publish::from_be_bytes(bytes.get(index..)?.to_vec()).ok()?;
+            index += message.size_of();
+            messages.insert(message.properties.topic_name.clone(), message);
+        }
+
+        if index != bytes.len() {
+            return None;
+        }
+        Some(messages)
+    }
+
+    /// ### new
+    ///
+    /// Crea el almacen de mensajes retenidos, recuperando los mensajes persistidos
+    ///
+    /// ### Parametros
+    /// - `sessions_db_path`: ruta del archivo de sesiones del servidor
+    ///
+    pub fn new(sessions_db_path: &Option<String>) -> Self {
+        let db_path = match sessions_db_path {
+            Some(path) if !path.is_empty() => Some(path.clone() + RETAINED_FILE_EXTENSION),
+            _ => None,
+        };
+
+        let (messages, load_error) = match &db_path {
+            Some(path) => {
+                PersistentFile::new(path).load(|_, payload| Self::messages_from_be_bytes(payload))
+            }
+            None => (None, None),
+        };
+
+        RetainedMessages {
+            messages: Arc::new(Mutex::new(messages.unwrap_or_default())),
+            db_path,
+            load_error,
+        }
+    }
+
+    /// ### load_error
+    ///
+    /// Descripción del error si el archivo de mensajes retenidos estaba corrupto al iniciar
+    ///
+    pub fn load_error(&self) -> Option<&String> {
+        self.load_error.as_ref()
+    }
+
+    fn save(&self, messages: &HashMap<String, Publish>) -> Result<(), Error> {
+        match &self.db_path {
+            Some(db_path) => PersistentFile::new(db_path)
+                .write(&Self::as_bytes(messages))
+                .map_err(|e| {
+                    Error::new(
+                        e.kind(),
+                        format!(
+                            "Error al guardar los mensajes retenidos en '{}': {}",
+                            db_path, e
+                        ),
+                    )
+                }),
+            None => Ok(()),
+        }
+    }
+
+    /// ### retain_message
+    ///
+    /// Actualiza el mensaje retenido del topico de la publicacion
+    ///
+    /// ### Parametros
+    /// - `message`: publicacion con el flag RETAIN
+    ///
+    /// ### Retorno
+    /// - `Result<bool, Error>`: true si el mensaje fue retenido, false si se elimino el mensaje
+    ///   del topico. Error si no se pudo persistir el cambio (igual queda aplicado en memoria)
+    ///
+    pub fn retain_message(&self, message: &Publish) -> Result<bool, Error> {
+        let topic = message.properties.topic_name.clone();
+        let mut messages = self.messages();
+
+        let retained = if message.properties.application_message.is_empty() {
+            messages.remove(&topic);
+            false
+        } else {
+            let mut message = message.clone();
+            message.fixed_header_flags |= 1;
+            messages.insert(topic, message);
+            true
+        };
+
+        self.save(&messages)?;
+        Ok(retained)
+    }
+
+    /// ### flush
+    ///
+    /// Persiste los mensajes retenidos, al apagarse el servidor
+    ///
+    /// ### Retorno
+    /// - `Result<(), Error>`: Error si no se pudo escribir el archivo de mensajes retenidos
+    ///
+    pub fn flush(&self) -> Result<(), Error> {
+        let messages = self.messages();
+        self.save(&messages)
+    }
+
+    /// ### len
+    ///
+    /// Cantidad de topicos con un mensaje retenido
+    ///
+    pub fn len(&self) -> usize {
+        self.messages().len()
+    }
+
+    /// ### is_empty
+    ///
+    /// Indica si no hay mensajes retenidos
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.messages().is_empty()
+    }
+
+    /// ### get_matching_messages
+    ///
+    /// Obtiene los mensajes retenidos cuyos topicos coinciden con el filtro
+    ///
+    /// ### Parametros
+    /// - `topic_filter`: filtro de topicos de la subscripcion
+    ///
+    /// ### Retorno
+    /// - `Vec<Publish>`: mensajes retenidos (con el flag RETAIN)
+    ///
+    pub fn get_matching_messages(&self, topic_filter: &TopicFilter) -> Vec<Publish> {
+        self.messages()
+            .iter()
+            .filter(|(topic, _)| topic_filter.matches(topic))
+            .map(|(_, message)| message.clone())
+            .collect()
+    }
+
+    /// ### remove_matching_messages
+    ///
+    /// Elimina los mensajes retenidos cuyos topicos coinciden con un filtro
+    ///
+    /// ### Parametros
+    /// - `topic_filter`: filtro de topicos (admite wildcards)
+    ///
+    /// ### Retorno
+    /// - `Result<Vec<String>, Error>`: topicos cuyos mensajes retenidos se eliminaron. Error si
+    ///   no se pudo persistir el cambio (igual queda aplicado en memoria)
+    ///
+    pub fn remove_matching_messages(&self, topic_filter: &str) -> Result<Vec<String>, Error> {
+        let mut messages = self.messages();
+        let mut removed: Vec<String> = messages
+            .keys()
+            .filter(|topic| topic_matches(topic_filter, topic))
+            .cloned()
+            .collect();
+        if removed.is_empty() {
+            return Ok(removed);
+        }
+        for topic in &removed {
+            messages.remove(topic);
+        }
+        self.save(&messages)?;
+        removed.sort();
+        Ok(removed)
+    }
+
+    /// ### get_messages_for_subscription
+    ///
+    /// Obtiene los mensajes retenidos a enviar al realizar una subscripcion,
+    /// segun su Retain Handling
+    ///
+    /// ### Parametros
+    /// - `topic_filter`: filtro de topicos de la subscripcion
+    /// - `is_new_subscription`: indica si la subscripcion no existia previamente
+    ///
+    /// ### Retorno
+    /// - `Vec<Publish>`: mensajes retenidos a enviar
+    ///
+    /// ### Consideraciones
+    /// - Retain Handling 0: se envian siempre
+    /// - Retain Handling 1: se envian solo si la subscripcion es nueva
+    /// - Retain Handling 2: no se envian
+    ///
+    pub fn get_messages_for_subscription(
+        &self,
+        topic_filter: &TopicFilter,
+        is_new_subscription: bool,
+    ) -> Vec<Publish> {
+        match flags_handler::get_subscribe_retain_handling(topic_filter.subscription_options) {
+            0 => self.get_matching_messages(topic_filter),
+            1 if is_new_subscription => self.get_matching_messages(topic_filter),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use crate::mqtt_packets::properties::publish_properties::PublishProperties;
+
+    use super::*;
+
+    fn retained_publish(topic: &str, payload: &[u8]) -> Publish {
+        let properties = PublishProperties {
+            topic_name: topic.to_string(),
+            packet_identifier: 1,
+            payload_format_indicator: Some(1),
+            application_message: payload.to_vec(),
+            ..Default::default()
+        };
+        Publish::new(0, 1, 1, properties)
+    }
+
+    fn topic_filter(filter: &str, retain_handling: u8) -> TopicFilter {
+        TopicFilter {
+            topic_filter: filter.to_string(),
+            subscription_options: 1 | (retain_handling << 4),
+            subscription_identifier: None,
+        }
+    }
+
+    #[test]
+    fn test_retain_replace_and_clear() {
+        let retained = RetainedMessages::new(&None);
+
+        assert!(retained
+            .retain_message(&retained_publish("cams/1", b"on"))
+            .unwrap());
+        assert!(retained
+            .retain_message(&retained_publish("cams/1", b"off"))
+            .unwrap());
+        assert!(retained
+            .retain_message(&retained_publish("cams/2", b"on"))
+            .unwrap());
+
+        let messages = retained.get_matching_messages(&topic_filter("cams/1", 0));
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].properties.application_message, b"off".to_vec());
+        assert_eq!(
+            flags_handler::get_publish_retain(messages[0].fixed_header_flags),
+            1
+        );
+
+        assert_eq!(
+            retained
+                .get_matching_messages(&topic_filter("cams/+", 0))
+                .len(),
+            2
+        );
+
+        assert!(!retained
+            .retain_message(&retained_publish("cams/1", b""))
+            .unwrap());
+        assert!(retained
+            .get_matching_messages(&topic_filter("cams/1", 0))
+            .is_empty());
+    }
+
+    #[test]
+    fn test_remove_matching_messages() {
+        let retained = RetainedMessages::new(&None);
+        retained
+            .retain_message(&retained_publish("cams/1", b"on"))
+            .unwrap();
+        retained
+            .retain_message(&retained_publish("cams/2", b"on"))
+            .unwrap();
+        retained
+            .retain_message(&retained_publish("drone/1", b"state"))
+            .unwrap();
+
+        assert_eq!(
+            retained.remove_matching_messages("cams/+").unwrap(),
+            vec!["cams/1".to_string(), "cams/2".to_string()]
+        );
+        assert!(retained
+            .remove_matching_messages("cams/#")
+            .unwrap()
+            .is_empty());
+        assert_eq!(retained.len(), 1);
+    }
+
+    #[test]
+    fn test_retain_handling() {
+        let retained = RetainedMessages::new(&None);
+        retained
+            .retain_message(&retained_publish("drone/1", b"state"))
+            .unwrap();
+
+        let send_always = topic_filter("drone/#", 0);
+        let send_if_new = topic_filter("drone/#", 1);
+        let never_send = topic_filter("drone/#", 2);
+
+        assert_eq!(
+            retained
+                .get_messages_for_subscription(&send_always, false)
+                .len(),
+            1
+        );
+        assert_eq!(
+            retained
+                .get_messages_for_subscription(&send_if_new, true)
+                .len(),
+            1
+        );
+        assert!(retained
+            .get_messages_for_subscription(&send_if_new, false)
+            .is_empty());
+        assert!(retained
+            .get_messages_for_subscription(&never_send, true)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_persistence() {
+        let mut db_path = std::env::temp_dir();
+        db_path.push("retained_test_1.db");
+        let file = db_path.to_str().unwrap().to_string();
+        let path = Some(file.clone());
+
+        let retained = RetainedMessages::new(&path);
+        retained
+            .retain_message(&retained_publish("cams/1", b"on"))
+            .unwrap();
+        retained
+            .retain_message(&retained_publish("cams/2", b"off"))
+            .unwrap();
+
+        let recovered = RetainedMessages::new(&path);
+        let messages = recovered.get_matching_messages(&topic_filter("cams/2", 0));
+
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].properties.application_message, b"off".to_vec());
+        assert_eq!(
+            recovered.get_matching_messages(&topic_filter("#", 0)).len(),
+            2
+        );
+
+        let _ = fs::remove_file(file + RETAINED_FILE_EXTENSION);
+    }
+
+    #[test]
+    fn test_corrupt_file_is_recovered() {
+        let mut db_path = std::env::temp_dir();
+        db_path.push("retained_test_corrupt.db");
+        let file = db_path.to_str().unwrap().to_string();
+        let retained_file = file.clone() + RETAINED_FILE_EXTENSION;
+        let path = Some(file);
+
+        let retained = RetainedMessages::new(&path);
+        retained
+            .retain_message(&retained_publish("cams/1", b"on"))
+            .unwrap();
+        assert!(retained.load_error().is_none());
+
+        let bytes = fs::read(&retained_file).unwrap();
+        fs::write(&retained_file, &bytes[..bytes.len() - 1]).unwrap();
+
+        let recovered = RetainedMessages::new(&path);
+        assert!(recovered.is_empty());
+        assert!(recovered.load_error().unwrap().contains(&retained_file));
+        assert!(fs::metadata(retained_file.clone() + ".corrupt").is_ok());
+
+        let _ = fs::remove_file(retained_file.clone() + ".corrupt");
+        let _ = fs::remove_file(retained_file);
+    }
+}
@@ -0,0 +1,346 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind},
+    sync::Arc,
+};
+
+use crate::common::topic_filter::{
+    filter_covers, is_valid_topic_filter, split_shared_subscription, topic_matches,
+};
+
+use super::server_stats::SYS_TOPIC_PREFIX;
+
+/// Se reemplaza por el id del cliente en el filtro de una regla
+const CLIENT_ID_PLACEHOLDER: &str = "%c";
+
+/// Se reemplaza por el usuario autenticado en el filtro de una regla
+const USERNAME_PLACEHOLDER: &str = "%u";
+
+/// ## AclSubject
+///
+/// A quien aplica una regla: a un usuario autenticado o a un client id.
+/// El patron admite `*`, que reemplaza a cualquier cantidad de caracteres
+///
+#[derive(Clone, Debug, PartialEq)]
+enum AclSubject {
+    User(String),
+    Client(String),
+}
+
+/// ## AclRule
+///
+/// Regla de una ACL: habilita a publicar y/o subscribirse en un filtro de topicos
+///
+#[derive(Clone, Debug, PartialEq)]
+struct AclRule {
+    subject: AclSubject,
+    publish: bool,
+    subscribe: bool,
+    topic_filter: String,
+}
+
+/// ## ServerAcl
+///
+/// Lista de control de acceso a los topicos del servidor
+///
+/// ### Consideraciones
+/// - Sin ACL (por defecto) todos los clientes pueden publicar y subscribirse en cualquier topico
+/// - Con ACL, solo se permite lo que habilita alguna regla
+/// - El archivo tiene una regla por linea: `<user|client> <patron> <publish|subscribe|all> <filtro>`.
+///   El filtro admite wildcards y los reemplazos `%c` (client id) y `%u` (usuario).
+///   Las lineas vacias o que empiezan con `#` se ignoran
+/// - Los filtros con wildcards al inicio no abarcan los topicos `$SYS`: para subscribirse
+///   a las estadisticas del servidor hace falta una regla como `user admin subscribe $SYS/#`
+/// - Ningun cliente puede publicar en los topicos `$SYS`, que solo publica el servidor
+///
+#[derive(Clone, Debug, Default)]
+pub struct ServerAcl {
+    rules: Option<Arc<Vec<AclRule>>>,
+}
+
+impl ServerAcl {
+    /// ### from_file
+    ///
+    /// Carga la ACL de un archivo
+    ///
+    /// ### Retorno
+    /// - `Result<Self, Error>`: error si no se pudo leer el archivo o alguna regla es invalida
+    ///
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        ServerAcl::parse(&fs::read_to_string(path)?)
+    }
+
+    /// ### parse
+    ///
+    /// Interpreta las reglas de una ACL, con el formato de su archivo
+    ///
+    pub fn parse(content: &str) -> Result<Self, Error> {
+        let mut rules = Vec::new();
+        for (i, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_rule(line) {
+                Some(rule) => rules.push(rule),
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Invalid ACL rule: line {}", i + 1),
+                    ))
+                }
+            }
+        }
+        Ok(ServerAcl {
+            rules: Some(Arc::new(rules)),
+        })
+    }
+
+    /// ### can_publish
+    ///
+    /// Determina si un cliente puede publicar en un topico
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `username`: usuario con el que se autenticó el cliente, si lo hizo
+    /// - `topic_name`: topico de la publicación
+    ///
+    pub fn can_publish(&self, client_id: &str, username: Option<&str>, topic_name: &str) -> bool {
+        if topic_name.starts_with(SYS_TOPIC_PREFIX) {
+            return false;
+        }
+        self.allows(client_id, username, |rule, topic_filter| {
+            rule.publish && topic_matches(topic_filter, topic_name)
+        })
+    }
+
+    /// ### can_subscribe
+    ///
+    /// Determina si un cliente puede subscribirse a un filtro de topicos
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `username`: usuario con el que se autenticó el cliente, si lo hizo
+    /// - `topic_filter`: filtro de la subscripción
+    ///
+    /// ### Consideraciones
+    /// - El filtro debe estar abarcado por el de alguna regla: una regla `drone/+` no
+    ///   habilita a subscribirse a `drone/#`
+    /// - En una subscripción compartida se verifica el filtro sin el prefijo `$share/{grupo}/`
+    ///
+    pub fn can_subscribe(
+        &self,
+        client_id: &str,
+        username: Option<&str>,
+        topic_filter: &str,
+    ) -> bool {
+        let requested = match split_shared_subscription(topic_filter) {
+            Some((_, requested)) => requested,
+            None => topic_filter,
+        };
+        self.allows(client_id, username, |rule, topic_filter| {
+            rule.subscribe && filter_covers(topic_filter, requested)
+        })
+    }
+
+    fn allows<F>(&self, client_id: &str, username: Option<&str>, check: F) -> bool
+    where
+        F: Fn(&AclRule, &str) -> bool,
+    {
+        let rules = match &self.rules {
+            Some(rules) => rules,
+            None => return true,
+        };
+
+        rules.iter().any(|rule| {
+            let applies = match &rule.subject {
+                AclSubject::User(pattern) => {
+                    username.is_some_and(|username| pattern_matches(pattern, username))
+                }
+                AclSubject::Client(pattern) => pattern_matches(pattern, client_id),
+            };
+            applies
+                && match expand_filter(&rule.topic_filter, client_id, username) {
+                    Some(topic_filter) => check(rule, &topic_filter),
+                    None => false,
+                }
+        })
+    }
+}
+
+fn parse_rule(line: &str) -> Option<AclRule> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() != 4 {
+        return None;
+    }
+
+    let subject = match fields[0] {
+        "user" => AclSubject::User(fields[1].to_string()),
+        "client" => AclSubject::Client(fields[1].to_string()),
+        _ => return None,
+    };
+    let (publish, subscribe) = match fields[2] {
+        "publish" => (true, false),
+        "subscribe" => (false, true),
+        "all" => (true, true),
+        _ => return None,
+    };
+    let topic_filter = fields[3].to_string();
+    if !is_valid_topic_filter(&topic_filter) {
+        return None;
+    }
+
+    Some(AclRule {
+        subject,
+        publish,
+        subscribe,
+        topic_filter,
+    })
+}
+
+/// ### expand_filter
+///
+/// Reemplaza `%c` y `%u` en el filtro de una regla
+///
+/// ### Retorno
+/// - `Option<String>`: `None` si la regla no aplica: usa `%u` y el cliente no se autenticó,
+///   o el valor a reemplazar no es un nivel de topico valido
+///
+fn expand_filter(topic_filter: &str, client_id: &str, username: Option<&str>) -> Option<String> {
+    let is_level = |value: &str| !value.is_empty() && !value.contains(['/', '+', '#']);
+    let mut expanded = topic_filter.to_string();
+
+    if expanded.contains(CLIENT_ID_PLACEHOLDER) {
+        if !is_level(client_id) {
+            return None;
+        }
+        expanded = expanded.replace(CLIENT_ID_PLACEHOLDER, client_id);
+    }
+    if expanded.contains(USERNAME_PLACEHOLDER) {
+        let username = username.filter(|username| is_level(username))?;
+        expanded = expanded.replace(USERNAME_PLACEHOLDER, username);
+    }
+    Some(expanded)
+}
+
+/// ### pattern_matches
+///
+/// Determina si un usuario o client id coincide con un patron, donde `*` reemplaza
+/// a cualquier cantidad de caracteres
+///
+fn pattern_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match value.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts: Vec<&str> = parts.collect();
+    let last = match parts.last() {
+        Some(last) => *last,
+        None => return rest.is_empty(),
+    };
+    for part in &parts[..parts.len() - 1] {
+        match rest.find(part) {
+            Some(position) => rest = &rest[position + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ACL: &str = "
+        # Aplicacion de monitoreo y sistema de camaras
+        user monitoringapp all inc
+        user monitoringapp subscribe drone
+        user camssystem publish camaras
+        # Cada dron solo publica en su propio topico
+        user drone* subscribe inc
+        user drone* publish drone/%u/#
+        client sensor-* subscribe sensors/+/temperature
+        user admin all $SYS/#
+        user admin all inc
+        user ops subscribe #
+    ";
+
+    #[test]
+    fn test_without_acl_everything_is_allowed() {
+        let acl = ServerAcl::default();
+        assert!(acl.can_publish("drone1", None, "inc"));
+        assert!(acl.can_subscribe("drone1", None, "#"));
+        assert!(!acl.can_publish("drone1", None, "$SYS/broker/uptime"));
+    }
+
+    #[test]
+    fn test_sys_topics() {
+        let acl = ServerAcl::parse(ACL).unwrap();
+        assert!(acl.can_subscribe("admin", Some("admin"), "$SYS/#"));
+        assert!(acl.can_subscribe("admin", Some("admin"), "$SYS/broker/uptime"));
+        assert!(!acl.can_subscribe("drone1", Some("drone1"), "$SYS/#"));
+        // `#` no abarca los topicos que empiezan con `$`
+        assert!(acl.can_subscribe("ops", Some("ops"), "inc"));
+        assert!(!acl.can_subscribe("ops", Some("ops"), "$SYS/#"));
+        // Ni siquiera una regla `all` habilita a publicar en `$SYS`
+        assert!(!acl.can_publish("admin", Some("admin"), "$SYS/broker/uptime"));
+        assert!(acl.can_publish("admin", Some("admin"), "inc"));
+    }
+
+    #[test]
+    fn test_publish() {
+        let acl = ServerAcl::parse(ACL).unwrap();
+        assert!(acl.can_publish("monitoringapp", Some("monitoringapp"), "inc"));
+        assert!(acl.can_publish("camssystem", Some("camssystem"), "camaras"));
+        assert!(acl.can_publish("drone1", Some("drone1"), "drone/drone1/state"));
+        assert!(!acl.can_publish("drone1", Some("drone1"), "drone/drone2/state"));
+        assert!(!acl.can_publish("drone1", Some("drone1"), "inc"));
+        // Las reglas de usuario no aplican a clientes anonimos
+        assert!(!acl.can_publish("monitoringapp", None, "inc"));
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let acl = ServerAcl::parse(ACL).unwrap();
+        assert!(acl.can_subscribe("monitoringapp", Some("monitoringapp"), "drone"));
+        assert!(acl.can_subscribe("drone7", Some("drone7"), "inc"));
+        assert!(acl.can_subscribe("drone7", Some("drone7"), "$share/drones/inc"));
+        assert!(!acl.can_subscribe("drone7", Some("drone7"), "#"));
+        assert!(!acl.can_subscribe("camssystem", Some("camssystem"), "camaras"));
+        assert!(acl.can_subscribe("sensor-1", None, "sensors/+/temperature"));
+        assert!(acl.can_subscribe("sensor-1", None, "sensors/kitchen/temperature"));
+        assert!(!acl.can_subscribe("sensor-1", None, "sensors/#"));
+    }
+
+    #[test]
+    fn test_invalid_rules() {
+        for rule in [
+            "user drone1 publish",
+            "group drone1 publish inc",
+            "user drone1 read inc",
+            "user drone1 publish inc/#/state",
+        ] {
+            assert_eq!(
+                ServerAcl::parse(rule).unwrap_err().kind(),
+                ErrorKind::InvalidData,
+                "{}",
+                rule
+            );
+        }
+    }
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("drone*", "drone7"));
+        assert!(pattern_matches("drone*", "drone"));
+        assert!(pattern_matches("*", "camssystem"));
+        assert!(pattern_matches("d*e*7", "drone7"));
+        assert!(pattern_matches("camssystem", "camssystem"));
+        assert!(!pattern_matches("camssystem", "camssystem2"));
+        assert!(!pattern_matches("drone*", "monitoringapp"));
+        assert!(!pattern_matches("*7", "drone1"));
+    }
+}
@@ -0,0 +1,404 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Read, Write},
+    net::Shutdown,
+    os::unix::net::{UnixListener, UnixStream},
+    time::Duration,
+};
+
+use logger::logger_handler::Logger;
+
+use crate::{
+    common::{flags::flags_handler, reason_codes::ReasonCode, topic_filter::is_valid_topic_filter},
+    logging::{actions::MqttActions, server_actions::MqttServerActions},
+};
+
+use super::{
+    mqtt_server::{MqttServer, ACCEPT_POLL_INTERVAL},
+    server_handlers::disconnect_handler,
+    server_session::Session,
+};
+
+/// Tiempo maximo que el servidor espera el comando de una conexion de administración
+const ADMIN_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Primera linea de la respuesta a un comando ejecutado
+const RESPONSE_OK: &str = "ok";
+
+/// Primera linea de la respuesta a un comando que falló
+const RESPONSE_ERROR: &str = "error";
+
+pub const ADMIN_USAGE: &str = "Uso: broker admin sessions | queue <client_id> | purge <client_id> | kick <client_id> [reason_code] | clear <client_id> | drop-retained <filtro>";
+
+/// ## AdminCommand
+///
+/// Comando de administración que recibe el servidor en ejecución
+///
+/// ### Variantes
+/// - `Sessions`: lista las sesiones, con su estado, subscripciones, cola y mensaje de voluntad
+/// - `Queue`: lista los mensajes en la cola de la sesión de un cliente
+/// - `Purge`: descarta los mensajes en la cola de la sesión de un cliente
+/// - `Kick`: desconecta a un cliente con el reason code indicado (por defecto Administrative action)
+/// - `Clear`: elimina la sesión de un cliente, desconectandolo si esta conectado
+/// - `DropRetained`: elimina los mensajes retenidos de los topicos que coinciden con un filtro
+///
+#[derive(Debug, PartialEq)]
+pub enum AdminCommand {
+    Sessions,
+    Queue(String),
+    Purge(String),
+    Kick(String, ReasonCode),
+    Clear(String),
+    DropRetained(String),
+}
+
+impl AdminCommand {
+    /// ### parse
+    ///
+    /// Interpreta los argumentos de un comando de administración
+    ///
+    /// ### Consideraciones
+    /// - El reason code de `kick` se indica en decimal o hexadecimal (`0x98`) y debe ser
+    ///   valido para un DISCONNECT enviado por el servidor
+    ///
+    pub fn parse(args: &[String]) -> Result<Self, Error> {
+        let command = match args {
+            [command] if command == "sessions" => AdminCommand::Sessions,
+            [command, id] if command == "queue" => AdminCommand::Queue(id.clone()),
+            [command, id] if command == "purge" => AdminCommand::Purge(id.clone()),
+            [command, id] if command == "kick" => {
+                AdminCommand::Kick(id.clone(), ReasonCode::AdministrativeAction)
+            }
+            [command, id, reason_code] if command == "kick" => {
+                AdminCommand::Kick(id.clone(), parse_disconnect_reason_code(reason_code)?)
+            }
+            [command, id] if command == "clear" => AdminCommand::Clear(id.clone()),
+            [command, topic_filter]
+                if command == "drop-retained" && is_valid_topic_filter(topic_filter) =>
+            {
+                AdminCommand::DropRetained(topic_filter.clone())
+            }
+            _ => return Err(Error::new(ErrorKind::InvalidInput, ADMIN_USAGE)),
+        };
+        Ok(command)
+    }
+
+    /// ### execute
+    ///
+    /// Ejecuta el comando sobre el servidor
+    ///
+    /// ### Retorno
+    /// - `Result<String, Error>`: mensaje para el administrador
+    ///
+    pub fn execute(self, server: &mut MqttServer, logger: &Logger) -> Result<String, Error> {
+        match self {
+            AdminCommand::Sessions => Ok(server
+                .register
+                .get_sessions()
+                .iter()
+                .map(|(id, session)| describe_session(id, session))
+                .collect::<Vec<String>>()
+                .join("\n")),
+            AdminCommand::Queue(id) => {
+                let (_, session) = server
+                    .register
+                    .get_sessions()
+                    .into_iter()
+                    .find(|(session_id, _)| *session_id == id)
+                    .ok_or_else(|| session_not_found(&id))?;
+                Ok(session
+                    .messages_in_queue
+                    .iter()
+                    .map(|queued| {
+                        format!(
+                            "{} (QoS {}, {} bytes)",
+                            queued.message.properties.topic_name,
+                            flags_handler::get_publish_qos_level(queued.message.fixed_header_flags),
+                            queued.message.properties.application_message.len()
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n"))
+            }
+            AdminCommand::Purge(id) => {
+                let purged = server
+                    .register
+                    .purge_queue(&id)
+                    .ok_or_else(|| session_not_found(&id))?;
+                Ok(format!(
+                    "Se descartaron {} mensajes de la cola de '{}'",
+                    purged, id
+                ))
+            }
+            AdminCommand::Kick(id, reason_code) => {
+                let message = format!("Cliente '{}' desconectado ({})", id, reason_code);
+                disconnect_handler::force_disconnect(server, &id, reason_code, logger)?.log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+                Ok(message)
+            }
+            AdminCommand::Clear(id) => {
+                if !server.register.has_session(&id) {
+                    return Err(session_not_found(&id));
+                }
+                if server.register.is_session_active(&id) {
+                    disconnect_handler::force_disconnect(
+                        server,
+                        &id,
+                        ReasonCode::AdministrativeAction,
+                        logger,
+                    )?
+                    .log_action(
+                        &server.config.general.id,
+                        logger,
+                        &server.config.general.log_in_term,
+                    );
+                }
+                // Si el mensaje de voluntad estaba demorado, se publica al terminar la sesión
+                if let Some(will_message) = server.register.clean_session(&id) {
+                    server
+                        .register
+                        .publish_will(&server.network, &id, will_message)
+                        .log_action(
+                            &server.config.general.id,
+                            logger,
+                            &server.config.general.log_in_term,
+                        );
+                }
+                Ok(format!("Sesión de '{}' eliminada", id))
+            }
+            AdminCommand::DropRetained(topic_filter) => {
+                let removed = server
+                    .retained_messages
+                    .remove_matching_messages(&topic_filter)?;
+                for topic in &removed {
+                    MqttServerActions::ClearRetainedMessage(topic.clone()).log_action(
+                        &server.config.general.id,
+                        logger,
+                        &server.config.general.log_in_term,
+                    );
+                }
+                Ok(format!(
+                    "Se eliminaron {} mensajes retenidos",
+                    removed.len()
+                ))
+            }
+        }
+    }
+}
+
+/// ## AdminSocket
+///
+/// Socket Unix en el que el servidor recibe los comandos de administración
+///
+/// ### Consideraciones
+/// - Cada conexion envia un comando (sus argumentos separados por espacios) y recibe la
+///   respuesta: `ok` o `error` en la primera linea, seguido del mensaje
+/// - Solo pueden conectarse los usuarios locales con permisos sobre el archivo del socket
+///
+pub struct AdminSocket {
+    listener: UnixListener,
+}
+
+impl AdminSocket {
+    /// ### bind
+    ///
+    /// Crea el socket de administración. Si quedó el archivo de un socket anterior, se reemplaza
+    ///
+    pub fn bind(path: &str) -> Result<Self, Error> {
+        if fs::metadata(path).is_ok() {
+            fs::remove_file(path)?;
+        }
+        Ok(AdminSocket {
+            listener: UnixListener::bind(path)?,
+        })
+    }
+
+    /// ### run
+    ///
+    /// Atiende los comandos de administración, de a uno por vez, hasta que se pide
+    /// el cierre del servidor
+    ///
+    pub fn run(self, mut server: MqttServer, logger: Logger) {
+        if self.listener.set_nonblocking(true).is_err() {
+            return;
+        }
+        while !server.shutdown_signal.is_triggered() {
+            let mut stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    server.shutdown_signal.wait(ACCEPT_POLL_INTERVAL);
+                    continue;
+                }
+                Err(_) => continue,
+            };
+            if stream.set_nonblocking(false).is_err() {
+                continue;
+            }
+            let response = match read_request(&mut stream) {
+                Ok(args) => {
+                    MqttServerActions::AdminCommand(args.join(" ")).log_action(
+                        &server.config.general.id,
+                        &logger,
+                        &server.config.general.log_in_term,
+                    );
+                    AdminCommand::parse(&args)
+                        .and_then(|command| command.execute(&mut server, &logger))
+                }
+                Err(e) => Err(e),
+            };
+            let response = match response {
+                Ok(message) => format!("{}\n{}", RESPONSE_OK, message),
+                Err(e) => format!("{}\n{}", RESPONSE_ERROR, e),
+            };
+            let _ = stream.write_all(response.as_bytes());
+        }
+    }
+}
+
+/// ### send_admin_command
+///
+/// Envia un comando de administración al servidor en ejecución
+///
+/// ### Parametros
+/// - `path`: socket de administración del servidor
+/// - `args`: argumentos del comando
+///
+/// ### Retorno
+/// - `Result<String, Error>`: respuesta del servidor, o error si no pudo ejecutar el comando
+///
+pub fn send_admin_command(path: &str, args: &[String]) -> Result<String, Error> {
+    let mut stream = UnixStream::connect(path)?;
+    stream.write_all(args.join(" ").as_bytes())?;
+    stream.shutdown(Shutdown::Write)?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    match response.split_once('\n') {
+        Some((RESPONSE_OK, message)) => Ok(message.to_string()),
+        Some((RESPONSE_ERROR, message)) => Err(Error::other(message.to_string())),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            "Respuesta invalida del servidor",
+        )),
+    }
+}
+
+fn read_request(stream: &mut UnixStream) -> Result<Vec<String>, Error> {
+    stream.set_read_timeout(Some(ADMIN_REQUEST_TIMEOUT))?;
+    let mut request = String::new();
+    stream.read_to_string(&mut request)?;
+    Ok(request.split_whitespace().map(String::from).collect())
+}
+
+fn parse_disconnect_reason_code(reason_code: &str) -> Result<ReasonCode, Error> {
+    let id = match reason_code.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16),
+        None => reason_code.parse::<u8>(),
+    }
+    .map_err(|_| Error::new(ErrorKind::InvalidInput, "Reason code invalido"))?;
+
+    // El id 0 es Success en general, pero en un DISCONNECT es Normal disconnection
+    let reason_code = match id {
+        0 => ReasonCode::NormalDisconnection,
+        id => ReasonCode::new(id),
+    };
+    if reason_code.get_id() != id || !reason_code.is_valid_disconnect_code_from_server() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Reason code invalido para un DISCONNECT: {}", id),
+        ));
+    }
+    Ok(reason_code)
+}
+
+fn describe_session(id: &str, session: &Session) -> String {
+    let state = if session.active {
+        "conectado"
+    } else {
+        "desconectado"
+    };
+    let subscriptions = session
+        .subscriptions
+        .iter()
+        .map(|subscription| {
+            format!(
+                "{} (QoS {})",
+                subscription.topic_filter,
+                flags_handler::get_subscribe_max_qos(subscription.subscription_options)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    let will = match &session.will_message {
+        Some(will) => format!("{} (demora {}s)", will.will_topic, will.will_delay_interval),
+        None => "-".to_string(),
+    };
+    format!(
+        "{} [{}]\n  subscripciones: {}\n  mensajes en cola: {}\n  mensajes en vuelo: {}\n  mensaje de voluntad: {}",
+        id,
+        state,
+        if subscriptions.is_empty() { "-".to_string() } else { subscriptions },
+        session.messages_in_queue.len(),
+        session.inflight_messages.len(),
+        will
+    )
+}
+
+fn session_not_found(id: &str) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("El cliente '{}' no tiene sesión", id),
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            AdminCommand::parse(&args(&["sessions"])).unwrap(),
+            AdminCommand::Sessions
+        );
+        assert_eq!(
+            AdminCommand::parse(&args(&["kick", "drone1"])).unwrap(),
+            AdminCommand::Kick("drone1".to_string(), ReasonCode::AdministrativeAction)
+        );
+        assert_eq!(
+            AdminCommand::parse(&args(&["kick", "drone1", "0x8E"])).unwrap(),
+            AdminCommand::Kick("drone1".to_string(), ReasonCode::SessionTakenOver)
+        );
+        assert_eq!(
+            AdminCommand::parse(&args(&["kick", "drone1", "0"])).unwrap(),
+            AdminCommand::Kick("drone1".to_string(), ReasonCode::NormalDisconnection)
+        );
+        assert_eq!(
+            AdminCommand::parse(&args(&["drop-retained", "cams/#"])).unwrap(),
+            AdminCommand::DropRetained("cams/#".to_string())
+        );
+
+        for invalid in [
+            vec!["sessions", "drone1"],
+            vec!["delete", "drone1"],
+            vec!["kick", "drone1", "abc"],
+            // Granted QoS 1 no es un reason code de DISCONNECT
+            vec!["kick", "drone1", "1"],
+            vec!["drop-retained", "cams/#/1"],
+        ] {
+            assert_eq!(
+                AdminCommand::parse(&args(&invalid)).unwrap_err().kind(),
+                ErrorKind::InvalidInput,
+                "{:?}",
+                invalid
+            );
+        }
+    }
+}
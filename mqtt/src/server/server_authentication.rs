@@ -0,0 +1,161 @@
+use crate::{
+    common::{
+        reason_codes::ReasonCode,
+        scram::{ScramServer, SCRAM_SHA_256},
+    },
+    mqtt_packets::packets::connect::Connect,
+};
+
+use super::server_credentials::ServerCredentials;
+
+/// ## AuthenticationStep
+///
+/// Resultado de procesar los datos de autenticación recibidos de un cliente
+///
+/// ### Variantes
+/// - `Continue`: el intercambio sigue; se envian los datos en un AUTH (Continue authentication)
+/// - `Success`: el cliente se autenticó como el usuario dado; los datos finales (si hay)
+///   se envian en el CONNACK o en el AUTH (Success)
+/// - `Failure`: el cliente no se pudo autenticar
+///
+#[derive(Debug, PartialEq)]
+pub enum AuthenticationStep {
+    Continue(Vec<u8>),
+    Success(String, Option<Vec<u8>>),
+    Failure(ReasonCode),
+}
+
+/// ## ServerAuthenticator
+///
+/// Metodo de autenticación extendida (Authentication Method) del lado del servidor.
+/// Cada intercambio, en el CONNECT o en una re-autenticación, usa una instancia nueva
+///
+pub trait ServerAuthenticator: Send {
+    /// ### step
+    ///
+    /// Procesa los datos de autenticación del CONNECT o de un AUTH del cliente
+    ///
+    /// ### Parametros
+    /// - `data`: Authentication Data recibido
+    /// - `credentials`: credenciales de los usuarios del servidor
+    ///
+    fn step(
+        &mut self,
+        data: Option<Vec<u8>>,
+        credentials: &ServerCredentials,
+    ) -> AuthenticationStep;
+}
+
+/// ### new_authenticator
+///
+/// Obtiene el autenticador de un Authentication Method
+///
+/// ### Retorno
+/// - `Option<Box<dyn ServerAuthenticator>>`: `None` si el servidor no soporta el metodo
+///
+pub fn new_authenticator(method: &str) -> Option<Box<dyn ServerAuthenticator>> {
+    match method {
+        SCRAM_SHA_256 => Some(Box::new(ScramAuthenticator::default())),
+        _ => None,
+    }
+}
+
+/// ## ScramAuthenticator
+///
+/// SCRAM-SHA-256: el cliente prueba conocer la contraseña sin enviarla, en dos pasos
+/// (client-first en el CONNECT y client-final en un AUTH)
+///
+#[derive(Default)]
+struct ScramAuthenticator {
+    exchange: Option<ScramServer>,
+}
+
+impl ServerAuthenticator for ScramAuthenticator {
+    fn step(
+        &mut self,
+        data: Option<Vec<u8>>,
+        credentials: &ServerCredentials,
+    ) -> AuthenticationStep {
+        let data = match data {
+            Some(data) => data,
+            None => return AuthenticationStep::Failure(ReasonCode::BadAuthenticationMethod),
+        };
+
+        match self.exchange.take() {
+            None => match ScramServer::server_first(&data, |user| credentials.get(user)) {
+                Ok((exchange, server_first)) => {
+                    self.exchange = Some(exchange);
+                    AuthenticationStep::Continue(server_first)
+                }
+                Err(reason_code) => AuthenticationStep::Failure(reason_code),
+            },
+            Some(exchange) => match exchange.server_final(&data) {
+                Ok(server_final) => {
+                    AuthenticationStep::Success(exchange.username().to_string(), Some(server_final))
+                }
+                Err(reason_code) => AuthenticationStep::Failure(reason_code),
+            },
+        }
+    }
+}
+
+/// ## ConnectionAuthentication
+///
+/// Estado de la autenticación de una conexión
+///
+/// ### Atributos
+/// - `client_id`: id del cliente de la conexión
+/// - `method`: Authentication Method negociado en el CONNECT (`None` si es anonima)
+/// - `username`: usuario con el que se autenticó el cliente
+/// - `exchange`: intercambio en curso, en el CONNECT o en una re-autenticación
+/// - `pending_connect`: CONNECT que se acepta cuando termina el intercambio inicial
+/// - `certificate_subject`: sujeto del certificado verificado que presentó el cliente
+///   en el handshake TLS, si lo hizo
+/// - `allow_anonymous`: si el listener de la conexión acepta clientes sin autenticación
+///
+#[derive(Default)]
+pub struct ConnectionAuthentication {
+    pub client_id: String,
+    pub method: Option<String>,
+    pub username: Option<String>,
+    pub exchange: Option<Box<dyn ServerAuthenticator>>,
+    pub pending_connect: Option<Connect>,
+    pub certificate_subject: Option<String>,
+    pub allow_anonymous: bool,
+}
+
+#[cfg(test)]
+mod test {
+    use crate::common::scram::{ScramClient, ScramCredentials};
+
+    use super::*;
+
+    #[test]
+    fn test_scram_authenticator() {
+        let credentials = ServerCredentials::default();
+        credentials.add_user("monitoring".to_string(), ScramCredentials::generate("1234"));
+        assert!(new_authenticator("PLAIN").is_none());
+
+        let mut client = ScramClient::new("monitoring", "1234");
+        let mut authenticator = new_authenticator(SCRAM_SHA_256).unwrap();
+        let server_first = match authenticator.step(Some(client.client_first()), &credentials) {
+            AuthenticationStep::Continue(server_first) => server_first,
+            step => panic!("Se esperaba continuar la autenticación: {:?}", step),
+        };
+        let client_final = client.client_final(&server_first).unwrap();
+        match authenticator.step(Some(client_final), &credentials) {
+            AuthenticationStep::Success(user, Some(server_final)) => {
+                assert_eq!(user, "monitoring");
+                assert_eq!(client.verify_server_final(&server_final), Ok(()));
+            }
+            step => panic!("Se esperaba una autenticación exitosa: {:?}", step),
+        }
+
+        // Sin datos de autenticación no hay intercambio posible
+        let mut authenticator = new_authenticator(SCRAM_SHA_256).unwrap();
+        assert_eq!(
+            authenticator.step(None, &credentials),
+            AuthenticationStep::Failure(ReasonCode::BadAuthenticationMethod)
+        );
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    io::Error,
+    sync::{
+        mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use logger::logger_handler::Logger;
+
+use crate::{
+    client::{client_message::MqttClientMessage, mqtt_client::MqttClient},
+    common::{flags::flags_handler, reason_codes::ReasonCode},
+    config::{
+        bridge_config::{BridgeConfig, BridgeTopic},
+        mqtt_config::Config,
+    },
+    logging::{actions::MqttActions, server_actions::MqttServerActions},
+    mqtt_packets::{packets::publish::Publish, properties::publish_properties::PublishProperties},
+};
+
+use super::{mqtt_server::MqttServer, server_handlers::publish_handler};
+
+/// User Property con el id del servidor que reenvió un mensaje por un puente
+pub const BRIDGE_ORIGIN_PROPERTY: &str = "bridge-origin";
+
+/// Intervalo con el que un puente revisa si se pidió el cierre del servidor
+/// mientras no hay mensajes para reenviar
+const BRIDGE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Puente registrado en el servidor: los patrones que reenvia al servidor remoto
+/// y el canal por el que recibe las publicaciones locales
+type BridgeOutlet = (Vec<BridgeTopic>, Sender<Publish>);
+
+/// ### bridge_origin
+///
+/// Servidor que reenvió un mensaje por un puente, segun sus User Properties
+///
+pub fn bridge_origin(user_properties: &[(String, String)]) -> Option<&str> {
+    user_properties
+        .iter()
+        .find(|(name, _)| name == BRIDGE_ORIGIN_PROPERTY)
+        .map(|(_, value)| value.as_str())
+}
+
+/// ## ServerBridges
+///
+/// Puentes del servidor que reenvian las publicaciones locales a servidores remotos
+///
+/// ### Consideraciones
+/// - Sus clones comparten los mismos puentes
+/// - Los mensajes que ya cruzaron un puente (con la User Property `bridge-origin`)
+///   no se reenvian, para que no circulen entre los servidores
+///
+#[derive(Clone, Default)]
+pub struct ServerBridges {
+    outlets: Arc<Mutex<Vec<BridgeOutlet>>>,
+}
+
+impl ServerBridges {
+    /// ### register
+    ///
+    /// Registra un puente en el servidor
+    ///
+    /// ### Parametros
+    /// - `topics`: patrones de topicos del puente
+    ///
+    /// ### Retorno
+    /// - `Receiver<Publish>`: canal por el que el puente recibe las publicaciones
+    ///   locales que debe reenviar
+    ///
+    pub fn register(&self, topics: Vec<BridgeTopic>) -> Receiver<Publish> {
+        let (sender, receiver) = mpsc::channel();
+        self.outlets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((topics, sender));
+        receiver
+    }
+
+    /// ### forward
+    ///
+    /// Entrega una publicacion local a los puentes que reenvian su topico
+    ///
+    /// ### Parametros
+    /// - `pub_packet`: publicacion recibida de un cliente
+    ///
+    pub fn forward(&self, pub_packet: &Publish) {
+        if bridge_origin(&pub_packet.properties.user_properties).is_some() {
+            return;
+        }
+        let topic = &pub_packet.properties.topic_name;
+        self.outlets
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(topics, sender)| {
+                !topics.iter().any(|t| t.to_remote(topic).is_some())
+                    || sender.send(pub_packet.clone()).is_ok()
+            });
+    }
+}
+
+/// ## MqttBridge
+///
+/// Puente que conecta el servidor con un servidor MQTT remoto y reenvia los mensajes
+/// de los patrones de topicos configurados
+///
+/// ### Atributos
+/// - `server`: servidor local
+/// - `config`: configuracion del puente
+/// - `outgoing`: publicaciones locales que se reenvian al servidor remoto
+/// - `logger`: logger del servidor
+///
+/// ### Consideraciones
+/// - Se conecta al servidor remoto como un cliente (`MqttClient`), subscripto con No Local
+///   a los patrones que recibe, por lo que no recibe de vuelta los mensajes que reenvia
+/// - Los mensajes que reenvia al servidor remoto llevan la User Property `bridge-origin`
+///   con el id del servidor local. El servidor remoto no los reenvia por sus puentes,
+///   y si vuelven al servidor local se descartan
+/// - Si pierde la conexión, reintenta con una espera que se duplica en cada intento,
+///   entre `reconnect_min_delay` y `reconnect_max_delay`. Las publicaciones locales
+///   de mientras tanto se descartan
+/// - Los mensajes recibidos del servidor remoto se publican localmente con el QoS maximo
+///   de la subscripción del puente, y no se retienen
+///
+pub struct MqttBridge {
+    server: MqttServer,
+    config: BridgeConfig,
+    outgoing: Receiver<Publish>,
+    logger: Logger,
+}
+
+impl MqttBridge {
+    /// ### start
+    ///
+    /// Registra el puente en el servidor e inicia su thread, que termina al pedirse
+    /// el cierre del servidor
+    ///
+    /// ### Parametros
+    /// - `server`: servidor local
+    /// - `config`: configuracion del puente
+    ///
+    pub fn start(server: &MqttServer, config: BridgeConfig, logger: Logger) -> JoinHandle<()> {
+        let outgoing = server.bridges.register(config.topics.clone());
+        let bridge = MqttBridge {
+            server: server.clone(),
+            config,
+            outgoing,
+            logger,
+        };
+        thread::spawn(move || bridge.run())
+    }
+
+    fn remote_address(&self) -> String {
+        self.config.get_socket_address().to_string()
+    }
+
+    fn log(&self, action: MqttServerActions) {
+        action.log_action(
+            &self.server.config.general.id,
+            &self.logger,
+            &self.server.config.general.log_in_term,
+        );
+    }
+
+    /// ### run
+    ///
+    /// Mantiene la conexión con el servidor remoto hasta que se pide el cierre del servidor
+    ///
+    fn run(mut self) {
+        let mut delay = self.config.reconnect_min_delay;
+        while !self.server.shutdown_signal.is_triggered() {
+            let discarded = self.outgoing.try_iter().count();
+            if discarded > 0 {
+                self.log(MqttServerActions::BridgeDiscardMessages(
+                    self.remote_address(),
+                    discarded,
+                ));
+            }
+
+            match MqttClient::init(self.config.client.clone()) {
+                Ok(client) => {
+                    delay = self.config.reconnect_min_delay;
+                    self.log(MqttServerActions::BridgeConnected(self.remote_address()));
+                    if let Err(e) = self.forward_messages(client) {
+                        self.logger.log_event(
+                            &("Error en el puente: ".to_string() + &e.to_string()),
+                            &self.server.config.general.id,
+                        );
+                    }
+                    if self.server.shutdown_signal.is_triggered() {
+                        break;
+                    }
+                    self.log(MqttServerActions::BridgeDisconnected(self.remote_address()));
+                }
+                Err(e) => {
+                    self.log(MqttServerActions::BridgeConnectionFailed(
+                        self.remote_address(),
+                        e.to_string(),
+                        delay,
+                    ));
+                }
+            }
+
+            if self.server.shutdown_signal.wait(Duration::from_secs(delay)) {
+                break;
+            }
+            delay = (delay * 2).min(self.config.reconnect_max_delay);
+        }
+    }
+
+    /// ### forward_messages
+    ///
+    /// Reenvia los mensajes en ambos sentidos mientras dure la conexión con el servidor remoto
+    ///
+    /// ### Parametros
+    /// - `client`: cliente conectado al servidor remoto
+    ///
+    /// ### Consideraciones
+    /// - Al pedirse el cierre del servidor, se desconecta del servidor remoto
+    ///
+    fn forward_messages(&mut self, mut client: MqttClient) -> Result<(), Error> {
+        let listener = client.run_listener(&self.logger)?;
+        let remote_filters = self.config.remote_filters();
+        let result = match remote_filters.is_empty() {
+            true => Ok(()),
+            false => client.subscribe(
+                remote_filters.iter().map(String::as_str).collect(),
+                &self.logger,
+            ),
+        }
+        .and_then(|_| self.relay(&mut client, &listener.receiver));
+
+        if self.server.shutdown_signal.is_triggered() {
+            let _ = client.disconnect(ReasonCode::NormalDisconnection, &self.logger);
+        }
+        let _ = client.stream.shutdown();
+        let _ = listener.handler.join();
+        result
+    }
+
+    /// ### relay
+    ///
+    /// Reenvia los mensajes hasta que se pierde la conexión o se pide el cierre del servidor
+    ///
+    fn relay(
+        &mut self,
+        client: &mut MqttClient,
+        incoming: &Receiver<MqttClientMessage>,
+    ) -> Result<(), Error> {
+        while !self.server.shutdown_signal.is_triggered() {
+            match self.outgoing.recv_timeout(BRIDGE_POLL_INTERVAL) {
+                Ok(pub_packet) => self.send_to_remote(client, pub_packet)?,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+
+            loop {
+                match incoming.try_recv() {
+                    Ok(message) => self.publish_locally(message),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// ### send_to_remote
+    ///
+    /// Reenvia al servidor remoto una publicacion local, con el topico remoto de su patron
+    ///
+    fn send_to_remote(&self, client: &mut MqttClient, pub_packet: Publish) -> Result<(), Error> {
+        let topic = match self.config.to_remote(&pub_packet.properties.topic_name) {
+            Some(topic) => topic,
+            None => return Ok(()),
+        };
+        let qos = flags_handler::get_publish_qos_level(pub_packet.fixed_header_flags);
+
+        let mut properties = pub_packet.properties;
+        properties.topic_name = topic.clone();
+        properties.user_properties.push((
+            BRIDGE_ORIGIN_PROPERTY.to_string(),
+            self.server.config.general.id.clone(),
+        ));
+        client.publish_with_properties(properties, qos, &self.logger)?;
+
+        self.log(MqttServerActions::BridgeForward(
+            topic,
+            self.remote_address(),
+        ));
+        Ok(())
+    }
+
+    /// ### publish_locally
+    ///
+    /// Publica en el servidor local un mensaje recibido del servidor remoto, con el topico
+    /// local de su patron
+    ///
+    /// ### Consideraciones
+    /// - Los mensajes que el servidor local reenvió por un puente se descartan
+    ///
+    fn publish_locally(&mut self, message: MqttClientMessage) {
+        if bridge_origin(&message.user_properties) == Some(&self.server.config.general.id) {
+            return;
+        }
+        let topic = match self.config.to_local(&message.topic) {
+            Some(topic) => topic,
+            None => return,
+        };
+
+        let properties = PublishProperties {
+            topic_name: topic.clone(),
+            application_message: message.data,
+            user_properties: message.user_properties,
+            content_type: message.content_type,
+            response_topic: message.response_topic,
+            correlation_data: message.correlation_data,
+            message_expiry_interval: message.message_expiry_interval,
+            ..Default::default()
+        };
+        let pub_packet = Publish::new(0, self.config.client.sub_max_qos, 0, properties);
+        let receivers =
+            publish_handler::publish_bridged_message(&mut self.server, pub_packet, &self.logger);
+
+        self.log(MqttServerActions::BridgeReceive(
+            topic,
+            self.remote_address(),
+            receivers,
+        ));
+    }
+}
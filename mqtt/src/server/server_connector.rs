@@ -1,29 +1,66 @@
-use native_tls::{Error, HandshakeError, Identity, TlsAcceptor, TlsStream};
-use std::fs::File;
-use std::io::Read;
+use openssl::{
+    nid::Nid,
+    pkcs12::Pkcs12,
+    ssl::{SslAcceptor, SslMethod, SslStream, SslVerifyMode},
+};
+use std::fs;
+use std::io::Error;
 use std::net::{TcpListener, TcpStream};
 
+/// ## TlsServerConnector
+///
+/// Conector TLS del servidor: escucha las conexiones y realiza el handshake de cada una
+///
+/// ### Consideraciones
+/// - Si se configura una CA de clientes, el handshake exige que el cliente presente
+///   un certificado firmado por ella (TLS mutuo)
+///
 pub struct TlsServerConnector {
     listener: TcpListener,
-    acceptor: TlsAcceptor,
+    acceptor: SslAcceptor,
 }
 
 impl TlsServerConnector {
-    // manejar unwraps
+    /// ### initialize
+    ///
+    /// Crea el conector TLS del servidor
+    ///
+    /// ### Parametros
+    /// - `cert_path`: identidad del servidor (PKCS#12)
+    /// - `pass`: contraseña de la identidad
+    /// - `client_ca_path`: certificados de las CA (formato PEM) con las que se verifican
+    ///   los certificados de los clientes. Si es `None`, no se piden certificados a los clientes
+    /// - `address`: direccion en la que escucha el servidor
+    ///
     pub fn initialize(
         cert_path: &str,
         pass: &str,
+        client_ca_path: Option<&str>,
         address: &str,
     ) -> Result<TlsServerConnector, Error> {
-        let mut file = File::open(cert_path).unwrap();
-        let mut buf: Vec<u8> = vec![];
+        let identity = Pkcs12::from_der(&fs::read(cert_path)?)?.parse2(pass)?;
 
-        file.read_to_end(&mut buf).unwrap();
+        let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls())?;
+        if let Some(key) = identity.pkey {
+            builder.set_private_key(&key)?;
+        }
+        if let Some(cert) = identity.cert {
+            builder.set_certificate(&cert)?;
+        }
+        if let Some(chain) = identity.ca {
+            for cert in chain {
+                builder.add_extra_chain_cert(cert)?;
+            }
+        }
+        builder.check_private_key()?;
 
-        let identity = Identity::from_pkcs12(&buf, pass)?;
+        if let Some(client_ca_path) = client_ca_path {
+            builder.set_ca_file(client_ca_path)?;
+            builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
 
-        let listener = TcpListener::bind(address).unwrap();
-        let acceptor = TlsAcceptor::new(identity).unwrap();
+        let listener = TcpListener::bind(address)?;
+        let acceptor = builder.build();
 
         Ok(TlsServerConnector { listener, acceptor })
     }
@@ -32,10 +69,36 @@ impl TlsServerConnector {
         self.listener.try_clone()
     }
 
+    /// ### accept_tls_connection
+    ///
+    /// Realiza el handshake TLS de una conexion entrante
+    ///
+    /// ### Retorno
+    /// - `Result<(SslStream<TcpStream>, Option<String>), Error>`: stream de la conexion y
+    ///   el sujeto (Common Name) del certificado del cliente, si presentó uno
+    ///
     pub fn accept_tls_connection(
         &self,
         stream: TcpStream,
-    ) -> Result<TlsStream<TcpStream>, HandshakeError<TcpStream>> {
-        self.acceptor.accept(stream)
+    ) -> Result<(SslStream<TcpStream>, Option<String>), Error> {
+        let tls_stream = self
+            .acceptor
+            .accept(stream)
+            .map_err(|e| Error::other(e.to_string()))?;
+        let subject = certificate_subject(&tls_stream);
+        Ok((tls_stream, subject))
     }
 }
+
+/// ### certificate_subject
+///
+/// Devuelve el Common Name del certificado que presentó el cliente en el handshake
+///
+fn certificate_subject(stream: &SslStream<TcpStream>) -> Option<String> {
+    let certificate = stream.ssl().peer_certificate()?;
+    let common_name = certificate
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()?;
+    common_name.data().to_string().ok()
+}
@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufRead, BufReader, Error, ErrorKind, Write},
+    sync::{Arc, Mutex, RwLock},
+    time::SystemTime,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::common::scram::ScramCredentials;
+
+/// Separador de los campos de cada linea del archivo de credenciales
+const FIELD_SEPARATOR: char = ':';
+
+/// ## ServerCredentials
+///
+/// Credenciales de los usuarios habilitados en el servidor
+///
+/// ### Consideraciones
+/// - Solo se guardan credenciales SCRAM (salt, iteraciones, StoredKey y ServerKey):
+///   el servidor nunca conoce las contraseñas en texto plano
+/// - Los clones comparten las credenciales, por lo que una recarga del archivo
+///   se ve en todas las conexiones
+/// - El archivo tiene una linea por usuario con el formato
+///   `usuario:iteraciones:salt:stored_key:server_key`, con los tres ultimos campos en base64.
+///   Las lineas vacias o que empiezan con `#` se ignoran
+///
+#[derive(Clone, Debug, Default)]
+pub struct ServerCredentials {
+    users: Arc<RwLock<HashMap<String, ScramCredentials>>>,
+    file: Option<CredentialsFile>,
+}
+
+/// ## CredentialsFile
+///
+/// Archivo del que se cargan las credenciales, junto a la fecha de modificación
+/// de la ultima version cargada
+///
+#[derive(Clone, Debug)]
+struct CredentialsFile {
+    path: String,
+    modified: Arc<Mutex<Option<SystemTime>>>,
+}
+
+impl ServerCredentials {
+    /// ### from_password
+    ///
+    /// Habilita a los usuarios con una misma contraseña, derivando las credenciales
+    /// de cada uno con su propio salt aleatorio
+    ///
+    /// ### Parametros
+    /// - `users`: usuarios habilitados
+    /// - `password`: contraseña de los usuarios
+    ///
+    pub fn from_password(users: &[String], password: &str) -> Self {
+        let credentials = ServerCredentials::default();
+        for user in users {
+            credentials.add_user(user.clone(), ScramCredentials::generate(password));
+        }
+        credentials
+    }
+
+    /// ### with_file
+    ///
+    /// Crea credenciales vacias asociadas a un archivo, que todavia no se lee
+    ///
+    pub fn with_file(path: &str) -> Self {
+        ServerCredentials {
+            file: Some(CredentialsFile {
+                path: path.to_string(),
+                modified: Arc::new(Mutex::new(None)),
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// ### from_file
+    ///
+    /// Carga las credenciales de un archivo
+    ///
+    /// ### Retorno
+    /// - `Result<Self, Error>`: error si no se pudo leer el archivo o alguna linea es invalida
+    ///
+    pub fn from_file(path: &str) -> Result<Self, Error> {
+        let credentials = ServerCredentials::with_file(path);
+        credentials.reload()?;
+        Ok(credentials)
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.file.as_ref().map(|file| file.path.as_str())
+    }
+
+    /// ### reload
+    ///
+    /// Vuelve a leer el archivo de credenciales, reemplazando a todos los usuarios
+    ///
+    /// ### Retorno
+    /// - `Result<usize, Error>`: cantidad de usuarios cargados
+    ///
+    /// ### Consideraciones
+    /// - Si el archivo es invalido se conservan las credenciales anteriores, y no se
+    ///   vuelve a leer hasta que se modifique otra vez
+    ///
+    pub fn reload(&self) -> Result<usize, Error> {
+        let file = self.credentials_file()?;
+        let modified = fs::metadata(&file.path)?.modified().ok();
+        if let Ok(mut last_modified) = file.modified.lock() {
+            *last_modified = modified;
+        }
+
+        let users = read_credentials(&file.path)?;
+        let count = users.len();
+        if let Ok(mut current) = self.users.write() {
+            *current = users;
+        }
+        Ok(count)
+    }
+
+    /// ### reload_if_modified
+    ///
+    /// Recarga el archivo de credenciales si cambió desde la ultima carga
+    ///
+    /// ### Retorno
+    /// - `Result<Option<usize>, Error>`: cantidad de usuarios cargados, o `None` si
+    ///   el archivo no cambió
+    ///
+    pub fn reload_if_modified(&self) -> Result<Option<usize>, Error> {
+        let file = self.credentials_file()?;
+        let modified = fs::metadata(&file.path)?.modified().ok();
+        let last_modified = match file.modified.lock() {
+            Ok(last_modified) => *last_modified,
+            Err(_) => None,
+        };
+        if modified.is_some() && modified == last_modified {
+            return Ok(None);
+        }
+        self.reload().map(Some)
+    }
+
+    /// ### save
+    ///
+    /// Guarda las credenciales en su archivo
+    ///
+    /// ### Consideraciones
+    /// - Se escribe un archivo temporal que luego reemplaza al original, para que
+    ///   un servidor que recarga el archivo nunca lea una version incompleta
+    ///
+    pub fn save(&self) -> Result<(), Error> {
+        let file = self.credentials_file()?;
+        let mut lines = Vec::new();
+        for user in self.users() {
+            if let Some(credentials) = self.get(&user) {
+                lines.push(credentials_line(&user, &credentials));
+            }
+        }
+
+        let tmp_path = format!("{}.tmp", file.path);
+        let mut tmp_file = File::create(&tmp_path)?;
+        for line in lines {
+            writeln!(tmp_file, "{}", line)?;
+        }
+        tmp_file.sync_all()?;
+        fs::rename(tmp_path, &file.path)
+    }
+
+    pub fn add_user(&self, user: String, credentials: ScramCredentials) {
+        if let Ok(mut users) = self.users.write() {
+            users.insert(user, credentials);
+        }
+    }
+
+    /// ### set_password
+    ///
+    /// Habilita a un usuario, o cambia su contraseña si ya estaba habilitado,
+    /// derivando sus credenciales con un salt nuevo
+    ///
+    /// ### Retorno
+    /// - `Result<(), Error>`: error si el nombre de usuario no se puede guardar en el archivo
+    ///
+    pub fn set_password(&self, user: &str, password: &str) -> Result<(), Error> {
+        if user.is_empty() || user.contains(FIELD_SEPARATOR) || user.contains(char::is_whitespace) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Usuario invalido: '{}'", user),
+            ));
+        }
+        self.add_user(user.to_string(), ScramCredentials::generate(password));
+        Ok(())
+    }
+
+    /// ### remove_user
+    ///
+    /// Deshabilita a un usuario
+    ///
+    /// ### Retorno
+    /// - `bool`: si el usuario estaba habilitado
+    ///
+    pub fn remove_user(&self, user: &str) -> bool {
+        match self.users.write() {
+            Ok(mut users) => users.remove(user).is_some(),
+            Err(_) => false,
+        }
+    }
+
+    pub fn contains(&self, user: &str) -> bool {
+        self.get(user).is_some()
+    }
+
+    pub fn get(&self, user: &str) -> Option<ScramCredentials> {
+        match self.users.read() {
+            Ok(users) => users.get(user).cloned(),
+            Err(_) => None,
+        }
+    }
+
+    /// ### users
+    ///
+    /// Devuelve los usuarios habilitados, ordenados alfabeticamente
+    ///
+    pub fn users(&self) -> Vec<String> {
+        let mut users: Vec<String> = match self.users.read() {
+            Ok(users) => users.keys().cloned().collect(),
+            Err(_) => Vec::new(),
+        };
+        users.sort();
+        users
+    }
+
+    /// ### verify_password
+    ///
+    /// Verifica el usuario y la contraseña recibidos en el CONNECT
+    ///
+    /// ### Retorno
+    /// - `bool`: si el usuario esta habilitado y la contraseña es correcta
+    ///
+    pub fn verify_password(&self, user: &str, password: &[u8]) -> bool {
+        let password = match std::str::from_utf8(password) {
+            Ok(password) => password,
+            Err(_) => return false,
+        };
+        match self.get(user) {
+            Some(credentials) => credentials.verify_password(password),
+            None => false,
+        }
+    }
+
+    fn credentials_file(&self) -> Result<&CredentialsFile, Error> {
+        self.file.as_ref().ok_or(Error::new(
+            ErrorKind::NotFound,
+            "Las credenciales no tienen un archivo asociado",
+        ))
+    }
+}
+
+/// ### read_credentials
+///
+/// Lee las credenciales de todos los usuarios de un archivo
+///
+fn read_credentials(path: &str) -> Result<HashMap<String, ScramCredentials>, Error> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut users = HashMap::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match parse_credentials_line(line) {
+            Some((user, credentials)) => {
+                users.insert(user, credentials);
+            }
+            None => {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Invalid credentials: line {}", i + 1),
+                ))
+            }
+        }
+    }
+
+    Ok(users)
+}
+
+fn parse_credentials_line(line: &str) -> Option<(String, ScramCredentials)> {
+    let fields: Vec<&str> = line.split(FIELD_SEPARATOR).collect();
+    if fields.len() != 5 || fields[0].is_empty() {
+        return None;
+    }
+
+    let credentials = ScramCredentials {
+        iterations: fields[1].parse().ok()?,
+        salt: STANDARD.decode(fields[2]).ok()?,
+        stored_key: STANDARD.decode(fields[3]).ok()?,
+        server_key: STANDARD.decode(fields[4]).ok()?,
+    };
+    Some((fields[0].to_string(), credentials))
+}
+
+fn credentials_line(user: &str, credentials: &ScramCredentials) -> String {
+    format!(
+        "{}:{}:{}:{}:{}",
+        user,
+        credentials.iterations,
+        STANDARD.encode(&credentials.salt),
+        STANDARD.encode(&credentials.stored_key),
+        STANDARD.encode(&credentials.server_key)
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use std::{thread, time::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_from_password_uses_distinct_salts() {
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let credentials = ServerCredentials::from_password(&users, "1234");
+
+        let camssystem = credentials.get("camssystem").unwrap();
+        let monitoring = credentials.get("monitoring").unwrap();
+        assert_ne!(camssystem.salt, monitoring.salt);
+        assert_ne!(camssystem.stored_key, monitoring.stored_key);
+        assert_eq!(
+            ScramCredentials::new("1234", monitoring.salt.clone(), monitoring.iterations),
+            monitoring
+        );
+        assert!(credentials.get("drone").is_none());
+    }
+
+    #[test]
+    fn test_credentials_file() {
+        let path = "test_credentials_file.tmp";
+        let credentials = ServerCredentials::with_file(path);
+        credentials.set_password("monitoring", "1234").unwrap();
+        credentials.set_password("drone1", "5678").unwrap();
+        assert!(credentials.set_password("drone:2", "1234").is_err());
+        credentials.save().unwrap();
+
+        let content = fs::read_to_string(path).unwrap();
+        assert_eq!(content.lines().count(), 2);
+        assert!(content.starts_with("drone1:4096:"));
+
+        let loaded = ServerCredentials::from_file(path).unwrap();
+        assert_eq!(loaded.users(), vec!["drone1", "monitoring"]);
+        assert_eq!(loaded.get("drone1"), credentials.get("drone1"));
+        assert!(loaded.verify_password("drone1", b"5678"));
+        assert!(!loaded.verify_password("drone1", b"1234"));
+        assert!(!loaded.verify_password("intruso", b"1234"));
+        assert_eq!(loaded.reload_if_modified().unwrap(), None);
+
+        // Otro proceso rota una contraseña y elimina un usuario: los clones lo ven al recargar
+        let server = loaded.clone();
+        thread::sleep(Duration::from_millis(20));
+        let manager = ServerCredentials::from_file(path).unwrap();
+        manager.set_password("drone1", "8765").unwrap();
+        assert!(manager.remove_user("monitoring"));
+        assert!(!manager.remove_user("monitoring"));
+        manager.save().unwrap();
+        assert_eq!(server.reload_if_modified().unwrap(), Some(1));
+        assert!(loaded.verify_password("drone1", b"8765"));
+        assert!(!loaded.contains("monitoring"));
+
+        // Un archivo invalido no reemplaza las credenciales cargadas
+        fs::write(path, "drone1:4096:sal\n").unwrap();
+        assert_eq!(server.reload().unwrap_err().kind(), ErrorKind::InvalidData);
+        assert!(server.contains("drone1"));
+        assert_eq!(server.reload_if_modified().unwrap(), None);
+
+        fs::remove_file(path).unwrap();
+    }
+}
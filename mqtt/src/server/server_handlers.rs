@@ -1,40 +1,450 @@
 pub mod connect_handler {
-    use std::{io::Error, net::TcpStream};
+    use std::io::Error;
 
     use logger::logger_handler::Logger;
 
     use crate::{
-        common::authentication::deserialize_username_password,
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
         logging::{actions::MqttActions, server_actions::MqttServerActions},
         mqtt_packets::{
             packet::generic_packet::Serialization,
-            packets::{auth::Auth, connack::Connack, connect::Connect},
-            properties::connack_properties::ConnackProperties,
+            packets::{auth::Auth, connack::Connack, connect::Connect, pubrel::Pubrel},
+            properties::{
+                auth_properties::AuthProperties, connack_properties::ConnackProperties,
+                pubrel_properties::PubrelProperties,
+            },
+        },
+        server::{
+            acknowledge_handler,
+            inflight_message::InflightState,
+            mqtt_server::{ConnectionState, MqttServer},
+            server_authentication::{
+                new_authenticator, AuthenticationStep, ConnectionAuthentication,
+            },
+            server_handlers::{disconnect_handler, publish_handler},
         },
-        server::{acknowledge_handler, mqtt_server::MqttServer},
     };
 
+    /// ### receive_connect
+    ///
+    /// Recibe el CONNECT de una conexión. Si el cliente indica un Authentication Method
+    /// se inicia el intercambio de autenticación; si envia un User Name se verifica su
+    /// contraseña; si presentó un certificado en el handshake TLS, se autentica con su sujeto;
+    /// si no, se acepta la conexión si el servidor admite clientes anonimos
+    ///
+    /// ### Parametros
+    /// - `state`: Estado de la conexión
+    /// - `authentication`: Estado de la autenticación de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `connect`: Paquete de conexión
+    ///
+    /// ### Consideraciones
+    /// - Si el metodo no es soportado se rechaza la conexión con Bad authentication method
+    /// - El intercambio de autenticación tambien debe respetar el keep alive
+    ///
+    pub fn receive_connect(
+        server: &mut MqttServer,
+        state: &mut ConnectionState,
+        authentication: &mut ConnectionAuthentication,
+        stream: MqttStream,
+        connect: Connect,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        let client_id = connect.payload.client_id.clone();
+        authentication.client_id = client_id.clone();
+
+        let method = match connect.properties.authentication_method.clone() {
+            Some(method) => method,
+            None if connect.payload.username.is_some() => {
+                return password_authentication(
+                    server,
+                    state,
+                    authentication,
+                    stream,
+                    connect,
+                    logger,
+                );
+            }
+            None if authentication.certificate_subject.is_some() => {
+                let subject = authentication.certificate_subject.clone();
+                MqttServerActions::ValidAuthentication(subject.clone().unwrap_or_default())
+                    .log_action(
+                        &server.config.general.id,
+                        logger,
+                        &server.config.general.log_in_term,
+                    );
+                authentication.username = subject;
+                return stablish_connection(
+                    server,
+                    state,
+                    authentication,
+                    stream,
+                    connect,
+                    None,
+                    logger,
+                );
+            }
+            None if authentication.allow_anonymous => {
+                return stablish_connection(
+                    server,
+                    state,
+                    authentication,
+                    stream,
+                    connect,
+                    None,
+                    logger,
+                );
+            }
+            None => {
+                *state = ConnectionState::Closed;
+                MqttServerActions::InvalidAuthentication(client_id.clone()).log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+                return refuse_connection(stream, client_id, ReasonCode::NotAuthorized);
+            }
+        };
+        let authenticator = match new_authenticator(&method) {
+            Some(authenticator) => authenticator,
+            None => {
+                *state = ConnectionState::Closed;
+                return refuse_connection(stream, client_id, ReasonCode::BadAuthenticationMethod);
+            }
+        };
+
+        let keep_alive = server
+            .config
+            .keep_alive
+            .unwrap_or(connect.properties.keep_alive);
+        stream.set_read_timeout(acknowledge_handler::keep_alive_timeout(keep_alive))?;
+
+        let data = connect.properties.authentication_data.clone();
+        authentication.method = Some(method);
+        authentication.exchange = Some(authenticator);
+        authentication.pending_connect = Some(connect);
+        *state = ConnectionState::Authenticating;
+        authentication_step(server, state, authentication, stream, data, logger)
+    }
+
+    /// ### password_authentication
+    ///
+    /// Autentica un CONNECT sin Authentication Method con los campos User Name y Password
+    ///
+    /// ### Parametros
+    /// - `state`: Estado de la conexión
+    /// - `authentication`: Estado de la autenticación de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `connect`: Paquete de conexión
+    ///
+    /// ### Consideraciones
+    /// - La contraseña se verifica con las credenciales guardadas del usuario, sin almacenarla
+    /// - Si el usuario no esta habilitado o la contraseña es incorrecta se rechaza la conexión
+    ///   con Bad User Name or Password
+    /// - Una conexión autenticada asi no puede re-autenticarse, ya que no negoció un metodo
+    ///
+    fn password_authentication(
+        server: &mut MqttServer,
+        state: &mut ConnectionState,
+        authentication: &mut ConnectionAuthentication,
+        stream: MqttStream,
+        connect: Connect,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        let username = connect.payload.username.clone().unwrap_or_default();
+        let password = connect.payload.password.clone().unwrap_or_default();
+
+        if !server.credentials.verify_password(&username, &password) {
+            *state = ConnectionState::Closed;
+            MqttServerActions::InvalidAuthentication(username).log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+            return refuse_connection(
+                stream,
+                authentication.client_id.clone(),
+                ReasonCode::BadUserNameOrPassword,
+            );
+        }
+
+        MqttServerActions::ValidAuthentication(username.clone()).log_action(
+            &server.config.general.id,
+            logger,
+            &server.config.general.log_in_term,
+        );
+        authentication.username = Some(username);
+        stablish_connection(server, state, authentication, stream, connect, None, logger)
+    }
+
+    /// ### receive_auth
+    ///
+    /// Recibe un paquete AUTH: continua el intercambio de autenticación en curso
+    /// (Continue authentication) o inicia una re-autenticación (Re-authenticate)
+    ///
+    /// ### Parametros
+    /// - `state`: Estado de la conexión
+    /// - `authentication`: Estado de la autenticación de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `auth`: Paquete AUTH
+    ///
+    /// ### Consideraciones
+    /// - Una conexión anonima no puede enviar AUTH: se cierra con Protocol Error
+    /// - El Authentication Method debe ser el negociado en el CONNECT
+    ///
+    pub fn receive_auth(
+        server: &mut MqttServer,
+        state: &mut ConnectionState,
+        authentication: &mut ConnectionAuthentication,
+        stream: MqttStream,
+        auth: Auth,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        if authentication.method.is_none() {
+            *state = ConnectionState::Closed;
+            return disconnect_handler::close_protocol_error(
+                server,
+                &authentication.client_id,
+                stream,
+                logger,
+            );
+        }
+        if auth.properties.authentication_method != authentication.method {
+            return fail_authentication(
+                server,
+                state,
+                authentication,
+                stream,
+                ReasonCode::BadAuthenticationMethod,
+                logger,
+            );
+        }
+
+        match (
+            ReasonCode::new(auth.properties.reason_code),
+            authentication.exchange.is_some(),
+        ) {
+            (ReasonCode::ContinueAuthentication, true) => (),
+            (ReasonCode::ReAuthenticate, false)
+                if matches!(state, ConnectionState::Connected(_)) =>
+            {
+                authentication.exchange =
+                    authentication.method.as_deref().and_then(new_authenticator);
+            }
+            _ => {
+                *state = ConnectionState::Closed;
+                return disconnect_handler::close_protocol_error(
+                    server,
+                    &authentication.client_id,
+                    stream,
+                    logger,
+                );
+            }
+        }
+        authentication_step(
+            server,
+            state,
+            authentication,
+            stream,
+            auth.properties.authentication_data,
+            logger,
+        )
+    }
+
+    /// ### authentication_step
+    ///
+    /// Procesa los datos de autenticación recibidos y responde segun el resultado:
+    /// un AUTH para continuar el intercambio, el CONNACK (o un AUTH Success en una
+    /// re-autenticación) si el cliente se autenticó, o el rechazo de la conexión
+    ///
+    fn authentication_step(
+        server: &mut MqttServer,
+        state: &mut ConnectionState,
+        authentication: &mut ConnectionAuthentication,
+        mut stream: MqttStream,
+        data: Option<Vec<u8>>,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        let step = match authentication.exchange.as_mut() {
+            Some(exchange) => exchange.step(data, &server.credentials),
+            None => AuthenticationStep::Failure(ReasonCode::ProtocolError),
+        };
+
+        match step {
+            AuthenticationStep::Continue(data) => {
+                send_auth(
+                    &mut stream,
+                    authentication,
+                    ReasonCode::ContinueAuthentication,
+                    Some(data),
+                )?;
+                Ok(MqttServerActions::ContinueAuthentication(
+                    authentication.client_id.clone(),
+                ))
+            }
+            AuthenticationStep::Success(username, data) => {
+                authentication.exchange = None;
+                MqttServerActions::ValidAuthentication(username.clone()).log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+                authentication.username = Some(username);
+
+                match authentication.pending_connect.take() {
+                    Some(connect) => stablish_connection(
+                        server,
+                        state,
+                        authentication,
+                        stream,
+                        connect,
+                        data,
+                        logger,
+                    ),
+                    None => {
+                        // Una re-autenticación puede cambiar el usuario de la conexión
+                        server.network.set_username(
+                            &authentication.client_id,
+                            authentication.username.clone(),
+                        );
+                        send_auth(&mut stream, authentication, ReasonCode::Success, data)?;
+                        Ok(MqttServerActions::SendAuth(
+                            authentication.client_id.clone(),
+                            ReasonCode::Success,
+                        ))
+                    }
+                }
+            }
+            AuthenticationStep::Failure(reason_code) => {
+                fail_authentication(server, state, authentication, stream, reason_code, logger)
+            }
+        }
+    }
+
+    /// ### fail_authentication
+    ///
+    /// Termina una autenticación fallida: si es la del CONNECT se rechaza la conexión
+    /// con un CONNACK, y si es una re-autenticación se cierra con un DISCONNECT
+    ///
+    /// ### Consideraciones
+    /// - Bad User Name or Password no es valido en un DISCONNECT: se envia Not authorized
+    ///
+    fn fail_authentication(
+        server: &mut MqttServer,
+        state: &mut ConnectionState,
+        authentication: &mut ConnectionAuthentication,
+        stream: MqttStream,
+        reason_code: ReasonCode,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        *state = ConnectionState::Closed;
+        authentication.exchange = None;
+        MqttServerActions::InvalidAuthentication(authentication.client_id.clone()).log_action(
+            &server.config.general.id,
+            logger,
+            &server.config.general.log_in_term,
+        );
+
+        if authentication.pending_connect.take().is_some() {
+            return refuse_connection(stream, authentication.client_id.clone(), reason_code);
+        }
+        let reason_code = match reason_code {
+            ReasonCode::BadUserNameOrPassword => ReasonCode::NotAuthorized,
+            reason_code => reason_code,
+        };
+        disconnect_handler::close_authentication_failure(
+            server,
+            &authentication.client_id,
+            stream,
+            reason_code,
+            logger,
+        )
+    }
+
+    fn send_auth(
+        stream: &mut MqttStream,
+        authentication: &ConnectionAuthentication,
+        reason_code: ReasonCode,
+        authentication_data: Option<Vec<u8>>,
+    ) -> Result<(), Error> {
+        Auth::new(AuthProperties {
+            reason_code: reason_code.get_id(),
+            authentication_method: authentication.method.clone(),
+            authentication_data,
+            ..Default::default()
+        })
+        .send(stream)
+    }
+
+    /// ### refuse_connection
+    ///
+    /// Rechaza una conexión antes de aceptarla: envia un CONNACK con el reason code
+    /// y cierra la conexión
+    ///
+    fn refuse_connection(
+        mut stream: MqttStream,
+        client_id: String,
+        reason_code: ReasonCode,
+    ) -> Result<MqttServerActions, Error> {
+        let sent = Connack::new(ConnackProperties {
+            connect_reason_code: reason_code.get_id(),
+            ..Default::default()
+        })
+        .send(&mut stream);
+        let _ = stream.shutdown();
+        sent?;
+        Ok(MqttServerActions::RefuseConnection(client_id, reason_code))
+    }
+
     /// ### stablish_connection
     ///
     /// Establece una conexión. Retorna un paquete CONNACK
     ///
     /// ### Parametros
+    /// - `state`: Estado de la conexión
+    /// - `authentication`: Estado de la autenticación de la conexión
     /// - `stream`: Stream de la conexión
     /// - `connect`: Paquete de conexión
+    /// - `authentication_data`: datos finales de la autenticación extendida, si hubo
+    ///
+    /// ### Consideraciones
+    /// - El mensaje de voluntad se publica en nombre del cliente: si la ACL no le permite
+    ///   publicar en su topico, se rechaza la conexión con Not authorized
     ///
     pub fn stablish_connection(
         server: &mut MqttServer,
-        mut stream: TcpStream,
+        state: &mut ConnectionState,
+        authentication: &ConnectionAuthentication,
+        mut stream: MqttStream,
         connect: Connect,
+        authentication_data: Option<Vec<u8>>,
         logger: &Logger,
     ) -> Result<MqttServerActions, Error> {
         let client = connect.payload.client_id.clone();
-        let connack_properties: ConnackProperties =
+        let username = authentication.username.clone();
+
+        if let Some(will_topic) = &connect.payload.will_topic {
+            if !server
+                .acl
+                .can_publish(&client, username.as_deref(), will_topic)
+            {
+                *state = ConnectionState::Closed;
+                return refuse_connection(stream, client, ReasonCode::NotAuthorized);
+            }
+        }
+        *state = ConnectionState::Connected(client.clone());
+
+        let authentication_method = connect.properties.authentication_method.clone();
+        let mut connack_properties: ConnackProperties =
             acknowledge_handler::determinate_connect_acknowledge(
                 server,
                 connect,
                 stream.try_clone()?,
+                logger,
             )?;
+        server.network.set_username(&client, username);
+        connack_properties.authentication_method = authentication_method;
+        connack_properties.authentication_data = authentication_data;
         let connack_flags = connack_properties.connect_acknowledge_flags;
         MqttServerActions::Connection(client.clone()).log_action(
             &server.config.general.id,
@@ -45,56 +455,80 @@ pub mod connect_handler {
         let action = if connack_flags == 0 {
             MqttServerActions::CreateSession(client.clone())
         } else {
-            let messages = server.register.get_pending_messages(&client);
-            if let Some(messages) = messages {
-                while let Some(message) = messages.pop_front() {
-                    MqttServerActions::SendPendingMessage(client.clone()).log_action(
-                        &server.config.general.id,
-                        logger,
-                        &server.config.general.log_in_term,
-                    );
-                    message.send(&mut stream)?;
-                }
-            }
+            resend_inflight_messages(server, &mut stream, &client, logger)?;
+            publish_handler::send_queued_messages(server, &client, logger);
             MqttServerActions::ReconnectSession(client.clone())
         };
         Ok(action)
     }
 
-    pub fn authenticate_client(
+    /// ### resend_inflight_messages
+    ///
+    /// Reenvia las publicaciones de la sesión que no completaron su confirmacion:
+    /// las que esperan PUBACK o PUBREC se reenvian con el flag DUP, y para las que esperan
+    /// PUBCOMP se reenvia el PUBREL
+    ///
+    /// ### Parametros
+    /// - `stream`: Stream de la conexión
+    /// - `client`: id del cliente
+    ///
+    fn resend_inflight_messages(
         server: &mut MqttServer,
-        auth: Auth,
-    ) -> Result<MqttServerActions, Error> {
-        if auth.properties.authentication_data.is_none() {
-            return Err(Error::new(
-                std::io::ErrorKind::Other,
-                "Server - No se recibieron datos de autenticación",
-            ));
-        }
-
-        let (username, password) =
-            deserialize_username_password(auth.properties.authentication_data.unwrap());
-
-        if server.users.contains(&username) && server.config.general.password == password {
-            Ok(MqttServerActions::ValidAuthentication(username))
-        } else {
-            Ok(MqttServerActions::InvalidAuthentication(username))
+        stream: &mut MqttStream,
+        client: &str,
+        logger: &Logger,
+    ) -> Result<(), Error> {
+        for inflight in server.register.get_inflight_messages(client) {
+            MqttServerActions::ResendInflightMessage(client.to_string(), inflight.packet_id())
+                .log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+            match inflight.state {
+                InflightState::AwaitingPuback | InflightState::AwaitingPubrec => {
+                    let mut message = inflight.message;
+                    message.fixed_header_flags |= 0b1000;
+                    message.send(stream)?;
+                }
+                InflightState::AwaitingPubcomp => {
+                    Pubrel::new(PubrelProperties {
+                        packet_id: inflight.packet_id(),
+                        pubrel_reason_code: ReasonCode::Success.get_id(),
+                        ..Default::default()
+                    })
+                    .send(stream)?;
+                }
+            }
         }
+        Ok(())
     }
 }
 
 pub mod publish_handler {
-    use std::{io::Error, net::TcpStream};
+    use std::io::Error;
 
     use logger::logger_handler::Logger;
 
     use crate::{
+        common::{
+            flags::flags_handler, mqtt_stream::MqttStream, reason_codes::ReasonCode,
+            topic_filter::is_valid_topic_name,
+        },
         logging::{actions::MqttActions, server_actions::MqttServerActions},
         mqtt_packets::{
             packet::generic_packet::Serialization,
-            packets::{puback::Puback, publish::Publish},
+            packets::{
+                puback::Puback, pubcomp::Pubcomp, publish::Publish, pubrec::Pubrec, pubrel::Pubrel,
+            },
+            properties::{
+                pubcomp_properties::PubcompProperties, publish_properties::PublishProperties,
+                pubrel_properties::PubrelProperties,
+            },
+        },
+        server::{
+            acknowledge_handler, mqtt_server::MqttServer, server_handlers::disconnect_handler,
         },
-        server::{acknowledge_handler, mqtt_server::MqttServer},
     };
 
     fn send_to_queue_session(
@@ -109,7 +543,19 @@ pub mod publish_handler {
             &server.config.general.log_in_term,
         );
         match server.register.store_message(&id, pub_packet.clone()) {
-            Ok(_) => (),
+            Ok(Some(discarded)) => {
+                MqttServerActions::QueueFull(
+                    id,
+                    discarded.properties.topic_name,
+                    server.config.queue_overflow_policy,
+                )
+                .log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+            }
+            Ok(None) => (),
             Err(_) => {
                 MqttServerActions::ErrorWhileSendingWillMessage().log_action(
                     &server.config.general.id,
@@ -120,85 +566,654 @@ pub mod publish_handler {
         }
     }
 
-    /// ### resend_publish_to_subscribers
+    /// ### retain_message
+    ///
+    /// Actualiza el mensaje retenido del topico si la publicacion tiene el flag RETAIN
+    ///
+    /// ### Parametros
+    /// - `pub_packet`: Paquete de publicación
+    ///
+    fn retain_message(server: &mut MqttServer, pub_packet: &Publish, logger: &Logger) {
+        let topic = pub_packet.properties.topic_name.clone();
+
+        if flags_handler::get_publish_retain(pub_packet.fixed_header_flags) == 0
+            || !is_valid_topic_name(&topic)
+        {
+            return;
+        }
+
+        let action = match server.retained_messages.retain_message(pub_packet) {
+            Ok(true) => MqttServerActions::RetainMessage(topic),
+            Ok(false) => MqttServerActions::ClearRetainedMessage(topic),
+            Err(e) => MqttServerActions::PersistenceFailed(e.to_string()),
+        };
+        action.log_action(
+            &server.config.general.id,
+            logger,
+            &server.config.general.log_in_term,
+        );
+    }
+
+    /// ### outgoing_publish
+    ///
+    /// Arma la publicacion que se reenvia a un suscriptor
+    ///
+    /// ### Parametros
+    /// - `pub_packet`: Paquete de publicación recibido
+    /// - `subscription_options`: Opciones de la subscripción del suscriptor
+    /// - `subscription_identifier`: Subscription Identifier de la subscripción del suscriptor
+    ///
+    /// ### Consideraciones
+    /// - El flag RETAIN solo se mantiene si la subscripción tiene Retain As Published
+    /// - El QoS es el menor entre el de la publicación y el maximo de la subscripción
+    /// - El flag DUP no se propaga, es propio de cada entrega
+    /// - Las demas propiedades (User Properties, Content Type, Response Topic, Correlation
+    ///   Data, Message Expiry Interval) se reenvian sin cambios
+    ///
+    pub fn outgoing_publish(
+        pub_packet: &Publish,
+        subscription_options: u8,
+        subscription_identifier: Option<u32>,
+    ) -> Publish {
+        let mut publish = pub_packet.clone();
+        publish.properties.subscription_identifier = subscription_identifier;
+        if flags_handler::get_subscribe_retain_as_published(subscription_options) == 0 {
+            publish.fixed_header_flags &= !1;
+        }
+        let qos = flags_handler::get_publish_qos_level(pub_packet.fixed_header_flags)
+            .min(flags_handler::get_subscribe_max_qos(subscription_options));
+        publish.fixed_header_flags = (publish.fixed_header_flags & 0b0001) | (qos << 1);
+        publish
+    }
+
+    /// ### send_to_subscriber
+    ///
+    /// Envia una publicacion a un suscriptor conectado. Si la ventana de mensajes en vuelo
+    /// de su sesión esta llena (o hay mensajes en cola), la publicacion se encola
+    ///
+    /// ### Parametros
+    /// - `id`: id del suscriptor
+    /// - `pub_packet`: publicacion a enviar (con el QoS otorgado)
+    ///
+    /// ### Retorno
+    /// - `bool`: true si la publicacion fue enviada
+    ///
+    pub fn send_to_subscriber(
+        server: &mut MqttServer,
+        id: &str,
+        pub_packet: Publish,
+        logger: &Logger,
+    ) -> bool {
+        let qos = flags_handler::get_publish_qos_level(pub_packet.fixed_header_flags);
+        if !server.register.can_send_message(id, qos) {
+            send_to_queue_session(id.to_string(), server, pub_packet, logger);
+            return false;
+        }
+        deliver_to_subscriber(server, id, pub_packet, logger)
+    }
+
+    /// ### send_queued_messages
+    ///
+    /// Envia los mensajes en cola de un suscriptor conectado, mientras la ventana
+    /// de mensajes en vuelo de su sesión lo permita
+    ///
+    /// ### Parametros
+    /// - `id`: id del suscriptor
+    ///
+    pub fn send_queued_messages(server: &mut MqttServer, id: &str, logger: &Logger) {
+        while let Some(message) = server.register.next_queued_message(id) {
+            MqttServerActions::SendPendingMessage(id.to_string()).log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+            if !deliver_to_subscriber(server, id, message, logger) {
+                break;
+            }
+        }
+    }
+
+    /// ### deliver_to_subscriber
+    ///
+    /// Envia una publicacion a un suscriptor, asignandole un packet identifier de su
+    /// sesión. Si el envio falla, se desconecta la sesión
+    ///
+    /// ### Consideraciones
+    /// - Las publicaciones QoS 1 y 2 quedan registradas como mensajes en vuelo de la sesión,
+    ///   por lo que si el envio falla se reenvian al reconectarse el cliente
+    /// - Las publicaciones mayores al Maximum Packet Size del suscriptor no se envian
+    ///
+    fn deliver_to_subscriber(
+        server: &mut MqttServer,
+        id: &str,
+        pub_packet: Publish,
+        logger: &Logger,
+    ) -> bool {
+        // Una publicacion mayor al Maximum Packet Size del suscriptor se descarta
+        // como si se hubiese enviado
+        if !server.network.can_send_publish(id, &pub_packet) {
+            MqttServerActions::DiscardPacketTooLarge(
+                id.to_string(),
+                pub_packet.properties.topic_name,
+            )
+            .log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+            return true;
+        }
+
+        let pub_packet = server.register.assign_outgoing_message(id, pub_packet);
+
+        let sent = server.network.send_publish(id, &pub_packet).is_ok();
+        if sent {
+            server.stats.message_sent();
+        }
+
+        if !sent {
+            if flags_handler::get_publish_qos_level(pub_packet.fixed_header_flags) == 0 {
+                send_to_queue_session(id.to_string(), server, pub_packet, logger);
+            }
+            let _ = disconnect_handler::disconnect_session(server, id.to_string(), None, logger);
+        }
+        sent
+    }
+
+    /// ### send_to_shared_subscription
+    ///
+    /// Envia una publicacion a un unico miembro de una subscripción compartida, elegido segun
+    /// su carga. Si ningun miembro esta conectado, la publicacion se encola en la sesión elegida
+    ///
+    /// ### Parametros
+    /// - `topic_filter`: subscripción compartida (`$share/{grupo}/{filtro}`)
+    /// - `pub_packet`: publicacion a enviar
+    ///
+    /// ### Retorno
+    /// - `Option<String>`: id del miembro que recibio la publicacion, si fue enviada
+    ///
+    fn send_to_shared_subscription(
+        server: &mut MqttServer,
+        topic_filter: &str,
+        pub_packet: &Publish,
+        logger: &Logger,
+    ) -> Option<String> {
+        let (id, session) = server.register.choose_shared_subscriber(topic_filter)?;
+        let subscription = session
+            .subscriptions
+            .iter()
+            .find(|t| t.topic_filter == topic_filter)
+            .cloned()
+            .unwrap_or_default();
+        let pub_packet = outgoing_publish(
+            pub_packet,
+            subscription.subscription_options,
+            subscription.subscription_identifier,
+        );
+
+        if !session.active {
+            send_to_queue_session(id, server, pub_packet, logger);
+            return None;
+        }
+        if send_to_subscriber(server, &id, pub_packet, logger) {
+            Some(id)
+        } else {
+            None
+        }
+    }
+
+    /// ### redispatch_shared_messages
+    ///
+    /// Redistribuye entre los demas miembros de cada grupo las publicaciones de
+    /// subscripciones compartidas que un cliente desconectado no llegó a recibir
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente desconectado
+    /// - `shared_messages`: publicaciones quitadas de su sesión, junto a su subscripción compartida
+    ///
+    pub fn redispatch_shared_messages(
+        server: &mut MqttServer,
+        client_id: &str,
+        shared_messages: Vec<(String, Publish)>,
+        logger: &Logger,
+    ) {
+        for (topic_filter, message) in shared_messages {
+            MqttServerActions::RedispatchSharedMessage(client_id.to_string(), topic_filter.clone())
+                .log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+            send_to_shared_subscription(server, &topic_filter, &message, logger);
+        }
+    }
+
+    /// ### check_receive_maximum
+    ///
+    /// Verifica que una publicacion recibida no supere el Receive Maximum del servidor
+    ///
+    /// ### Parametros
+    /// - `publisher`: id del cliente de la conexión
+    /// - `pub_packet`: Paquete de publicación
+    ///
+    /// ### Retorno
+    /// - `Result<(), ReasonCode>`: `ReceiveMaximumExceeded` si el cliente tiene demasiadas
+    ///   publicaciones sin confirmar
+    ///
+    /// ### Consideraciones
+    /// - Las publicaciones QoS 1 se confirman al procesarlas, antes de leer el siguiente paquete;
+    ///   solo las QoS 2 quedan sin confirmar hasta recibir su PUBREL
+    ///
+    pub fn check_receive_maximum(
+        server: &MqttServer,
+        publisher: &str,
+        pub_packet: &Publish,
+    ) -> Result<(), ReasonCode> {
+        if flags_handler::get_publish_qos_level(pub_packet.fixed_header_flags) != 2 {
+            return Ok(());
+        }
+        let unreleased = server
+            .register
+            .unreleased_publishes(publisher, pub_packet.properties.packet_identifier);
+        server
+            .network
+            .get_flow_control(publisher)
+            .check_received_publish(unreleased)
+    }
+
+    /// ### resend_publish_to_subscribers
+    ///
+    /// Reenvia un mensaje a los suscriptores
+    ///
+    /// ### Parametros
+    /// - `publisher`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `pub_packet`: Paquete de publicación
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    /// ### Consideraciones
+    /// - Con QoS 1 o 2, las sesiones se persisten antes del PUBACK/PUBREC: si la escritura
+    ///   falla no se confirma la publicación, y el cliente la vuelve a enviar
+    ///
+    pub fn resend_publish_to_subscribers(
+        server: &mut MqttServer,
+        publisher: &str,
+        mut stream: MqttStream,
+        pub_packet: Publish,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        let topic = pub_packet.properties.topic_name.clone();
+        let packet_id = pub_packet.properties.packet_identifier;
+        let qos = flags_handler::get_publish_qos_level(pub_packet.fixed_header_flags);
+
+        MqttServerActions::ReceivePublish(topic.clone()).log_action(
+            &server.config.general.id,
+            logger,
+            &server.config.general.log_in_term,
+        );
+        server.stats.message_received();
+
+        // La ACL decide si el cliente puede publicar en el topico: si no, la publicacion
+        // se descarta y (con QoS 1 o 2) se responde Not authorized
+        let publisher = publisher.to_string();
+        let username = server.network.get_username(&publisher);
+        if !server
+            .acl
+            .can_publish(&publisher, username.as_deref(), &topic)
+        {
+            match qos {
+                0 => (),
+                1 => Puback::new(acknowledge_handler::determinate_publish_acknowledge(
+                    pub_packet, false, false,
+                )?)
+                .send(&mut stream)?,
+                _ => Pubrec::new(acknowledge_handler::determinate_publish_received(
+                    pub_packet, false, false,
+                )?)
+                .send(&mut stream)?,
+            }
+            return Ok(MqttServerActions::PublishNotAuthorized(publisher, topic));
+        }
+
+        // QoS 2: una publicacion cuyo PUBREL todavia no se recibio es un reenvio,
+        // solo se vuelve a confirmar su recepcion
+        if qos == 2 && !server.register.receive_exactly_once(&publisher, packet_id) {
+            MqttServerActions::DiscardDuplicatePublish(publisher.clone(), packet_id).log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+            let has_subscribers = !server.register.get_subscribers(&topic).is_empty()
+                || !server.register.get_shared_subscriptions(&topic).is_empty();
+            server.register.flush()?;
+            return send_pubrec(stream, pub_packet, publisher, has_subscribers);
+        }
+
+        retain_message(server, &pub_packet, logger);
+        let (receivers, has_subscribers) =
+            deliver_publish(server, &pub_packet, Some(&publisher), logger);
+        server.bridges.forward(&pub_packet);
+
+        // QoS 0: el publicador no espera confirmacion
+        let action = MqttServerActions::SendPublish(topic.clone(), receivers);
+        if qos == 0 {
+            return Ok(action);
+        }
+        action.log_action(
+            &server.config.general.id,
+            logger,
+            &server.config.general.log_in_term,
+        );
+
+        // Las publicaciones encoladas y el estado QoS 2 se persisten antes de confirmarse
+        server.register.flush()?;
+        if qos == 2 {
+            return send_pubrec(stream, pub_packet, publisher, has_subscribers);
+        }
+
+        let puback = Puback::new(acknowledge_handler::determinate_publish_acknowledge(
+            pub_packet,
+            true,
+            has_subscribers,
+        )?);
+        puback.send(&mut stream)?;
+
+        Ok(MqttServerActions::SendPuback(topic.clone()))
+    }
+
+    /// ### deliver_publish
+    ///
+    /// Entrega una publicacion a los suscriptores de su topico
+    ///
+    /// ### Parametros
+    /// - `pub_packet`: Paquete de publicación
+    /// - `publisher`: id del cliente que publicó, si la publicacion la envió un cliente
+    ///
+    /// ### Retorno
+    /// - `(Vec<String>, bool)`: ids de los suscriptores que la recibieron, y si el topico
+    ///   tiene suscriptores
+    ///
+    /// ### Consideraciones
+    /// - Las sesiones sin conexion activa reciben la publicacion en su cola
+    /// - Cada subscripción compartida recibe la publicacion en uno solo de sus miembros
+    /// - Una subscripción con No Local no recibe las publicaciones de su propio cliente
+    ///
+    fn deliver_publish(
+        server: &mut MqttServer,
+        pub_packet: &Publish,
+        publisher: Option<&str>,
+        logger: &Logger,
+    ) -> (Vec<String>, bool) {
+        let topic = pub_packet.properties.topic_name.clone();
+        let mut receivers = Vec::new();
+
+        let subscribers = server.register.get_subscribers(&topic);
+        let shared_subscriptions = server.register.get_shared_subscriptions(&topic);
+        let has_subscribers = !subscribers.is_empty() || !shared_subscriptions.is_empty();
+
+        subscribers.into_iter().for_each(|(id, s)| {
+            let subscription = s
+                .subscriptions
+                .iter()
+                .find(|t| t.share_name().is_none() && t.matches(&topic))
+                .cloned()
+                .unwrap_or_default();
+            if publisher == Some(id.as_str())
+                && flags_handler::get_subscribe_no_local_option(subscription.subscription_options)
+                    == 1
+            {
+                return;
+            }
+            let pub_packet = outgoing_publish(
+                pub_packet,
+                subscription.subscription_options,
+                subscription.subscription_identifier,
+            );
+
+            if s.active {
+                if send_to_subscriber(server, &id, pub_packet, logger) {
+                    receivers.push(id.clone());
+                }
+            } else {
+                send_to_queue_session(id.clone(), server, pub_packet, logger);
+            }
+        });
+
+        for topic_filter in shared_subscriptions {
+            if let Some(id) = send_to_shared_subscription(server, &topic_filter, pub_packet, logger)
+            {
+                receivers.push(id);
+            }
+        }
+
+        (receivers, has_subscribers)
+    }
+
+    /// ### publish_server_message
+    ///
+    /// Publica un mensaje generado por el propio servidor (por ejemplo, las estadisticas
+    /// de los topicos `$SYS`)
+    ///
+    /// ### Parametros
+    /// - `topic`: topico de la publicacion
+    /// - `payload`: contenido de la publicacion
+    ///
+    /// ### Retorno
+    /// - `Vec<String>`: ids de los suscriptores que la recibieron
+    ///
+    /// ### Consideraciones
+    /// - Se publica con QoS 0 y sin retener
+    ///
+    pub fn publish_server_message(
+        server: &mut MqttServer,
+        topic: String,
+        payload: Vec<u8>,
+        logger: &Logger,
+    ) -> Vec<String> {
+        let pub_packet = Publish::new(
+            0,
+            0,
+            0,
+            PublishProperties {
+                topic_name: topic,
+                application_message: payload,
+                ..Default::default()
+            },
+        );
+        deliver_publish(server, &pub_packet, None, logger).0
+    }
+
+    /// ### publish_bridged_message
+    ///
+    /// Publica en el servidor un mensaje que un puente recibió de un servidor remoto
+    ///
+    /// ### Parametros
+    /// - `pub_packet`: publicacion con el topico local del mensaje
+    ///
+    /// ### Retorno
+    /// - `Vec<String>`: ids de los suscriptores que la recibieron
+    ///
+    /// ### Consideraciones
+    /// - No se reenvia por los puentes del servidor, para que no vuelva a un servidor remoto
+    ///
+    pub fn publish_bridged_message(
+        server: &mut MqttServer,
+        pub_packet: Publish,
+        logger: &Logger,
+    ) -> Vec<String> {
+        server.stats.message_received();
+        deliver_publish(server, &pub_packet, None, logger).0
+    }
+
+    /// ### send_pubrec
+    ///
+    /// Confirma la recepción de una publicación QoS 2
+    ///
+    /// ### Parametros
+    /// - `stream`: Stream de la conexión
+    /// - `pub_packet`: Paquete de publicación
+    /// - `publisher`: id del cliente que publicó
+    /// - `has_subscribers`: si la publicación tiene suscriptores
+    ///
+    fn send_pubrec(
+        mut stream: MqttStream,
+        pub_packet: Publish,
+        publisher: String,
+        has_subscribers: bool,
+    ) -> Result<MqttServerActions, Error> {
+        let packet_id = pub_packet.properties.packet_identifier;
+        Pubrec::new(acknowledge_handler::determinate_publish_received(
+            pub_packet,
+            true,
+            has_subscribers,
+        )?)
+        .send(&mut stream)?;
+
+        Ok(MqttServerActions::SendPubrec(publisher, packet_id))
+    }
+
+    /// ### release_publish
+    ///
+    /// Recibe un PUBREC de un suscriptor: la publicacion en vuelo pasa a estar liberada
+    /// y se responde con un PUBREL
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `pubrec`: Paquete PUBREC
+    ///
+    pub fn release_publish(
+        server: &mut MqttServer,
+        client_id: &str,
+        mut stream: MqttStream,
+        pubrec: Pubrec,
+    ) -> Result<MqttServerActions, Error> {
+        let client_id = client_id.to_string();
+        let packet_id = pubrec.properties.packet_id;
+
+        let pubrel_reason_code = if server
+            .register
+            .release_inflight_message(&client_id, packet_id)
+        {
+            ReasonCode::Success.get_id()
+        } else {
+            ReasonCode::PacketIdentifierNotFound.get_id()
+        };
+
+        Pubrel::new(PubrelProperties {
+            packet_id,
+            pubrel_reason_code,
+            ..Default::default()
+        })
+        .send(&mut stream)?;
+
+        Ok(MqttServerActions::SendPubrel(client_id, packet_id))
+    }
+
+    /// ### complete_publish
+    ///
+    /// Recibe un PUBREL de un publicador: se libera el packet identifier de la
+    /// publicacion QoS 2 recibida y se responde con un PUBCOMP
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `pubrel`: Paquete PUBREL
+    ///
+    pub fn complete_publish(
+        server: &mut MqttServer,
+        client_id: &str,
+        mut stream: MqttStream,
+        pubrel: Pubrel,
+    ) -> Result<MqttServerActions, Error> {
+        let client_id = client_id.to_string();
+        let packet_id = pubrel.properties.packet_id;
+
+        let pubcomp_reason_code = if server.register.release_exactly_once(&client_id, packet_id) {
+            ReasonCode::Success.get_id()
+        } else {
+            ReasonCode::PacketIdentifierNotFound.get_id()
+        };
+        server.register.flush()?;
+
+        Pubcomp::new(PubcompProperties {
+            packet_id,
+            pubcomp_reason_code,
+            ..Default::default()
+        })
+        .send(&mut stream)?;
+
+        Ok(MqttServerActions::SendPubcomp(client_id, packet_id))
+    }
+
+    /// ### receive_puback
     ///
-    /// Reenvia un mensaje a los suscriptores
+    /// Recibe un PUBACK de un suscriptor: finaliza el flujo de la publicacion QoS 1
+    /// en vuelo y envia los mensajes en cola que entren en la ventana
     ///
     /// ### Parametros
-    /// - `stream`: Stream de la conexión
-    /// - `pub_packet`: Paquete de publicación
+    /// - `client_id`: id del cliente de la conexión
+    /// - `puback`: Paquete PUBACK
     ///
-    /// ### Retorno
-    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
-    ///
-    pub fn resend_publish_to_subscribers(
+    pub fn receive_puback(
         server: &mut MqttServer,
-        mut stream: TcpStream,
-        pub_packet: Publish,
+        client_id: &str,
+        puback: Puback,
         logger: &Logger,
     ) -> Result<MqttServerActions, Error> {
-        let topic = pub_packet.properties.topic_name.clone();
-        let mut receivers = Vec::new();
-
-        MqttServerActions::ReceivePublish(topic.clone()).log_action(
-            &server.config.general.id,
-            logger,
-            &server.config.general.log_in_term,
-        );
+        let client_id = client_id.to_string();
+        let packet_id = puback.properties.packet_id;
 
-        let subscribers = server.register.get_subscribers(&topic);
+        server
+            .register
+            .complete_inflight_message(&client_id, packet_id);
+        send_queued_messages(server, &client_id, logger);
 
-        subscribers.into_iter().for_each(|(id, s)| {
-            if s.active {
-                let stream = server.network.connections.get_mut(&id).unwrap();
-                match pub_packet.send(stream) {
-                    Ok(_) => {
-                        receivers.push(id.clone());
-                    }
-                    Err(_) => {
-                        let _ = server.register.disconnect_session(
-                            &mut server.network,
-                            id.clone(),
-                            &server.config.general.id,
-                            &server.config.general.log_in_term,
-                            logger,
-                        );
-                        send_to_queue_session(id.clone(), server, pub_packet.clone(), logger);
-                    }
-                }
-            } else {
-                send_to_queue_session(id.clone(), server, pub_packet.clone(), logger);
-            }
-        });
+        Ok(MqttServerActions::ReceivePuback(client_id, packet_id))
+    }
 
-        MqttServerActions::SendPublish(topic.clone(), receivers).log_action(
-            &server.config.general.id,
-            logger,
-            &server.config.general.log_in_term,
-        );
+    /// ### receive_pubcomp
+    ///
+    /// Recibe un PUBCOMP de un suscriptor: finaliza el flujo de la publicacion QoS 2
+    /// en vuelo y envia los mensajes en cola que entren en la ventana
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `pubcomp`: Paquete PUBCOMP
+    ///
+    pub fn receive_pubcomp(
+        server: &mut MqttServer,
+        client_id: &str,
+        pubcomp: Pubcomp,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        let client_id = client_id.to_string();
+        let packet_id = pubcomp.properties.packet_id;
 
-        let puback = Puback::new(acknowledge_handler::determinate_publish_acknowledge(
-            pub_packet,
-        )?);
-        puback.send(&mut stream)?;
+        server
+            .register
+            .complete_inflight_message(&client_id, packet_id);
+        send_queued_messages(server, &client_id, logger);
 
-        Ok(MqttServerActions::SendPuback(topic.clone()))
+        Ok(MqttServerActions::ReceivePubcomp(client_id, packet_id))
     }
 }
 
 pub mod subscribe_handler {
-    use std::{io::Error, net::TcpStream};
+    use std::io::Error;
 
     use logger::logger_handler::Logger;
 
     use crate::{
-        common::topic_filter::TopicFilter,
+        common::{mqtt_stream::MqttStream, topic_filter::TopicFilter},
         logging::{actions::MqttActions, server_actions::MqttServerActions},
         mqtt_packets::{
             packet::generic_packet::Serialization,
             packets::{suback::Suback, subscribe::Subscribe},
         },
-        server::{acknowledge_handler, mqtt_server::MqttServer},
+        server::{acknowledge_handler, mqtt_server::MqttServer, server_handlers::publish_handler},
     };
 
     /// ### get_sub_id_and_topics
@@ -251,23 +1266,86 @@ pub mod subscribe_handler {
     /// Agrega subscripciones. Retorna un paquete SUBACK
     ///
     /// ### Parametros
+    /// - `connection_id`: id del cliente de la conexión
     /// - `stream`: Stream de la conexión
     /// - `sub_packet`: Paquete de subscripción
     ///
     /// ### Retorno
     /// - `Result<MqttServerActions, Error>`: Resultado de la operación
     ///
+    /// ### Consideraciones
+    /// - Las subscripciones se persisten antes del SUBACK: si la escritura falla no se
+    ///   confirman
+    ///
     pub fn add_subscriptions(
         server: &mut MqttServer,
-        mut stream: TcpStream,
+        connection_id: &str,
+        mut stream: MqttStream,
         mut sub_packet: Subscribe,
         logger: &Logger,
     ) -> Result<MqttServerActions, Error> {
         let client_id = get_sub_id_and_topics(&mut sub_packet.properties.topic_filters)?;
 
-        server
+        // La ACL decide cada subscripción con la identidad de la conexión. Un cliente
+        // solo puede subscribir a su propia sesión: si el prefijo de los filtros tiene
+        // otro id, se rechazan todas las subscripciones
+        let connection_id = connection_id.to_string();
+        let owns_session = client_id == connection_id;
+        let username = server.network.get_username(&connection_id);
+        let authorized = sub_packet
+            .properties
+            .topic_filters
+            .iter()
+            .map(|t| {
+                owns_session
+                    && server.acl.can_subscribe(
+                        &connection_id,
+                        username.as_deref(),
+                        &t.topic_filter,
+                    )
+            })
+            .collect::<Vec<bool>>();
+
+        let not_authorized = sub_packet
+            .properties
+            .topic_filters
+            .iter()
+            .zip(&authorized)
+            .filter(|(t, authorized)| t.is_valid() && !**authorized)
+            .map(|(t, _)| t.topic_filter.clone())
+            .collect::<Vec<String>>();
+        if !not_authorized.is_empty() {
+            MqttServerActions::SubscribeNotAuthorized(connection_id.clone(), not_authorized)
+                .log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+        }
+
+        if !owns_session {
+            let suback = Suback::new(acknowledge_handler::determinate_subscribe_acknowledge(
+                sub_packet,
+                &authorized,
+            )?);
+            suback.send(&mut stream)?;
+            return Ok(MqttServerActions::SendSuback(connection_id));
+        }
+
+        // Los filtros invalidos o no autorizados no se registran, se informan en el SUBACK
+        let valid_topic_filters = sub_packet
+            .properties
+            .topic_filters
+            .iter()
+            .zip(&authorized)
+            .filter(|(t, authorized)| t.is_valid() && **authorized)
+            .map(|(t, _)| t.clone())
+            .collect::<Vec<TopicFilter>>();
+
+        let new_subscriptions = server
             .register
-            .add_subscription(&client_id, sub_packet.properties.topic_filters.clone())?;
+            .add_subscription(&client_id, valid_topic_filters.clone())?;
+        server.register.flush()?;
 
         MqttServerActions::ReceiveSubscribe(
             client_id.clone(),
@@ -281,19 +1359,83 @@ pub mod subscribe_handler {
 
         let suback = Suback::new(acknowledge_handler::determinate_subscribe_acknowledge(
             sub_packet,
+            &authorized,
         )?);
         suback.send(&mut stream)?;
 
+        send_retained_messages(
+            server,
+            &mut stream,
+            &client_id,
+            valid_topic_filters.iter().zip(new_subscriptions).collect(),
+            logger,
+        )?;
+
         Ok(MqttServerActions::SendSuback(client_id.clone()))
     }
+
+    /// ### send_retained_messages
+    ///
+    /// Envia los mensajes retenidos que coinciden con las nuevas subscripciones,
+    /// segun el Retain Handling de cada una
+    ///
+    /// ### Parametros
+    /// - `stream`: Stream de la conexión
+    /// - `client_id`: id del cliente
+    /// - `subscriptions`: subscripciones junto a un indicador de si son nuevas
+    ///
+    fn send_retained_messages(
+        server: &mut MqttServer,
+        stream: &mut MqttStream,
+        client_id: &str,
+        subscriptions: Vec<(&TopicFilter, bool)>,
+        logger: &Logger,
+    ) -> Result<(), Error> {
+        let mut topics = Vec::new();
+
+        for (topic_filter, is_new) in subscriptions {
+            // Los mensajes retenidos no se envian a las subscripciones compartidas
+            if topic_filter.share_name().is_some() {
+                continue;
+            }
+            // Los mensajes retenidos se envian con el flag RETAIN (Retain As Published)
+            let subscription_options = topic_filter.subscription_options | 0b1000;
+            for message in server
+                .retained_messages
+                .get_messages_for_subscription(topic_filter, is_new)
+            {
+                let message = server.register.assign_outgoing_message(
+                    client_id,
+                    publish_handler::outgoing_publish(
+                        &message,
+                        subscription_options,
+                        topic_filter.subscription_identifier,
+                    ),
+                );
+                message.send(stream)?;
+                topics.push(message.properties.topic_name.clone());
+            }
+        }
+
+        if !topics.is_empty() {
+            MqttServerActions::SendRetainedMessages(client_id.to_string(), topics).log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+        }
+
+        Ok(())
+    }
 }
 
 pub mod unsubscribe_handler {
-    use std::{io::Error, net::TcpStream};
+    use std::io::Error;
 
     use logger::logger_handler::Logger;
 
     use crate::{
+        common::mqtt_stream::MqttStream,
         logging::{actions::MqttActions, server_actions::MqttServerActions},
         mqtt_packets::{
             packet::generic_packet::Serialization,
@@ -348,56 +1490,77 @@ pub mod unsubscribe_handler {
     /// Elimina subscripciones. Retorna un paquete UNSUBACK
     ///
     /// ### Parametros
+    /// - `connection_id`: id del cliente de la conexión
     /// - `stream`: Stream de la conexión
     /// - `unsub_packet`: Paquete de desubscripción
     ///
     /// ### Retorno
     /// - `Result<MqttServerActions, Error>`: Resultado de la operación
     ///
+    /// ### Consideraciones
+    /// - Un cliente solo puede desubscribir su propia sesión: si el prefijo de los filtros
+    ///   tiene otro id, no se elimina ninguna subscripción y se responde Not authorized
+    ///
     pub fn remove_subscriptions(
         server: &mut MqttServer,
-        mut stream: TcpStream,
+        connection_id: &str,
+        mut stream: MqttStream,
         mut unsub_packet: Unsubscribe,
         logger: &Logger,
     ) -> Result<MqttServerActions, Error> {
         let client_id = get_unsub_id_and_topics(&mut unsub_packet.properties.topic_filters)?;
+        let owns_session = client_id == connection_id;
 
-        server
-            .register
-            .remove_subscription(&client_id, unsub_packet.properties.topic_filters.clone())?;
+        if owns_session {
+            server
+                .register
+                .remove_subscription(&client_id, unsub_packet.properties.topic_filters.clone())?;
+            server.register.flush()?;
 
-        MqttServerActions::ReceiveUnsubscribe(
-            client_id.clone(),
-            unsub_packet.properties.topic_filters.clone(),
-        )
-        .log_action(
-            &server.config.general.id,
-            logger,
-            &server.config.general.log_in_term,
-        );
+            MqttServerActions::ReceiveUnsubscribe(
+                client_id.clone(),
+                unsub_packet.properties.topic_filters.clone(),
+            )
+            .log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+        } else {
+            MqttServerActions::UnsubscribeNotAuthorized(
+                connection_id.to_string(),
+                unsub_packet.properties.topic_filters.clone(),
+            )
+            .log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+        }
 
         let unsuback = Unsuback::new(acknowledge_handler::determinate_unsubscribe_acknowledge(
             unsub_packet,
+            owns_session,
         )?);
         unsuback.send(&mut stream)?;
 
-        Ok(MqttServerActions::SendUnsuback(client_id.clone()))
+        Ok(MqttServerActions::SendUnsuback(connection_id.to_string()))
     }
 }
 
 pub mod disconnect_handler {
-    use std::{io::Error, net::TcpStream};
+    use std::io::Error;
 
     use logger::logger_handler::Logger;
 
     use crate::{
-        common::reason_codes::ReasonCode,
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
         logging::{actions::MqttActions, server_actions::MqttServerActions},
         mqtt_packets::{
             packet::generic_packet::Serialization, packets::disconnect::Disconnect,
             properties::disconnect_properties::DisconnectProperties,
         },
-        server::mqtt_server::MqttServer,
+        server::{mqtt_server::MqttServer, server_handlers::publish_handler},
     };
 
     /// ### receive_disconnect
@@ -405,14 +1568,22 @@ pub mod disconnect_handler {
     /// Recibe un paquete de desconexión
     ///
     /// ### Parametros
-    /// - `stream_connection`: Stream de la conexión
+    /// - `client_id`: Id del cliente de la conexión que envió el paquete
+    /// - `stream`: Stream de la conexión
     /// - `packet`: Paquete de desconexión
     ///
     /// ### Retorno
     /// - `Result<MqttServerActions, Error>`: Resultado de la operación
     ///
+    /// ### Consideraciones
+    /// - Se desconecta la sesión de la conexión que envió el paquete: se ignora el id que
+    ///   traigan sus propiedades, para que un cliente no pueda cerrar la sesión de otro
+    /// - Si la conexión ya fue reemplazada por una nueva del mismo cliente, no se toca la sesión
+    ///
     pub fn receive_disconnect(
         server: &mut MqttServer,
+        client_id: &str,
+        stream: &MqttStream,
         packet: Disconnect,
         logger: &Logger,
     ) -> Result<MqttServerActions, Error> {
@@ -427,13 +1598,325 @@ pub mod disconnect_handler {
             logger,
             &server.config.general.log_in_term,
         );
-        server.register.disconnect_session(
-            &mut server.network,
-            packet.properties.id.clone(),
+        if !server.network.is_current_connection(client_id, stream) {
+            return Ok(MqttServerActions::NoSendWillMessage());
+        }
+        disconnect_session(
+            server,
+            client_id.to_string(),
+            Some(&packet.properties),
+            logger,
+        )
+    }
+
+    /// ### close_inactive_connection
+    ///
+    /// Cierra la conexión de un cliente que no envió paquetes durante 1.5 veces su keep alive,
+    /// como si la conexión de red hubiese fallado: se le envía un DISCONNECT (Keep Alive timeout)
+    /// y se publica su mensaje de voluntad
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión inactiva
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    pub fn close_inactive_connection(
+        server: &mut MqttServer,
+        client_id: &str,
+        stream: MqttStream,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        close_connection(
+            server,
+            client_id,
+            stream,
+            Some(ReasonCode::KeepAliveTimeout),
+            MqttServerActions::CloseInactiveConnection,
+            logger,
+        )
+    }
+
+    /// ### close_protocol_error
+    ///
+    /// Cierra la conexión de un cliente que no respetó el protocolo (por ejemplo, si su
+    /// primer paquete no es un CONNECT o si envía un segundo CONNECT): se le envía un
+    /// DISCONNECT (Protocol Error) y se publica su mensaje de voluntad
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    pub fn close_protocol_error(
+        server: &mut MqttServer,
+        client_id: &str,
+        stream: MqttStream,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        close_connection(
+            server,
+            client_id,
+            stream,
+            Some(ReasonCode::ProtocolError),
+            MqttServerActions::CloseProtocolError,
+            logger,
+        )
+    }
+
+    /// ### close_invalid_topic_alias
+    ///
+    /// Cierra la conexión de un cliente que publicó con un Topic Alias invalido o desconocido:
+    /// se le envía un DISCONNECT con el reason code correspondiente y se publica su mensaje
+    /// de voluntad
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `reason_code`: `TopicAliasInvalid` o `ProtocolError`
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    pub fn close_invalid_topic_alias(
+        server: &mut MqttServer,
+        client_id: &str,
+        stream: MqttStream,
+        reason_code: ReasonCode,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        close_connection(
+            server,
+            client_id,
+            stream,
+            Some(reason_code),
+            MqttServerActions::CloseProtocolError,
+            logger,
+        )
+    }
+
+    /// ### close_flow_control_violation
+    ///
+    /// Cierra la conexión de un cliente que no respetó los limites de flujo del servidor
+    /// (un paquete mayor al Maximum Packet Size o mas publicaciones sin confirmar que el
+    /// Receive Maximum): se le envía un DISCONNECT con el reason code correspondiente y se
+    /// publica su mensaje de voluntad
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `reason_code`: `PacketTooLarge` o `ReceiveMaximumExceeded`
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    pub fn close_flow_control_violation(
+        server: &mut MqttServer,
+        client_id: &str,
+        stream: MqttStream,
+        reason_code: ReasonCode,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        close_connection(
+            server,
+            client_id,
+            stream,
+            Some(reason_code),
+            MqttServerActions::CloseProtocolError,
+            logger,
+        )
+    }
+
+    /// ### close_authentication_failure
+    ///
+    /// Cierra la conexión de un cliente que no pudo re-autenticarse: se le envía un
+    /// DISCONNECT con el reason code correspondiente y se publica su mensaje de voluntad
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `reason_code`: reason code del DISCONNECT
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    pub fn close_authentication_failure(
+        server: &mut MqttServer,
+        client_id: &str,
+        stream: MqttStream,
+        reason_code: ReasonCode,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        close_connection(
+            server,
+            client_id,
+            stream,
+            Some(reason_code),
+            MqttServerActions::CloseUnauthenticated,
+            logger,
+        )
+    }
+
+    /// ### close_lost_connection
+    ///
+    /// Cierra la conexión de un cliente que se perdió sin recibir su DISCONNECT,
+    /// publicando su mensaje de voluntad
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión perdida
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: Resultado de la operación
+    ///
+    pub fn close_lost_connection(
+        server: &mut MqttServer,
+        client_id: &str,
+        stream: MqttStream,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        close_connection(
+            server,
+            client_id,
+            stream,
+            None,
+            MqttServerActions::ConnectionLost,
+            logger,
+        )
+    }
+
+    /// ### force_disconnect
+    ///
+    /// Desconecta a un cliente por pedido de un administrador: se desconecta su sesión
+    /// (publicando su mensaje de voluntad), se le envía un DISCONNECT con el reason code
+    /// indicado y se cierra su conexión
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `reason_code`: reason code del DISCONNECT
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: accion realizada con el mensaje de voluntad,
+    ///   o error si el cliente no esta conectado
+    ///
+    /// ### Consideraciones
+    /// - La sesión se desconecta antes de cerrar la conexión, para que el thread de la
+    ///   conexión no vuelva a publicar el mensaje de voluntad
+    ///
+    pub fn force_disconnect(
+        server: &mut MqttServer,
+        client_id: &str,
+        reason_code: ReasonCode,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        let mut stream = match server.network.get_connection(client_id) {
+            Some(stream) if server.register.is_session_active(client_id) => stream,
+            _ => {
+                return Err(Error::new(
+                    std::io::ErrorKind::NotConnected,
+                    "Server - Cliente no conectado",
+                ))
+            }
+        };
+
+        MqttServerActions::ForceDisconnect(client_id.to_string(), reason_code.clone()).log_action(
+            &server.config.general.id,
+            logger,
+            &server.config.general.log_in_term,
+        );
+        let action = disconnect_session(server, client_id.to_string(), None, logger);
+        if let Ok(action) = send_disconnect(&mut stream, reason_code) {
+            action.log_action(
+                &server.config.general.id,
+                logger,
+                &server.config.general.log_in_term,
+            );
+        }
+        let _ = stream.shutdown();
+        action
+    }
+
+    /// ### close_connection
+    ///
+    /// Cierra una conexión por iniciativa del servidor y desconecta la sesión del cliente
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente de la conexión
+    /// - `stream`: Stream de la conexión
+    /// - `reason_code`: Reason code del DISCONNECT a enviar (si la conexión sigue abierta)
+    /// - `close_action`: Accion a registrar con el id del cliente
+    ///
+    /// ### Consideraciones
+    /// - Si la conexión fue reemplazada por una nueva del mismo cliente, la sesión no se desconecta
+    ///
+    fn close_connection(
+        server: &mut MqttServer,
+        client_id: &str,
+        mut stream: MqttStream,
+        reason_code: Option<ReasonCode>,
+        close_action: fn(String) -> MqttServerActions,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        if let Some(reason_code) = reason_code {
+            if let Ok(action) = send_disconnect(&mut stream, reason_code) {
+                action.log_action(
+                    &server.config.general.id,
+                    logger,
+                    &server.config.general.log_in_term,
+                );
+            }
+        }
+        let _ = stream.shutdown();
+
+        // La conexion pudo haber sido reemplazada por una nueva del mismo cliente
+        if !server.network.is_current_connection(client_id, &stream)
+            || !server.register.is_session_active(client_id)
+        {
+            return Ok(MqttServerActions::NoSendWillMessage());
+        }
+        let client_id = client_id.to_string();
+
+        close_action(client_id.clone()).log_action(
+            &server.config.general.id,
+            logger,
+            &server.config.general.log_in_term,
+        );
+        disconnect_session(server, client_id, None, logger)
+    }
+
+    /// ### disconnect_session
+    ///
+    /// Desconecta la sesión de un cliente y redistribuye entre los demas miembros de sus
+    /// subscripciones compartidas las publicaciones que no llegó a recibir
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `disconnect`: propiedades del DISCONNECT recibido, o `None` si la conexión
+    ///   se cerró sin recibirlo
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: accion realizada con el mensaje de voluntad
+    ///
+    pub fn disconnect_session(
+        server: &mut MqttServer,
+        client_id: String,
+        disconnect: Option<&DisconnectProperties>,
+        logger: &Logger,
+    ) -> Result<MqttServerActions, Error> {
+        let shared_messages = server.register.take_shared_messages(&client_id);
+        let action = server.register.disconnect_session(
+            &server.network,
+            client_id.clone(),
+            disconnect,
             &server.config.general.id,
             &server.config.general.log_in_term,
             logger,
-        )
+        );
+        publish_handler::redispatch_shared_messages(server, &client_id, shared_messages, logger);
+        action
     }
 
     /// ### send_disconnect
@@ -448,7 +1931,7 @@ pub mod disconnect_handler {
     /// - `Result<MqttServerActions, Error>`: Resultado de la operación
     ///
     pub fn send_disconnect(
-        stream_connection: &mut TcpStream,
+        stream_connection: &mut MqttStream,
         reason_code: ReasonCode,
     ) -> Result<MqttServerActions, Error> {
         let disconnect = Disconnect::new(DisconnectProperties {
@@ -1,16 +1,350 @@
-use std::{collections::HashMap, net::TcpStream};
+use std::{
+    collections::HashMap,
+    io::Error,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+    time::Duration,
+};
 
-#[derive(Default)]
+use crate::{
+    common::{
+        flow_control::FlowControl, mqtt_stream::MqttStream, reason_codes::ReasonCode,
+        topic_alias::TopicAliases,
+    },
+    mqtt_packets::{
+        packet::generic_packet::Serialization,
+        packets::{disconnect::Disconnect, publish::Publish},
+        properties::disconnect_properties::DisconnectProperties,
+    },
+};
+
+/// Tiempo maximo que puede demorar el envio de un paquete a un cliente: un cliente que
+/// deja de leer no bloquea indefinidamente a quienes le envian publicaciones
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// ## Connection
+///
+/// Conexion activa de un cliente
+///
+/// ### Atributos
+/// - `stream`: stream de la conexion
+/// - `topic_aliases`: tablas de Topic Alias de la conexion
+/// - `flow_control`: Receive Maximum y Maximum Packet Size negociados en la conexion
+/// - `username`: usuario con el que se autenticó el cliente, si lo hizo
+///
+#[derive(Clone)]
+struct Connection {
+    stream: MqttStream,
+    topic_aliases: Arc<Mutex<TopicAliases>>,
+    flow_control: FlowControl,
+    username: Option<String>,
+}
+
+impl Connection {
+    fn topic_aliases(&self) -> MutexGuard<'_, TopicAliases> {
+        self.topic_aliases
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// ## ServerNetwork
+///
+/// Conexiones activas de los clientes del servidor
+///
+/// ### Consideraciones
+/// - Es compartido por todas las conexiones del servidor (sus clones referencian las mismas conexiones)
+/// - Los streams se devuelven clonados, por lo que no se retiene el lock mientras se escribe en ellos
+///
+#[derive(Clone, Default)]
 pub struct ServerNetwork {
-    pub connections: HashMap<String, TcpStream>,
+    connections: Arc<Mutex<HashMap<String, Connection>>>,
 }
 
-impl Clone for ServerNetwork {
-    fn clone(&self) -> Self {
-        let mut connections = HashMap::new();
-        for (key, value) in self.connections.iter() {
-            connections.insert(key.clone(), value.try_clone().unwrap());
+impl ServerNetwork {
+    fn connections(&self) -> MutexGuard<'_, HashMap<String, Connection>> {
+        self.connections
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// ### add_connection
+    ///
+    /// Asocia la conexion al cliente, reemplazando su conexion anterior
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `stream`: stream de la conexion
+    /// - `topic_aliases`: tablas de Topic Alias negociadas en el CONNECT y el CONNACK
+    /// - `flow_control`: limites de flujo negociados en el CONNECT y el CONNACK
+    ///
+    /// ### Consideraciones
+    /// - Si el cliente tenia otra conexion abierta, se le envia un DISCONNECT
+    ///   (Session taken over) y se cierra
+    /// - Las escrituras en la conexion fallan si demoran mas de `SEND_TIMEOUT`
+    ///
+    pub fn add_connection(
+        &self,
+        client_id: String,
+        stream: MqttStream,
+        topic_aliases: TopicAliases,
+        flow_control: FlowControl,
+    ) {
+        let _ = stream.set_write_timeout(Some(SEND_TIMEOUT));
+        let connection_stream = stream.clone();
+        let connection = Connection {
+            stream,
+            topic_aliases: Arc::new(Mutex::new(topic_aliases)),
+            flow_control,
+            username: None,
+        };
+        let replaced = self.connections().insert(client_id, connection);
+
+        if let Some(replaced) = replaced {
+            if !replaced.stream.is_same_connection(&connection_stream) {
+                let mut stream = replaced.stream;
+                let _ = Disconnect::new(DisconnectProperties {
+                    disconnect_reason_code: ReasonCode::SessionTakenOver.get_id(),
+                    ..Default::default()
+                })
+                .send(&mut stream);
+                let _ = stream.shutdown();
+            }
+        }
+    }
+
+    /// ### remove_connection
+    ///
+    /// Quita la conexion del cliente al cerrarse
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `stream`: stream de la conexion que se cerró
+    ///
+    /// ### Consideraciones
+    /// - Si el cliente ya tiene una nueva conexion, se mantiene
+    ///
+    pub fn remove_connection(&self, client_id: &str, stream: &MqttStream) {
+        let mut connections = self.connections();
+        if connections
+            .get(client_id)
+            .is_some_and(|connection| connection.stream.is_same_connection(stream))
+        {
+            connections.remove(client_id);
+        }
+    }
+
+    /// ### set_username
+    ///
+    /// Registra el usuario con el que se autenticó (o re-autenticó) el cliente
+    ///
+    pub fn set_username(&self, client_id: &str, username: Option<String>) {
+        if let Some(connection) = self.connections().get_mut(client_id) {
+            connection.username = username;
+        }
+    }
+
+    /// ### get_username
+    ///
+    /// Obtiene el usuario con el que se autenticó el cliente conectado
+    ///
+    pub fn get_username(&self, client_id: &str) -> Option<String> {
+        self.connections()
+            .get(client_id)
+            .and_then(|connection| connection.username.clone())
+    }
+
+    /// ### get_connection
+    ///
+    /// Obtiene la conexion actual del cliente
+    ///
+    pub fn get_connection(&self, client_id: &str) -> Option<MqttStream> {
+        self.connections()
+            .get(client_id)
+            .map(|connection| connection.stream.clone())
+    }
+
+    /// ### is_current_connection
+    ///
+    /// Indica si el stream es la conexion actual del cliente (y no una reemplazada por
+    /// una nueva conexion con el mismo id)
+    ///
+    pub fn is_current_connection(&self, client_id: &str, stream: &MqttStream) -> bool {
+        self.connections()
+            .get(client_id)
+            .is_some_and(|connection| connection.stream.is_same_connection(stream))
+    }
+
+    /// ### get_flow_control
+    ///
+    /// Obtiene los limites de flujo negociados en la conexion del cliente
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    ///
+    pub fn get_flow_control(&self, client_id: &str) -> FlowControl {
+        self.connections()
+            .get(client_id)
+            .map(|connection| connection.flow_control)
+            .unwrap_or_default()
+    }
+
+    /// ### can_send_publish
+    ///
+    /// Indica si una publicacion respeta el Maximum Packet Size del cliente
+    ///
+    /// ### Consideraciones
+    /// - Se verifica con el topico completo: el Topic Alias solo puede achicar el paquete
+    ///
+    pub fn can_send_publish(&self, client_id: &str, publish: &Publish) -> bool {
+        match self.connections().get(client_id) {
+            Some(connection) => connection.flow_control.can_send_packet(publish.size_of()),
+            None => true,
         }
-        ServerNetwork { connections }
+    }
+
+    /// ### send_publish
+    ///
+    /// Envia una publicacion por la conexion actual del cliente, usando un Topic Alias
+    /// si el cliente los acepta
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `publish`: publicacion a enviar (con el topico completo)
+    ///
+    /// ### Consideraciones
+    /// - Las conexiones no quedan bloqueadas durante el envio, solo las tablas de
+    ///   Topic Alias del cliente, para que un alias nunca llegue antes que la publicacion
+    ///   que lo define
+    /// - Si el envio falla (por ejemplo, si supera `SEND_TIMEOUT`) la conexion queda
+    ///   con un paquete incompleto, por lo que se cierra
+    ///
+    pub fn send_publish(&self, client_id: &str, publish: &Publish) -> Result<(), Error> {
+        let connection = self.connections().get(client_id).cloned();
+        let connection = match connection {
+            Some(connection) => connection,
+            None => {
+                return Err(Error::new(
+                    std::io::ErrorKind::NotConnected,
+                    "Server - Cliente no conectado",
+                ))
+            }
+        };
+
+        let mut publish = publish.clone();
+        let mut topic_aliases = connection.topic_aliases();
+        topic_aliases.assign(&mut publish);
+        let result = publish.send(&mut connection.stream.clone());
+        if result.is_err() {
+            let _ = connection.stream.shutdown();
+        }
+        result
+    }
+
+    /// ### resolve_topic_alias
+    ///
+    /// Resuelve el Topic Alias de una publicacion recibida por la conexion del cliente
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `publish`: publicacion recibida
+    ///
+    /// ### Retorno
+    /// - `Result<(), ReasonCode>`: reason code con el que debe cerrarse la conexion
+    ///   si el alias es invalido
+    ///
+    pub fn resolve_topic_alias(
+        &self,
+        client_id: &str,
+        publish: &mut Publish,
+    ) -> Result<(), ReasonCode> {
+        let connection = self.connections().get(client_id).cloned();
+
+        match connection {
+            Some(connection) => connection.topic_aliases().resolve(publish),
+            None => TopicAliases::default().resolve(publish),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::Read,
+        net::{TcpListener, TcpStream},
+    };
+
+    use crate::mqtt_packets::properties::publish_properties::PublishProperties;
+
+    use super::*;
+
+    fn connection_pair() -> (MqttStream, MqttStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (
+            MqttStream::new(server).unwrap(),
+            MqttStream::new(client).unwrap(),
+        )
+    }
+
+    fn add(network: &ServerNetwork, client_id: &str, stream: &MqttStream) {
+        network.add_connection(
+            client_id.to_string(),
+            stream.clone(),
+            TopicAliases::default(),
+            FlowControl::default(),
+        );
+    }
+
+    #[test]
+    fn test_replace_and_remove_connection() {
+        let network = ServerNetwork::default();
+        let (first, first_client) = connection_pair();
+        let (second, _second_client) = connection_pair();
+
+        add(&network, "drone1", &first);
+        add(&network, "drone1", &second);
+
+        // La conexion reemplazada recibe un DISCONNECT (Session taken over) y se cierra
+        let mut bytes = Vec::new();
+        first_client.clone().read_to_end(&mut bytes).unwrap();
+        let disconnect = Disconnect::new(DisconnectProperties {
+            disconnect_reason_code: ReasonCode::SessionTakenOver.get_id(),
+            ..Default::default()
+        });
+        let mut expected = Vec::new();
+        disconnect.write_to(&mut expected).unwrap();
+        assert_eq!(bytes, expected);
+
+        // Cerrar la conexion reemplazada no quita la nueva conexion del cliente
+        network.remove_connection("drone1", &first);
+        assert!(network.is_current_connection("drone1", &second));
+
+        network.remove_connection("drone1", &second);
+        assert!(network.get_connection("drone1").is_none());
+    }
+
+    #[test]
+    fn test_send_to_stalled_client_times_out() {
+        let network = ServerNetwork::default();
+        let (stalled, _stalled_client) = connection_pair();
+        add(&network, "stalled", &stalled);
+
+        // El cliente nunca lee: al llenarse los buffers del socket el envio falla
+        // en lugar de bloquear indefinidamente
+        let publish = Publish::new(
+            0,
+            0,
+            0,
+            PublishProperties {
+                topic_name: "drone".to_string(),
+                application_message: vec![0; 16 * 1024],
+                ..Default::default()
+            },
+        );
+        let sent = (0..4096)
+            .take_while(|_| network.send_publish("stalled", &publish).is_ok())
+            .count();
+        assert!(sent < 4096);
     }
 }
@@ -0,0 +1,309 @@
+use std::{
+    fs::{self, File},
+    io::{Error, ErrorKind, Write},
+    path::Path,
+};
+
+/// Identifica a los archivos escritos por `PersistentFile`
+const MAGIC: &[u8; 4] = b"MQDB";
+
+/// Version del formato de los archivos persistidos
+pub const FORMAT_VERSION: u16 = 1;
+
+/// Tamaño del encabezado: magic, version, largo del contenido y checksum
+const HEADER_LEN: usize = 4 + 2 + 4 + 4;
+
+/// Extension del archivo temporal en el que se escribe antes de reemplazar al original
+const TEMP_FILE_EXTENSION: &str = ".tmp";
+
+/// Extension con la que se aparta un archivo corrupto
+const CORRUPT_FILE_EXTENSION: &str = ".corrupt";
+
+/// ## LoadedFile
+///
+/// Resultado de leer un archivo persistido
+///
+/// ### Variantes
+/// - `Missing`: el archivo no existe
+/// - `Valid`: contenido del archivo, con su version de formato
+/// - `Corrupt`: el archivo no puede usarse, con el motivo
+///
+#[derive(Debug, PartialEq)]
+pub enum LoadedFile {
+    Missing,
+    Valid(u16, Vec<u8>),
+    Corrupt(String),
+}
+
+/// ## PersistentFile
+///
+/// Archivo en el que el servidor persiste su estado (sesiones, mensajes retenidos)
+///
+/// ### Consideraciones
+/// - El contenido se guarda con un encabezado con la version del formato y un checksum
+///   (CRC-32), para detectar archivos truncados o modificados
+/// - Se escribe en un archivo temporal que luego reemplaza al original, por lo que una
+///   caida del servidor durante la escritura conserva la version anterior
+/// - Un archivo sin encabezado se considera de la version 0 (anterior al encabezado)
+///
+#[derive(Clone, Debug)]
+pub struct PersistentFile {
+    path: String,
+}
+
+impl PersistentFile {
+    pub fn new(path: &str) -> Self {
+        PersistentFile {
+            path: path.to_string(),
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// ### write
+    ///
+    /// Reemplaza el contenido del archivo de forma atomica
+    ///
+    /// ### Parametros
+    /// - `payload`: contenido a persistir
+    ///
+    pub fn write(&self, payload: &[u8]) -> Result<(), Error> {
+        let payload_len = u32::try_from(payload.len()).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Persistence - Contenido demasiado grande",
+            )
+        })?;
+
+        let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(FORMAT_VERSION.to_be_bytes().as_ref());
+        bytes.extend_from_slice(payload_len.to_be_bytes().as_ref());
+        bytes.extend_from_slice(checksum(payload).to_be_bytes().as_ref());
+        bytes.extend_from_slice(payload);
+
+        let temp_path = self.path.clone() + TEMP_FILE_EXTENSION;
+        let mut temp_file = File::create(&temp_path)?;
+        temp_file.write_all(&bytes)?;
+        temp_file.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+
+        // Se persiste tambien el reemplazo del archivo en el directorio
+        if let Some(dir) = Path::new(&self.path).parent() {
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+            if let Ok(dir) = File::open(dir) {
+                let _ = dir.sync_all();
+            }
+        }
+        Ok(())
+    }
+
+    /// ### read
+    ///
+    /// Lee el contenido del archivo, verificando su encabezado y checksum
+    ///
+    /// ### Retorno
+    /// - `LoadedFile`: contenido del archivo, o el motivo por el que no puede usarse
+    ///
+    pub fn read(&self) -> LoadedFile {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return LoadedFile::Missing,
+            Err(e) => return LoadedFile::Corrupt(e.to_string()),
+        };
+        if !bytes.starts_with(MAGIC) {
+            return LoadedFile::Valid(0, bytes);
+        }
+        if bytes.len() < HEADER_LEN {
+            return LoadedFile::Corrupt("encabezado incompleto".to_string());
+        }
+
+        let version = u16::from_be_bytes([bytes[4], bytes[5]]);
+        if version > FORMAT_VERSION {
+            return LoadedFile::Corrupt(format!("version de formato {} no soportada", version));
+        }
+        let payload_len = u32::from_be_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]) as usize;
+        let expected_checksum = u32::from_be_bytes([bytes[10], bytes[11], bytes[12], bytes[13]]);
+
+        let payload = &bytes[HEADER_LEN..];
+        if payload.len() != payload_len {
+            return LoadedFile::Corrupt(format!(
+                "se esperaban {} bytes de contenido y se leyeron {}",
+                payload_len,
+                payload.len()
+            ));
+        }
+        if checksum(payload) != expected_checksum {
+            return LoadedFile::Corrupt("checksum invalido".to_string());
+        }
+        LoadedFile::Valid(version, payload.to_vec())
+    }
+
+    /// ### quarantine
+    ///
+    /// Aparta un archivo corrupto, para que no se sobreescriba y pueda inspeccionarse
+    ///
+    /// ### Retorno
+    /// - `Result<String, Error>`: ruta a la que se movió el archivo
+    ///
+    pub fn quarantine(&self) -> Result<String, Error> {
+        let corrupt_path = self.path.clone() + CORRUPT_FILE_EXTENSION;
+        fs::rename(&self.path, &corrupt_path)?;
+        Ok(corrupt_path)
+    }
+
+    /// ### load
+    ///
+    /// Lee y decodifica el contenido del archivo. Si esta corrupto o no puede
+    /// decodificarse, lo aparta y lo informa
+    ///
+    /// ### Parametros
+    /// - `decode`: decodifica el contenido segun su version de formato
+    ///
+    /// ### Retorno
+    /// - `(Option<T>, Option<String>)`: contenido decodificado (`None` si no hay un archivo
+    ///   valido) y la descripción del error si el archivo estaba corrupto
+    ///
+    pub fn load<T>(
+        &self,
+        decode: impl FnOnce(u16, Vec<u8>) -> Option<T>,
+    ) -> (Option<T>, Option<String>) {
+        let reason = match self.read() {
+            LoadedFile::Missing => return (None, None),
+            LoadedFile::Valid(version, payload) => match decode(version, payload) {
+                Some(value) => return (Some(value), None),
+                None => "contenido invalido".to_string(),
+            },
+            LoadedFile::Corrupt(reason) => reason,
+        };
+
+        let error = match self.quarantine() {
+            Ok(corrupt_path) => format!(
+                "Archivo '{}' corrupto ({}), se movió a '{}'",
+                self.path, reason, corrupt_path
+            ),
+            Err(e) => format!(
+                "Archivo '{}' corrupto ({}), no se pudo apartar: {}",
+                self.path, reason, e
+            ),
+        };
+        (None, Some(error))
+    }
+}
+
+/// ### checksum
+///
+/// Calcula el CRC-32 (IEEE 802.3) de los bytes
+///
+pub fn checksum(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFF_u32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn test_file(name: &str) -> PersistentFile {
+        let path = std::env::temp_dir().join(format!(
+            "mqtt_persistence_{}_{}.tmp",
+            name,
+            std::process::id()
+        ));
+        PersistentFile::new(path.to_str().unwrap())
+    }
+
+    fn clean(file: &PersistentFile) {
+        let _ = fs::remove_file(file.path());
+        let _ = fs::remove_file(file.path().to_string() + CORRUPT_FILE_EXTENSION);
+    }
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(checksum(b"123456789"), 0xCBF4_3926);
+        assert_eq!(checksum(b""), 0);
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let file = test_file("write");
+        assert_eq!(file.read(), LoadedFile::Missing);
+
+        file.write(b"sesiones").unwrap();
+        assert_eq!(
+            file.read(),
+            LoadedFile::Valid(FORMAT_VERSION, b"sesiones".to_vec())
+        );
+        assert!(fs::metadata(file.path().to_string() + TEMP_FILE_EXTENSION).is_err());
+
+        file.write(b"").unwrap();
+        assert_eq!(file.read(), LoadedFile::Valid(FORMAT_VERSION, Vec::new()));
+        clean(&file);
+    }
+
+    #[test]
+    fn test_legacy_file_without_header() {
+        let file = test_file("legacy");
+        fs::write(file.path(), [0, 0]).unwrap();
+        assert_eq!(file.read(), LoadedFile::Valid(0, vec![0, 0]));
+        clean(&file);
+    }
+
+    #[test]
+    fn test_corrupt_files() {
+        let file = test_file("corrupt");
+        file.write(b"sesiones").unwrap();
+        let bytes = fs::read(file.path()).unwrap();
+
+        // Truncado
+        fs::write(file.path(), &bytes[..bytes.len() - 2]).unwrap();
+        assert!(matches!(file.read(), LoadedFile::Corrupt(_)));
+        fs::write(file.path(), &bytes[..HEADER_LEN - 1]).unwrap();
+        assert!(matches!(file.read(), LoadedFile::Corrupt(_)));
+
+        // Modificado
+        let mut modified = bytes.clone();
+        *modified.last_mut().unwrap() ^= 1;
+        fs::write(file.path(), &modified).unwrap();
+        assert_eq!(
+            file.read(),
+            LoadedFile::Corrupt("checksum invalido".to_string())
+        );
+
+        // Version posterior
+        let mut newer = bytes;
+        newer[5] += 1;
+        fs::write(file.path(), &newer).unwrap();
+        assert!(matches!(file.read(), LoadedFile::Corrupt(_)));
+        clean(&file);
+    }
+
+    #[test]
+    fn test_load_quarantines_corrupt_files() {
+        let file = test_file("load");
+        file.write(b"sesiones").unwrap();
+
+        let (value, error) = file.load(|_, _| None::<()>);
+        assert!(value.is_none());
+        assert!(error.unwrap().contains("contenido invalido"));
+        assert_eq!(file.read(), LoadedFile::Missing);
+        assert!(fs::metadata(file.path().to_string() + CORRUPT_FILE_EXTENSION).is_ok());
+
+        let (value, error) = file.load(|_, payload| Some(payload));
+        assert!(value.is_none() && error.is_none());
+        clean(&file);
+    }
+}
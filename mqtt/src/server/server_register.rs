@@ -1,7 +1,11 @@
 use std::{
-    collections::{HashMap, VecDeque},
-    fs,
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
     io::Error,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
 };
 
 use logger::logger_handler::Logger;
@@ -10,92 +14,336 @@ use crate::{
     common::{flags::flags_handler, reason_codes::ReasonCode, topic_filter::TopicFilter},
     config::server_config::ServerConfig,
     logging::{actions::MqttActions, server_actions::MqttServerActions},
-    mqtt_packets::packets::{connect::Connect, publish::Publish},
+    mqtt_packets::{
+        packets::{connect::Connect, publish::Publish},
+        properties::disconnect_properties::DisconnectProperties,
+    },
 };
 
 use super::{
-    server_handlers::disconnect_handler, server_network::ServerNetwork, server_session::Session,
+    inflight_message::InflightMessage,
+    queued_message::QueueOverflowPolicy,
+    server_handlers::disconnect_handler,
+    server_network::ServerNetwork,
+    server_persistence::{PersistentFile, FORMAT_VERSION},
+    server_session::{self, Session},
     will_message::WillMessage,
 };
 
-#[derive(Clone, Default)]
+/// Cantidad de particiones del registro de sesiones
+const SESSION_SHARDS: usize = 16;
+
+type Sessions = HashMap<String, Session>;
+
+/// Publicacion del mensaje de voluntad al desconectarse una sesión
+enum WillPublication {
+    Now(Option<WillMessage>),
+    Delayed(u32),
+    Discarded,
+}
+
+/// ## SessionRegister
+///
+/// Registro de las sesiones de los clientes del servidor
+///
+/// ### Atributos
+/// - `shards`: sesiones particionadas segun el client identifier, cada particion con su propio lock
+/// - `persistence`: lock que serializa las escrituras del archivo de sesiones
+/// - `dirty`: indica si hay cambios en las sesiones que todavia no se persistieron
+/// - `shared_cursors`: por cada subscripción compartida, posicion del ultimo miembro
+///   elegido (para repartir los mensajes de forma rotativa)
+/// - `db_path`: ruta del archivo donde se persisten las sesiones
+/// - `max_queued_messages`: cantidad maxima de mensajes en la cola de cada sesión
+/// - `queue_overflow_policy`: mensaje que se descarta al superarse la cantidad maxima
+/// - `load_error`: descripción del error si el archivo de sesiones estaba corrupto al iniciar
+///
+/// ### Consideraciones
+/// - Es compartido por todas las conexiones del servidor (sus clones referencian las mismas sesiones)
+/// - Las operaciones sobre la sesion de un cliente solo bloquean su particion, por lo que
+///   las conexiones de clientes distintos pueden procesarse en paralelo
+/// - Nunca se retiene el lock de una particion mientras se escribe en la red o en el archivo
+/// - Las operaciones no escriben el archivo: solo marcan que hay cambios. El archivo se
+///   reescribe con `flush`, que el servidor llama antes de confirmarle a un cliente un cambio
+///   de estado (PUBACK, PUBREC, PUBCOMP, SUBACK, UNSUBACK), periodicamente y al apagarse
+/// - El archivo de sesiones se escribe con `PersistentFile`: si al iniciar esta corrupto,
+///   se aparta y el servidor comienza sin sesiones. Un archivo sin encabezado (version 0)
+///   se recupera tanto con el formato actual como con el anterior a las User Properties
+///   multiples (ver `Session::from_v0_be_bytes`)
+///
+#[derive(Clone)]
 pub struct SessionRegister {
-    sessions: HashMap<String, Session>,
+    shards: Arc<Vec<Mutex<Sessions>>>,
+    persistence: Arc<Mutex<()>>,
+    dirty: Arc<AtomicBool>,
+    shared_cursors: Arc<Mutex<HashMap<String, usize>>>,
     pub db_path: Option<String>,
+    max_queued_messages: Option<usize>,
+    queue_overflow_policy: QueueOverflowPolicy,
+    load_error: Option<String>,
+}
+
+impl Default for SessionRegister {
+    fn default() -> Self {
+        Self::with_sessions(HashMap::new(), None)
+    }
 }
 
 impl SessionRegister {
-    fn sessions_as_bytes(&self) -> Vec<u8> {
-        let mut bytes = Vec::new();
+    fn with_sessions(sessions: Sessions, db_path: Option<String>) -> Self {
+        let shards = (0..SESSION_SHARDS)
+            .map(|_| Mutex::new(HashMap::new()))
+            .collect::<Vec<Mutex<Sessions>>>();
 
-        let sessions_len = self.sessions.len() as u16;
-        bytes.extend_from_slice(sessions_len.to_be_bytes().as_ref());
+        let register = SessionRegister {
+            shards: Arc::new(shards),
+            persistence: Arc::new(Mutex::new(())),
+            dirty: Arc::new(AtomicBool::new(false)),
+            shared_cursors: Arc::new(Mutex::new(HashMap::new())),
+            db_path,
+            max_queued_messages: None,
+            queue_overflow_policy: QueueOverflowPolicy::default(),
+            load_error: None,
+        };
+        for (id, session) in sessions {
+            register.shard(&id).insert(id, session);
+        }
+        register
+    }
+
+    /// ### shard
+    ///
+    /// Bloquea y devuelve la particion que contiene la sesion del cliente
+    ///
+    fn shard(&self, client_id: &str) -> MutexGuard<'_, Sessions> {
+        let mut hasher = DefaultHasher::new();
+        client_id.hash(&mut hasher);
+        let index = (hasher.finish() % self.shards.len() as u64) as usize;
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn shards(&self) -> impl Iterator<Item = MutexGuard<'_, Sessions>> {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap_or_else(PoisonError::into_inner))
+    }
 
-        for (id, session) in &self.sessions {
-            let id_len = id.len() as u16;
-            bytes.extend_from_slice(id_len.to_be_bytes().as_ref());
-            bytes.extend_from_slice(id.as_bytes().as_ref());
-            bytes.extend_from_slice(session.as_bytes().as_ref());
+    /// ### with_session
+    ///
+    /// Ejecuta una operacion sobre la sesion del cliente, bloqueando solo su particion
+    ///
+    /// ### Retorno
+    /// - `Option<T>`: resultado de la operacion, o `None` si el cliente no tiene sesion
+    ///
+    fn with_session<T>(
+        &self,
+        client_id: &str,
+        operation: impl FnOnce(&mut Session) -> T,
+    ) -> Option<T> {
+        self.shard(client_id).get_mut(client_id).map(operation)
+    }
+
+    fn sessions_as_bytes(&self) -> Vec<u8> {
+        let mut sessions_len: u16 = 0;
+        let mut sessions_bytes = Vec::new();
+
+        for shard in self.shards() {
+            for (id, session) in shard.iter() {
+                let id_len = id.len() as u16;
+                sessions_bytes.extend_from_slice(id_len.to_be_bytes().as_ref());
+                sessions_bytes.extend_from_slice(id.as_bytes().as_ref());
+                sessions_bytes.extend_from_slice(session.as_bytes().as_ref());
+                sessions_len += 1;
+            }
         }
+
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(sessions_len.to_be_bytes().as_ref());
+        bytes.extend_from_slice(&sessions_bytes);
         bytes
     }
 
-    fn sessions_from_be_bytes(bytes: Vec<u8>) -> HashMap<String, Session> {
+    /// ### sessions_from_be_bytes
+    ///
+    /// Decodifica las sesiones persistidas
+    ///
+    /// ### Retorno
+    /// - `Option<Sessions>`: sesiones, o `None` si los bytes estan incompletos o son invalidos
+    ///
+    fn sessions_from_be_bytes(bytes: Vec<u8>) -> Option<Sessions> {
+        Self::decode_sessions(bytes, |bytes| {
+            let session = Session::from_be_bytes(bytes)?;
+            let size = session.size_of();
+            Some((session, size))
+        })
+    }
+
+    /// ### sessions_from_v0_be_bytes
+    ///
+    /// Decodifica las sesiones de un archivo sin encabezado (version 0) escrito con el
+    /// formato anterior a las User Properties multiples
+    ///
+    /// ### Retorno
+    /// - `Option<Sessions>`: sesiones, o `None` si los bytes estan incompletos o son invalidos
+    ///
+    fn sessions_from_v0_be_bytes(bytes: Vec<u8>) -> Option<Sessions> {
+        Self::decode_sessions(bytes, Session::from_v0_be_bytes)
+    }
+
+    fn decode_sessions(
+        bytes: Vec<u8>,
+        decode_session: impl Fn(Vec<u8>) -> Option<(Session, usize)>,
+    ) -> Option<Sessions> {
         let mut sessions = HashMap::new();
 
         let mut index = 0;
 
-        let sessions_len = u16::from_be_bytes([bytes[index], bytes[index + 1]]);
+        let sessions_len = u16::from_be_bytes(bytes.get(index..index + 2)?.try_into().ok()?);
         index += 2;
 
         for _ in 0..sessions_len {
-            let id_len = u16::from_be_bytes([bytes[index], bytes[index + 1]]);
+            let id_len = u16::from_be_bytes(bytes.get(index..index + 2)?.try_into().ok()?);
             index += 2;
-            let id = String::from_utf8(bytes[index..id_len as usize + index].to_vec()).unwrap();
+            let id = String::from_utf8(bytes.get(index..index + id_len as usize)?.to_vec()).ok()?;
             index += id.len();
-            let session = Session::from_be_bytes(bytes[index..].to_vec());
-            index += session.size_of();
-            sessions.insert(id.clone(), session);
+            let (session, size) = decode_session(bytes.get(index..)?.to_vec())?;
+            index += size;
+            sessions.insert(id, session);
         }
 
-        sessions
+        if index != bytes.len() {
+            return None;
+        }
+        Some(sessions)
     }
 
+    /// ### new
+    ///
+    /// Crea el registro de sesiones, recuperando las sesiones persistidas
+    ///
+    /// ### Parametros
+    /// - `db_path`: archivo donde se persisten las sesiones
+    ///
+    /// ### Consideraciones
+    /// - Si el archivo esta corrupto se aparta y se informa en `load_error`
+    /// - Los cambios al recuperar las sesiones se persisten en el proximo `flush`
+    ///
     pub fn new(db_path: Option<String>) -> Self {
         let mut sessions = HashMap::new();
+        let mut load_error = None;
+        let mut changed = false;
 
         if let Some(db_path) = &db_path {
             if !db_path.is_empty() {
-                sessions = match fs::read(db_path) {
-                    Ok(bytes) => Self::sessions_from_be_bytes(bytes),
-                    Err(_) => HashMap::new(),
-                };
-                if !sessions.is_empty() {
-                    for session in sessions.values_mut() {
-                        session.active = false;
+                let (loaded, error) = PersistentFile::new(db_path).load(|version, payload| {
+                    // Un archivo sin encabezado puede tener el formato actual o el anterior
+                    // a las User Properties multiples
+                    let sessions = match version {
+                        0 => Self::sessions_from_be_bytes(payload.clone())
+                            .or_else(|| Self::sessions_from_v0_be_bytes(payload)),
+                        _ => Self::sessions_from_be_bytes(payload),
+                    };
+                    // Un archivo de una version anterior se reescribe con la actual
+                    changed = sessions.is_some() && version < FORMAT_VERSION;
+                    sessions
+                });
+                sessions = loaded.unwrap_or_default();
+                load_error = error;
+                // Las conexiones activas al detenerse el servidor se consideran cerradas
+                // al iniciarlo: comienza a correr su expiracion y su mensaje de voluntad
+                let now = server_session::now();
+                for session in sessions.values_mut() {
+                    if session.active {
+                        session.disconnect();
+                        session.will_publish_at =
+                            session.will_delay().map(|delay| now + delay as u64);
+                        changed = true;
                     }
                 }
             }
         }
 
-        SessionRegister { sessions, db_path }
+        let mut register = Self::with_sessions(sessions, db_path);
+        register.load_error = load_error;
+        if changed {
+            register.save();
+        }
+        register
+    }
+
+    /// ### load_error
+    ///
+    /// Descripción del error si el archivo de sesiones estaba corrupto al iniciar
+    ///
+    pub fn load_error(&self) -> Option<&String> {
+        self.load_error.as_ref()
+    }
+
+    /// ### with_queue_limit
+    ///
+    /// Limita la cantidad de mensajes en la cola de cada sesión
+    ///
+    /// ### Parametros
+    /// - `max_queued_messages`: cantidad maxima de mensajes en cola (`None`: sin limite)
+    /// - `policy`: mensaje que se descarta al superarse la cantidad maxima
+    ///
+    pub fn with_queue_limit(
+        mut self,
+        max_queued_messages: Option<usize>,
+        policy: QueueOverflowPolicy,
+    ) -> Self {
+        self.max_queued_messages = max_queued_messages;
+        self.queue_overflow_policy = policy;
+        self
     }
 
     pub fn log_sessions(&self, server_id: &String, log_in_term: &bool, logger: &Logger) {
-        MqttServerActions::RecoverSessions(self.sessions.keys().cloned().collect()).log_action(
-            server_id,
-            logger,
-            log_in_term,
-        );
+        let ids = self
+            .shards()
+            .flat_map(|shard| shard.keys().cloned().collect::<Vec<String>>())
+            .collect();
+        MqttServerActions::RecoverSessions(ids).log_action(server_id, logger, log_in_term);
     }
 
+    /// ### save
+    ///
+    /// Marca que las sesiones cambiaron y deben persistirse en el proximo `flush`
+    ///
     fn save(&self) {
-        if let Some(db_path) = &self.db_path {
-            if !db_path.is_empty() {
-                let bytes = self.sessions_as_bytes();
-                fs::write(db_path, bytes).unwrap();
-            }
+        self.dirty.store(true, Ordering::SeqCst);
+    }
+
+    /// ### flush
+    ///
+    /// Persiste las sesiones si cambiaron desde la ultima escritura
+    ///
+    /// ### Retorno
+    /// - `Result<(), Error>`: Error si no se pudo escribir el archivo de sesiones
+    ///
+    /// ### Consideraciones
+    /// - Si la escritura falla, las sesiones se vuelven a intentar persistir en el proximo `flush`
+    ///
+    pub fn flush(&self) -> Result<(), Error> {
+        let db_path = match &self.db_path {
+            Some(db_path) if !db_path.is_empty() => db_path,
+            _ => return Ok(()),
+        };
+        let _persistence = self
+            .persistence
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
         }
+        let bytes = self.sessions_as_bytes();
+        PersistentFile::new(db_path).write(&bytes).map_err(|e| {
+            self.dirty.store(true, Ordering::SeqCst);
+            Error::new(
+                e.kind(),
+                format!("Error al guardar las sesiones en '{}': {}", db_path, e),
+            )
+        })
     }
 
     /// ### open_session
@@ -109,199 +357,762 @@ impl SessionRegister {
     /// ### Retorno
     /// - `u8`: Resultado de la operación
     ///
-    pub fn open_session(&mut self, connect: Connect) -> u8 {
-        if let Some(session) = self.sessions.get_mut(&connect.payload.client_id) {
-            // Resumes session
-            let will_message = WillMessage::new(
-                flags_handler::get_connect_flag_will_flag(connect.properties.connect_flags),
-                connect.payload.will_topic.as_ref(),
-                connect.payload.will_payload.clone(),
-            );
-            if let Some(will) = will_message {
-                session.will_message = Some(will);
+    pub fn open_session(&self, connect: Connect) -> u8 {
+        let session_present = {
+            let mut shard = self.shard(&connect.payload.client_id);
+            if let Some(session) = shard.get_mut(&connect.payload.client_id) {
+                // Resumes session
+                session.reconnect(&connect);
+                1
+            } else {
+                // New session
+                let session = Session::new(&connect);
+                shard.insert(connect.payload.client_id, session);
+                0
             }
-
-            session.reconnect();
-            self.save();
-            1
-        } else {
-            // New session
-            let session = Session::new(&connect);
-            self.sessions.insert(connect.payload.client_id, session);
-            self.save();
-            0
-        }
+        };
+        self.save();
+        session_present
     }
 
-    pub fn clean_session(&mut self, client_id: &str) {
-        self.sessions.remove(client_id);
+    /// ### clean_session
+    ///
+    /// Descarta la sesión del cliente (Clean Start)
+    ///
+    /// ### Retorno
+    /// - `Option<WillMessage>`: mensaje de voluntad demorado de la sesión descartada,
+    ///   que debe publicarse ya que la sesión terminó
+    ///
+    pub fn clean_session(&self, client_id: &str) -> Option<WillMessage> {
+        let session = self.shard(client_id).remove(client_id);
         self.save();
+        session
+            .filter(|session| session.will_publish_at.is_some())
+            .and_then(|session| session.will_message)
     }
 
+    /// ### add_subscription
+    ///
+    /// Agrega subscripciones a la sesión de un cliente.
+    /// Si ya existia una subscripción con el mismo filtro, se reemplazan sus opciones
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente
+    /// - `topics`: filtros de topicos a subscribir
+    ///
+    /// ### Retorno
+    /// - `Result<Vec<bool>, Error>`: por cada filtro, si la subscripción es nueva
+    ///
     pub fn add_subscription(
-        &mut self,
+        &self,
         client_id: &str,
-        mut topics: Vec<TopicFilter>,
-    ) -> Result<(), Error> {
-        if let Some(session) = self.sessions.get_mut(client_id) {
-            for topic in topics.iter_mut() {
-                if !session
+        topics: Vec<TopicFilter>,
+    ) -> Result<Vec<bool>, Error> {
+        let new_subscriptions = self.with_session(client_id, |session| {
+            let mut new_subscriptions = Vec::new();
+
+            for topic in topics {
+                match session
                     .subscriptions
-                    .iter()
-                    .any(|t| t.topic_filter == topic.topic_filter)
+                    .iter_mut()
+                    .find(|t| t.topic_filter == topic.topic_filter)
                 {
-                    session.subscriptions.push(topic.clone());
+                    Some(subscription) => {
+                        subscription.subscription_options = topic.subscription_options;
+                        subscription.subscription_identifier = topic.subscription_identifier;
+                        new_subscriptions.push(false);
+                    }
+                    None => {
+                        session.subscriptions.push(topic);
+                        new_subscriptions.push(true);
+                    }
                 }
             }
+            new_subscriptions
+        });
 
-            self.save();
-            return Ok(());
+        match new_subscriptions {
+            Some(new_subscriptions) => {
+                self.save();
+                Ok(new_subscriptions)
+            }
+            None => Err(Error::new(
+                std::io::ErrorKind::Other,
+                "Server - Cliente no encontrado",
+            )),
         }
-        Err(Error::new(
-            std::io::ErrorKind::Other,
-            "Server - Cliente no encontrado",
-        ))
     }
 
     pub fn remove_subscription(
-        &mut self,
+        &self,
         client_id: &str,
         topic_filters: Vec<String>,
     ) -> Result<(), Error> {
-        if let Some(session) = self.sessions.get_mut(client_id) {
+        let removed = self.with_session(client_id, |session| {
             session
                 .subscriptions
                 .retain(|t| topic_filters.iter().any(|u| *u != t.topic_filter));
-            self.save();
-            return Ok(());
+        });
+
+        match removed {
+            Some(_) => {
+                self.save();
+                Ok(())
+            }
+            None => Err(Error::new(
+                std::io::ErrorKind::Other,
+                "Server - Cliente no encontrado",
+            )),
         }
-        Err(Error::new(
-            std::io::ErrorKind::Other,
-            "Server - Cliente no encontrado",
-        ))
     }
 
+    /// ### get_subscribers
+    ///
+    /// Obtiene las sesiones con una subscripción no compartida que coincide con el topico
+    ///
     pub fn get_subscribers(&self, topic: &str) -> Vec<(String, Session)> {
         let mut subscribers = Vec::new();
-        for (id, session) in &self.sessions {
-            if session
-                .subscriptions
-                .iter()
-                .any(|t| t.topic_filter == topic)
-            {
-                subscribers.push((id.clone(), session.clone()));
+        for shard in self.shards() {
+            for (id, session) in shard.iter() {
+                if session
+                    .subscriptions
+                    .iter()
+                    .any(|t| t.share_name().is_none() && t.matches(topic))
+                {
+                    subscribers.push((id.clone(), session.clone()));
+                }
             }
         }
         subscribers
     }
 
-    pub fn store_message(&mut self, client_id: &str, message: Publish) -> Result<(), Error> {
-        if let Some(session) = self.sessions.get_mut(client_id) {
-            session.store_message(message);
+    /// ### get_shared_subscriptions
+    ///
+    /// Obtiene las subscripciones compartidas (`$share/{grupo}/{filtro}`) que coinciden
+    /// con el topico. Cada una recibe la publicacion una unica vez
+    ///
+    pub fn get_shared_subscriptions(&self, topic: &str) -> Vec<String> {
+        let mut shared_subscriptions = Vec::new();
+        for shard in self.shards() {
+            for session in shard.values() {
+                for t in session.subscriptions.iter() {
+                    if t.share_name().is_some()
+                        && t.matches(topic)
+                        && !shared_subscriptions.contains(&t.topic_filter)
+                    {
+                        shared_subscriptions.push(t.topic_filter.clone());
+                    }
+                }
+            }
+        }
+        shared_subscriptions
+    }
+
+    /// ### choose_shared_subscriber
+    ///
+    /// Elige el miembro de una subscripción compartida que debe recibir la proxima publicacion
+    ///
+    /// ### Parametros
+    /// - `topic_filter`: subscripción compartida (`$share/{grupo}/{filtro}`)
+    ///
+    /// ### Retorno
+    /// - `Option<(String, Session)>`: id y sesión del miembro elegido, o `None`
+    ///   si la subscripción no tiene miembros
+    ///
+    /// ### Consideraciones
+    /// - Se prefieren los miembros conectados, y entre ellos el que tenga menos
+    ///   publicaciones pendientes (en vuelo y en cola)
+    /// - Entre miembros con la misma carga, los mensajes se reparten de forma rotativa
+    ///
+    pub fn choose_shared_subscriber(&self, topic_filter: &str) -> Option<(String, Session)> {
+        let mut members = Vec::new();
+        for shard in self.shards() {
+            for (id, session) in shard.iter() {
+                if session
+                    .subscriptions
+                    .iter()
+                    .any(|t| t.topic_filter == topic_filter)
+                {
+                    members.push((id.clone(), session.clone()));
+                }
+            }
+        }
+        if members.is_empty() {
+            return None;
+        }
+        members.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let mut cursors = self
+            .shared_cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let cursor = cursors.entry(topic_filter.to_string()).or_insert(0);
+        let chosen = (0..members.len())
+            .map(|i| (*cursor + i) % members.len())
+            .min_by_key(|i| {
+                let session = &members[*i].1;
+                (!session.active, session.pending_messages())
+            })?;
+        *cursor = chosen + 1;
+
+        Some(members.swap_remove(chosen))
+    }
+
+    /// ### take_shared_messages
+    ///
+    /// Quita de la sesión del cliente las publicaciones de subscripciones compartidas que
+    /// todavia no recibio, para redistribuirlas entre los demas miembros de cada grupo
+    ///
+    /// ### Retorno
+    /// - `Vec<(String, Publish)>`: subscripción compartida de cada publicacion, junto a la publicacion
+    ///
+    pub fn take_shared_messages(&self, client_id: &str) -> Vec<(String, Publish)> {
+        let shared_messages = self
+            .with_session(client_id, |session| {
+                session.take_shared_messages(server_session::now())
+            })
+            .unwrap_or_default();
+        if !shared_messages.is_empty() {
             self.save();
-            return Ok(());
         }
-        Err(Error::new(
-            std::io::ErrorKind::Other,
-            "Server - Cliente no encontrado",
-        ))
+        shared_messages
+    }
+
+    /// ### store_message
+    ///
+    /// Encola una publicacion en la sesión del cliente
+    ///
+    /// ### Retorno
+    /// - `Result<Option<Publish>, Error>`: publicacion descartada si la cola superó su
+    ///   cantidad maxima de mensajes (la mas antigua o la nueva, segun la politica)
+    ///
+    pub fn store_message(
+        &self,
+        client_id: &str,
+        message: Publish,
+    ) -> Result<Option<Publish>, Error> {
+        let discarded = self.with_session(client_id, |session| {
+            session.store_message(message, server_session::now());
+            self.max_queued_messages.and_then(|max_queued_messages| {
+                session.trim_queue(max_queued_messages, self.queue_overflow_policy)
+            })
+        });
+        match discarded {
+            Some(discarded) => {
+                self.save();
+                Ok(discarded)
+            }
+            None => Err(Error::new(
+                std::io::ErrorKind::Other,
+                "Server - Cliente no encontrado",
+            )),
+        }
+    }
+
+    /// ### has_session
+    ///
+    /// Indica si el cliente tiene una sesion en el servidor
+    ///
+    pub fn has_session(&self, client_id: &str) -> bool {
+        self.shard(client_id).contains_key(client_id)
+    }
+
+    /// ### is_session_active
+    ///
+    /// Indica si el cliente tiene una sesion con una conexion activa
+    ///
+    pub fn is_session_active(&self, client_id: &str) -> bool {
+        self.with_session(client_id, |session| session.active)
+            .unwrap_or(false)
+    }
+
+    /// ### count_sessions
+    ///
+    /// Cuenta las sesiones del servidor segun tengan o no una conexion activa
+    ///
+    /// ### Retorno
+    /// - `(usize, usize)`: cantidad de sesiones conectadas y desconectadas
+    ///
+    pub fn count_sessions(&self) -> (usize, usize) {
+        self.shards()
+            .flat_map(|sessions| {
+                sessions
+                    .values()
+                    .map(|session| session.active)
+                    .collect::<Vec<bool>>()
+            })
+            .fold((0, 0), |(connected, disconnected), active| {
+                if active {
+                    (connected + 1, disconnected)
+                } else {
+                    (connected, disconnected + 1)
+                }
+            })
     }
 
+    /// ### queued_messages
+    ///
+    /// Obtiene la cantidad de mensajes en la cola de cada sesion
+    ///
+    /// ### Retorno
+    /// - `Vec<(String, usize)>`: id del cliente y cantidad de mensajes en cola
+    ///
+    pub fn queued_messages(&self) -> Vec<(String, usize)> {
+        self.shards()
+            .flat_map(|sessions| {
+                sessions
+                    .iter()
+                    .map(|(id, session)| (id.clone(), session.messages_in_queue.len()))
+                    .collect::<Vec<(String, usize)>>()
+            })
+            .collect()
+    }
+
+    /// ### get_sessions
+    ///
+    /// Obtiene una copia de todas las sesiones del servidor, ordenadas por client id
+    ///
+    pub fn get_sessions(&self) -> Vec<(String, Session)> {
+        let mut sessions: Vec<(String, Session)> = self
+            .shards()
+            .flat_map(|sessions| {
+                sessions
+                    .iter()
+                    .map(|(id, session)| (id.clone(), session.clone()))
+                    .collect::<Vec<(String, Session)>>()
+            })
+            .collect();
+        sessions.sort_by(|(a, _), (b, _)| a.cmp(b));
+        sessions
+    }
+
+    /// ### purge_queue
+    ///
+    /// Descarta los mensajes en la cola de la sesión de un cliente
+    ///
+    /// ### Retorno
+    /// - `Option<usize>`: cantidad de mensajes descartados, o `None` si el cliente no tiene sesion
+    ///
+    pub fn purge_queue(&self, client_id: &str) -> Option<usize> {
+        let purged = self.with_session(client_id, |session| {
+            let purged = session.messages_in_queue.len();
+            session.messages_in_queue.clear();
+            purged
+        });
+        if purged.is_some() {
+            self.save();
+        }
+        purged
+    }
+
+    /// ### disconnect_session
+    ///
+    /// Desconecta la sesión de un cliente cuya conexión se cerró
+    ///
+    /// ### Parametros
+    /// - `network`: conexiones del servidor (para publicar el mensaje de voluntad)
+    /// - `client_id`: id del cliente
+    /// - `disconnect`: propiedades del DISCONNECT recibido, o `None` si la conexión
+    ///   se cerró sin recibirlo
+    ///
+    /// ### Retorno
+    /// - `Result<MqttServerActions, Error>`: accion realizada con el mensaje de voluntad
+    ///
+    /// ### Consideraciones
+    /// - Un DISCONNECT con reason code 0x00 descarta el mensaje de voluntad
+    /// - El mensaje de voluntad se publica luego de su Will Delay Interval, o al expirar
+    ///   la sesión si esto ocurre antes
+    /// - Una sesión con Session Expiry Interval 0 termina al cerrarse la conexión
+    ///
     pub fn disconnect_session(
-        &mut self,
-        network: &mut ServerNetwork,
+        &self,
+        network: &ServerNetwork,
         client_id: String,
+        disconnect: Option<&DisconnectProperties>,
         server_id: &String,
         log_in_term: &bool,
         logger: &Logger,
     ) -> Result<MqttServerActions, Error> {
-        let sessions = self.sessions.clone();
+        let discard_will = disconnect.is_some_and(|disconnect| {
+            disconnect.disconnect_reason_code == ReasonCode::NormalDisconnection.get_id()
+        });
 
-        let action = if let Some(session) = self.sessions.get_mut(&client_id) {
+        let disconnected = self.with_session(&client_id, |session| {
+            if let Some(interval) = disconnect.and_then(|d| d.session_expiry_interval) {
+                session.update_session_expiry_interval(interval);
+            }
             session.disconnect();
 
-            MqttServerActions::DisconnectSession(client_id.clone()).log_action(
+            let had_will = session.will_message.is_some();
+            if discard_will {
+                session.will_message = None;
+            }
+            let will = match session.will_delay() {
+                Some(0) => WillPublication::Now(session.will_message.take()),
+                Some(delay) => {
+                    session.will_publish_at = Some(server_session::now() + delay as u64);
+                    WillPublication::Delayed(delay)
+                }
+                None if had_will => WillPublication::Discarded,
+                None => WillPublication::Now(None),
+            };
+            (will, session.session_expiry_interval == 0)
+        });
+
+        let (will, session_ended) = match disconnected {
+            Some(disconnected) => disconnected,
+            None => {
+                return Err(Error::new(
+                    std::io::ErrorKind::Other,
+                    "Server - Cliente no encontrado",
+                ))
+            }
+        };
+
+        if session_ended {
+            let mut shard = self.shard(&client_id);
+            if shard.get(&client_id).is_some_and(|session| !session.active) {
+                shard.remove(&client_id);
+            }
+        }
+        self.save();
+
+        MqttServerActions::DisconnectSession(client_id.clone()).log_action(
+            server_id,
+            logger,
+            log_in_term,
+        );
+        if session_ended {
+            MqttServerActions::SessionExpired(client_id.clone()).log_action(
                 server_id,
                 logger,
                 log_in_term,
             );
+        }
 
-            if let Some(will_message) = session.will_message.clone() {
-                let mut receivers = Vec::new();
-                let mut will_message_sent = false;
-
-                sessions.into_iter().for_each(|(id, s)| {
-                    if s.active
-                        && id != client_id
-                        && s.subscriptions
-                            .iter()
-                            .any(|t| t.topic_filter == will_message.will_topic)
-                    {
-                        let stream = network.connections.get_mut(&id).unwrap();
+        Ok(match will {
+            WillPublication::Now(Some(will_message)) => {
+                self.publish_will(network, &client_id, will_message)
+            }
+            WillPublication::Now(None) => MqttServerActions::NoSendWillMessage(),
+            WillPublication::Delayed(delay) => {
+                MqttServerActions::DelayWillMessage(client_id, delay)
+            }
+            WillPublication::Discarded => MqttServerActions::DiscardWillMessage(client_id),
+        })
+    }
 
-                        will_message_sent =
-                            will_message.send_message(&mut stream.try_clone().unwrap());
-                        receivers.push(id.clone());
-                    }
-                });
-                if will_message_sent {
-                    Ok(MqttServerActions::SendWillMessage(
-                        will_message.will_topic,
-                        receivers,
-                    ))
-                } else {
-                    Ok(MqttServerActions::ErrorWhileSendingWillMessage())
+    /// ### publish_will
+    ///
+    /// Publica el mensaje de voluntad de un cliente a los suscriptores conectados
+    ///
+    pub fn publish_will(
+        &self,
+        network: &ServerNetwork,
+        client_id: &str,
+        will_message: WillMessage,
+    ) -> MqttServerActions {
+        let mut receivers = Vec::new();
+        let mut will_message_sent = false;
+
+        for (id, s) in self.get_subscribers(&will_message.will_topic) {
+            if s.active && id != client_id {
+                if let Some(mut stream) = network.get_connection(&id) {
+                    will_message_sent = will_message.send_message(&mut stream);
+                    receivers.push(id.clone());
                 }
-            } else {
-                Ok(MqttServerActions::NoSendWillMessage())
             }
+        }
+
+        if will_message_sent {
+            MqttServerActions::SendWillMessage(will_message.will_topic, receivers)
         } else {
-            Err(Error::new(
-                std::io::ErrorKind::Other,
-                "Server - Cliente no encontrado",
-            ))
-        };
+            MqttServerActions::ErrorWhileSendingWillMessage()
+        }
+    }
 
-        self.save();
-        action
+    /// ### expire_sessions
+    ///
+    /// Publica los mensajes de voluntad demorados que ya deben publicarse, descarta los
+    /// mensajes en cola vencidos y elimina las sesiones que superaron su Session Expiry Interval
+    ///
+    /// ### Parametros
+    /// - `network`: conexiones del servidor (para publicar los mensajes de voluntad)
+    ///
+    pub fn expire_sessions(
+        &self,
+        network: &ServerNetwork,
+        server_id: &String,
+        log_in_term: &bool,
+        logger: &Logger,
+    ) {
+        let now = server_session::now();
+        let mut due_wills = Vec::new();
+        let mut expired = Vec::new();
+        let mut expired_messages = Vec::new();
+
+        for mut shard in self.shards() {
+            shard.retain(|id, session| {
+                if let Some(will_message) = session.take_due_will_message(now) {
+                    due_wills.push((id.clone(), will_message));
+                }
+                for message in session.discard_expired_messages(now) {
+                    expired_messages.push((id.clone(), message.properties.topic_name));
+                }
+                if !session.is_expired(now) {
+                    return true;
+                }
+                // El mensaje de voluntad demorado se publica a mas tardar al expirar la sesión
+                if session.will_publish_at.take().is_some() {
+                    if let Some(will_message) = session.will_message.take() {
+                        due_wills.push((id.clone(), will_message));
+                    }
+                }
+                expired.push(id.clone());
+                false
+            });
+        }
+
+        if !due_wills.is_empty() || !expired.is_empty() || !expired_messages.is_empty() {
+            self.save();
+        }
+
+        for (id, topic) in expired_messages {
+            MqttServerActions::DiscardExpiredMessage(id, topic).log_action(
+                server_id,
+                logger,
+                log_in_term,
+            );
+        }
+
+        for id in expired {
+            MqttServerActions::SessionExpired(id).log_action(server_id, logger, log_in_term);
+        }
+        for (id, will_message) in due_wills {
+            self.publish_will(network, &id, will_message).log_action(
+                server_id,
+                logger,
+                log_in_term,
+            );
+        }
     }
 
+    /// ### disconnect_all_sessions
+    ///
+    /// Desconecta a todos los clientes conectados al apagarse el servidor: se les envia
+    /// un DISCONNECT con el reason code `ServerShuttingDown` y se cierra su conexion
+    ///
+    /// ### Consideraciones
+    /// - Las sesiones se marcan desconectadas antes de cerrar las conexiones, para que
+    ///   los threads de las conexiones no publiquen sus mensajes de voluntad
+    /// - Las sesiones con Session Expiry Interval 0 terminan con su conexion
+    /// - El registro se persiste antes de cerrar las conexiones
+    ///
     pub fn disconnect_all_sessions(
-        &mut self,
-        network: &mut ServerNetwork,
-        config: &mut ServerConfig,
+        &self,
+        network: &ServerNetwork,
+        config: &ServerConfig,
         logger: &Logger,
     ) {
-        for (id, session) in &mut self.sessions {
-            println!("Intenta desconectar cliente {}", id);
+        let mut disconnected = Vec::new();
+        for mut shard in self.shards() {
+            shard.retain(|id, session| {
+                if !session.active {
+                    return true;
+                }
+                session.disconnect();
+                disconnected.push(id.clone());
+                session.session_expiry_interval != 0
+            });
+        }
+        self.save();
 
-            let stream = match network.connections.get_mut(id) {
+        for id in disconnected {
+            let mut stream = match network.get_connection(&id) {
                 Some(stream) => stream,
                 None => continue,
             };
+            if let Ok(action) =
+                disconnect_handler::send_disconnect(&mut stream, ReasonCode::ServerShuttingDown)
+            {
+                action.log_action(&config.general.id, logger, &config.general.log_in_term);
+            }
+            let _ = stream.shutdown();
+            MqttServerActions::DisconnectSession(id).log_action(
+                &config.general.id,
+                logger,
+                &config.general.log_in_term,
+            );
+        }
+    }
 
-            println!("Desconectando cliente {}", id);
+    /// ### assign_outgoing_message
+    ///
+    /// Prepara una publicacion a enviar a un cliente: si tiene QoS mayor a 0 le asigna
+    /// un packet identifier de la sesión y la registra como mensaje en vuelo
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente destinatario
+    /// - `message`: publicacion a enviar (con el QoS ya otorgado)
+    ///
+    /// ### Retorno
+    /// - `Publish`: publicacion lista para enviar
+    ///
+    pub fn assign_outgoing_message(&self, client_id: &str, mut message: Publish) -> Publish {
+        let qos = flags_handler::get_publish_qos_level(message.fixed_header_flags);
+        if qos == 0 {
+            return message;
+        }
 
-            match disconnect_handler::send_disconnect(stream, ReasonCode::NormalDisconnection) {
-                Ok(a) => a.log_action(&config.general.id, logger, &config.general.log_in_term),
-                Err(e) => eprintln!("Error al enviar el paquete de desconexión: {}", e),
-            };
-            session.disconnect();
+        let assigned = self.with_session(client_id, |session| {
+            message.properties.packet_identifier = session.next_packet_id();
+            session.add_inflight_message(message.clone());
+        });
+        if assigned.is_some() {
+            self.save();
         }
-        self.save();
+        message
+    }
+
+    /// ### can_send_message
+    ///
+    /// Indica si una publicacion puede enviarse al cliente en este momento
+    ///
+    /// ### Parametros
+    /// - `client_id`: id del cliente destinatario
+    /// - `qos`: QoS otorgado a la publicacion
+    ///
+    /// ### Consideraciones
+    /// - Si hay mensajes en cola, la publicacion se encola detras de ellos para
+    ///   mantener el orden
+    /// - Las publicaciones QoS 1 y 2 se encolan si la ventana de mensajes en vuelo esta llena
+    ///
+    pub fn can_send_message(&self, client_id: &str, qos: u8) -> bool {
+        self.with_session(client_id, |session| {
+            session.messages_in_queue.is_empty() && (qos == 0 || !session.is_inflight_window_full())
+        })
+        .unwrap_or(false)
     }
 
-    pub fn get_pending_messages(&mut self, client_id: &str) -> Option<&mut VecDeque<Publish>> {
-        if let Some(session) = self.sessions.get_mut(client_id) {
-            return Some(&mut session.messages_in_queue);
+    /// ### next_queued_message
+    ///
+    /// Obtiene el siguiente mensaje en cola del cliente, si la ventana de mensajes
+    /// en vuelo lo permite, con su Message Expiry Interval restante
+    ///
+    pub fn next_queued_message(&self, client_id: &str) -> Option<Publish> {
+        let message = self
+            .with_session(client_id, |session| {
+                session.next_queued_message(server_session::now())
+            })
+            .flatten();
+        if message.is_some() {
+            self.save();
         }
-        None
+        message
+    }
+
+    /// ### get_inflight_messages
+    ///
+    /// Obtiene las publicaciones QoS 1 y 2 enviadas al cliente que no completaron su confirmacion
+    ///
+    pub fn get_inflight_messages(&self, client_id: &str) -> Vec<InflightMessage> {
+        self.with_session(client_id, |session| session.inflight_messages.clone())
+            .unwrap_or_default()
+    }
+
+    /// ### release_inflight_message
+    ///
+    /// Marca como liberada la publicacion en vuelo del cliente al recibir su PUBREC
+    ///
+    /// ### Retorno
+    /// - `bool`: true si la publicacion estaba en vuelo
+    ///
+    pub fn release_inflight_message(&self, client_id: &str, packet_id: u16) -> bool {
+        let released = self
+            .with_session(client_id, |session| {
+                session.release_inflight_message(packet_id)
+            })
+            .unwrap_or(false);
+        self.save();
+        released
+    }
+
+    /// ### complete_inflight_message
+    ///
+    /// Elimina la publicacion en vuelo del cliente al recibir su PUBACK o PUBCOMP
+    ///
+    /// ### Retorno
+    /// - `bool`: true si la publicacion estaba en vuelo
+    ///
+    pub fn complete_inflight_message(&self, client_id: &str, packet_id: u16) -> bool {
+        let completed = self
+            .with_session(client_id, |session| {
+                session.complete_inflight_message(packet_id)
+            })
+            .unwrap_or(false);
+        self.save();
+        completed
+    }
+
+    /// ### unreleased_publishes
+    ///
+    /// Cantidad de publicaciones QoS 2 recibidas del cliente que esperan su PUBREL,
+    /// sin contar la del `packet_id` dado (un reenvio no es una publicacion nueva)
+    ///
+    pub fn unreleased_publishes(&self, client_id: &str, packet_id: u16) -> usize {
+        self.with_session(client_id, |session| {
+            if session.awaiting_release.contains(&packet_id) {
+                return 0;
+            }
+            session.awaiting_release.len()
+        })
+        .unwrap_or(0)
+    }
+
+    /// ### receive_exactly_once
+    ///
+    /// Registra una publicacion QoS 2 recibida del cliente
+    ///
+    /// ### Retorno
+    /// - `bool`: true si la publicacion es nueva y debe reenviarse a los suscriptores,
+    ///   false si es un duplicado
+    ///
+    pub fn receive_exactly_once(&self, client_id: &str, packet_id: u16) -> bool {
+        let is_new = self
+            .with_session(client_id, |session| session.receive_exactly_once(packet_id))
+            .unwrap_or(true);
+        self.save();
+        is_new
+    }
+
+    /// ### release_exactly_once
+    ///
+    /// Libera una publicacion QoS 2 recibida del cliente al recibir su PUBREL
+    ///
+    /// ### Retorno
+    /// - `bool`: true si el packet identifier estaba registrado
+    ///
+    pub fn release_exactly_once(&self, client_id: &str, packet_id: u16) -> bool {
+        let released = self
+            .with_session(client_id, |session| session.release_exactly_once(packet_id))
+            .unwrap_or(false);
+        self.save();
+        released
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::mqtt_packets::properties::publish_properties::PublishProperties;
+    use crate::mqtt_packets::properties::{
+        connect_payload::ConnectPayload, publish_properties::PublishProperties,
+    };
+
+    use crate::server::{
+        inflight_message::InflightState, queued_message::QueuedMessage,
+        server_session::DEFAULT_INFLIGHT_WINDOW,
+    };
+
+    use std::{collections::VecDeque, fs};
 
     use super::*;
 
@@ -313,6 +1124,12 @@ mod tests {
             subscriptions: Vec::new(),
             messages_in_queue: VecDeque::new(),
             will_message: None,
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let path = "tests/files/register_test_1.db".to_string();
@@ -320,14 +1137,11 @@ mod tests {
         let mut sessions = HashMap::new();
         sessions.insert("test".to_string(), session.clone());
 
-        let register = SessionRegister {
-            sessions,
-            db_path: Some(path.clone()),
-        };
+        let register = SessionRegister::with_sessions(sessions, Some(path.clone()));
 
         let bytes = register.sessions_as_bytes();
 
-        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes);
+        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes).unwrap();
 
         let session_2 = sessions_2.get("test").unwrap();
 
@@ -351,23 +1165,27 @@ mod tests {
             subscriptions: vec![TopicFilter {
                 topic_filter: "test".to_string(),
                 subscription_options: 1,
+                subscription_identifier: None,
             }],
             messages_in_queue: VecDeque::new(),
             will_message: None,
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let path = "tests/files/register_test_2.db".to_string();
         let mut sessions = HashMap::new();
         sessions.insert("test".to_string(), session.clone());
 
-        let register = SessionRegister {
-            sessions,
-            db_path: Some(path.clone()),
-        };
+        let register = SessionRegister::with_sessions(sessions, Some(path.clone()));
 
         let bytes = register.sessions_as_bytes();
 
-        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes);
+        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes).unwrap();
 
         let session_2 = sessions_2.get("test").unwrap();
 
@@ -393,21 +1211,25 @@ mod tests {
             will_message: Some(WillMessage {
                 will_topic: "test".to_string(),
                 will_payload: vec![1, 2, 3],
+                will_delay_interval: 0,
             }),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let path = "tests/files/register_test_3.db".to_string();
         let mut sessions = HashMap::new();
         sessions.insert("test".to_string(), session.clone());
 
-        let register = SessionRegister {
-            sessions,
-            db_path: Some(path.clone()),
-        };
+        let register = SessionRegister::with_sessions(sessions, Some(path.clone()));
 
         let bytes = register.sessions_as_bytes();
 
-        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes);
+        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes).unwrap();
 
         let session_2 = sessions_2.get("test").unwrap();
 
@@ -440,9 +1262,16 @@ mod tests {
             subscriptions: vec![TopicFilter {
                 topic_filter: "test".to_string(),
                 subscription_options: 1,
+                subscription_identifier: None,
             }],
             messages_in_queue: VecDeque::new(),
             will_message: None,
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let session_2 = Session {
@@ -453,7 +1282,14 @@ mod tests {
             will_message: Some(WillMessage {
                 will_topic: "test".to_string(),
                 will_payload: vec![1, 2, 3],
+                will_delay_interval: 0,
             }),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let properties = PublishProperties {
@@ -472,12 +1308,20 @@ mod tests {
             subscriptions: vec![TopicFilter {
                 topic_filter: "test".to_string(),
                 subscription_options: 1,
+                subscription_identifier: None,
             }],
-            messages_in_queue: VecDeque::from(vec![msg.clone()]),
+            messages_in_queue: VecDeque::from(vec![QueuedMessage::new(msg.clone(), 0)]),
             will_message: Some(WillMessage {
                 will_topic: "test".to_string(),
                 will_payload: vec![1, 2, 3],
+                will_delay_interval: 0,
             }),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let path = "tests/files/register_test_4.db".to_string();
@@ -486,14 +1330,11 @@ mod tests {
         sessions.insert("id_test2".to_string(), session_2.clone());
         sessions.insert("id_test3".to_string(), session_3.clone());
 
-        let register = SessionRegister {
-            sessions,
-            db_path: Some(path.clone()),
-        };
+        let register = SessionRegister::with_sessions(sessions, Some(path.clone()));
 
         let bytes = register.sessions_as_bytes();
 
-        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes);
+        let sessions_2 = SessionRegister::sessions_from_be_bytes(bytes).unwrap();
 
         let session_deserializated = sessions_2.get("id_test").unwrap();
 
@@ -562,4 +1403,621 @@ mod tests {
             panic!("Will message not found in session");
         }
     }
+
+    #[test]
+    fn test_get_subscribers_with_wildcards() {
+        let session_with = |filter: &str| Session {
+            active: true,
+            session_expiry_interval: 0,
+            subscriptions: vec![TopicFilter {
+                topic_filter: filter.to_string(),
+                subscription_options: 1,
+                subscription_identifier: None,
+            }],
+            messages_in_queue: VecDeque::new(),
+            will_message: None,
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
+        };
+
+        let mut sessions = HashMap::new();
+        sessions.insert("exact".to_string(), session_with("drone/1/state"));
+        sessions.insert("single".to_string(), session_with("drone/+/state"));
+        sessions.insert("multi".to_string(), session_with("inc/#"));
+        sessions.insert("all".to_string(), session_with("#"));
+
+        let register = SessionRegister::with_sessions(sessions, None);
+
+        let subscribers = |topic: &str| {
+            let mut ids = register
+                .get_subscribers(topic)
+                .into_iter()
+                .map(|(id, _)| id)
+                .collect::<Vec<String>>();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(subscribers("drone/1/state"), vec!["all", "exact", "single"]);
+        assert_eq!(subscribers("drone/2/state"), vec!["all", "single"]);
+        assert_eq!(subscribers("inc/7/resolved"), vec!["all", "multi"]);
+        assert_eq!(subscribers("$SYS/broker/clients"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_shared_subscriptions() {
+        let session_with = |filter: &str, active: bool| Session {
+            active,
+            session_expiry_interval: 0,
+            subscriptions: vec![TopicFilter {
+                topic_filter: filter.to_string(),
+                subscription_options: 1,
+                subscription_identifier: None,
+            }],
+            messages_in_queue: VecDeque::new(),
+            will_message: None,
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
+        };
+
+        let mut sessions = HashMap::new();
+        sessions.insert("cams1".to_string(), session_with("$share/cams/inc", true));
+        sessions.insert("cams2".to_string(), session_with("$share/cams/inc", true));
+        sessions.insert("cams3".to_string(), session_with("$share/cams/inc", false));
+        sessions.insert(
+            "monitor".to_string(),
+            session_with("$share/monitor/inc", true),
+        );
+        sessions.insert("drone".to_string(), session_with("inc", true));
+
+        let register = SessionRegister::with_sessions(sessions, None);
+
+        let subscribers = register
+            .get_subscribers("inc")
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<String>>();
+        assert_eq!(subscribers, vec!["drone"]);
+
+        let mut shared_subscriptions = register.get_shared_subscriptions("inc");
+        shared_subscriptions.sort();
+        assert_eq!(
+            shared_subscriptions,
+            vec!["$share/cams/inc", "$share/monitor/inc"]
+        );
+
+        // Los miembros conectados se eligen de forma rotativa
+        let chosen = |register: &SessionRegister| {
+            register
+                .choose_shared_subscriber("$share/cams/inc")
+                .map(|(id, _)| id)
+                .unwrap()
+        };
+        assert_eq!(chosen(&register), "cams1");
+        assert_eq!(chosen(&register), "cams2");
+        assert_eq!(chosen(&register), "cams1");
+
+        // Se elige el miembro con menos publicaciones pendientes
+        let properties = PublishProperties {
+            topic_name: "inc".to_string(),
+            ..Default::default()
+        };
+        register
+            .store_message("cams2", Publish::new(0, 1, 0, properties))
+            .unwrap();
+        assert_eq!(chosen(&register), "cams1");
+        assert_eq!(chosen(&register), "cams1");
+
+        assert!(register
+            .choose_shared_subscriber("$share/drones/inc")
+            .is_none());
+    }
+
+    #[test]
+    fn test_exactly_once_state_survives_restart() {
+        let mut db_path = std::env::temp_dir();
+        db_path.push("register_test_exactly_once.db");
+        let path = db_path.to_str().unwrap().to_string();
+        let _ = fs::remove_file(&path);
+
+        let properties = PublishProperties {
+            topic_name: "inc".to_string(),
+            packet_identifier: 42,
+            payload_format_indicator: Some(1),
+            application_message: "incident".as_bytes().to_vec(),
+            ..Default::default()
+        };
+
+        let client_id = "incidents".to_string();
+        let connect = Connect::new(
+            Default::default(),
+            ConnectPayload {
+                client_id: client_id.clone(),
+                ..Default::default()
+            },
+        );
+
+        let register = SessionRegister::new(Some(path.clone()));
+        register.open_session(connect);
+
+        let outgoing =
+            register.assign_outgoing_message(&client_id, Publish::new(0, 2, 0, properties.clone()));
+        assert_ne!(outgoing.properties.packet_identifier, 42);
+        let at_least_once =
+            register.assign_outgoing_message(&client_id, Publish::new(0, 1, 0, properties));
+        assert_ne!(
+            at_least_once.properties.packet_identifier,
+            outgoing.properties.packet_identifier
+        );
+        assert!(register.receive_exactly_once(&client_id, 42));
+
+        // El estado se escribe recien al persistir las sesiones
+        assert!(fs::metadata(&path).is_err());
+        register.flush().unwrap();
+
+        let recovered = SessionRegister::new(Some(path.clone()));
+        let inflight = recovered.get_inflight_messages(&client_id);
+        assert_eq!(inflight.len(), 2);
+        assert_eq!(
+            inflight[0].packet_id(),
+            outgoing.properties.packet_identifier
+        );
+        assert_eq!(inflight[0].state, InflightState::AwaitingPubrec);
+        assert_eq!(
+            inflight[1].packet_id(),
+            at_least_once.properties.packet_identifier
+        );
+        assert_eq!(inflight[1].state, InflightState::AwaitingPuback);
+        assert!(!recovered.receive_exactly_once(&client_id, 42));
+
+        assert!(recovered.complete_inflight_message(&client_id, inflight[1].packet_id()));
+        assert!(recovered.release_inflight_message(&client_id, inflight[0].packet_id()));
+        assert!(recovered.complete_inflight_message(&client_id, inflight[0].packet_id()));
+        assert!(recovered.get_inflight_messages(&client_id).is_empty());
+        assert!(recovered.release_exactly_once(&client_id, 42));
+        assert!(!recovered.release_exactly_once(&client_id, 42));
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_corrupt_database_is_recovered() {
+        let mut db_path = std::env::temp_dir();
+        db_path.push("register_test_corrupt.db");
+        let path = db_path.to_str().unwrap().to_string();
+        let corrupt_path = path.clone() + ".corrupt";
+
+        let client_id = "drone1".to_string();
+        let connect = Connect::new(
+            Default::default(),
+            ConnectPayload {
+                client_id: client_id.clone(),
+                ..Default::default()
+            },
+        );
+        let register = SessionRegister::new(Some(path.clone()));
+        register.open_session(connect);
+        let topic = TopicFilter {
+            topic_filter: "inc".to_string(),
+            subscription_options: 1,
+            subscription_identifier: None,
+        };
+        register.add_subscription(&client_id, vec![topic]).unwrap();
+        let properties = PublishProperties {
+            topic_name: "inc".to_string(),
+            application_message: "incident".as_bytes().to_vec(),
+            ..Default::default()
+        };
+        register.assign_outgoing_message(&client_id, Publish::new(0, 2, 0, properties));
+        assert!(register.load_error().is_none());
+
+        // Un contenido truncado en cualquier posicion se rechaza sin fallar
+        let payload = register.sessions_as_bytes();
+        assert!(SessionRegister::sessions_from_be_bytes(payload.clone()).is_some());
+        for len in 0..payload.len() {
+            assert!(
+                SessionRegister::sessions_from_be_bytes(payload[..len].to_vec()).is_none(),
+                "{}",
+                len
+            );
+        }
+
+        // Un archivo modificado se aparta y el servidor comienza sin sesiones
+        register.flush().unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        assert!(bytes.starts_with(b"MQDB"));
+        *bytes.last_mut().unwrap() ^= 0xFF;
+        fs::write(&path, &bytes).unwrap();
+
+        let recovered = SessionRegister::new(Some(path.clone()));
+        assert!(!recovered.has_session(&client_id));
+        assert!(recovered.load_error().unwrap().contains("checksum"));
+        assert!(fs::metadata(&corrupt_path).is_ok());
+        assert!(fs::metadata(&path).is_err());
+
+        // Un archivo sin encabezado (version 0) se recupera y se reescribe con encabezado
+        fs::write(&path, &payload).unwrap();
+        let recovered = SessionRegister::new(Some(path.clone()));
+        assert!(recovered.load_error().is_none());
+        assert_eq!(recovered.get_subscribers("inc").len(), 1);
+        recovered.flush().unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(b"MQDB"));
+
+        // Un archivo sin encabezado con las subscripciones sin Subscription Identifier
+        // (formato anterior) tambien se recupera
+        let filter_start = payload.windows(3).position(|w| w == b"inc").unwrap();
+        let mut old_layout = payload.clone();
+        old_layout.drain(filter_start + 4..filter_start + 8);
+        fs::write(&path, &old_layout).unwrap();
+        let recovered = SessionRegister::new(Some(path.clone()));
+        assert!(recovered.load_error().is_none());
+        assert_eq!(recovered.get_subscribers("inc").len(), 1);
+
+        // Un archivo sin encabezado que no tiene ninguno de los dos formatos se aparta
+        old_layout.drain(filter_start + 4..filter_start + 5);
+        fs::write(&path, &old_layout).unwrap();
+        let recovered = SessionRegister::new(Some(path.clone()));
+        assert!(!recovered.has_session(&client_id));
+        assert!(recovered.load_error().is_some());
+        assert!(fs::metadata(&path).is_err());
+
+        let _ = fs::remove_file(corrupt_path);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_v0_sessions_file_is_recovered() {
+        let mut db_path = std::env::temp_dir();
+        db_path.push("register_test_v0.db");
+        let path = db_path.to_str().unwrap().to_string();
+
+        let queued = Publish::new(
+            0,
+            1,
+            0,
+            PublishProperties {
+                topic_name: "inc".to_string(),
+                user_properties: vec![("trace".to_string(), "1".to_string())],
+                application_message: "incident".as_bytes().to_vec(),
+                ..Default::default()
+            },
+        );
+
+        // Archivo sin encabezado escrito antes de las User Properties multiples: filtros sin
+        // Subscription Identifier y cola con las publicaciones, con una unica User Property
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&6u16.to_be_bytes());
+        bytes.extend_from_slice(b"drone1");
+        bytes.push(1);
+        bytes.extend_from_slice(&3600u32.to_be_bytes());
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.push(4);
+        bytes.extend_from_slice(b"inc");
+        bytes.push(1);
+        bytes.extend_from_slice(&1u16.to_be_bytes());
+        bytes.extend_from_slice(&queued.as_bytes().unwrap());
+        bytes.push(0);
+        bytes.extend_from_slice(&0u16.to_be_bytes());
+        bytes.extend_from_slice(&0u16.to_be_bytes());
+        bytes.extend_from_slice(&7u16.to_be_bytes());
+        bytes.extend_from_slice(&0u64.to_be_bytes());
+        bytes.extend_from_slice(&0u64.to_be_bytes());
+        fs::write(&path, &bytes).unwrap();
+
+        let register = SessionRegister::new(Some(path.clone()));
+        assert!(register.load_error().is_none());
+        let subscribers = register.get_subscribers("inc");
+        assert_eq!(subscribers.len(), 1);
+        let (id, session) = &subscribers[0];
+        assert_eq!(id, "drone1");
+        assert!(!session.active);
+        assert_eq!(session.session_expiry_interval, 3600);
+        assert_eq!(session.subscriptions[0].subscription_identifier, None);
+        assert_eq!(session.last_packet_id, 7);
+        assert_eq!(session.messages_in_queue.len(), 1);
+        assert_eq!(
+            session.messages_in_queue[0]
+                .message
+                .properties
+                .user_properties,
+            vec![("trace".to_string(), "1".to_string())]
+        );
+
+        // Se reescribe con el formato actual
+        register.flush().unwrap();
+        assert!(fs::read(&path).unwrap().starts_with(b"MQDB"));
+        let recovered = SessionRegister::new(Some(path.clone()));
+        assert_eq!(recovered.get_subscribers("inc").len(), 1);
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_failed_flush_is_retried() {
+        let mut db_path = std::env::temp_dir();
+        db_path.push("register_test_missing_dir");
+        let dir = db_path.clone();
+        db_path.push("sessions.db");
+        let path = db_path.to_str().unwrap().to_string();
+        let _ = fs::remove_dir_all(&dir);
+
+        let connect = Connect::new(
+            Default::default(),
+            ConnectPayload {
+                client_id: "drone1".to_string(),
+                ..Default::default()
+            },
+        );
+        let register = SessionRegister::new(Some(path.clone()));
+        register.open_session(connect);
+
+        // El error se informa y las sesiones siguen pendientes de persistir
+        assert!(register.flush().unwrap_err().to_string().contains(&path));
+        assert!(register.flush().is_err());
+
+        fs::create_dir(&dir).unwrap();
+        register.flush().unwrap();
+        assert!(SessionRegister::new(Some(path)).has_session("drone1"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_inflight_window_queues_messages() {
+        let client_id = "subscriber".to_string();
+        let connect = Connect::new(
+            Default::default(),
+            ConnectPayload {
+                client_id: client_id.clone(),
+                ..Default::default()
+            },
+        );
+
+        let register = SessionRegister::new(None);
+        register.open_session(connect);
+
+        let properties = PublishProperties {
+            topic_name: "drone".to_string(),
+            ..Default::default()
+        };
+
+        for _ in 0..DEFAULT_INFLIGHT_WINDOW {
+            assert!(register.can_send_message(&client_id, 1));
+            register.assign_outgoing_message(&client_id, Publish::new(0, 1, 0, properties.clone()));
+        }
+
+        // Ventana llena: QoS 1 se encola, QoS 0 puede enviarse
+        assert!(!register.can_send_message(&client_id, 1));
+        assert!(register.can_send_message(&client_id, 0));
+
+        register
+            .store_message(&client_id, Publish::new(0, 1, 0, properties.clone()))
+            .unwrap();
+        assert!(!register.can_send_message(&client_id, 0));
+        assert!(register.next_queued_message(&client_id).is_none());
+
+        // Al recibir un PUBACK se libera un lugar de la ventana
+        let first = register.get_inflight_messages(&client_id)[0].packet_id();
+        assert!(register.complete_inflight_message(&client_id, first));
+        assert!(register.next_queued_message(&client_id).is_some());
+        assert!(register.next_queued_message(&client_id).is_none());
+    }
+
+    #[test]
+    fn test_queue_limit_and_expiry() {
+        let connect = |client_id: &str| {
+            Connect::new(
+                Default::default(),
+                ConnectPayload {
+                    client_id: client_id.to_string(),
+                    ..Default::default()
+                },
+            )
+        };
+        let publish = |data: &str, message_expiry_interval: Option<u32>| {
+            let properties = PublishProperties {
+                topic_name: "drone".to_string(),
+                application_message: data.as_bytes().to_vec(),
+                message_expiry_interval,
+                ..Default::default()
+            };
+            Publish::new(0, 1, 0, properties)
+        };
+
+        // Con la cola llena se descarta el mensaje mas antiguo
+        let register =
+            SessionRegister::new(None).with_queue_limit(Some(2), QueueOverflowPolicy::DropOldest);
+        register.open_session(connect("oldest"));
+        for data in ["1", "2"] {
+            assert!(register
+                .store_message("oldest", publish(data, None))
+                .unwrap()
+                .is_none());
+        }
+        let discarded = register
+            .store_message("oldest", publish("3", None))
+            .unwrap();
+        assert_eq!(discarded.unwrap().properties.application_message, b"1");
+
+        // O el mensaje nuevo
+        let register =
+            SessionRegister::new(None).with_queue_limit(Some(1), QueueOverflowPolicy::Reject);
+        register.open_session(connect("reject"));
+        register
+            .store_message("reject", publish("1", None))
+            .unwrap();
+        let discarded = register
+            .store_message("reject", publish("2", None))
+            .unwrap();
+        assert_eq!(discarded.unwrap().properties.application_message, b"2");
+        let queued = register.next_queued_message("reject").unwrap();
+        assert_eq!(queued.properties.application_message, b"1");
+
+        // Los mensajes vencidos no se entregan
+        let register = SessionRegister::new(None);
+        register.open_session(connect("expiry"));
+        register.with_session("expiry", |session| {
+            session.messages_in_queue.extend([
+                QueuedMessage::new(publish("vencido", Some(5)), 0),
+                QueuedMessage::new(publish("vigente", Some(3600)), server_session::now()),
+            ])
+        });
+        let queued = register.next_queued_message("expiry").unwrap();
+        assert_eq!(queued.properties.application_message, b"vigente");
+        assert!(queued.properties.message_expiry_interval.unwrap() <= 3600);
+        assert!(register.next_queued_message("expiry").is_none());
+    }
+
+    #[test]
+    fn test_concurrent_sessions() {
+        let mut db_path = std::env::temp_dir();
+        db_path.push("session_test_concurrent.db");
+        let path = db_path.to_str().unwrap().to_string();
+
+        let register = SessionRegister::new(Some(path.clone()));
+
+        // Cada thread opera sobre la sesion de su propio cliente
+        let handles = (0..8)
+            .map(|i| {
+                let register = register.clone();
+                std::thread::spawn(move || {
+                    let client_id = format!("client{}", i);
+                    let connect = Connect::new(
+                        Default::default(),
+                        ConnectPayload {
+                            client_id: client_id.clone(),
+                            ..Default::default()
+                        },
+                    );
+                    register.open_session(connect);
+                    let topic = TopicFilter {
+                        topic_filter: format!("drone/{}", i),
+                        subscription_options: 1,
+                        subscription_identifier: None,
+                    };
+                    register.add_subscription(&client_id, vec![topic]).unwrap();
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        for i in 0..8 {
+            let subscribers = register.get_subscribers(&format!("drone/{}", i));
+            assert_eq!(subscribers.len(), 1);
+            assert_eq!(subscribers[0].0, format!("client{}", i));
+        }
+
+        // El archivo persistido contiene todas las sesiones
+        register.flush().unwrap();
+        let recovered = SessionRegister::new(Some(path.clone()));
+        assert_eq!(recovered.get_subscribers("drone/7").len(), 1);
+        assert!((0..8).all(|i| !recovered.is_session_active(&format!("client{}", i))));
+        assert_eq!(
+            SessionRegister::new(Some(path.clone()))
+                .get_sessions()
+                .len(),
+            8
+        );
+
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_disconnect_session_will_handling() {
+        let mut log_path = std::env::temp_dir();
+        log_path.push("session_test_will_handling.log");
+        let log_path = log_path.to_str().unwrap().to_string();
+        let logger_handler = logger::logger_handler::create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        let network = ServerNetwork::default();
+        let server_id = "server".to_string();
+
+        let connect = |session_expiry_interval: u32, will_delay_interval: u32| {
+            Connect::new(
+                crate::mqtt_packets::properties::connect_properties::ConnectProperties {
+                    connect_flags: flags_handler::add_connect_flag_will_flag(0, "true".to_string())
+                        .unwrap(),
+                    session_expiry_interval: Some(session_expiry_interval),
+                    ..Default::default()
+                },
+                ConnectPayload {
+                    client_id: "drone".to_string(),
+                    will_topic: Some("desconexion".to_string()),
+                    will_payload: Some(b"drone desconectado".to_vec()),
+                    will_delay_interval: Some(will_delay_interval),
+                    ..Default::default()
+                },
+            )
+        };
+        let disconnect = |reason_code: ReasonCode| DisconnectProperties {
+            disconnect_reason_code: reason_code.get_id(),
+            ..Default::default()
+        };
+
+        let register = SessionRegister::new(None);
+
+        // DISCONNECT 0x00: se descarta el mensaje de voluntad
+        register.open_session(connect(10, 0));
+        let action = register
+            .disconnect_session(
+                &network,
+                "drone".to_string(),
+                Some(&disconnect(ReasonCode::NormalDisconnection)),
+                &server_id,
+                &false,
+                &logger,
+            )
+            .unwrap();
+        assert!(matches!(action, MqttServerActions::DiscardWillMessage(_)));
+
+        // DISCONNECT 0x04: se demora el mensaje de voluntad, y se cancela al reconectarse
+        assert_eq!(register.open_session(connect(10, 5)), 1);
+        let action = register
+            .disconnect_session(
+                &network,
+                "drone".to_string(),
+                Some(&disconnect(ReasonCode::DisconnectWithWillMessage)),
+                &server_id,
+                &false,
+                &logger,
+            )
+            .unwrap();
+        assert!(matches!(action, MqttServerActions::DelayWillMessage(_, 5)));
+        assert!(register
+            .with_session("drone", |session| session.will_publish_at.is_some())
+            .unwrap());
+        register.open_session(connect(10, 5));
+        assert!(register
+            .with_session("drone", |session| session.will_publish_at.is_none())
+            .unwrap());
+
+        // Session Expiry Interval 0: la sesión termina al cerrarse la conexión
+        register.open_session(connect(0, 5));
+        register
+            .disconnect_session(
+                &network,
+                "drone".to_string(),
+                None,
+                &server_id,
+                &false,
+                &logger,
+            )
+            .unwrap();
+        assert!(register.with_session("drone", |_| ()).is_none());
+
+        logger.close();
+        logger_handler.close();
+        let _ = fs::remove_file(log_path);
+    }
 }
@@ -1,11 +1,35 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    time::{SystemTime, UNIX_EPOCH},
+};
 
 use crate::{
     common::{flags::flags_handler, topic_filter::TopicFilter},
     mqtt_packets::packets::{connect::Connect, publish::Publish},
 };
 
-use super::will_message::WillMessage;
+use super::{
+    inflight_message::{InflightMessage, InflightState},
+    queued_message::{QueueOverflowPolicy, QueuedMessage},
+    will_message::WillMessage,
+};
+
+/// Cantidad maxima de publicaciones en vuelo por sesión, si el cliente no informa su Receive Maximum
+pub const DEFAULT_INFLIGHT_WINDOW: u16 = 32;
+
+/// Session Expiry Interval con el que la sesión no expira nunca
+pub const SESSION_NEVER_EXPIRES: u32 = u32::MAX;
+
+/// ### now
+///
+/// Segundos transcurridos desde UNIX EPOCH
+///
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
 
 /// ## Session
 ///
@@ -16,15 +40,30 @@ use super::will_message::WillMessage;
 /// - `stream_connection`: conexión del cliente
 /// - `session_expiry_interval`: intervalo de expiración de la sesión
 /// - `subscriptions`: subscripciones del cliente
+/// - `messages_in_queue`: publicaciones a la espera de poder enviarse al cliente, junto a su vencimiento
 /// - `will_message`: mensaje de voluntad
+/// - `inflight_messages`: publicaciones QoS 1 y 2 enviadas al cliente sin confirmar
+/// - `awaiting_release`: packet identifiers de publicaciones QoS 2 recibidas del cliente
+///   a la espera del PUBREL
+/// - `last_packet_id`: ultimo packet identifier asignado a una publicacion saliente
+/// - `inflight_window`: cantidad maxima de publicaciones en vuelo; las siguientes
+///   quedan en la cola de la sesión hasta que se confirmen las anteriores
+/// - `disconnected_at`: momento (segundos desde UNIX EPOCH) en que se cerró la conexión
+/// - `will_publish_at`: momento en que debe publicarse el mensaje de voluntad demorado
 ///
 #[derive(Clone, Debug)]
 pub struct Session {
     pub active: bool,
     pub session_expiry_interval: u32,
     pub subscriptions: Vec<TopicFilter>,
-    pub messages_in_queue: VecDeque<Publish>,
+    pub messages_in_queue: VecDeque<QueuedMessage>,
     pub will_message: Option<WillMessage>,
+    pub inflight_messages: Vec<InflightMessage>,
+    pub awaiting_release: Vec<u16>,
+    pub last_packet_id: u16,
+    pub inflight_window: u16,
+    pub disconnected_at: Option<u64>,
+    pub will_publish_at: Option<u64>,
 }
 
 impl Session {
@@ -41,17 +80,37 @@ impl Session {
     pub fn new(connection: &Connect) -> Self {
         Session {
             active: true,
-            session_expiry_interval: 0,
+            session_expiry_interval: connection.properties.session_expiry_interval.unwrap_or(0),
             subscriptions: Vec::new(),
             messages_in_queue: VecDeque::new(),
-            will_message: WillMessage::new(
-                flags_handler::get_connect_flag_will_flag(connection.properties.connect_flags),
-                connection.payload.will_topic.as_ref(),
-                connection.payload.will_payload.clone(),
-            ),
+            will_message: Self::will_message_from(connection),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: Self::inflight_window_from(connection),
+            disconnected_at: None,
+            will_publish_at: None,
         }
     }
 
+    /// La ventana de mensajes en vuelo es el Receive Maximum del cliente, si lo informa
+    fn inflight_window_from(connection: &Connect) -> u16 {
+        connection
+            .properties
+            .receive_maximum
+            .filter(|receive_maximum| *receive_maximum > 0)
+            .unwrap_or(DEFAULT_INFLIGHT_WINDOW)
+    }
+
+    fn will_message_from(connection: &Connect) -> Option<WillMessage> {
+        WillMessage::new(
+            flags_handler::get_connect_flag_will_flag(connection.properties.connect_flags),
+            connection.payload.will_topic.as_ref(),
+            connection.payload.will_payload.clone(),
+            connection.payload.will_delay_interval,
+        )
+    }
+
     pub fn size_of(&self) -> usize {
         let mut len = 1 + 4;
 
@@ -71,6 +130,17 @@ impl Session {
             len += 1;
         }
 
+        len += 2;
+        for inflight in self.inflight_messages.iter() {
+            len += inflight.size_of();
+        }
+
+        len += 2 + 2 * self.awaiting_release.len();
+
+        len += 2;
+
+        len += 8 + 8;
+
         len
     }
 
@@ -94,7 +164,7 @@ impl Session {
         bytes.extend_from_slice(msg_len.to_be_bytes().as_ref());
 
         for msg in &self.messages_in_queue {
-            bytes.extend_from_slice(msg.as_bytes().unwrap().as_ref());
+            bytes.extend_from_slice(msg.as_bytes().as_ref());
         }
 
         if let Some(will) = &self.will_message {
@@ -103,78 +173,507 @@ impl Session {
             bytes.extend_from_slice([0].as_ref());
         }
 
+        let inflight_len = self.inflight_messages.len() as u16;
+
+        bytes.extend_from_slice(inflight_len.to_be_bytes().as_ref());
+
+        for inflight in &self.inflight_messages {
+            bytes.extend_from_slice(inflight.as_bytes().as_ref());
+        }
+
+        let release_len = self.awaiting_release.len() as u16;
+
+        bytes.extend_from_slice(release_len.to_be_bytes().as_ref());
+
+        for packet_id in &self.awaiting_release {
+            bytes.extend_from_slice(packet_id.to_be_bytes().as_ref());
+        }
+
+        bytes.extend_from_slice(self.last_packet_id.to_be_bytes().as_ref());
+
+        // 0: la sesión esta conectada / no hay mensaje de voluntad demorado
+        bytes.extend_from_slice(self.disconnected_at.unwrap_or(0).to_be_bytes().as_ref());
+        bytes.extend_from_slice(self.will_publish_at.unwrap_or(0).to_be_bytes().as_ref());
+
         bytes
     }
 
-    pub fn from_be_bytes(bytes: Vec<u8>) -> Self {
+    /// ### from_be_bytes
+    ///
+    /// Convierte un vector de bytes en una sesión
+    ///
+    /// ### Retorno
+    /// - `Option<Session>`: sesión, o `None` si los bytes estan incompletos o son invalidos
+    ///
+    pub fn from_be_bytes(bytes: Vec<u8>) -> Option<Self> {
+        Self::from_layout_be_bytes(bytes, false).map(|(session, _)| session)
+    }
+
+    /// ### from_v0_be_bytes
+    ///
+    /// Convierte en una sesión los bytes persistidos en un archivo sin encabezado (version 0)
+    /// con el formato anterior a las User Properties multiples
+    ///
+    /// ### Retorno
+    /// - `Option<(Session, usize)>`: sesión y cantidad de bytes que ocupaba, o `None` si los
+    ///   bytes estan incompletos o son invalidos
+    ///
+    /// ### Consideraciones
+    /// - Las subscripciones no tenian Subscription Identifier
+    /// - La cola tenia solo las publicaciones: su vencimiento se calcula desde el momento
+    ///   en que se recupera la sesión
+    /// - Las publicaciones tienen el formato del paquete, por lo que su unica User Property
+    ///   se recupera como el primer elemento de `user_properties`
+    ///
+    pub fn from_v0_be_bytes(bytes: Vec<u8>) -> Option<(Self, usize)> {
+        Self::from_layout_be_bytes(bytes, true)
+    }
+
+    fn from_layout_be_bytes(bytes: Vec<u8>, v0_layout: bool) -> Option<(Self, usize)> {
         let mut index = 0;
+        let read_u16 = |index: usize| -> Option<u16> {
+            Some(u16::from_be_bytes(
+                bytes.get(index..index + 2)?.try_into().ok()?,
+            ))
+        };
+        // 0: la sesión esta conectada / no hay mensaje de voluntad demorado
+        let read_timestamp = |index: usize| -> Option<Option<u64>> {
+            match u64::from_be_bytes(bytes.get(index..index + 8)?.try_into().ok()?) {
+                0 => Some(None),
+                timestamp => Some(Some(timestamp)),
+            }
+        };
 
-        let active = bytes[index] == 1;
+        let active = *bytes.get(index)? == 1;
         index += 1;
 
-        let session_expiry_interval = u32::from_be_bytes([
-            bytes[index],
-            bytes[index + 1],
-            bytes[index + 2],
-            bytes[index + 3],
-        ]);
+        let session_expiry_interval =
+            u32::from_be_bytes(bytes.get(index..index + 4)?.try_into().ok()?);
         index += 4;
 
-        let subs_len = u16::from_be_bytes([bytes[index], bytes[index + 1]]);
+        let subs_len = read_u16(index)?;
         index += 2;
 
         let mut subscriptions = Vec::new();
         for _ in 0..subs_len {
-            let sub = TopicFilter::from_be_bytes(bytes[index..].to_vec());
-            index += sub.as_bytes().len();
+            let sub = if v0_layout {
+                let (sub, size) = TopicFilter::from_v0_be_bytes(bytes.get(index..)?.to_vec())?;
+                index += size;
+                sub
+            } else {
+                let sub = TopicFilter::from_be_bytes(bytes.get(index..)?.to_vec())?;
+                index += sub.as_bytes().len();
+                sub
+            };
             subscriptions.push(sub);
         }
 
-        let msg_len = u16::from_be_bytes([bytes[index], bytes[index + 1]]);
+        let msg_len = read_u16(index)?;
         index += 2;
 
         let mut messages_in_queue = VecDeque::new();
         for _ in 0..msg_len {
-            let buffer = &bytes[index..];
-            let msg = Publish::from_be_bytes(buffer.to_vec()).unwrap();
-            index += msg.size_of();
+            let msg = if v0_layout {
+                let message = Publish::from_be_bytes(bytes.get(index..)?.to_vec()).ok()?;
+                index += message.size_of();
+                QueuedMessage::new(message, now())
+            } else {
+                let msg = QueuedMessage::from_be_bytes(bytes.get(index..)?.to_vec())?;
+                index += msg.size_of();
+                msg
+            };
             messages_in_queue.push_back(msg)
         }
 
-        let will_message = WillMessage::from_be_bytes(bytes[index..].to_vec());
+        let will_message = match bytes.get(index)? {
+            0 => None,
+            _ => Some(WillMessage::from_be_bytes(bytes.get(index..)?.to_vec())?),
+        };
+        index += match &will_message {
+            Some(will) => will.size_of(),
+            None => 1,
+        };
 
-        Session {
+        let inflight_len = read_u16(index)?;
+        index += 2;
+
+        let mut inflight_messages = Vec::new();
+        for _ in 0..inflight_len {
+            let inflight = InflightMessage::from_be_bytes(bytes.get(index..)?.to_vec())?;
+            index += inflight.size_of();
+            inflight_messages.push(inflight);
+        }
+
+        let release_len = read_u16(index)?;
+        index += 2;
+
+        let mut awaiting_release = Vec::new();
+        for _ in 0..release_len {
+            awaiting_release.push(read_u16(index)?);
+            index += 2;
+        }
+
+        let last_packet_id = read_u16(index)?;
+        index += 2;
+
+        let disconnected_at = read_timestamp(index)?;
+        let will_publish_at = read_timestamp(index + 8)?;
+        index += 16;
+
+        let session = Session {
             active,
             session_expiry_interval,
             subscriptions,
             messages_in_queue,
             will_message,
-        }
+            inflight_messages,
+            awaiting_release,
+            last_packet_id,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at,
+            will_publish_at,
+        };
+        Some((session, index))
     }
 
     /// ### reconnect
     ///
-    /// Reestablece la sesión del cliente
+    /// Reestablece la sesión del cliente con los datos de su nueva conexión
+    ///
+    /// #### Parametros
+    /// - `connection`: paquete de conexión del cliente
+    ///
+    /// #### Consideraciones
+    /// - El mensaje de voluntad de la conexión anterior se reemplaza por el de la nueva,
+    ///   por lo que un mensaje de voluntad demorado no llega a publicarse
     ///
-    pub fn reconnect(&mut self) {
+    pub fn reconnect(&mut self, connection: &Connect) {
         self.active = true;
+        self.session_expiry_interval = connection.properties.session_expiry_interval.unwrap_or(0);
+        self.will_message = Self::will_message_from(connection);
+        self.inflight_window = Self::inflight_window_from(connection);
+        self.disconnected_at = None;
+        self.will_publish_at = None;
     }
 
     /// ### disconnect
     ///
-    /// Desconecta al cliente
-    ///
-    /// #### Retorno
-    /// - `Result<(), Error>`:
-    ///   - Ok: cliente desconectado
-    ///   - Err: error al desconectar al cliente (std::io::Error)
+    /// Desconecta al cliente, registrando el momento en que se cerró la conexión
     ///
     pub fn disconnect(&mut self) {
         self.active = false;
+        self.disconnected_at = Some(now());
+    }
+
+    /// ### update_session_expiry_interval
+    ///
+    /// Actualiza el Session Expiry Interval con el enviado en el DISCONNECT
+    ///
+    /// #### Consideraciones
+    /// - Si la conexión se estableció con un Session Expiry Interval de 0, no puede
+    ///   modificarse desde el DISCONNECT y se mantiene en 0
+    ///
+    pub fn update_session_expiry_interval(&mut self, session_expiry_interval: u32) {
+        if self.session_expiry_interval != 0 {
+            self.session_expiry_interval = session_expiry_interval;
+        }
+    }
+
+    /// ### will_delay
+    ///
+    /// Segundos que debe demorarse la publicacion del mensaje de voluntad al cerrarse
+    /// la conexión: el menor entre su Will Delay Interval y el Session Expiry Interval
+    ///
+    pub fn will_delay(&self) -> Option<u32> {
+        self.will_message
+            .as_ref()
+            .map(|will| will.will_delay_interval.min(self.session_expiry_interval))
+    }
+
+    /// ### is_expired
+    ///
+    /// Indica si la sesión de un cliente desconectado superó su Session Expiry Interval
+    ///
+    /// #### Parametros
+    /// - `now`: momento actual (segundos desde UNIX EPOCH)
+    ///
+    pub fn is_expired(&self, now: u64) -> bool {
+        if self.active || self.session_expiry_interval == SESSION_NEVER_EXPIRES {
+            return false;
+        }
+        match self.disconnected_at {
+            // Los momentos se truncan a segundos: la sesión expira recien cuando pasó un
+            // segundo completo mas, para que nunca expire antes de su intervalo
+            Some(disconnected_at) => now > disconnected_at + self.session_expiry_interval as u64,
+            None => false,
+        }
+    }
+
+    /// ### take_due_will_message
+    ///
+    /// Obtiene el mensaje de voluntad demorado si ya debe publicarse, quitandolo de la sesión
+    ///
+    /// #### Parametros
+    /// - `now`: momento actual (segundos desde UNIX EPOCH)
+    ///
+    pub fn take_due_will_message(&mut self, now: u64) -> Option<WillMessage> {
+        match self.will_publish_at {
+            Some(publish_at) if publish_at <= now => {
+                self.will_publish_at = None;
+                self.will_message.take()
+            }
+            _ => None,
+        }
+    }
+
+    /// ### store_message
+    ///
+    /// Encola una publicacion para enviarla cuando sea posible
+    ///
+    /// #### Parametros
+    /// - `message`: publicacion a encolar
+    /// - `now`: momento actual (segundos desde UNIX EPOCH)
+    ///
+    pub fn store_message(&mut self, message: Publish, now: u64) {
+        self.messages_in_queue
+            .push_back(QueuedMessage::new(message, now));
+    }
+
+    /// ### trim_queue
+    ///
+    /// Si la cola supera la cantidad maxima de mensajes, descarta un mensaje segun la politica
+    ///
+    /// #### Parametros
+    /// - `max_queued_messages`: cantidad maxima de mensajes en cola
+    /// - `policy`: `DropOldest` descarta el primero de la cola, `Reject` el ultimo encolado
+    ///
+    /// #### Retorno
+    /// - `Option<Publish>`: publicacion descartada
+    ///
+    pub fn trim_queue(
+        &mut self,
+        max_queued_messages: usize,
+        policy: QueueOverflowPolicy,
+    ) -> Option<Publish> {
+        if self.messages_in_queue.len() <= max_queued_messages {
+            return None;
+        }
+        let discarded = match policy {
+            QueueOverflowPolicy::DropOldest => self.messages_in_queue.pop_front(),
+            QueueOverflowPolicy::Reject => self.messages_in_queue.pop_back(),
+        };
+        discarded.map(|queued| queued.message)
+    }
+
+    /// ### next_queued_message
+    ///
+    /// Quita de la cola la siguiente publicacion a enviar, si la ventana de mensajes
+    /// en vuelo lo permite
+    ///
+    /// #### Retorno
+    /// - `Option<Publish>`: publicacion con su Message Expiry Interval restante
+    ///
+    /// #### Consideraciones
+    /// - Las publicaciones vencidas se descartan
+    ///
+    pub fn next_queued_message(&mut self, now: u64) -> Option<Publish> {
+        if self.is_inflight_window_full() {
+            return None;
+        }
+        while let Some(queued) = self.messages_in_queue.pop_front() {
+            if !queued.is_expired(now) {
+                return Some(queued.into_message(now));
+            }
+        }
+        None
+    }
+
+    /// ### discard_expired_messages
+    ///
+    /// Descarta de la cola las publicaciones que superaron su Message Expiry Interval
+    ///
+    /// #### Retorno
+    /// - `Vec<Publish>`: publicaciones descartadas
+    ///
+    pub fn discard_expired_messages(&mut self, now: u64) -> Vec<Publish> {
+        let mut expired = Vec::new();
+        self.messages_in_queue.retain(|queued| {
+            if queued.is_expired(now) {
+                expired.push(queued.message.clone());
+                return false;
+            }
+            true
+        });
+        expired
+    }
+
+    /// ### next_packet_id
+    ///
+    /// Asigna un packet identifier para una publicacion saliente,
+    /// distinto de 0 y de los que se encuentran en vuelo
+    ///
+    pub fn next_packet_id(&mut self) -> u16 {
+        loop {
+            self.last_packet_id = self.last_packet_id.wrapping_add(1);
+            if self.last_packet_id != 0
+                && !self
+                    .inflight_messages
+                    .iter()
+                    .any(|m| m.packet_id() == self.last_packet_id)
+            {
+                return self.last_packet_id;
+            }
+        }
+    }
+
+    /// ### is_inflight_window_full
+    ///
+    /// Indica si la sesión alcanzo la cantidad maxima de publicaciones en vuelo
+    ///
+    pub fn is_inflight_window_full(&self) -> bool {
+        self.inflight_messages.len() >= self.inflight_window as usize
+    }
+
+    /// ### add_inflight_message
+    ///
+    /// Registra una publicacion QoS 1 o 2 enviada al cliente, a la espera de su confirmacion
+    ///
+    pub fn add_inflight_message(&mut self, message: Publish) {
+        self.inflight_messages.push(InflightMessage::new(message));
+    }
+
+    /// ### release_inflight_message
+    ///
+    /// Marca como liberada (PUBREL enviado) la publicacion con el packet identifier
+    ///
+    /// #### Retorno
+    /// - `bool`: true si la publicacion estaba en vuelo
+    ///
+    pub fn release_inflight_message(&mut self, packet_id: u16) -> bool {
+        match self
+            .inflight_messages
+            .iter_mut()
+            .find(|m| m.packet_id() == packet_id)
+        {
+            Some(inflight) => {
+                inflight.state = InflightState::AwaitingPubcomp;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// ### complete_inflight_message
+    ///
+    /// Elimina la publicacion en vuelo al recibir el PUBACK (QoS 1) o el PUBCOMP (QoS 2)
+    ///
+    /// #### Retorno
+    /// - `bool`: true si la publicacion estaba en vuelo
+    ///
+    pub fn complete_inflight_message(&mut self, packet_id: u16) -> bool {
+        let len = self.inflight_messages.len();
+        self.inflight_messages
+            .retain(|m| m.packet_id() != packet_id);
+        len != self.inflight_messages.len()
+    }
+
+    /// ### pending_messages
+    ///
+    /// Cantidad de publicaciones pendientes de entregar al cliente (en vuelo y en cola)
+    ///
+    pub fn pending_messages(&self) -> usize {
+        self.inflight_messages.len() + self.messages_in_queue.len()
     }
 
-    pub fn store_message(&mut self, message: Publish) {
-        self.messages_in_queue.push_back(message);
+    /// ### take_shared_messages
+    ///
+    /// Quita de la sesión las publicaciones de subscripciones compartidas que el cliente
+    /// todavia no recibio: las que estan en cola y las que esperan PUBACK o PUBREC
+    ///
+    /// #### Parametros
+    /// - `now`: momento actual (segundos desde UNIX EPOCH)
+    ///
+    /// #### Retorno
+    /// - `Vec<(String, Publish)>`: subscripción compartida de cada publicacion, junto a la
+    ///   publicacion (sin el flag DUP)
+    ///
+    /// #### Consideraciones
+    /// - Las publicaciones que tambien coinciden con una subscripción no compartida
+    ///   del cliente se mantienen en la sesión
+    /// - Las publicaciones en cola vencidas se descartan, y las demas se devuelven con
+    ///   su Message Expiry Interval restante
+    ///
+    pub fn take_shared_messages(&mut self, now: u64) -> Vec<(String, Publish)> {
+        let subscriptions = self.subscriptions.clone();
+        let shared_subscription = |message: &Publish| {
+            let topic = &message.properties.topic_name;
+            if subscriptions
+                .iter()
+                .any(|t| t.share_name().is_none() && t.matches(topic))
+            {
+                return None;
+            }
+            subscriptions
+                .iter()
+                .find(|t| t.share_name().is_some() && t.matches(topic))
+                .map(|t| t.topic_filter.clone())
+        };
+
+        let mut shared_messages = Vec::new();
+        self.inflight_messages.retain(|inflight| {
+            if inflight.state == InflightState::AwaitingPubcomp {
+                return true;
+            }
+            match shared_subscription(&inflight.message) {
+                Some(topic_filter) => {
+                    let mut message = inflight.message.clone();
+                    message.fixed_header_flags &= !0b1000;
+                    shared_messages.push((topic_filter, message));
+                    false
+                }
+                None => true,
+            }
+        });
+        self.messages_in_queue
+            .retain(|queued| match shared_subscription(&queued.message) {
+                Some(topic_filter) => {
+                    if !queued.is_expired(now) {
+                        shared_messages.push((topic_filter, queued.clone().into_message(now)));
+                    }
+                    false
+                }
+                None => true,
+            });
+        shared_messages
+    }
+
+    /// ### receive_exactly_once
+    ///
+    /// Registra el packet identifier de una publicacion QoS 2 recibida del cliente
+    ///
+    /// #### Retorno
+    /// - `bool`: true si la publicacion es nueva, false si es un duplicado
+    ///   (todavia no se recibio su PUBREL)
+    ///
+    pub fn receive_exactly_once(&mut self, packet_id: u16) -> bool {
+        if self.awaiting_release.contains(&packet_id) {
+            return false;
+        }
+        self.awaiting_release.push(packet_id);
+        true
+    }
+
+    /// ### release_exactly_once
+    ///
+    /// Libera el packet identifier de una publicacion QoS 2 al recibir el PUBREL
+    ///
+    /// #### Retorno
+    /// - `bool`: true si el packet identifier estaba registrado
+    ///
+    pub fn release_exactly_once(&mut self, packet_id: u16) -> bool {
+        let len = self.awaiting_release.len();
+        self.awaiting_release.retain(|id| *id != packet_id);
+        len != self.awaiting_release.len()
     }
 }
 
@@ -194,13 +693,20 @@ mod tests {
             subscriptions: vec![TopicFilter {
                 topic_filter: "test".to_string(),
                 subscription_options: 1,
+                subscription_identifier: None,
             }],
             messages_in_queue: VecDeque::new(),
             will_message: None,
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let bytes = session.as_bytes();
-        let session2 = Session::from_be_bytes(bytes);
+        let session2 = Session::from_be_bytes(bytes).unwrap();
 
         assert_eq!(session.active, session2.active);
         assert_eq!(
@@ -224,11 +730,18 @@ mod tests {
             will_message: Some(WillMessage {
                 will_topic: "test".to_string(),
                 will_payload: vec![1, 2, 3],
+                will_delay_interval: 0,
             }),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let bytes = session.as_bytes();
-        let session2 = Session::from_be_bytes(bytes);
+        let session2 = Session::from_be_bytes(bytes).unwrap();
 
         assert_eq!(session.active, session2.active);
         assert_eq!(
@@ -270,16 +783,28 @@ mod tests {
             subscriptions: vec![TopicFilter {
                 topic_filter: "test".to_string(),
                 subscription_options: 1,
+                subscription_identifier: None,
             }],
-            messages_in_queue: VecDeque::from(vec![msg.clone()]),
+            messages_in_queue: VecDeque::from(vec![QueuedMessage::new(msg.clone(), 0)]),
             will_message: Some(WillMessage {
                 will_topic: "test".to_string(),
                 will_payload: vec![1, 2, 3],
+                will_delay_interval: 0,
             }),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
         };
 
         let bytes = session.as_bytes();
-        let mut session2 = Session::from_be_bytes(bytes);
+        // Una sesión truncada no se puede leer
+        for len in 0..bytes.len() {
+            assert!(Session::from_be_bytes(bytes[..len].to_vec()).is_none());
+        }
+        let mut session2 = Session::from_be_bytes(bytes).unwrap();
 
         assert_eq!(session.active, session2.active);
         assert_eq!(
@@ -299,7 +824,7 @@ mod tests {
             session.messages_in_queue.len(),
             session2.messages_in_queue.len()
         );
-        let message_deserialized = session2.messages_in_queue.pop_back().unwrap();
+        let message_deserialized = session2.messages_in_queue.pop_back().unwrap().message;
         assert_eq!(
             message_deserialized.properties.topic_name,
             msg.clone().properties.topic_name
@@ -327,4 +852,174 @@ mod tests {
             panic!("Will message not found in session");
         }
     }
+
+    #[test]
+    fn test_serialization_with_inflight_messages() {
+        let properties = PublishProperties {
+            topic_name: "inc".to_string(),
+            packet_identifier: 0,
+            payload_format_indicator: Some(1),
+            application_message: "incident".as_bytes().to_vec(),
+            ..Default::default()
+        };
+
+        let mut session = Session {
+            active: true,
+            session_expiry_interval: 0,
+            subscriptions: Vec::new(),
+            messages_in_queue: VecDeque::new(),
+            will_message: Some(WillMessage {
+                will_topic: "test".to_string(),
+                will_payload: vec![1, 2, 3],
+                will_delay_interval: 0,
+            }),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
+        };
+
+        let mut first = Publish::new(0, 2, 0, properties.clone());
+        first.properties.packet_identifier = session.next_packet_id();
+        session.add_inflight_message(first);
+        let mut second = Publish::new(0, 2, 0, properties);
+        second.properties.packet_identifier = session.next_packet_id();
+        session.add_inflight_message(second);
+        assert!(session.release_inflight_message(1));
+        assert!(session.receive_exactly_once(10));
+        assert!(!session.receive_exactly_once(10));
+
+        let bytes = session.as_bytes();
+        assert_eq!(bytes.len(), session.size_of());
+        let mut session2 = Session::from_be_bytes(bytes).unwrap();
+
+        assert_eq!(session2.inflight_messages.len(), 2);
+        assert_eq!(
+            session2.inflight_messages[0].state,
+            InflightState::AwaitingPubcomp
+        );
+        assert_eq!(
+            session2.inflight_messages[1].state,
+            InflightState::AwaitingPubrec
+        );
+        assert_eq!(session2.awaiting_release, vec![10]);
+        assert_eq!(session2.last_packet_id, 2);
+        assert!(session2.will_message.is_some());
+
+        assert_eq!(session2.next_packet_id(), 3);
+        assert!(session2.complete_inflight_message(1));
+        assert!(!session2.complete_inflight_message(1));
+        assert!(session2.release_exactly_once(10));
+        assert!(session2.receive_exactly_once(10));
+    }
+
+    #[test]
+    fn test_session_expiry_and_will_delay() {
+        let mut session = Session {
+            active: true,
+            session_expiry_interval: 10,
+            subscriptions: Vec::new(),
+            messages_in_queue: VecDeque::new(),
+            will_message: Some(WillMessage {
+                will_topic: "desconexion".to_string(),
+                will_payload: vec![1, 2, 3],
+                will_delay_interval: 30,
+            }),
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
+        };
+
+        // El mensaje de voluntad se publica a mas tardar al expirar la sesión
+        assert_eq!(session.will_delay(), Some(10));
+
+        session.disconnect();
+        let disconnected_at = session.disconnected_at.unwrap();
+        assert!(!session.is_expired(disconnected_at + 10));
+        assert!(session.is_expired(disconnected_at + 11));
+
+        session.will_publish_at = Some(disconnected_at + 5);
+        assert!(session.take_due_will_message(disconnected_at + 4).is_none());
+        assert!(session.take_due_will_message(disconnected_at + 5).is_some());
+        assert!(session.will_message.is_none());
+
+        // Un Session Expiry Interval de 0 no puede modificarse desde el DISCONNECT
+        session.session_expiry_interval = 0;
+        session.update_session_expiry_interval(60);
+        assert_eq!(session.session_expiry_interval, 0);
+
+        session.session_expiry_interval = SESSION_NEVER_EXPIRES;
+        assert!(!session.is_expired(u64::MAX / 2));
+
+        let bytes = session.as_bytes();
+        assert_eq!(bytes.len(), session.size_of());
+        let session2 = Session::from_be_bytes(bytes).unwrap();
+        assert_eq!(session2.disconnected_at, Some(disconnected_at));
+        assert_eq!(session2.will_publish_at, None);
+    }
+
+    #[test]
+    fn test_take_shared_messages() {
+        let mut session = Session {
+            active: true,
+            session_expiry_interval: 0,
+            subscriptions: vec![
+                TopicFilter {
+                    topic_filter: "$share/camssystem/inc/#".to_string(),
+                    subscription_options: 1,
+                    subscription_identifier: None,
+                },
+                TopicFilter {
+                    topic_filter: "inc/resolved".to_string(),
+                    subscription_options: 1,
+                    subscription_identifier: None,
+                },
+            ],
+            messages_in_queue: VecDeque::new(),
+            will_message: None,
+            inflight_messages: Vec::new(),
+            awaiting_release: Vec::new(),
+            last_packet_id: 0,
+            inflight_window: DEFAULT_INFLIGHT_WINDOW,
+            disconnected_at: None,
+            will_publish_at: None,
+        };
+
+        let publish = |topic: &str| {
+            let properties = PublishProperties {
+                topic_name: topic.to_string(),
+                application_message: topic.as_bytes().to_vec(),
+                ..Default::default()
+            };
+            Publish::new(0, 1, 0, properties)
+        };
+
+        let mut inflight = publish("inc/1");
+        inflight.properties.packet_identifier = session.next_packet_id();
+        inflight.fixed_header_flags |= 0b1000;
+        session.add_inflight_message(inflight);
+        session.store_message(publish("inc/2"), 0);
+        session.store_message(publish("inc/resolved"), 0);
+        assert_eq!(session.pending_messages(), 3);
+
+        let shared_messages = session.take_shared_messages(0);
+
+        assert_eq!(shared_messages.len(), 2);
+        assert_eq!(shared_messages[0].0, "$share/camssystem/inc/#");
+        assert_eq!(shared_messages[0].1.properties.topic_name, "inc/1");
+        assert_eq!(shared_messages[0].1.fixed_header_flags & 0b1000, 0);
+        assert_eq!(shared_messages[1].1.properties.topic_name, "inc/2");
+
+        // La publicacion tambien coincide con una subscripción no compartida
+        assert_eq!(session.pending_messages(), 1);
+        assert_eq!(
+            session.messages_in_queue[0].message.properties.topic_name,
+            "inc/resolved"
+        );
+    }
 }
@@ -0,0 +1,79 @@
+use std::{
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    time::Duration,
+};
+
+/// ## ShutdownSignal
+///
+/// Señal con la que se pide el cierre del servidor
+///
+/// ### Consideraciones
+/// - Sus clones comparten la misma señal
+/// - Los threads del servidor la esperan en lugar de dormir, para terminar apenas
+///   se pide el cierre
+///
+#[derive(Clone, Default)]
+pub struct ShutdownSignal {
+    state: Arc<(Mutex<bool>, Condvar)>,
+}
+
+impl ShutdownSignal {
+    /// ### trigger
+    ///
+    /// Pide el cierre del servidor, despertando a los threads que esperan la señal
+    ///
+    pub fn trigger(&self) {
+        let (triggered, condvar) = &*self.state;
+        *triggered.lock().unwrap_or_else(PoisonError::into_inner) = true;
+        condvar.notify_all();
+    }
+
+    /// ### is_triggered
+    ///
+    /// Indica si se pidió el cierre del servidor
+    ///
+    pub fn is_triggered(&self) -> bool {
+        *self.state.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// ### wait
+    ///
+    /// Espera a que se pida el cierre del servidor, como maximo el tiempo indicado
+    ///
+    /// ### Retorno
+    /// - `bool`: true si se pidió el cierre
+    ///
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let (triggered, condvar) = &*self.state;
+        let triggered = triggered.lock().unwrap_or_else(PoisonError::into_inner);
+        let (triggered, _) = condvar
+            .wait_timeout_while(triggered, timeout, |triggered| !*triggered)
+            .unwrap_or_else(PoisonError::into_inner);
+        *triggered
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{thread, time::Instant};
+
+    use super::*;
+
+    #[test]
+    fn test_wait_returns_when_triggered() {
+        let signal = ShutdownSignal::default();
+        assert!(!signal.wait(Duration::from_millis(10)));
+
+        let trigger = signal.clone();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            trigger.trigger();
+        });
+
+        let start = Instant::now();
+        assert!(signal.wait(Duration::from_secs(5)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(signal.is_triggered());
+        handle.join().unwrap();
+    }
+}
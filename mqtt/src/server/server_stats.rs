@@ -0,0 +1,260 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::common::{mqtt_stream::TrafficCounters, topic_filter::is_valid_topic_name};
+
+/// Prefijo de los topicos en los que el servidor publica sus estadisticas
+pub const SYS_TOPIC_PREFIX: &str = "$SYS/";
+
+/// ## ServerStats
+///
+/// Contadores de la actividad del servidor, que actualizan los handlers de los paquetes
+///
+/// ### Atributos
+/// - `started`: momento en que se creo el servidor
+/// - `messages_received`: publicaciones recibidas de los clientes
+/// - `messages_sent`: publicaciones enviadas a los suscriptores
+/// - `traffic`: bytes recibidos y enviados por las conexiones
+///
+/// ### Consideraciones
+/// - Es compartido por todas las conexiones del servidor (sus clones actualizan los mismos contadores)
+///
+#[derive(Clone)]
+pub struct ServerStats {
+    started: Instant,
+    messages_received: Arc<AtomicU64>,
+    messages_sent: Arc<AtomicU64>,
+    traffic: TrafficCounters,
+}
+
+/// ## StatsSnapshot
+///
+/// Valores de los contadores del servidor en un momento dado
+///
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatsSnapshot {
+    pub uptime: Duration,
+    pub messages_received: u64,
+    pub messages_sent: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+/// ## StatsReport
+///
+/// Estadisticas que el servidor publica periodicamente en los topicos `$SYS`
+///
+/// ### Atributos
+/// - `current`: contadores al momento de la publicacion
+/// - `previous`: contadores de la publicacion anterior, para calcular las tasas por segundo
+/// - `connected_sessions` / `disconnected_sessions`: sesiones con y sin conexion activa
+/// - `retained_messages`: cantidad de mensajes retenidos
+/// - `queued_messages`: mensajes en la cola de cada sesion
+///
+#[derive(Clone, Debug, Default)]
+pub struct StatsReport {
+    pub current: StatsSnapshot,
+    pub previous: StatsSnapshot,
+    pub connected_sessions: usize,
+    pub disconnected_sessions: usize,
+    pub retained_messages: usize,
+    pub queued_messages: Vec<(String, usize)>,
+}
+
+impl Default for ServerStats {
+    fn default() -> Self {
+        ServerStats {
+            started: Instant::now(),
+            messages_received: Arc::new(AtomicU64::new(0)),
+            messages_sent: Arc::new(AtomicU64::new(0)),
+            traffic: TrafficCounters::default(),
+        }
+    }
+}
+
+impl ServerStats {
+    /// ### message_received
+    ///
+    /// Registra una publicacion recibida de un cliente
+    ///
+    pub fn message_received(&self) {
+        self.messages_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// ### message_sent
+    ///
+    /// Registra una publicacion enviada a un suscriptor
+    ///
+    pub fn message_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// ### traffic
+    ///
+    /// Contadores de bytes que comparten los streams de las conexiones
+    ///
+    pub fn traffic(&self) -> TrafficCounters {
+        self.traffic.clone()
+    }
+
+    /// ### snapshot
+    ///
+    /// Obtiene el valor actual de los contadores
+    ///
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            uptime: self.started.elapsed(),
+            messages_received: self.messages_received.load(Ordering::Relaxed),
+            messages_sent: self.messages_sent.load(Ordering::Relaxed),
+            bytes_received: self.traffic.bytes_received(),
+            bytes_sent: self.traffic.bytes_sent(),
+        }
+    }
+}
+
+impl StatsReport {
+    /// ### per_second
+    ///
+    /// Calcula cuantas veces por segundo aumento un contador desde la publicacion anterior
+    ///
+    fn per_second(&self, counter: impl Fn(&StatsSnapshot) -> u64) -> f64 {
+        let elapsed = self
+            .current
+            .uptime
+            .saturating_sub(self.previous.uptime)
+            .as_secs_f64();
+        if elapsed == 0.0 {
+            return 0.0;
+        }
+        counter(&self.current).saturating_sub(counter(&self.previous)) as f64 / elapsed
+    }
+
+    /// ### messages
+    ///
+    /// Arma las publicaciones de las estadisticas
+    ///
+    /// ### Retorno
+    /// - `Vec<(String, String)>`: topico y payload de cada estadistica
+    ///
+    /// ### Consideraciones
+    /// - Las sesiones cuyo client id no puede formar parte de un topico no publican su cola
+    ///
+    pub fn messages(&self) -> Vec<(String, String)> {
+        let broker = SYS_TOPIC_PREFIX.to_string() + "broker/";
+        let mut messages = vec![
+            ("uptime", self.current.uptime.as_secs().to_string()),
+            ("clients/connected", self.connected_sessions.to_string()),
+            (
+                "clients/disconnected",
+                self.disconnected_sessions.to_string(),
+            ),
+            (
+                "messages/received",
+                self.current.messages_received.to_string(),
+            ),
+            ("messages/sent", self.current.messages_sent.to_string()),
+            (
+                "load/messages/received",
+                format!("{:.2}", self.per_second(|s| s.messages_received)),
+            ),
+            (
+                "load/messages/sent",
+                format!("{:.2}", self.per_second(|s| s.messages_sent)),
+            ),
+            ("bytes/received", self.current.bytes_received.to_string()),
+            ("bytes/sent", self.current.bytes_sent.to_string()),
+            ("retained/count", self.retained_messages.to_string()),
+        ]
+        .into_iter()
+        .map(|(topic, payload)| (broker.clone() + topic, payload))
+        .collect::<Vec<(String, String)>>();
+
+        for (client_id, queued) in &self.queued_messages {
+            let topic = format!("{}sessions/{}/queued", broker, client_id);
+            if is_valid_topic_name(&topic) {
+                messages.push((topic, queued.to_string()));
+            }
+        }
+        messages
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn payload<'a>(messages: &'a [(String, String)], topic: &str) -> Option<&'a str> {
+        messages
+            .iter()
+            .find(|(t, _)| t == topic)
+            .map(|(_, payload)| payload.as_str())
+    }
+
+    #[test]
+    fn test_counters_are_shared_between_clones() {
+        let stats = ServerStats::default();
+        let clone = stats.clone();
+        stats.message_received();
+        clone.message_received();
+        clone.message_sent();
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.messages_received, 2);
+        assert_eq!(snapshot.messages_sent, 1);
+    }
+
+    #[test]
+    fn test_report_messages() {
+        let report = StatsReport {
+            current: StatsSnapshot {
+                uptime: Duration::from_secs(30),
+                messages_received: 40,
+                messages_sent: 25,
+                bytes_received: 1024,
+                bytes_sent: 2048,
+            },
+            previous: StatsSnapshot {
+                uptime: Duration::from_secs(20),
+                messages_received: 20,
+                messages_sent: 20,
+                ..Default::default()
+            },
+            connected_sessions: 2,
+            disconnected_sessions: 1,
+            retained_messages: 3,
+            queued_messages: vec![("drone1".to_string(), 4), ("+".to_string(), 1)],
+        };
+        let messages = report.messages();
+
+        assert_eq!(payload(&messages, "$SYS/broker/uptime"), Some("30"));
+        assert_eq!(
+            payload(&messages, "$SYS/broker/clients/connected"),
+            Some("2")
+        );
+        assert_eq!(
+            payload(&messages, "$SYS/broker/clients/disconnected"),
+            Some("1")
+        );
+        assert_eq!(
+            payload(&messages, "$SYS/broker/load/messages/received"),
+            Some("2.00")
+        );
+        assert_eq!(
+            payload(&messages, "$SYS/broker/load/messages/sent"),
+            Some("0.50")
+        );
+        assert_eq!(payload(&messages, "$SYS/broker/bytes/sent"), Some("2048"));
+        assert_eq!(payload(&messages, "$SYS/broker/retained/count"), Some("3"));
+        assert_eq!(
+            payload(&messages, "$SYS/broker/sessions/drone1/queued"),
+            Some("4")
+        );
+        // Un client id con wildcards no forma un topico valido
+        assert!(!messages.iter().any(|(topic, _)| topic.contains('+')));
+    }
+}
@@ -1,8 +1,9 @@
-use std::io::Write;
-
-use crate::mqtt_packets::{
-    packet::generic_packet::Serialization, packets::publish::Publish,
-    properties::publish_properties::PublishProperties,
+use crate::{
+    common::mqtt_stream::MqttStream,
+    mqtt_packets::{
+        packet::generic_packet::Serialization, packets::publish::Publish,
+        properties::publish_properties::PublishProperties,
+    },
 };
 
 /// ## WillMessage
@@ -13,11 +14,13 @@ use crate::mqtt_packets::{
 /// ### Atributos
 /// - `will_topic`: topico del mensaje
 /// - `will_payload`: payload del mensaje
+/// - `will_delay_interval`: segundos que se demora la publicacion luego de cerrarse la conexion
 ///
 #[derive(Debug)]
 pub struct WillMessage {
     pub will_topic: String,
     pub will_payload: Vec<u8>,
+    pub will_delay_interval: u32,
 }
 
 impl WillMessage {
@@ -29,6 +32,7 @@ impl WillMessage {
     /// - `will_flag`: bandera de voluntad
     /// - `will_topic`: topico del mensaje
     /// - `will_payload`: payload del mensaje
+    /// - `will_delay_interval`: Will Delay Interval del CONNECT (0 si no se envió)
     ///
     /// #### Retorno
     /// - `Option<WillMessage>`:
@@ -38,6 +42,7 @@ impl WillMessage {
         will_flag: u8,
         will_topic: Option<&String>,
         will_payload: Option<Vec<u8>>,
+        will_delay_interval: Option<u32>,
     ) -> Option<WillMessage> {
         if will_flag != 1 {
             return None;
@@ -46,13 +51,14 @@ impl WillMessage {
             Some(WillMessage {
                 will_topic: topic.clone(),
                 will_payload: payload.clone(),
+                will_delay_interval: will_delay_interval.unwrap_or(0),
             })
         } else {
             None
         }
     }
 
-    pub fn send_message(&self, stream: &mut dyn Write) -> bool {
+    pub fn send_message(&self, stream: &mut MqttStream) -> bool {
         let publish_props = PublishProperties {
             topic_name: self.will_topic.clone(),
             packet_identifier: 0,
@@ -63,7 +69,7 @@ impl WillMessage {
         };
 
         let publish = Publish::new(0, 1, 0, publish_props);
-        publish.write_to(stream).is_ok()
+        publish.send(stream).is_ok()
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
@@ -75,32 +81,38 @@ impl WillMessage {
         let payload_len = self.will_payload.len() as u16;
         bytes.extend_from_slice(&payload_len.to_be_bytes());
         bytes.extend_from_slice(&self.will_payload);
+        bytes.extend_from_slice(&self.will_delay_interval.to_be_bytes());
 
         bytes
     }
 
+    /// ### from_be_bytes
+    ///
+    /// Convierte un vector de bytes en un mensaje de voluntad
+    ///
+    /// ### Retorno
+    /// - `Option<WillMessage>`: mensaje de voluntad, o `None` si los bytes no contienen uno
+    ///   o estan incompletos
+    ///
     pub fn from_be_bytes(bytes: Vec<u8>) -> Option<Self> {
-        let mut index = 0;
-
-        let topic_len = bytes[index];
-        index += 1;
-        if topic_len == 0 {
-            return None;
-        }
-        let topic = String::from_utf8(bytes[index..topic_len as usize].to_vec()).unwrap();
-        index += topic.len();
-        let payload_len = u16::from_be_bytes([bytes[index], bytes[index + 1]]);
+        let topic_len = *bytes.first()? as usize;
+        let topic = String::from_utf8(bytes.get(1..topic_len)?.to_vec()).ok()?;
+        let mut index = topic_len;
+        let payload_len = u16::from_be_bytes(bytes.get(index..index + 2)?.try_into().ok()?);
         index += 2;
-        let payload = bytes[index..payload_len as usize + index].to_vec();
+        let payload = bytes.get(index..index + payload_len as usize)?.to_vec();
+        index += payload.len();
+        let will_delay_interval = u32::from_be_bytes(bytes.get(index..index + 4)?.try_into().ok()?);
 
         Some(WillMessage {
             will_topic: topic,
             will_payload: payload,
+            will_delay_interval,
         })
     }
 
     pub fn size_of(&self) -> usize {
-        1 + self.will_topic.len() + 2 + self.will_payload.len()
+        1 + self.will_topic.len() + 2 + self.will_payload.len() + 4
     }
 }
 
@@ -109,6 +121,7 @@ impl Clone for WillMessage {
         WillMessage {
             will_topic: self.will_topic.clone(),
             will_payload: self.will_payload.clone(),
+            will_delay_interval: self.will_delay_interval,
         }
     }
 }
@@ -122,6 +135,7 @@ mod test {
         let will_message = WillMessage {
             will_topic: "topic".to_string(),
             will_payload: vec![1, 2, 3],
+            will_delay_interval: 30,
         };
 
         let bytes = will_message.as_bytes();
@@ -132,6 +146,10 @@ mod test {
                 will_message2.will_payload.len()
             );
             assert_eq!(will_message.will_payload, will_message2.will_payload);
+            assert_eq!(
+                will_message.will_delay_interval,
+                will_message2.will_delay_interval
+            );
         } else {
             panic!("Error al deserializar")
         }
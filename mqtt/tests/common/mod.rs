@@ -0,0 +1,85 @@
+//! Utilidades compartidas por los tests de integracion: rutas de las configuraciones,
+//! clientes y el servidor que corre en otro thread durante cada test.
+//!
+//! Cada archivo de tests compila su propia copia de este modulo y usa solo una parte.
+#![allow(dead_code)]
+
+use logger::logger_handler::{create_logger_handler, LoggerHandler};
+use mqtt::{
+    client::mqtt_client::MqttClient,
+    config::{client_config::ClientConfig, mqtt_config::Config, server_config::ServerConfig},
+    server::mqtt_server::MqttServer,
+};
+
+use std::{
+    fs::remove_file,
+    io::Error,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread,
+    time::Duration,
+};
+
+/// Ruta absoluta de un archivo relativo al directorio del crate
+pub fn config_path(file: &str) -> String {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push(file);
+    String::from(path.to_str().unwrap())
+}
+
+pub fn server_config(file: &str) -> ServerConfig {
+    ServerConfig::from_file(config_path(file)).unwrap()
+}
+
+/// Configuracion de un cliente que se conecta al servidor `server_config`
+pub fn client_config(file: &str, server_config: &ServerConfig) -> ClientConfig {
+    let mut config = ClientConfig::from_file(config_path(file)).unwrap();
+    config.general.ip = server_config.general.ip;
+    config.general.port = server_config.general.port;
+    config.general.log_path = config_path(&config.general.log_path);
+    config
+}
+
+pub fn init_client(config: ClientConfig) -> (MqttClient, LoggerHandler) {
+    let logger_handler = create_logger_handler(&config.general.log_path).unwrap();
+    (MqttClient::init(config).unwrap(), logger_handler)
+}
+
+/// Inicia el servidor en otro thread y devuelve un clon para apagarlo, junto al
+/// canal por el que se recibe el resultado de `start_server`
+pub fn start_server(server: MqttServer) -> (MqttServer, Receiver<Result<(), Error>>) {
+    let shutdown_server = server.clone();
+    let (result_sender, result_receiver) = mpsc::channel();
+    let log_path = server.config.general.log_path.clone();
+    thread::spawn(move || {
+        let logger = create_logger_handler(&log_path).unwrap();
+        let result = server.start_server(logger.get_logger());
+        logger.close();
+        let _ = result_sender.send(result);
+    });
+    thread::sleep(Duration::from_millis(500));
+    (shutdown_server, result_receiver)
+}
+
+/// Apaga el servidor, verifica que `start_server` termine sin errores y elimina sus archivos
+pub fn stop_server(
+    server: MqttServer,
+    result_receiver: Receiver<Result<(), Error>>,
+    config: &ServerConfig,
+) {
+    server.shutdown();
+    let result = result_receiver
+        .recv_timeout(Duration::from_secs(config.shutdown_timeout + 1))
+        .unwrap();
+    assert!(result.is_ok(), "{:?}", result);
+    remove_server_files(config);
+}
+
+/// Elimina el log, las sesiones y los mensajes retenidos del servidor
+pub fn remove_server_files(config: &ServerConfig) {
+    let _ = remove_file(&config.general.log_path);
+    if let Some(db_path) = &config.db_path {
+        let _ = remove_file(db_path);
+        let _ = remove_file(format!("{db_path}.retained"));
+    }
+}
@@ -0,0 +1,265 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{
+            client_connector::connect,
+            client_listener::MqttClientListener,
+            mqtt_client::{receive_packet, MqttClient},
+        },
+        common::{flags::flags_handler, mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::{client_config::ClientConfig, mqtt_config::Config, server_config::ServerConfig},
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::{
+                connect::Connect, disconnect::Disconnect, subscribe::Subscribe,
+                unsubscribe::Unsubscribe,
+            },
+            properties::{
+                connect_payload::ConnectPayload, connect_properties::ConnectProperties,
+                disconnect_properties::DisconnectProperties,
+                subscribe_properties::SubscribeProperties,
+                unsubscribe_properties::UnsubscribeProperties,
+            },
+        },
+        server::{mqtt_server::MqttServer, server_acl::ServerAcl},
+    };
+
+    use std::{fs::remove_file, io::ErrorKind, thread, time::Duration};
+
+    /// Configuracion de un cliente que se autentica con su id como usuario
+    fn drone_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/drone_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config
+    }
+
+    fn receive_all(listener: &MqttClientListener) -> Vec<String> {
+        let mut received = Vec::new();
+        while let Ok(message) = listener.receiver.recv_timeout(Duration::from_millis(500)) {
+            received.push(message.topic);
+        }
+        received
+    }
+
+    /// Conecta un cliente anonimo sin pasar por MqttClient, que agrega su propio id como prefijo
+    fn raw_connect(address: &str, client_id: &str, logger: &Logger) -> MqttStream {
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &client_id.to_string(),
+            logger,
+        )
+        .unwrap();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: client_id.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+        assert!(matches!(
+            receive_packet(&mut stream).unwrap(),
+            PacketReceived::Connack(_)
+        ));
+        stream
+    }
+
+    /// Cliente anonimo que se subscribe a varios filtros, con el prefijo del id de sesión
+    /// indicado, y devuelve los reason codes del SUBACK
+    fn raw_subscribe(
+        address: &str,
+        client_id: &str,
+        session_id: &str,
+        topics: &[&str],
+        logger: &Logger,
+    ) -> Vec<u8> {
+        let mut stream = raw_connect(address, client_id, logger);
+        let mut properties = SubscribeProperties {
+            packet_identifier: 1,
+            ..Default::default()
+        };
+        for topic in topics {
+            // El servidor quita el prefijo con el id que agrega el cliente
+            properties.add_topic_filter(format!("{}/{}", session_id, topic), 1, false, false, 0);
+        }
+        Subscribe::new(properties).send(&mut stream).unwrap();
+
+        let reason_codes = match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Suback(suback) => suback.properties.reason_codes,
+            _ => panic!("Se esperaba un SUBACK"),
+        };
+        let _ = stream.shutdown();
+        reason_codes
+    }
+
+    /// Cliente anonimo que se desubscribe de varios filtros, con el prefijo del id de sesión
+    /// indicado, y devuelve los reason codes del UNSUBACK
+    fn raw_unsubscribe(
+        address: &str,
+        client_id: &str,
+        session_id: &str,
+        topics: &[&str],
+        logger: &Logger,
+    ) -> Vec<u8> {
+        let mut stream = raw_connect(address, client_id, logger);
+        let properties = UnsubscribeProperties {
+            packet_identifier: 1,
+            user_properties: Vec::new(),
+            topic_filters: topics
+                .iter()
+                .map(|topic| format!("{}/{}", session_id, topic))
+                .collect(),
+        };
+        Unsubscribe::new(properties).send(&mut stream).unwrap();
+
+        let reason_codes = match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Unsuback(unsuback) => unsuback.properties.reason_codes,
+            _ => panic!("Se esperaba un UNSUBACK"),
+        };
+        let _ = stream.shutdown();
+        reason_codes
+    }
+
+    #[test]
+    fn test_acl_restricts_publish_subscribe_and_will() {
+        let server_config = server_config("tests/config/acl_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+        let acl =
+            ServerAcl::from_file(&config_path(&server_config.acl_path.clone().unwrap())).unwrap();
+
+        // SERVER
+        let users = vec![
+            "drone1".to_string(),
+            "drone2".to_string(),
+            "monitoring".to_string(),
+        ];
+        let (server, result_receiver) =
+            start_server(MqttServer::new(server_config.clone(), users).with_acl(acl));
+
+        let client_log_path = drone_config("drone1", &server_config).general.log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // Cada filtro del SUBACK tiene su propia decision
+        assert_eq!(
+            raw_subscribe(
+                &address,
+                "sensor-1",
+                "sensor-1",
+                &["sensors/kitchen", "inc", "sensors/#", "#"],
+                &logger
+            ),
+            vec![
+                ReasonCode::GrantedQoS1.get_id(),
+                ReasonCode::NotAuthorized.get_id(),
+                ReasonCode::GrantedQoS1.get_id(),
+                ReasonCode::NotAuthorized.get_id(),
+            ]
+        );
+
+        // Un cliente no puede subscribir a la sesión de otro, aunque la ACL lo habilite
+        assert_eq!(
+            raw_subscribe(
+                &address,
+                "sensor-2",
+                "sensor-1",
+                &["sensors/kitchen"],
+                &logger
+            ),
+            vec![ReasonCode::NotAuthorized.get_id()]
+        );
+
+        let mut monitoring = MqttClient::init(drone_config("monitoring", &server_config)).unwrap();
+        let listener = monitoring.run_listener(&logger).unwrap();
+        monitoring
+            .subscribe(vec!["drone/+", "inc", "camaras"], &logger)
+            .unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // Un dron solo publica en su propio topico: el resto se descarta
+        let mut drone1 = MqttClient::init(drone_config("drone1", &server_config)).unwrap();
+        let mut publish = |topic: &str| {
+            drone1
+                .publish(b"estado".to_vec(), topic.to_string(), &logger)
+                .unwrap()
+        };
+        assert_eq!(publish("drone/drone1"), ReasonCode::Success);
+        assert_eq!(publish("drone/drone2"), ReasonCode::NotAuthorized);
+        assert_eq!(publish("inc"), ReasonCode::NotAuthorized);
+        assert_eq!(
+            drone1
+                .publish_with_qos(b"estado".to_vec(), "inc".to_string(), 2, &logger)
+                .unwrap(),
+            ReasonCode::NotAuthorized
+        );
+        monitoring
+            .publish(b"incidente".to_vec(), "inc".to_string(), &logger)
+            .unwrap();
+
+        assert_eq!(
+            receive_all(&listener),
+            vec!["drone/drone1".to_string(), "inc".to_string()]
+        );
+
+        // Un cliente no puede quitar las subscripciones de la sesión de otro
+        assert_eq!(
+            raw_unsubscribe(&address, "sensor-2", "monitoring", &["inc"], &logger),
+            vec![ReasonCode::NotAuthorized.get_id()]
+        );
+
+        // Ni cerrarla con un DISCONNECT que indique el id de otro cliente
+        let mut stream = raw_connect(&address, "sensor-3", &logger);
+        Disconnect::new(DisconnectProperties {
+            id: "monitoring".to_string(),
+            disconnect_reason_code: ReasonCode::NormalDisconnection.get_id(),
+            ..Default::default()
+        })
+        .send(&mut stream)
+        .unwrap();
+        // El servidor cierra la conexion una vez procesado el DISCONNECT
+        assert!(receive_packet(&mut stream).is_err());
+
+        monitoring
+            .publish(b"incidente".to_vec(), "inc".to_string(), &logger)
+            .unwrap();
+        assert_eq!(receive_all(&listener), vec!["inc".to_string()]);
+
+        // No se acepta una conexion con un Will Message en un topico no autorizado
+        let mut config = drone_config("drone2", &server_config);
+        config.connect_properties.connect_flags = flags_handler::add_connect_flag_will_flag(
+            config.connect_properties.connect_flags,
+            "true".to_string(),
+        )
+        .unwrap();
+        config.set_will_message("inc".to_string(), b"drone2 desconectado".to_vec());
+        assert_eq!(
+            MqttClient::init(config.clone()).err().map(|e| e.kind()),
+            Some(ErrorKind::PermissionDenied)
+        );
+        config.set_will_message("drone/drone2".to_string(), b"drone2 desconectado".to_vec());
+        let mut drone2 = MqttClient::init(config).unwrap();
+
+        for client in [&mut drone1, &mut drone2, &mut monitoring] {
+            client
+                .disconnect(ReasonCode::NormalDisconnection, &logger)
+                .unwrap();
+        }
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
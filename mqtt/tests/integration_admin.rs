@@ -0,0 +1,122 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::mqtt_client::MqttClient,
+        common::{flags::flags_handler, reason_codes::ReasonCode},
+        server::{mqtt_server::MqttServer, server_admin::send_admin_command},
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const WILL_TOPIC: &str = "drone/drone1/estado";
+
+    #[test]
+    fn test_admin_commands() {
+        let server_config = server_config("tests/config/admin_server_config.txt");
+        let socket_path = server_config.admin_socket_path.clone().unwrap();
+        let admin = |command: &str| {
+            let args: Vec<String> = command.split_whitespace().map(String::from).collect();
+            send_admin_command(&socket_path, &args)
+        };
+
+        // SERVER
+        let users = vec!["drone1".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        // La aplicacion de monitoreo recibe el mensaje de voluntad del dron y publica
+        // mensajes retenidos
+        let mut monitoring_config =
+            client_config("tests/config/monitoring_client_config.txt", &server_config);
+        monitoring_config.pub_retain = 1;
+        let log_path = monitoring_config.general.log_path.clone();
+        let logger_handler = create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        let mut monitoring = MqttClient::init(monitoring_config).unwrap();
+        let listener = monitoring.run_listener(&logger).unwrap();
+        monitoring.subscribe(vec![WILL_TOPIC], &logger).unwrap();
+        monitoring
+            .publish(b"on".to_vec(), "cams/1".to_string(), &logger)
+            .unwrap();
+
+        // El dron conserva su sesión al desconectarse
+        let mut drone_config =
+            client_config("tests/config/persistent_client_config.txt", &server_config);
+        drone_config.general.id = "drone1".to_string();
+        drone_config.connect_properties.connect_flags = flags_handler::add_connect_flag_will_flag(
+            drone_config.connect_properties.connect_flags,
+            "true".to_string(),
+        )
+        .unwrap();
+        drone_config.set_will_message(WILL_TOPIC.to_string(), b"desconectado".to_vec());
+        let drone_log_path = drone_config.general.log_path.clone();
+        let mut drone1 = MqttClient::init(drone_config).unwrap();
+        drone1.subscribe(vec!["inc"], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        let sessions = admin("sessions").unwrap();
+        assert!(sessions.contains("drone1 [conectado]"), "{}", sessions);
+        assert!(
+            sessions.contains("subscripciones: inc (QoS 1)"),
+            "{}",
+            sessions
+        );
+        assert!(
+            sessions.contains(&format!("mensaje de voluntad: {}", WILL_TOPIC)),
+            "{}",
+            sessions
+        );
+        assert!(sessions.contains("monitoring [conectado]"), "{}", sessions);
+
+        // Desconectar al dron publica su mensaje de voluntad, pero conserva su sesión
+        assert!(admin("kick drone1 1").is_err());
+        admin("kick drone1 0x98").unwrap();
+        let will = listener
+            .receiver
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert!(will.is_will_message);
+        assert!(admin("kick drone1").is_err());
+        assert!(admin("sessions").unwrap().contains("drone1 [desconectado]"));
+
+        // Las publicaciones para el dron desconectado quedan en su cola
+        for _ in 0..2 {
+            monitoring
+                .publish(b"incidente".to_vec(), "inc".to_string(), &logger)
+                .unwrap();
+        }
+        assert_eq!(
+            admin("queue drone1").unwrap(),
+            "inc (QoS 1, 9 bytes)\ninc (QoS 1, 9 bytes)"
+        );
+        assert_eq!(
+            admin("purge drone1").unwrap(),
+            "Se descartaron 2 mensajes de la cola de 'drone1'"
+        );
+        assert_eq!(admin("queue drone1").unwrap(), "");
+
+        admin("clear drone1").unwrap();
+        assert!(!admin("sessions").unwrap().contains("drone1 ["));
+        assert!(admin("clear drone1").is_err());
+
+        assert_eq!(
+            admin("drop-retained cams/#").unwrap(),
+            "Se eliminaron 1 mensajes retenidos"
+        );
+        assert!(admin("drop-retained cams/#/1").is_err());
+
+        monitoring
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(log_path);
+        let _ = remove_file(drone_log_path);
+        let _ = remove_file(socket_path);
+    }
+}
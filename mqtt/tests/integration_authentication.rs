@@ -0,0 +1,146 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::{
+            client_connector::connect,
+            mqtt_client::{receive_packet, MqttClient},
+        },
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode, scram::SCRAM_SHA_256},
+        config::{client_config::ClientConfig, mqtt_config::Config, server_config::ServerConfig},
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::connect::Connect,
+            properties::{connect_payload::ConnectPayload, connect_properties::ConnectProperties},
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, io::ErrorKind, thread, time::Duration};
+
+    const TOPIC: &str = "inc";
+
+    /// Configuracion de un cliente que se autentica con SCRAM-SHA-256
+    fn scram_config(id: &str, password: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config.general.password = password.to_string();
+        config.connect_properties.authentication_method = Some(SCRAM_SHA_256.to_string());
+        config
+    }
+
+    /// Envia un CONNECT con el Authentication Method dado y devuelve el reason code del CONNACK
+    fn raw_connect(address: &str, authentication_method: Option<String>) -> u8 {
+        let log_path = config_path("log_auth_raw.tmp");
+        let logger_handler = create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &60,
+            &"raw".to_string(),
+            &logger,
+        )
+        .unwrap();
+        logger.close();
+        logger_handler.close();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            authentication_method,
+            authentication_data: Some(b"monitoring:1234".to_vec()),
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: "raw".to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(connack) => connack.properties.connect_reason_code,
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+    }
+
+    #[test]
+    fn test_scram_authentication() {
+        let server_config = server_config("tests/config/auth_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = scram_config("monitoring", "1234", &server_config)
+            .general
+            .log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // Un usuario habilitado se autentica con SCRAM-SHA-256
+        let mut monitoring =
+            MqttClient::init(scram_config("monitoring", "1234", &server_config)).unwrap();
+        let listener = monitoring.run_listener(&logger).unwrap();
+        monitoring.subscribe(vec![TOPIC], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // Contraseña incorrecta o usuario desconocido: se rechaza la conexion
+        for (id, password) in [("camssystem", "4321"), ("intruso", "1234")] {
+            let error = MqttClient::init(scram_config(id, password, &server_config))
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        }
+
+        // Sin Authentication Method, o con uno que el servidor no soporta
+        assert_eq!(
+            raw_connect(&address, None),
+            ReasonCode::NotAuthorized.get_id()
+        );
+        assert_eq!(
+            raw_connect(&address, Some("PLAIN".to_string())),
+            ReasonCode::BadAuthenticationMethod.get_id()
+        );
+
+        // La re-autenticacion no interrumpe la conexion
+        let mut camssystem =
+            MqttClient::init(scram_config("camssystem", "1234", &server_config)).unwrap();
+        monitoring.reauthenticate(&logger).unwrap();
+        camssystem
+            .publish(b"incidente".to_vec(), TOPIC.to_string(), &logger)
+            .unwrap();
+        let message = listener
+            .receiver
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(message.data, b"incidente".to_vec());
+
+        // Sin listener no se puede re-autenticar
+        let error = camssystem.reauthenticate(&logger).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotConnected);
+
+        // Una re-autenticacion fallida cierra la conexion
+        let mut impostor = monitoring.clone();
+        impostor.config.general.password = "4321".to_string();
+        let error = impostor.reauthenticate(&logger).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+
+        camssystem
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+        let _ = remove_file(config_path("log_auth_raw.tmp"));
+    }
+}
@@ -0,0 +1,206 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{
+            client_listener::MqttClientListener, client_message::MqttClientMessage,
+            mqtt_client::MqttClient,
+        },
+        common::reason_codes::ReasonCode,
+        config::{
+            bridge_config::{BridgeConfig, BridgeDirection, BridgeTopic},
+            mqtt_config::Config,
+            server_config::ServerConfig,
+        },
+        server::{
+            mqtt_server::MqttServer,
+            server_bridge::{bridge_origin, BRIDGE_ORIGIN_PROPERTY},
+        },
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    /// Conecta una aplicacion al servidor y la subscribe a los topicos dados
+    fn connect_client(
+        id: &str,
+        server_config: &ServerConfig,
+        topics: Vec<&str>,
+        logger: &Logger,
+    ) -> (MqttClient, MqttClientListener) {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        let mut client = MqttClient::init(config).unwrap();
+        let listener = client.run_listener(logger).unwrap();
+        client.subscribe(topics, logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        (client, listener)
+    }
+
+    fn receive(listener: &MqttClientListener) -> MqttClientMessage {
+        listener
+            .receiver
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap()
+    }
+
+    /// Verifica que no llegue ningun otro mensaje (por ejemplo, un mensaje que volvio por el puente)
+    fn assert_no_more_messages(listener: &MqttClientListener) {
+        let message = listener.receiver.recv_timeout(Duration::from_millis(500));
+        assert!(message.is_err(), "{:?}", message);
+    }
+
+    #[test]
+    fn test_bridge_topic_rules() {
+        let topic = BridgeTopic::from_rule("in:status/#:south/:").unwrap();
+        assert_eq!(topic.direction, BridgeDirection::In);
+        assert_eq!(topic.local_filter(), "south/status/#");
+        assert_eq!(topic.remote_filter(), "status/#");
+        assert_eq!(
+            topic.to_local("status/hospital").as_deref(),
+            Some("south/status/hospital")
+        );
+        assert_eq!(topic.to_local("incidents/fire"), None);
+        assert_eq!(topic.to_remote("south/status/hospital"), None);
+
+        let topic = BridgeTopic::from_rule("both:incidents/+").unwrap();
+        assert_eq!(
+            topic.to_remote("incidents/fire").as_deref(),
+            Some("incidents/fire")
+        );
+        assert_eq!(
+            topic.to_local("incidents/fire").as_deref(),
+            Some("incidents/fire")
+        );
+        assert_eq!(topic.to_local("incidents/fire/1"), None);
+
+        for rule in [
+            "incidents/#",
+            "sideways:incidents/#",
+            "in:incidents/#:north/",
+            "out:incidents/#/more",
+            "both:incidents/#::north/#/",
+        ] {
+            assert!(BridgeTopic::from_rule(rule).is_err(), "{}", rule);
+        }
+
+        // El puente siempre se subscribe con No Local y maneja sus propios reintentos
+        let bridge =
+            BridgeConfig::from_file(config_path("tests/config/bridge_north_to_south.txt")).unwrap();
+        assert!(bridge.client.sub_no_local);
+        assert!(!bridge.client.connect_retry);
+        assert_eq!(bridge.remote_filters(), vec!["incidents/#", "status/#"]);
+        assert_eq!(
+            bridge.to_remote("alerts/smoke").as_deref(),
+            Some("north/alerts/smoke")
+        );
+        assert_eq!(
+            (bridge.reconnect_min_delay, bridge.reconnect_max_delay),
+            (1, 2)
+        );
+
+        let server_config = server_config("tests/config/bridge_north_server_config.txt");
+        assert_eq!(server_config.bridges.len(), 1);
+    }
+
+    #[test]
+    fn test_bridge_forwards_topics_between_brokers() {
+        let north_config = server_config("tests/config/bridge_north_server_config.txt");
+        let south_config = server_config("tests/config/broker_server_config.txt");
+        let bridge_log_path = north_config.bridges[0].client.general.log_path.clone();
+
+        // El servidor remoto todavia no esta disponible: el puente reintenta la conexion
+        let (north, north_result) = start_server(MqttServer::new(north_config.clone(), vec![]));
+        thread::sleep(Duration::from_millis(1500));
+        let (south, south_result) = start_server(MqttServer::new(south_config.clone(), vec![]));
+
+        let client_log_path = config_path("log_bridge_clients.tmp");
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        let (mut north_app, north_listener) = connect_client(
+            "north_app",
+            &north_config,
+            vec!["incidents/#", "south/status/#", "alerts/#"],
+            &logger,
+        );
+        let (mut south_app, south_listener) = connect_client(
+            "south_app",
+            &south_config,
+            vec!["incidents/#", "north/alerts/#", "status/#"],
+            &logger,
+        );
+
+        // Se espera a que el puente se conecte al servidor remoto
+        let mut connected = false;
+        for _ in 0..20 {
+            south_app
+                .publish_with_qos(
+                    b"online".to_vec(),
+                    "status/hospital".to_string(),
+                    0,
+                    &logger,
+                )
+                .unwrap();
+            let _ = south_listener.receiver.recv_timeout(Duration::from_secs(1));
+            if let Ok(message) = north_listener
+                .receiver
+                .recv_timeout(Duration::from_millis(500))
+            {
+                assert_eq!(message.topic, "south/status/hospital");
+                connected = true;
+                break;
+            }
+        }
+        assert!(connected, "El puente no se conecto al servidor remoto");
+        while north_listener.receiver.try_recv().is_ok() {}
+        while south_listener.receiver.try_recv().is_ok() {}
+
+        // Un incidente del servidor local llega al remoto una sola vez, sin volver
+        north_app
+            .publish(b"fire".to_vec(), "incidents/fire".to_string(), &logger)
+            .unwrap();
+        let message = receive(&south_listener);
+        assert_eq!(message.topic, "incidents/fire");
+        assert_eq!(message.data, b"fire".to_vec());
+        assert_eq!(bridge_origin(&message.user_properties), Some("north"));
+        assert_eq!(receive(&north_listener).topic, "incidents/fire");
+        assert_no_more_messages(&south_listener);
+        assert_no_more_messages(&north_listener);
+
+        // Y uno del servidor remoto llega al local, sin que el puente lo reenvie de vuelta
+        south_app
+            .publish(b"flood".to_vec(), "incidents/flood".to_string(), &logger)
+            .unwrap();
+        let message = receive(&north_listener);
+        assert_eq!(message.topic, "incidents/flood");
+        assert_eq!(message.data, b"flood".to_vec());
+        assert!(!message
+            .user_properties
+            .iter()
+            .any(|(name, _)| name == BRIDGE_ORIGIN_PROPERTY));
+        assert_eq!(receive(&south_listener).topic, "incidents/flood");
+        assert_no_more_messages(&north_listener);
+        assert_no_more_messages(&south_listener);
+
+        // Los patrones de un solo sentido reemplazan el prefijo del topico
+        north_app
+            .publish(b"smoke".to_vec(), "alerts/smoke".to_string(), &logger)
+            .unwrap();
+        assert_eq!(receive(&north_listener).topic, "alerts/smoke");
+        assert_eq!(receive(&south_listener).topic, "north/alerts/smoke");
+
+        let _ = north_app.disconnect(ReasonCode::NormalDisconnection, &logger);
+        let _ = south_app.disconnect(ReasonCode::NormalDisconnection, &logger);
+        stop_server(north, north_result, &north_config);
+        stop_server(south, south_result, &south_config);
+        logger.close();
+        logger_handler.close();
+
+        let _ = remove_file(client_log_path);
+        let _ = remove_file(bridge_log_path);
+        let _ = remove_file(config_path("log_monitoring_client.tmp"));
+    }
+}
@@ -0,0 +1,93 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::{client_connector::connect, mqtt_client::receive_packet},
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::mqtt_config::Config,
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::{connect::Connect, pingreq::PingReq},
+            properties::{connect_payload::ConnectPayload, connect_properties::ConnectProperties},
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::fs::remove_file;
+
+    fn open_connection(address: &str, client_id: &str) -> MqttStream {
+        let log_path = config_path("log_connection_state_client.tmp");
+        let logger_handler = create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &client_id.to_string(),
+            &logger,
+        )
+        .unwrap();
+        logger.close();
+        logger_handler.close();
+        MqttStream::new(tls_stream).unwrap()
+    }
+
+    fn connect_packet(client_id: &str) -> Connect {
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: client_id.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload)
+    }
+
+    fn receive_disconnect_reason(stream: &mut MqttStream) -> u8 {
+        match receive_packet(stream).unwrap() {
+            PacketReceived::Disconnect(disconnect) => disconnect.properties.disconnect_reason_code,
+            _ => panic!("Se esperaba un DISCONNECT"),
+        }
+    }
+
+    #[test]
+    fn test_connection_must_start_with_a_single_connect() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        // El primer paquete de la conexion no es un CONNECT
+        let mut stream = open_connection(&address, "camssystem");
+        PingReq.send(&mut stream).unwrap();
+        assert_eq!(
+            receive_disconnect_reason(&mut stream),
+            ReasonCode::ProtocolError.get_id()
+        );
+
+        // Un segundo CONNECT en la misma conexion
+        let mut stream = open_connection(&address, "monitoring");
+        connect_packet("monitoring").send(&mut stream).unwrap();
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(_) => (),
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+        connect_packet("monitoring").send(&mut stream).unwrap();
+        assert_eq!(
+            receive_disconnect_reason(&mut stream),
+            ReasonCode::ProtocolError.get_id()
+        );
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(config_path("log_connection_state_client.tmp"));
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::mqtt_client::MqttClient,
+        common::{reason_codes::ReasonCode, scram::SCRAM_SHA_256},
+        config::{client_config::ClientConfig, server_config::ServerConfig},
+        server::{mqtt_server::MqttServer, server_credentials::ServerCredentials},
+    };
+
+    use std::{fs::remove_file, io::ErrorKind, thread, time::Duration};
+
+    /// Configuracion de un cliente que se autentica con SCRAM-SHA-256 o, si no,
+    /// con los campos User Name y Password del CONNECT
+    fn drone_config(
+        id: &str,
+        password: &str,
+        scram: bool,
+        server_config: &ServerConfig,
+    ) -> ClientConfig {
+        let mut config = client_config("tests/config/drone_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config.general.password = password.to_string();
+        if scram {
+            config.connect_properties.authentication_method = Some(SCRAM_SHA_256.to_string());
+        }
+        config
+    }
+
+    fn connect(
+        server_config: &ServerConfig,
+        id: &str,
+        password: &str,
+        scram: bool,
+    ) -> Result<(), ErrorKind> {
+        let config = drone_config(id, password, scram, server_config);
+        let log_path = config.general.log_path.clone();
+        let mut client = MqttClient::init(config).map_err(|e| e.kind())?;
+        let logger_handler = create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        client
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        logger.close();
+        logger_handler.close();
+        Ok(())
+    }
+
+    #[test]
+    fn test_credentials_file_hot_reload() {
+        let server_config = server_config("tests/config/credentials_server_config.txt");
+        let credentials_path = config_path(&server_config.credentials_path.clone().unwrap());
+
+        let manager = ServerCredentials::with_file(&credentials_path);
+        manager.set_password("drone1", "1234").unwrap();
+        manager.set_password("drone2", "5678").unwrap();
+        manager.save().unwrap();
+
+        // SERVER
+        let srv_credentials = ServerCredentials::from_file(&credentials_path).unwrap();
+        let (server, result_receiver) = start_server(MqttServer::with_credentials(
+            server_config.clone(),
+            srv_credentials,
+        ));
+
+        // Cada usuario tiene su propia contraseña, por ambos caminos de autenticación
+        for scram in [true, false] {
+            assert_eq!(connect(&server_config, "drone1", "1234", scram), Ok(()));
+            assert!(connect(&server_config, "drone2", "5678", scram).is_ok());
+            assert_eq!(
+                connect(&server_config, "drone2", "1234", scram).err(),
+                Some(ErrorKind::PermissionDenied)
+            );
+            assert_eq!(
+                connect(&server_config, "drone3", "1234", scram).err(),
+                Some(ErrorKind::PermissionDenied)
+            );
+        }
+
+        // Se rota una contraseña, se elimina un usuario y se agrega otro sin reiniciar el servidor
+        thread::sleep(Duration::from_millis(20));
+        manager.set_password("drone1", "4321").unwrap();
+        manager.remove_user("drone2");
+        manager.set_password("drone3", "1234").unwrap();
+        manager.save().unwrap();
+        thread::sleep(Duration::from_millis(1500));
+
+        for scram in [true, false] {
+            assert_eq!(
+                connect(&server_config, "drone1", "1234", scram).err(),
+                Some(ErrorKind::PermissionDenied)
+            );
+            assert!(connect(&server_config, "drone1", "4321", scram).is_ok());
+            assert_eq!(
+                connect(&server_config, "drone2", "5678", scram).err(),
+                Some(ErrorKind::PermissionDenied)
+            );
+            assert!(connect(&server_config, "drone3", "1234", scram).is_ok());
+        }
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(config_path("log_drone_client.tmp"));
+        let _ = remove_file(&credentials_path);
+    }
+}
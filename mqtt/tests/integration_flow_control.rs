@@ -0,0 +1,229 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{
+            client_connector::connect,
+            mqtt_client::{receive_packet, MqttClient},
+        },
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::{client_config::ClientConfig, mqtt_config::Config, server_config::ServerConfig},
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::{connect::Connect, puback::Puback, publish::Publish, subscribe::Subscribe},
+            properties::{
+                connect_payload::ConnectPayload, connect_properties::ConnectProperties,
+                puback_properties::PubackProperties, publish_properties::PublishProperties,
+                subscribe_properties::SubscribeProperties,
+            },
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, io::ErrorKind, thread, time::Duration};
+
+    const TOPIC: &str = "inc";
+    const WINDOW_TOPIC: &str = "drone/status";
+
+    /// Configuracion de un cliente que no acepta paquetes de mas de 128 bytes
+    fn limited_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config.connect_properties.maximum_packet_size = Some(128);
+        config
+    }
+
+    fn incident(packet_identifier: u16, qos: u8, topic: &str, size: usize) -> Publish {
+        let properties = PublishProperties {
+            topic_name: topic.to_string(),
+            packet_identifier,
+            application_message: vec![b'x'; size],
+            ..Default::default()
+        };
+        Publish::new(0, qos, 0, properties)
+    }
+
+    /// Conecta un cliente sin listener, que envia y lee los paquetes directamente
+    fn connect_raw_client(address: &str, id: &str, receive_maximum: Option<u16>) -> MqttStream {
+        let log_path = config_path("log_flow_control_raw.tmp");
+        let logger_handler = create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &60,
+            &id.to_string(),
+            &logger,
+        )
+        .unwrap();
+        logger.close();
+        logger_handler.close();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            receive_maximum,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: id.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(connack) => {
+                assert_eq!(connack.properties.receive_maximum, Some(2));
+                assert_eq!(connack.properties.maximum_packet_size, Some(256));
+            }
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+        stream
+    }
+
+    fn receive_disconnect(stream: &mut MqttStream) -> u8 {
+        loop {
+            match receive_packet(stream).unwrap() {
+                PacketReceived::Disconnect(disconnect) => {
+                    return disconnect.properties.disconnect_reason_code
+                }
+                PacketReceived::Pubrec(_) => continue,
+                _ => panic!("Se esperaba un DISCONNECT"),
+            }
+        }
+    }
+
+    fn receive_publish(stream: &mut MqttStream) -> Publish {
+        match receive_packet(stream).unwrap() {
+            PacketReceived::Publish(publish) => *publish,
+            _ => panic!("Se esperaba un PUBLISH"),
+        }
+    }
+
+    fn publish(client: &mut MqttClient, size: usize, logger: &Logger) -> ReasonCode {
+        client
+            .publish(vec![b'x'; size], TOPIC.to_string(), logger)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_receive_maximum_and_maximum_packet_size() {
+        let server_config = server_config("tests/config/flow_control_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = limited_config("monitoring", &server_config)
+            .general
+            .log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        let mut monitoring =
+            MqttClient::init(limited_config("monitoring", &server_config)).unwrap();
+        let listener = monitoring.run_listener(&logger).unwrap();
+        monitoring.subscribe(vec![TOPIC], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // El servidor no envia publicaciones mayores al Maximum Packet Size del suscriptor
+        let mut camssystem =
+            MqttClient::init(limited_config("camssystem", &server_config)).unwrap();
+        assert_eq!(publish(&mut camssystem, 200, &logger), ReasonCode::Success);
+        assert!(listener
+            .receiver
+            .recv_timeout(Duration::from_millis(500))
+            .is_err());
+        assert_eq!(publish(&mut camssystem, 20, &logger), ReasonCode::Success);
+        let message = listener
+            .receiver
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(message.data.len(), 20);
+
+        // El cliente no envia publicaciones mayores al Maximum Packet Size del servidor
+        let error = camssystem
+            .publish(vec![b'x'; 300], TOPIC.to_string(), &logger)
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+        // El servidor respeta el Receive Maximum del suscriptor: la segunda publicacion
+        // se envia recien al confirmarse la primera
+        let mut subscriber = connect_raw_client(&address, "window", Some(1));
+        let mut properties = SubscribeProperties {
+            packet_identifier: 1,
+            ..Default::default()
+        };
+        // El cliente antepone su id al filtro (ver `MqttClient::subscribe`)
+        properties.add_topic_filter(format!("window/{}", WINDOW_TOPIC), 1, false, false, 0);
+        Subscribe::new(properties).send(&mut subscriber).unwrap();
+        assert!(matches!(
+            receive_packet(&mut subscriber).unwrap(),
+            PacketReceived::Suback(_)
+        ));
+
+        for data in [b"bateria 1", b"bateria 2"] {
+            camssystem
+                .publish(data.to_vec(), WINDOW_TOPIC.to_string(), &logger)
+                .unwrap();
+        }
+        let first = receive_publish(&mut subscriber);
+        assert_eq!(first.properties.application_message, b"bateria 1".to_vec());
+
+        subscriber
+            .set_read_timeout(Some(Duration::from_millis(500)))
+            .unwrap();
+        assert!(receive_packet(&mut subscriber).is_err());
+        subscriber.set_read_timeout(None).unwrap();
+
+        Puback::new(PubackProperties {
+            packet_id: first.properties.packet_identifier,
+            puback_reason_code: ReasonCode::Success.get_id(),
+            ..Default::default()
+        })
+        .send(&mut subscriber)
+        .unwrap();
+        let second = receive_publish(&mut subscriber);
+        assert_eq!(second.properties.application_message, b"bateria 2".to_vec());
+        let _ = subscriber.shutdown();
+
+        // Un paquete mayor al Maximum Packet Size del servidor cierra la conexion
+        let mut stream = connect_raw_client(&address, "large", None);
+        incident(0, 0, TOPIC, 300).send(&mut stream).unwrap();
+        assert_eq!(
+            receive_disconnect(&mut stream),
+            ReasonCode::PacketTooLarge.get_id()
+        );
+
+        // Superar el Receive Maximum del servidor cierra la conexion
+        let mut stream = connect_raw_client(&address, "flood", None);
+        for packet_identifier in 1..=3 {
+            incident(packet_identifier, 2, TOPIC, 10)
+                .send(&mut stream)
+                .unwrap();
+        }
+        assert_eq!(
+            receive_disconnect(&mut stream),
+            ReasonCode::ReceiveMaximumExceeded.get_id()
+        );
+
+        for client in [&mut monitoring, &mut camssystem] {
+            client
+                .disconnect(ReasonCode::NormalDisconnection, &logger)
+                .unwrap();
+        }
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+        let _ = remove_file(config_path("log_flow_control_raw.tmp"));
+    }
+}
@@ -0,0 +1,125 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{
+        client_config, config_path, init_client, server_config, start_server, stop_server,
+    };
+    use logger::logger_handler::{create_logger_handler, LoggerHandler};
+    use mqtt::{
+        client::{client_connector::connect, mqtt_client::receive_packet},
+        common::{flags::flags_handler, mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::mqtt_config::Config,
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::connect::Connect,
+            properties::{connect_payload::ConnectPayload, connect_properties::ConnectProperties},
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const WILL_TOPIC: &str = "desconexion";
+
+    /// Cliente que se conecta con un mensaje de voluntad y luego no envia mas paquetes
+    fn connect_silent_client(address: &str, logger_handler: &LoggerHandler) -> MqttStream {
+        let logger = logger_handler.get_logger();
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &"camssystem".to_string(),
+            &logger,
+        )
+        .unwrap();
+        logger.close();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            connect_flags: flags_handler::add_connect_flag_will_flag(0, "true".to_string())
+                .unwrap(),
+            keep_alive: 60,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: "camssystem".to_string(),
+            will_topic: Some(WILL_TOPIC.to_string()),
+            will_payload: Some(b"camssystem desconectado".to_vec()),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+        stream
+    }
+
+    #[test]
+    fn test_silent_client_is_disconnected_and_its_will_is_sent() {
+        let server_config = server_config("tests/config/keep_alive_server_config.txt");
+
+        // SERVER: Server Keep Alive de 1 segundo
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        // SUBSCRIBER: pide keep alive de 60 segundos, pero usa el del servidor
+        // y envia PINGREQ automaticamente
+        let subscriber_config =
+            client_config("tests/config/monitoring_client_config.txt", &server_config);
+        let subscriber_log_path = subscriber_config.general.log_path.clone();
+        let (mut subscriber, subscriber_logger) = init_client(subscriber_config);
+        let sub_logger = subscriber_logger.get_logger();
+        assert_eq!(subscriber.keep_alive, 1);
+        let listener = subscriber.run_listener(&sub_logger).unwrap();
+        subscriber.subscribe(vec![WILL_TOPIC], &sub_logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // SILENT CLIENT
+        let silent_log_path = config_path("log_keep_alive_silent.tmp");
+        let silent_logger = create_logger_handler(&silent_log_path).unwrap();
+        let mut silent = connect_silent_client(
+            &server_config.get_socket_address().to_string(),
+            &silent_logger,
+        );
+        silent_logger.close();
+
+        let connack = match receive_packet(&mut silent).unwrap() {
+            PacketReceived::Connack(connack) => connack,
+            _ => panic!("Se esperaba un CONNACK"),
+        };
+        assert_eq!(connack.properties.server_keep_alive, Some(1));
+
+        // Luego de 1.5 veces el keep alive sin paquetes, el servidor cierra la conexion
+        let reason_code = match receive_packet(&mut silent).unwrap() {
+            PacketReceived::Disconnect(disconnect) => disconnect.properties.disconnect_reason_code,
+            _ => panic!("Se esperaba un DISCONNECT"),
+        };
+        assert_eq!(reason_code, ReasonCode::KeepAliveTimeout.get_id());
+
+        let will = listener
+            .receiver
+            .recv_timeout(Duration::from_secs(3))
+            .unwrap();
+        assert!(will.is_will_message);
+        assert_eq!(will.topic, WILL_TOPIC);
+        assert_eq!(will.data, b"camssystem desconectado".to_vec());
+
+        // El subscriber sigue conectado gracias a los PINGREQ automaticos
+        thread::sleep(Duration::from_secs(2));
+        let reason_code = subscriber
+            .publish(b"sigo conectado".to_vec(), "drone".to_string(), &sub_logger)
+            .unwrap();
+        assert_eq!(reason_code, ReasonCode::NoMatchingSubscribers);
+
+        subscriber
+            .disconnect(ReasonCode::NormalDisconnection, &sub_logger)
+            .unwrap();
+        sub_logger.close();
+        subscriber_logger.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(subscriber_log_path);
+        let _ = remove_file(silent_log_path);
+    }
+}
@@ -0,0 +1,198 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::{
+            client_connector::connect,
+            mqtt_client::{receive_packet, MqttClient},
+        },
+        common::{
+            mqtt_stream::MqttStream, reason_codes::ReasonCode, scram::SCRAM_SHA_256,
+            websocket::WebSocketTransport,
+        },
+        config::{
+            client_config::ClientConfig,
+            listener_config::{ListenerConfig, ListenerProtocol},
+            mqtt_config::Config,
+            server_config::ServerConfig,
+        },
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::{connect::Connect, publish::Publish},
+            properties::{
+                connect_payload::ConnectPayload, connect_properties::ConnectProperties,
+                publish_properties::PublishProperties,
+            },
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{
+        fs::remove_file,
+        net::{SocketAddr, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    const TOPIC: &str = "inc";
+
+    /// Configuracion de un cliente que se autentica con SCRAM-SHA-256
+    fn scram_config(server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.connect_properties.authentication_method = Some(SCRAM_SHA_256.to_string());
+        config
+    }
+
+    /// Envia un CONNECT anonimo y devuelve el stream con el reason code del CONNACK
+    fn anonymous_connect(mut stream: MqttStream, client_id: &str) -> (MqttStream, u8) {
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: client_id.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(connack) => (stream, connack.properties.connect_reason_code),
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+    }
+
+    fn listener_address(server_config: &ServerConfig, protocol: ListenerProtocol) -> String {
+        server_config
+            .listeners
+            .iter()
+            .find(|listener| listener.protocol == protocol)
+            .unwrap()
+            .address
+            .to_string()
+    }
+
+    #[test]
+    fn test_listener_urls() {
+        let address = "127.0.0.1:1883".parse::<SocketAddr>().unwrap();
+        assert_eq!(
+            ListenerConfig::from_url("tcp://127.0.0.1:1883").unwrap(),
+            ListenerConfig::new(ListenerProtocol::Tcp, address)
+        );
+
+        let listener = ListenerConfig::from_url(
+            "wss://127.0.0.1:1883/live?cert_path=field.pfx&cert_pass=1234&allow_anonymous=false",
+        )
+        .unwrap();
+        assert_eq!(listener.protocol, ListenerProtocol::SecureWebSocket);
+        assert_eq!(listener.websocket_path, "/live");
+        assert_eq!(listener.cert_path.as_deref(), Some("field.pfx"));
+        assert_eq!(listener.cert_pass.as_deref(), Some("1234"));
+        assert_eq!(listener.allow_anonymous, Some(false));
+        assert_eq!(listener.to_string(), "wss://127.0.0.1:1883/live");
+
+        for url in [
+            "127.0.0.1:1883",
+            "udp://127.0.0.1:1883",
+            "tcp://localhost",
+            "tls://127.0.0.1:1883/mqtt",
+            "tcp://127.0.0.1:1883?cert_path=field.pfx",
+            "ws://127.0.0.1:1883?allow_anonymous=si",
+            "ws://127.0.0.1:1883?keep_alive=10",
+        ] {
+            assert!(ListenerConfig::from_url(url).is_err(), "{}", url);
+        }
+
+        // Sin listeners, se escucha con TLS en ip/port
+        let server_config = server_config("tests/config/broker_server_config.txt");
+        assert_eq!(
+            server_config.listeners,
+            vec![ListenerConfig::new(
+                ListenerProtocol::Tls,
+                server_config.get_socket_address()
+            )]
+        );
+    }
+
+    #[test]
+    fn test_listeners_share_the_session_register() {
+        let server_config = server_config("tests/config/listeners_server_config.txt");
+        assert_eq!(server_config.listeners.len(), 3);
+        let tcp_address = listener_address(&server_config, ListenerProtocol::Tcp);
+        let tls_address = listener_address(&server_config, ListenerProtocol::Tls);
+        let websocket_address = listener_address(&server_config, ListenerProtocol::WebSocket);
+
+        // SERVER
+        let (server, result_receiver) = start_server(MqttServer::new(
+            server_config.clone(),
+            vec!["monitoring".to_string()],
+        ));
+
+        let client_log_path = scram_config(&server_config).general.log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // Un usuario autenticado por el listener TLS
+        let mut monitoring = MqttClient::init(scram_config(&server_config)).unwrap();
+        let listener = monitoring.run_listener(&logger).unwrap();
+        monitoring.subscribe(vec![TOPIC], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // El listener TCP local acepta clientes anonimos, que comparten las sesiones
+        let (mut sensor, reason_code) = anonymous_connect(
+            MqttStream::new(TcpStream::connect(&tcp_address).unwrap()).unwrap(),
+            "sensor",
+        );
+        assert_ne!(reason_code, ReasonCode::NotAuthorized.get_id());
+        let properties = PublishProperties {
+            topic_name: TOPIC.to_string(),
+            application_message: b"incidente".to_vec(),
+            ..Default::default()
+        };
+        Publish::new(0, 0, 0, properties).send(&mut sensor).unwrap();
+        let message = listener
+            .receiver
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(message.data, b"incidente".to_vec());
+
+        // Los listeners TLS y WebSocket usan el allow_anonymous del servidor
+        let tls_stream = connect(
+            &tls_address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &60,
+            &"anonymous".to_string(),
+            &logger,
+        )
+        .unwrap();
+        let (_, reason_code) = anonymous_connect(MqttStream::new(tls_stream).unwrap(), "anonymous");
+        assert_eq!(reason_code, ReasonCode::NotAuthorized.get_id());
+
+        let transport = WebSocketTransport::connect(
+            TcpStream::connect(&websocket_address).unwrap(),
+            "localhost",
+            "/incidents",
+        )
+        .unwrap();
+        let (_, reason_code) = anonymous_connect(MqttStream::new(transport).unwrap(), "dashboard");
+        assert_eq!(reason_code, ReasonCode::NotAuthorized.get_id());
+
+        // Al apagarse el servidor se cierran todos los listeners
+        stop_server(server, result_receiver, &server_config);
+        for address in [tcp_address, tls_address, websocket_address] {
+            assert!(TcpStream::connect(&address).is_err(), "{}", address);
+        }
+
+        let _ = sensor.shutdown();
+        let _ = monitoring.disconnect(ReasonCode::NormalDisconnection, &logger);
+        logger.close();
+        logger_handler.close();
+
+        let _ = remove_file(client_log_path);
+    }
+}
@@ -0,0 +1,143 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, server_config, start_server, stop_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{client_listener::MqttClientListener, mqtt_client::MqttClient},
+        common::reason_codes::ReasonCode,
+        config::{client_config::ClientConfig, server_config::ServerConfig},
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const CLIENTS: usize = 8;
+    const MESSAGES_PER_CLIENT: usize = 100;
+
+    /// Plazo para recibir cada mensaje
+    const RECEIVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Configuracion de un cliente que publica y se subscribe con QoS 0
+    fn load_config(id: String, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = id;
+        config.pub_qos = 0;
+        config.sub_max_qos = 0;
+        config
+    }
+
+    /// Publica con QoS 1 hasta que el servidor confirma que hay un suscriptor en el topico,
+    /// y espera ese mensaje en el suscriptor
+    fn wait_subscription(
+        publisher: &mut MqttClient,
+        topic: &str,
+        listener: &MqttClientListener,
+        logger: &Logger,
+    ) {
+        while publisher
+            .publish_with_qos(b"ready".to_vec(), topic.to_string(), 1, logger)
+            .unwrap()
+            != ReasonCode::Success
+        {}
+        let message = listener.receiver.recv_timeout(RECEIVE_TIMEOUT).unwrap();
+        assert_eq!(message.data, b"ready");
+    }
+
+    /// Conecta `clients` pares publicador/suscriptor, cada par con su propio topico,
+    /// publica desde todos los publicadores a la vez y devuelve los mensajes que
+    /// recibio cada suscriptor, en orden de llegada
+    fn publish_concurrently(
+        clients: usize,
+        server_config: &ServerConfig,
+        logger: &Logger,
+    ) -> Vec<Vec<Vec<u8>>> {
+        let mut subscribers = Vec::new();
+        let mut publishers = Vec::new();
+
+        for i in 0..clients {
+            let topic = format!("load/{}", i);
+
+            let mut subscriber =
+                MqttClient::init(load_config(format!("loadsub{}", i), server_config)).unwrap();
+            let listener = subscriber.run_listener(logger).unwrap();
+            subscriber.subscribe(vec![topic.as_str()], logger).unwrap();
+
+            let mut publisher =
+                MqttClient::init(load_config(format!("loadpub{}", i), server_config)).unwrap();
+            wait_subscription(&mut publisher, &topic, &listener, logger);
+
+            subscribers.push((subscriber, listener));
+            publishers.push((publisher, topic));
+        }
+
+        let publisher_handles = publishers
+            .into_iter()
+            .map(|(mut publisher, topic)| {
+                let logger = logger.clone();
+                thread::spawn(move || {
+                    for n in 0..MESSAGES_PER_CLIENT {
+                        publisher
+                            .publish(n.to_string().into_bytes(), topic.clone(), &logger)
+                            .unwrap();
+                    }
+                    publisher
+                        .disconnect(ReasonCode::NormalDisconnection, &logger)
+                        .unwrap();
+                    logger.close();
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let received = subscribers
+            .iter()
+            .map(|(_, listener)| {
+                (0..MESSAGES_PER_CLIENT)
+                    .map(|n| match listener.receiver.recv_timeout(RECEIVE_TIMEOUT) {
+                        Ok(message) => message.data,
+                        Err(e) => panic!("No se recibio el mensaje {}: {}", n, e),
+                    })
+                    .collect::<Vec<Vec<u8>>>()
+            })
+            .collect::<Vec<_>>();
+
+        for handle in publisher_handles {
+            handle.join().unwrap();
+        }
+        for (mut subscriber, _) in subscribers {
+            subscriber
+                .disconnect(ReasonCode::NormalDisconnection, logger)
+                .unwrap();
+        }
+        received
+    }
+
+    #[test]
+    fn test_concurrent_clients_receive_all_messages() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = load_config(String::new(), &server_config).general.log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // Cada conexion se procesa en su propio thread: todos los clientes publicando
+        // a la vez reciben sus mensajes completos y en orden
+        let expected = (0..MESSAGES_PER_CLIENT)
+            .map(|n| n.to_string().into_bytes())
+            .collect::<Vec<Vec<u8>>>();
+        for messages in publish_concurrently(CLIENTS, &server_config, &logger) {
+            assert_eq!(messages, expected);
+        }
+
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::mqtt_client::MqttClient,
+        common::reason_codes::ReasonCode,
+        config::{client_config::ClientConfig, server_config::ServerConfig},
+        server::{mqtt_server::MqttServer, server_acl::ServerAcl},
+    };
+
+    use std::fs::remove_file;
+
+    /// Configuracion de un cliente que presenta la identidad indicada en el handshake TLS
+    fn identity_config(identity_path: Option<&str>, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = "drone1".to_string();
+        config.identity_path = identity_path.map(config_path);
+        config.identity_pass = "1234".to_string();
+        config
+    }
+
+    #[test]
+    fn test_client_certificate_is_required_and_identifies_the_user() {
+        let server_config = server_config("tests/config/mtls_server_config.txt");
+        let acl =
+            ServerAcl::from_file(&config_path(&server_config.acl_path.clone().unwrap())).unwrap();
+
+        // SERVER: no acepta clientes anonimos, pero si clientes con certificado
+        let (server, result_receiver) =
+            start_server(MqttServer::new(server_config.clone(), vec![]).with_acl(acl));
+
+        // Sin certificado, o con uno que no firmó la CA configurada, no hay conexion
+        assert!(MqttClient::init(identity_config(None, &server_config)).is_err());
+        assert!(MqttClient::init(identity_config(
+            Some("tests/certificates/untrusted_client.pfx"),
+            &server_config
+        ))
+        .is_err());
+
+        // El sujeto del certificado (CN=drone1) es el usuario de la conexion para la ACL
+        let config = identity_config(Some("tests/certificates/client.pfx"), &server_config);
+        let client_log_path = config.general.log_path.clone();
+        let mut drone1 = MqttClient::init(config).unwrap();
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        let mut publish = |topic: &str| {
+            drone1
+                .publish(b"estado".to_vec(), topic.to_string(), &logger)
+                .unwrap()
+        };
+        assert_eq!(publish("drone/drone1"), ReasonCode::NoMatchingSubscribers);
+        assert_eq!(publish("drone/drone2"), ReasonCode::NotAuthorized);
+
+        drone1
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
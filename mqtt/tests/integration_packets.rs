@@ -160,10 +160,10 @@ mod test {
             topic_alias: Some(0),
             response_topic: Some("response".to_string()),
             correlation_data: Some(correlation_data),
-            user_property: Some((
+            user_properties: vec![(
                 sender.clone(),
                 "pass".to_string() + &packet_identifier.to_string(),
-            )),
+            )],
             subscription_identifier: Some(0),
             content_type: Some("type".to_string()),
             application_message,
@@ -178,10 +178,10 @@ mod test {
             packet_id: *packet_identifier,
             puback_reason_code: ReasonCode::Success.get_id(),
             reason_string: Some("received".to_string()),
-            user_property: Some((
+            user_properties: vec![(
                 sender,
                 "pass".to_string() + &(*packet_identifier - 1).to_string(),
-            )),
+            )],
         };
 
         let puback = Puback::new(properties);
@@ -227,7 +227,7 @@ mod test {
             request_response_information: Some(1),
             receive_maximum: Some(0),
             topic_alias_maximum: Some(15),
-            user_property: Some(("key".to_string(), "value".to_string())),
+            user_properties: vec![("key".to_string(), "value".to_string())],
             maximum_packet_size: Some(20),
         };
 
@@ -239,7 +239,7 @@ mod test {
             content_type: Some("type".to_string()),
             response_topic: Some("topic".to_string()),
             correlation_data: Some(correlation_data),
-            user_property: Some(("super key".to_string(), "super value".to_string())),
+            user_properties: vec![("super key".to_string(), "super value".to_string())],
 
             will_topic: Some("topic".to_string()),
             will_payload: Some(will_message),
@@ -276,7 +276,7 @@ mod test {
             wildcard_subscription_available: Some(1),
             subscription_identifiers_available: Some(1),
             shared_subscription_available: Some(1),
-            user_property: Some(("user key".to_string(), "user value".to_string())),
+            user_properties: vec![("user key".to_string(), "user value".to_string())],
             maximum_packet_size: Some(100),
         };
 
@@ -288,7 +288,7 @@ mod test {
         let mut properties = SubscribeProperties {
             packet_identifier,
             subscription_identifier: Some(10),
-            user_property: Some(("hey".to_string(), "hoy".to_string())),
+            user_properties: vec![("hey".to_string(), "hoy".to_string())],
             ..Default::default()
         };
 
@@ -303,7 +303,7 @@ mod test {
         let properties = SubackProperties {
             packet_identifier,
             reason_string: Some("reason".to_string()),
-            user_property: Some(("hey".to_string(), "hay".to_string())),
+            user_properties: vec![("hey".to_string(), "hay".to_string())],
 
             reason_codes: vec![
                 ReasonCode::GrantedQoS0.get_id(),
@@ -364,7 +364,7 @@ mod test {
             authentication_method: Some("password".to_string()),
             authentication_data: Some(authentication_data),
             reason_string: Some("reason string".to_string()),
-            user_property: Some(("final key".to_string(), "final value".to_string())),
+            user_properties: vec![("final key".to_string(), "final value".to_string())],
         };
 
         let auth = Auth::new(properties);
@@ -374,7 +374,7 @@ mod test {
 
         let properties = UnsubscribeProperties {
             packet_identifier,
-            user_property: Some(("buenas".to_string(), "noches".to_string())),
+            user_properties: vec![("buenas".to_string(), "noches".to_string())],
             topic_filters: vec!["mensajes".to_string()],
         };
 
@@ -387,7 +387,7 @@ mod test {
         let properties = UnsubackProperties {
             packet_identifier,
             reason_string: Some("success".to_string()),
-            user_property: Some(("buenas".to_string(), "noches".to_string())),
+            user_properties: vec![("buenas".to_string(), "noches".to_string())],
             reason_codes: vec![
                 ReasonCode::Success.get_id(),
                 ReasonCode::NoSubscriptionExisted.get_id(),
@@ -405,7 +405,7 @@ mod test {
             disconnect_reason_code: ReasonCode::NormalDisconnection.get_id(),
             session_expiry_interval: Some(3000),
             reason_string: Some("its joever".to_string()),
-            user_property: Some(("bye".to_string(), "bye".to_string())),
+            user_properties: vec![("bye".to_string(), "bye".to_string())],
             server_reference: Some("server ref".to_string()),
         };
 
@@ -465,8 +465,8 @@ mod test {
                 assert_eq!(connect.properties.receive_maximum, Some(0));
                 assert_eq!(connect.properties.topic_alias_maximum, Some(15));
                 assert_eq!(
-                    connect.properties.user_property,
-                    Some(("key".to_string(), "value".to_string()))
+                    connect.properties.user_properties,
+                    vec![("key".to_string(), "value".to_string())]
                 );
 
                 assert_eq!(connect.payload.client_id, "Esther".to_string());
@@ -480,8 +480,8 @@ mod test {
                 assert_eq!(correlation_data.name, "corr".to_string());
                 assert_eq!(correlation_data.code, 4321);
                 assert_eq!(
-                    connect.payload.user_property,
-                    Some(("super key".to_string(), "super value".to_string()))
+                    connect.payload.user_properties,
+                    vec![("super key".to_string(), "super value".to_string())]
                 );
                 assert_eq!(connect.payload.will_topic, Some("topic".to_string()));
                 let will_message = deserialize_message(connect.payload.will_payload.unwrap());
@@ -529,8 +529,8 @@ mod test {
                 );
                 assert_eq!(connack.properties.shared_subscription_available, Some(1));
                 assert_eq!(
-                    connack.properties.user_property,
-                    Some(("user key".to_string(), "user value".to_string()))
+                    connack.properties.user_properties,
+                    vec![("user key".to_string(), "user value".to_string())]
                 );
                 assert_eq!(connack.properties.maximum_packet_size, Some(100));
             }
@@ -538,8 +538,8 @@ mod test {
                 assert_eq!(subscribe.properties.packet_identifier, 1);
                 assert_eq!(subscribe.properties.subscription_identifier, Some(10));
                 assert_eq!(
-                    subscribe.properties.user_property,
-                    Some(("hey".to_string(), "hoy".to_string()))
+                    subscribe.properties.user_properties,
+                    vec![("hey".to_string(), "hoy".to_string())]
                 );
 
                 assert_eq!(subscribe.properties.topic_filters.len(), 1);
@@ -570,8 +570,8 @@ mod test {
                 assert_eq!(suback.properties.packet_identifier, 2);
                 assert_eq!(suback.properties.reason_string, Some("reason".to_string()));
                 assert_eq!(
-                    suback.properties.user_property,
-                    Some(("hey".to_string(), "hay".to_string()))
+                    suback.properties.user_properties,
+                    vec![("hey".to_string(), "hay".to_string())]
                 );
 
                 assert_eq!(suback.properties.reason_codes.len(), 6);
@@ -615,8 +615,8 @@ mod test {
                     assert_eq!(correlation_data.name, "correlat".to_string());
                     assert_eq!(correlation_data.code, 5000 + 3);
                     assert_eq!(
-                        publish.properties.user_property,
-                        Some(("nicolas".to_string(), "pass3".to_string()))
+                        publish.properties.user_properties,
+                        vec![("nicolas".to_string(), "pass3".to_string())]
                     );
                     assert_eq!(publish.properties.subscription_identifier, Some(0));
                     assert_eq!(publish.properties.content_type, Some("type".to_string()));
@@ -638,8 +638,8 @@ mod test {
                     assert_eq!(correlation_data.name, "correlat".to_string());
                     assert_eq!(correlation_data.code, 5000 + 5);
                     assert_eq!(
-                        publish.properties.user_property,
-                        Some(("marian".to_string(), "pass5".to_string()))
+                        publish.properties.user_properties,
+                        vec![("marian".to_string(), "pass5".to_string())]
                     );
                     assert_eq!(publish.properties.subscription_identifier, Some(0));
                     assert_eq!(publish.properties.content_type, Some("type".to_string()));
@@ -661,8 +661,8 @@ mod test {
                     assert_eq!(correlation_data.name, "correlat".to_string());
                     assert_eq!(correlation_data.code, 5000 + 7);
                     assert_eq!(
-                        publish.properties.user_property,
-                        Some(("ghjk".to_string(), "pass7".to_string()))
+                        publish.properties.user_properties,
+                        vec![("ghjk".to_string(), "pass7".to_string())]
                     );
                     assert_eq!(publish.properties.subscription_identifier, Some(0));
                     assert_eq!(publish.properties.content_type, Some("type".to_string()));
@@ -684,8 +684,8 @@ mod test {
                         Some("received".to_string())
                     );
                     assert_eq!(
-                        puback.properties.user_property,
-                        Some(("nicolas".to_string(), "pass3".to_string()))
+                        puback.properties.user_properties,
+                        vec![("nicolas".to_string(), "pass3".to_string())]
                     );
                 } else if puback.properties.packet_id == 6 {
                     assert_eq!(puback.properties.packet_id, 6);
@@ -698,8 +698,8 @@ mod test {
                         Some("received".to_string())
                     );
                     assert_eq!(
-                        puback.properties.user_property,
-                        Some(("marian".to_string(), "pass5".to_string()))
+                        puback.properties.user_properties,
+                        vec![("marian".to_string(), "pass5".to_string())]
                     );
                 } else if puback.properties.packet_id == 8 {
                     assert_eq!(puback.properties.packet_id, 8);
@@ -712,8 +712,8 @@ mod test {
                         Some("received".to_string())
                     );
                     assert_eq!(
-                        puback.properties.user_property,
-                        Some(("ghjk".to_string(), "pass7".to_string()))
+                        puback.properties.user_properties,
+                        vec![("ghjk".to_string(), "pass7".to_string())]
                     );
                 }
             }
@@ -735,15 +735,15 @@ mod test {
                     Some("reason string".to_string())
                 );
                 assert_eq!(
-                    auth.properties.user_property,
-                    Some(("final key".to_string(), "final value".to_string()))
+                    auth.properties.user_properties,
+                    vec![("final key".to_string(), "final value".to_string())]
                 );
             }
             PacketReceived::Unsubscribe(unsubscribe) => {
                 assert_eq!(unsubscribe.properties.packet_identifier, 9);
                 assert_eq!(
-                    unsubscribe.properties.user_property,
-                    Some(("buenas".to_string(), "noches".to_string()))
+                    unsubscribe.properties.user_properties,
+                    vec![("buenas".to_string(), "noches".to_string())]
                 );
                 assert_eq!(unsubscribe.properties.topic_filters.len(), 1);
                 assert_eq!(unsubscribe.properties.topic_filters[0], "mensajes");
@@ -755,8 +755,8 @@ mod test {
                     Some("success".to_string())
                 );
                 assert_eq!(
-                    unsuback.properties.user_property,
-                    Some(("buenas".to_string(), "noches".to_string()))
+                    unsuback.properties.user_properties,
+                    vec![("buenas".to_string(), "noches".to_string())]
                 );
                 assert_eq!(unsuback.properties.reason_codes.len(), 3);
                 assert_eq!(
@@ -784,8 +784,8 @@ mod test {
                     Some("its joever".to_string())
                 );
                 assert_eq!(
-                    disconnect.properties.user_property,
-                    Some(("bye".to_string(), "bye".to_string()))
+                    disconnect.properties.user_properties,
+                    vec![("bye".to_string(), "bye".to_string())]
                 );
                 assert_eq!(
                     disconnect.properties.server_reference,
@@ -0,0 +1,107 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::mqtt_client::MqttClient,
+        common::reason_codes::ReasonCode,
+        config::{client_config::ClientConfig, server_config::ServerConfig},
+        mqtt_packets::properties::publish_properties::PublishProperties,
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const TOPIC: &str = "inc";
+
+    fn monitoring_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config
+    }
+
+    #[test]
+    fn test_publish_properties_round_trip() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = monitoring_config("monitoring", &server_config)
+            .general
+            .log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        let mut monitoring =
+            MqttClient::init(monitoring_config("monitoring", &server_config)).unwrap();
+        let monitoring_listener = monitoring.run_listener(&logger).unwrap();
+        monitoring
+            .subscribe_with_identifier(vec![TOPIC], 42, &logger)
+            .unwrap();
+
+        let mut drone = MqttClient::init(monitoring_config("drone", &server_config)).unwrap();
+        let drone_listener = drone.run_listener(&logger).unwrap();
+        drone.subscribe(vec![TOPIC], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        let mut camssystem =
+            MqttClient::init(monitoring_config("camssystem", &server_config)).unwrap();
+        let user_properties = vec![
+            ("schema".to_string(), "2".to_string()),
+            ("trace".to_string(), "abc-123".to_string()),
+            ("trace".to_string(), "def-456".to_string()),
+        ];
+        let properties = PublishProperties {
+            topic_name: TOPIC.to_string(),
+            application_message: b"inc 1".to_vec(),
+            user_properties: user_properties.clone(),
+            content_type: Some("application/json".to_string()),
+            response_topic: Some("inc/respuesta".to_string()),
+            correlation_data: Some(vec![1, 2, 3]),
+            message_expiry_interval: Some(60),
+            // El cliente no envia el Subscription Identifier
+            subscription_identifier: Some(7),
+            ..Default::default()
+        };
+        let reason_code = camssystem
+            .publish_with_properties(properties, 1, &logger)
+            .unwrap();
+        assert_eq!(reason_code, ReasonCode::Success);
+
+        let message = monitoring_listener
+            .receiver
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(message.topic, TOPIC);
+        assert_eq!(message.data, b"inc 1".to_vec());
+        assert_eq!(message.user_properties, user_properties);
+        assert_eq!(message.content_type, Some("application/json".to_string()));
+        assert_eq!(message.response_topic, Some("inc/respuesta".to_string()));
+        assert_eq!(message.correlation_data, Some(vec![1, 2, 3]));
+        assert_eq!(message.message_expiry_interval, Some(60));
+        assert_eq!(message.subscription_identifier, Some(42));
+
+        // Cada suscriptor recibe el Subscription Identifier de su propia subscripción
+        let message = drone_listener
+            .receiver
+            .recv_timeout(Duration::from_secs(2))
+            .unwrap();
+        assert_eq!(message.user_properties, user_properties);
+        assert_eq!(message.subscription_identifier, None);
+
+        for client in [&mut monitoring, &mut drone, &mut camssystem] {
+            client
+                .disconnect(ReasonCode::NormalDisconnection, &logger)
+                .unwrap();
+        }
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
@@ -0,0 +1,155 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{
+        client_config, config_path, init_client, server_config, start_server, stop_server,
+    };
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::mqtt_client::{receive_packet, MqttClient},
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::connack::Connack,
+            properties::connack_properties::ConnackProperties,
+        },
+        server::{
+            mqtt_server::MqttServer, server_connector::TlsServerConnector,
+            server_register::SessionRegister,
+        },
+    };
+
+    use std::{fs::remove_file, io::ErrorKind, sync::mpsc::channel, thread, time::Duration};
+
+    const PUBLISHER_CONFIG: &str = "tests/config/publisher_client_config.txt";
+    const SUBSCRIBER_CONFIG: &str = "tests/config/persistent_client_config.txt";
+
+    #[test]
+    fn test_qos1_publish_is_acknowledged_and_redelivered() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        // PUBLISHER: sin listener, publish lee la confirmacion directamente
+        let publisher_config = client_config(PUBLISHER_CONFIG, &server_config);
+        let publisher_log_path = publisher_config.general.log_path.clone();
+        let (mut publisher, publisher_logger) = init_client(publisher_config);
+        let pub_logger = publisher_logger.get_logger();
+
+        let reason_code = publisher
+            .publish(b"drone 1".to_vec(), "drone".to_string(), &pub_logger)
+            .unwrap();
+        assert_eq!(reason_code, ReasonCode::NoMatchingSubscribers);
+
+        // SUBSCRIBER: con sesion persistente y sin listener, por lo que no
+        // confirma las publicaciones recibidas antes de desconectarse
+        let subscriber_config = client_config(SUBSCRIBER_CONFIG, &server_config);
+        let subscriber_log_path = subscriber_config.general.log_path.clone();
+        let (mut subscriber, subscriber_logger) = init_client(subscriber_config);
+        let sub_logger = subscriber_logger.get_logger();
+        subscriber.subscribe(vec!["drone"], &sub_logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        let reason_code = publisher
+            .publish(b"drone 2".to_vec(), "drone".to_string(), &pub_logger)
+            .unwrap();
+        assert_eq!(reason_code, ReasonCode::Success);
+
+        // Lo confirmado ya esta persistido: sobrevive a una caida del servidor
+        let persisted = SessionRegister::new(server_config.db_path.clone());
+        assert_eq!(persisted.get_subscribers("drone").len(), 1);
+        assert_eq!(persisted.get_inflight_messages("monitoring").len(), 1);
+        thread::sleep(Duration::from_millis(300));
+
+        subscriber
+            .disconnect(ReasonCode::NormalDisconnection, &sub_logger)
+            .unwrap();
+        sub_logger.close();
+        subscriber_logger.close();
+        thread::sleep(Duration::from_millis(300));
+
+        // Al reconectarse, el servidor reenvia la publicacion sin confirmar
+        let (mut subscriber, subscriber_logger) =
+            init_client(client_config(SUBSCRIBER_CONFIG, &server_config));
+        let sub_logger = subscriber_logger.get_logger();
+        let listener = subscriber.run_listener(&sub_logger).unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(message) = listener.receiver.recv_timeout(Duration::from_secs(2)) {
+            received.push(message.data);
+        }
+
+        publisher
+            .disconnect(ReasonCode::NormalDisconnection, &pub_logger)
+            .unwrap();
+        pub_logger.close();
+        publisher_logger.close();
+        subscriber
+            .disconnect(ReasonCode::NormalDisconnection, &sub_logger)
+            .unwrap();
+        sub_logger.close();
+        subscriber_logger.close();
+
+        assert_eq!(received, vec![b"drone 2".to_vec()]);
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(publisher_log_path);
+        let _ = remove_file(subscriber_log_path);
+    }
+
+    #[test]
+    fn test_qos1_publish_without_listener_times_out() {
+        // Servidor que acepta la conexion pero nunca confirma las publicaciones
+        let connector = TlsServerConnector::initialize(
+            &config_path("tests/certificates/identity.pfx"),
+            "1234",
+            None,
+            "127.0.0.1:6131",
+        )
+        .unwrap();
+        thread::spawn(move || {
+            let (tcp_stream, _) = connector.get_listener().unwrap().accept().unwrap();
+            let (tls_stream, _) = connector.accept_tls_connection(tcp_stream).unwrap();
+            let mut stream = MqttStream::new(tls_stream).unwrap();
+            assert!(matches!(
+                receive_packet(&mut stream).unwrap(),
+                PacketReceived::Connect(_)
+            ));
+            Connack::new(ConnackProperties::default())
+                .send(&mut stream)
+                .unwrap();
+            while receive_packet(&mut stream).is_ok() {}
+        });
+
+        let mut config = client_config(
+            PUBLISHER_CONFIG,
+            &server_config("tests/config/broker_server_config.txt"),
+        );
+        config.general.port = 6131;
+        config.general.log_path = config_path("log_qos1_unacknowledged.tmp");
+        let log_path = config.general.log_path.clone();
+        let logger_handler = create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        let mut publisher = MqttClient::init(config).unwrap();
+
+        // Sin listener, la publicacion lee los paquetes hasta que vence el plazo
+        let (sender, receiver) = channel();
+        let publisher_logger = logger.clone();
+        thread::spawn(move || {
+            let result =
+                publisher.publish(b"drone 1".to_vec(), "drone".to_string(), &publisher_logger);
+            let _ = sender.send(result.map_err(|e| e.kind()));
+        });
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(30)).unwrap(),
+            Err(ErrorKind::TimedOut)
+        );
+
+        logger.close();
+        logger_handler.close();
+        let _ = remove_file(log_path);
+    }
+}
@@ -0,0 +1,113 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, init_client, server_config, start_server, stop_server};
+    use mqtt::{
+        common::reason_codes::ReasonCode,
+        mqtt_packets::{
+            packet::generic_packet::Serialization,
+            packets::{publish::Publish, pubrel::Pubrel},
+            properties::{
+                publish_properties::PublishProperties, pubrel_properties::PubrelProperties,
+            },
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    fn incident(packet_identifier: u16, dup_flag: u8, payload: &[u8]) -> Publish {
+        let properties = PublishProperties {
+            topic_name: "inc".to_string(),
+            packet_identifier,
+            payload_format_indicator: Some(1),
+            application_message: payload.to_vec(),
+            ..Default::default()
+        };
+        Publish::new(dup_flag, 2, 0, properties)
+    }
+
+    #[test]
+    fn test_qos2_incidents_are_not_duplicated() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        // SUBSCRIBER: recibe los incidentes con QoS 2
+        let mut subscriber_config =
+            client_config("tests/config/monitoring_client_config.txt", &server_config);
+        subscriber_config.sub_max_qos = 2;
+        let subscriber_log_path = subscriber_config.general.log_path.clone();
+        let (mut subscriber, subscriber_logger) = init_client(subscriber_config);
+        let sub_logger = subscriber_logger.get_logger();
+        let listener = subscriber.run_listener(&sub_logger).unwrap();
+        subscriber.subscribe(vec!["inc"], &sub_logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // PUBLISHER: sin listener, por lo que no responde los PUBREC y
+        // puede reenviar la publicacion como lo haria tras perder la conexion
+        let publisher_config =
+            client_config("tests/config/publisher_client_config.txt", &server_config);
+        let publisher_log_path = publisher_config.general.log_path.clone();
+        let (mut publisher, publisher_logger) = init_client(publisher_config);
+        let pub_logger = publisher_logger.get_logger();
+
+        incident(100, 0, b"incidente 1")
+            .send(&mut publisher.stream)
+            .unwrap();
+        incident(100, 1, b"incidente 1")
+            .send(&mut publisher.stream)
+            .unwrap();
+        incident(100, 1, b"incidente 1")
+            .send(&mut publisher.stream)
+            .unwrap();
+
+        // Luego del PUBREL, el packet identifier puede reutilizarse para un nuevo incidente
+        Pubrel::new(PubrelProperties {
+            packet_id: 100,
+            ..Default::default()
+        })
+        .send(&mut publisher.stream)
+        .unwrap();
+        incident(100, 0, b"incidente 2")
+            .send(&mut publisher.stream)
+            .unwrap();
+
+        // Flujo completo a traves del cliente
+        publisher
+            .publish_with_qos(b"incidente 3".to_vec(), "inc".to_string(), 2, &pub_logger)
+            .unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(message) = listener.receiver.recv_timeout(Duration::from_secs(2)) {
+            received.push(message.data);
+        }
+
+        publisher
+            .disconnect(ReasonCode::NormalDisconnection, &pub_logger)
+            .unwrap();
+        pub_logger.close();
+        publisher_logger.close();
+        subscriber
+            .disconnect(ReasonCode::NormalDisconnection, &sub_logger)
+            .unwrap();
+        sub_logger.close();
+        subscriber_logger.close();
+
+        assert_eq!(
+            received,
+            vec![
+                b"incidente 1".to_vec(),
+                b"incidente 2".to_vec(),
+                b"incidente 3".to_vec()
+            ]
+        );
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(publisher_log_path);
+        let _ = remove_file(subscriber_log_path);
+    }
+}
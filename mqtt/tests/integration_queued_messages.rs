@@ -0,0 +1,118 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, server_config, start_server, stop_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{client_message::MqttClientMessage, mqtt_client::MqttClient},
+        common::reason_codes::ReasonCode,
+        config::{client_config::ClientConfig, server_config::ServerConfig},
+        mqtt_packets::properties::publish_properties::PublishProperties,
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const TOPIC: &str = "inc";
+
+    /// Configuracion de un cliente que conserva su sesión al desconectarse
+    fn persistent_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/persistent_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config
+    }
+
+    fn publish(
+        client: &mut MqttClient,
+        data: &str,
+        message_expiry_interval: Option<u32>,
+        logger: &Logger,
+    ) {
+        let properties = PublishProperties {
+            topic_name: TOPIC.to_string(),
+            application_message: data.as_bytes().to_vec(),
+            message_expiry_interval,
+            ..Default::default()
+        };
+        client
+            .publish_with_properties(properties, 1, logger)
+            .unwrap();
+    }
+
+    /// Reconecta al suscriptor y devuelve los mensajes que tenia en cola
+    fn receive_queued_messages(
+        server_config: &ServerConfig,
+        logger: &Logger,
+    ) -> Vec<MqttClientMessage> {
+        let mut monitoring =
+            MqttClient::init(persistent_config("monitoring", server_config)).unwrap();
+        let listener = monitoring.run_listener(logger).unwrap();
+        let mut messages = Vec::new();
+        while let Ok(message) = listener.receiver.recv_timeout(Duration::from_millis(800)) {
+            messages.push(message);
+        }
+        monitoring
+            .disconnect(ReasonCode::NormalDisconnection, logger)
+            .unwrap();
+        thread::sleep(Duration::from_millis(300));
+        messages
+    }
+
+    #[test]
+    fn test_queued_messages_expiry_and_limit() {
+        let server_config = server_config("tests/config/queue_server_config.txt");
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = persistent_config("monitoring", &server_config)
+            .general
+            .log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // El suscriptor se desconecta y conserva su sesión
+        let mut monitoring =
+            MqttClient::init(persistent_config("monitoring", &server_config)).unwrap();
+        let _listener = monitoring.run_listener(&logger).unwrap();
+        monitoring.subscribe(vec![TOPIC], &logger).unwrap();
+        monitoring
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // Los mensajes vencidos se descartan y los demas llegan con su tiempo de vida restante
+        let mut camssystem =
+            MqttClient::init(persistent_config("camssystem", &server_config)).unwrap();
+        publish(&mut camssystem, "vencido", Some(1), &logger);
+        publish(&mut camssystem, "vigente", Some(60), &logger);
+        thread::sleep(Duration::from_millis(2500));
+
+        let messages = receive_queued_messages(&server_config, &logger);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].data, b"vigente".to_vec());
+        let remaining = messages[0].message_expiry_interval.unwrap();
+        assert!((55..60).contains(&remaining));
+
+        // Con la cola llena se descarta el mensaje mas antiguo
+        for data in ["inc 1", "inc 2", "inc 3"] {
+            publish(&mut camssystem, data, None, &logger);
+        }
+        thread::sleep(Duration::from_millis(300));
+
+        let messages = receive_queued_messages(&server_config, &logger);
+        let data: Vec<Vec<u8>> = messages.into_iter().map(|message| message.data).collect();
+        assert_eq!(data, vec![b"inc 2".to_vec(), b"inc 3".to_vec()]);
+
+        camssystem
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
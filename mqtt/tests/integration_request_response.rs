@@ -0,0 +1,115 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::mqtt_client::MqttClient,
+        common::reason_codes::ReasonCode,
+        config::{client_config::ClientConfig, server_config::ServerConfig},
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, io::ErrorKind, thread, time::Duration};
+
+    const STATUS_TOPIC: &str = "drone/status";
+
+    /// Configuracion de un cliente que pide la Response Information al conectarse
+    fn requester_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config.connect_properties.request_response_information = Some(1);
+        config
+    }
+
+    #[test]
+    fn test_request_response() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = requester_config("monitoring", &server_config)
+            .general
+            .log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // El dron responde las solicitudes de estado
+        let mut drone = MqttClient::init(requester_config("drone", &server_config)).unwrap();
+        let drone_listener = drone.run_listener(&logger).unwrap();
+        drone.subscribe(vec![STATUS_TOPIC], &logger).unwrap();
+        let mut responder = drone.clone();
+        let responder_logger = logger.clone();
+        thread::spawn(move || {
+            while let Ok(request) = drone_listener.receiver.recv() {
+                responder
+                    .respond(
+                        &request,
+                        |request| [b"drone 1: ".to_vec(), request.data.clone()].concat(),
+                        &responder_logger,
+                    )
+                    .unwrap();
+            }
+        });
+        thread::sleep(Duration::from_millis(300));
+
+        // El servidor informa la Response Information pedida en el CONNECT
+        let mut monitoring =
+            MqttClient::init(requester_config("monitoring", &server_config)).unwrap();
+        assert_eq!(monitoring.requests.response_topic(), "response/monitoring");
+        let monitoring_listener = monitoring.run_listener(&logger).unwrap();
+
+        let response = monitoring
+            .request(
+                STATUS_TOPIC.to_string(),
+                b"ok?".to_vec(),
+                Duration::from_secs(2),
+                &logger,
+            )
+            .unwrap();
+        assert_eq!(response.topic, "response/monitoring");
+        assert_eq!(response.data, b"drone 1: ok?".to_vec());
+
+        // Cada respuesta corresponde a su solicitud
+        let response = monitoring
+            .request(
+                STATUS_TOPIC.to_string(),
+                b"bateria?".to_vec(),
+                Duration::from_secs(2),
+                &logger,
+            )
+            .unwrap();
+        assert_eq!(response.data, b"drone 1: bateria?".to_vec());
+
+        // Las respuestas no llegan al receptor del listener
+        assert!(monitoring_listener
+            .receiver
+            .recv_timeout(Duration::from_millis(300))
+            .is_err());
+
+        // Sin nadie que responda, la solicitud expira
+        let error = monitoring
+            .request(
+                "cams/list".to_string(),
+                Vec::new(),
+                Duration::from_millis(500),
+                &logger,
+            )
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+
+        for client in [&mut monitoring, &mut drone] {
+            client
+                .disconnect(ReasonCode::NormalDisconnection, &logger)
+                .unwrap();
+        }
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
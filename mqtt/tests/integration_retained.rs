@@ -0,0 +1,71 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, init_client, server_config, start_server, stop_server};
+    use mqtt::{common::reason_codes::ReasonCode, server::mqtt_server::MqttServer};
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    #[test]
+    fn test_late_subscriber_receives_retained_message() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        // PUBLISHER: publica el estado de las camaras con RETAIN y se desconecta
+        let mut publisher_config =
+            client_config("tests/config/publisher_client_config.txt", &server_config);
+        publisher_config.pub_retain = 1;
+        let publisher_log_path = publisher_config.general.log_path.clone();
+        let (mut publisher, publisher_logger) = init_client(publisher_config);
+        let logger = publisher_logger.get_logger();
+        publisher
+            .publish(b"cam 1 activa".to_vec(), "camaras/1".to_string(), &logger)
+            .unwrap();
+        publisher
+            .publish(b"cam 2 activa".to_vec(), "camaras/2".to_string(), &logger)
+            .unwrap();
+        // Un payload vacio elimina el mensaje retenido
+        publisher
+            .publish(Vec::new(), "camaras/2".to_string(), &logger)
+            .unwrap();
+        thread::sleep(Duration::from_millis(300));
+        publisher
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        logger.close();
+        publisher_logger.close();
+
+        // SUBSCRIBER: se conecta despues y recibe el ultimo estado retenido
+        let subscriber_config =
+            client_config("tests/config/monitoring_client_config.txt", &server_config);
+        let subscriber_log_path = subscriber_config.general.log_path.clone();
+        let (mut subscriber, subscriber_logger) = init_client(subscriber_config);
+        let logger = subscriber_logger.get_logger();
+        let listener = subscriber.run_listener(&logger).unwrap();
+        subscriber.subscribe(vec!["camaras/+"], &logger).unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(message) = listener.receiver.recv_timeout(Duration::from_secs(2)) {
+            received.push((message.topic, message.data));
+        }
+
+        subscriber
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        logger.close();
+        subscriber_logger.close();
+
+        assert_eq!(
+            received,
+            vec![("camaras/1".to_string(), b"cam 1 activa".to_vec())]
+        );
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(publisher_log_path);
+        let _ = remove_file(subscriber_log_path);
+    }
+}
@@ -0,0 +1,181 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{
+        client_config, config_path, init_client, server_config, start_server, stop_server,
+    };
+    use logger::logger_handler::{create_logger_handler, LoggerHandler};
+    use mqtt::{
+        client::{
+            client_connector::connect,
+            mqtt_client::{receive_packet, MqttClient},
+        },
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::{mqtt_config::Config, server_config::ServerConfig},
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::{connect::Connect, disconnect::Disconnect},
+            properties::{
+                connect_payload::ConnectPayload, connect_properties::ConnectProperties,
+                disconnect_properties::DisconnectProperties,
+            },
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const WILL_TOPIC: &str = "desconexion";
+    const EXPIRING_CLIENT: &str = "expiring";
+    const WILL_CLIENT_CONFIG: &str = "tests/config/session_expiry_client_config.txt";
+
+    /// Cliente con un mensaje de voluntad demorado 2 segundos y una sesión de 10 segundos
+    fn init_will_client(server_config: &ServerConfig) -> (MqttClient, LoggerHandler) {
+        let mut config = client_config(WILL_CLIENT_CONFIG, server_config);
+        config.set_will_message(WILL_TOPIC.to_string(), b"camssystem desconectado".to_vec());
+        init_client(config)
+    }
+
+    /// Conecta un cliente con Clean Start 0 y devuelve el flag Session Present del CONNACK
+    fn connect_expiring_client(address: &str, session_expiry_interval: u32) -> (MqttStream, u8) {
+        let log_path = config_path("log_session_expiry_raw.tmp");
+        let logger_handler = create_logger_handler(&log_path).unwrap();
+        let logger = logger_handler.get_logger();
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &EXPIRING_CLIENT.to_string(),
+            &logger,
+        )
+        .unwrap();
+        logger.close();
+        logger_handler.close();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            session_expiry_interval: Some(session_expiry_interval),
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: EXPIRING_CLIENT.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(connack) => {
+                let session_present = connack.properties.connect_acknowledge_flags;
+                (stream, session_present)
+            }
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+    }
+
+    fn disconnect_expiring_client(mut stream: MqttStream) {
+        Disconnect::new(DisconnectProperties {
+            id: EXPIRING_CLIENT.to_string(),
+            disconnect_reason_code: ReasonCode::NormalDisconnection.get_id(),
+            ..Default::default()
+        })
+        .send(&mut stream)
+        .unwrap();
+        let _ = stream.shutdown();
+        thread::sleep(Duration::from_millis(300));
+    }
+
+    #[test]
+    fn test_will_delay_and_session_expiry() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        // SUBSCRIBER
+        let sub_config = client_config("tests/config/monitoring_client_config.txt", &server_config);
+        let sub_log_path = sub_config.general.log_path.clone();
+        let (mut subscriber, subscriber_logger) = init_client(sub_config);
+        let sub_logger = subscriber_logger.get_logger();
+        let listener = subscriber.run_listener(&sub_logger).unwrap();
+        subscriber.subscribe(vec![WILL_TOPIC], &sub_logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // DISCONNECT 0x04: el mensaje de voluntad se publica luego de su Will Delay Interval
+        let (mut will_client, will_logger_handler) = init_will_client(&server_config);
+        let will_logger = will_logger_handler.get_logger();
+        will_client
+            .disconnect(ReasonCode::DisconnectWithWillMessage, &will_logger)
+            .unwrap();
+        assert!(listener
+            .receiver
+            .recv_timeout(Duration::from_millis(800))
+            .is_err());
+        let will = listener
+            .receiver
+            .recv_timeout(Duration::from_secs(4))
+            .unwrap();
+        assert!(will.is_will_message);
+        assert_eq!(will.data, b"camssystem desconectado".to_vec());
+        will_logger.close();
+        will_logger_handler.close();
+
+        // Al reconectarse antes del Will Delay Interval, el mensaje de voluntad se cancela,
+        // y un DISCONNECT 0x00 descarta el de la nueva conexión
+        let (mut will_client, will_logger_handler) = init_will_client(&server_config);
+        let will_logger = will_logger_handler.get_logger();
+        will_client
+            .disconnect(ReasonCode::DisconnectWithWillMessage, &will_logger)
+            .unwrap();
+        thread::sleep(Duration::from_millis(300));
+        will_logger.close();
+        will_logger_handler.close();
+
+        let (mut will_client, will_logger_handler) = init_will_client(&server_config);
+        let will_logger = will_logger_handler.get_logger();
+        will_client
+            .disconnect(ReasonCode::NormalDisconnection, &will_logger)
+            .unwrap();
+        assert!(listener
+            .receiver
+            .recv_timeout(Duration::from_secs(4))
+            .is_err());
+        will_logger.close();
+        will_logger_handler.close();
+
+        // La sesión se mantiene durante su Session Expiry Interval y luego expira
+        let (stream, session_present) = connect_expiring_client(&address, 1);
+        assert_eq!(session_present, 0);
+        disconnect_expiring_client(stream);
+
+        let (stream, session_present) = connect_expiring_client(&address, 1);
+        assert_eq!(session_present, 1);
+        disconnect_expiring_client(stream);
+
+        thread::sleep(Duration::from_secs(3));
+        let (stream, session_present) = connect_expiring_client(&address, 1);
+        assert_eq!(session_present, 0);
+        disconnect_expiring_client(stream);
+
+        subscriber
+            .disconnect(ReasonCode::NormalDisconnection, &sub_logger)
+            .unwrap();
+        sub_logger.close();
+        subscriber_logger.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(
+            client_config(WILL_CLIENT_CONFIG, &server_config)
+                .general
+                .log_path,
+        );
+        let _ = remove_file(sub_log_path);
+        let _ = remove_file(config_path("log_session_expiry_raw.tmp"));
+    }
+}
@@ -0,0 +1,188 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{
+            client_connector::connect,
+            client_listener::MqttClientListener,
+            mqtt_client::{receive_packet, MqttClient},
+        },
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::{client_config::ClientConfig, mqtt_config::Config, server_config::ServerConfig},
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::connect::Connect,
+            properties::{connect_payload::ConnectPayload, connect_properties::ConnectProperties},
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const SHARED_TOPIC: &str = "$share/camssystem/inc";
+    const TOPIC: &str = "inc";
+
+    /// Configuracion de un cliente que conserva su sesión al desconectarse
+    fn persistent_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/persistent_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config
+    }
+
+    fn init_subscriber(
+        id: &str,
+        topic: &str,
+        server_config: &ServerConfig,
+        logger: &Logger,
+    ) -> (MqttClient, MqttClientListener) {
+        let mut client = MqttClient::init(persistent_config(id, server_config)).unwrap();
+        let listener = client.run_listener(logger).unwrap();
+        client.subscribe(vec![topic], logger).unwrap();
+        (client, listener)
+    }
+
+    fn receive_all(listener: &MqttClientListener) -> Vec<Vec<u8>> {
+        let mut received = Vec::new();
+        while let Ok(message) = listener.receiver.recv_timeout(Duration::from_millis(500)) {
+            received.push(message.data);
+        }
+        received
+    }
+
+    /// Conecta un cliente y devuelve el Shared Subscription Available del CONNACK
+    fn shared_subscription_available(address: &str, logger: &Logger) -> Option<u8> {
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &"sharedraw".to_string(),
+            logger,
+        )
+        .unwrap();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: "sharedraw".to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+
+        let available = match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(connack) => connack.properties.shared_subscription_available,
+            _ => panic!("Se esperaba un CONNACK"),
+        };
+        let _ = stream.shutdown();
+        available
+    }
+
+    #[test]
+    fn test_shared_subscription_delivers_once_per_group() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = persistent_config("camssystem", &server_config)
+            .general
+            .log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        assert_eq!(shared_subscription_available(&address, &logger), Some(1));
+
+        // Dos miembros del grupo y un suscriptor no compartido
+        let (mut cams1, cams1_listener) =
+            init_subscriber("cams1", SHARED_TOPIC, &server_config, &logger);
+        let (mut cams2, cams2_listener) =
+            init_subscriber("cams2", SHARED_TOPIC, &server_config, &logger);
+        let (mut monitoring, monitoring_listener) =
+            init_subscriber("monitoring", TOPIC, &server_config, &logger);
+        thread::sleep(Duration::from_millis(300));
+
+        let mut publisher =
+            MqttClient::init(persistent_config("sharedpub", &server_config)).unwrap();
+        for n in 0..10 {
+            publisher
+                .publish(
+                    format!("inc {}", n).into_bytes(),
+                    TOPIC.to_string(),
+                    &logger,
+                )
+                .unwrap();
+        }
+
+        // Cada incidente lo procesa un unico miembro del grupo
+        let mut cams1_received = receive_all(&cams1_listener);
+        let cams2_received = receive_all(&cams2_listener);
+        assert!(!cams1_received.is_empty());
+        assert!(!cams2_received.is_empty());
+        cams1_received.extend(cams2_received);
+        cams1_received.sort();
+        let mut published = (0..10)
+            .map(|n| format!("inc {}", n).into_bytes())
+            .collect::<Vec<Vec<u8>>>();
+        published.sort();
+        assert_eq!(cams1_received, published);
+        assert_eq!(receive_all(&monitoring_listener).len(), 10);
+
+        cams1
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        cams2
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // Un miembro sin listener recibe las publicaciones pero no las confirma
+        let mut cams3 = MqttClient::init(persistent_config("cams3", &server_config)).unwrap();
+        cams3.subscribe(vec![SHARED_TOPIC], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        for n in 10..12 {
+            publisher
+                .publish(
+                    format!("inc {}", n).into_bytes(),
+                    TOPIC.to_string(),
+                    &logger,
+                )
+                .unwrap();
+        }
+        thread::sleep(Duration::from_millis(300));
+
+        // Al desconectarse, sus publicaciones sin confirmar se redistribuyen en el grupo
+        let mut cams1 = MqttClient::init(persistent_config("cams1", &server_config)).unwrap();
+        let cams1_listener = cams1.run_listener(&logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        cams3
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+
+        assert_eq!(
+            receive_all(&cams1_listener),
+            vec![b"inc 10".to_vec(), b"inc 11".to_vec()]
+        );
+        assert_eq!(receive_all(&monitoring_listener).len(), 2);
+
+        for client in [&mut cams1, &mut monitoring, &mut publisher] {
+            client
+                .disconnect(ReasonCode::NormalDisconnection, &logger)
+                .unwrap();
+        }
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
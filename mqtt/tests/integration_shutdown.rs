@@ -0,0 +1,105 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{config_path, remove_server_files, server_config, start_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::{client_connector::connect, mqtt_client::receive_packet},
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::mqtt_config::Config,
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::connect::Connect,
+            properties::{connect_payload::ConnectPayload, connect_properties::ConnectProperties},
+        },
+        server::{mqtt_server::MqttServer, server_register::SessionRegister},
+    };
+
+    use std::{
+        fs::remove_file,
+        net::TcpStream,
+        time::{Duration, Instant},
+    };
+
+    /// Cliente que se conecta con un Session Expiry Interval de 60 segundos
+    fn connect_client(address: &str, client_id: &str, log_path: &str) -> MqttStream {
+        let logger_handler = create_logger_handler(&log_path.to_string()).unwrap();
+        let logger = logger_handler.get_logger();
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &client_id.to_string(),
+            &logger,
+        )
+        .unwrap();
+        logger.close();
+        logger_handler.close();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            session_expiry_interval: Some(60),
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: client_id.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(_) => stream,
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+    }
+
+    #[test]
+    fn test_shutdown_disconnects_clients_and_persists_sessions() {
+        let server_config = server_config("tests/config/shutdown_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+
+        // SERVER: se apaga desde un clon, como al recibir SIGTERM
+        let (shutdown_server, result_receiver) = start_server(MqttServer::new(
+            server_config.clone(),
+            vec!["drone1".to_string()],
+        ));
+
+        let client_log_path = config_path("log_shutdown_client.tmp");
+        let mut drone1 = connect_client(&address, "drone1", &client_log_path);
+
+        // Una conexion que no llega a enviar el CONNECT no demora el cierre
+        let idle_connection = TcpStream::connect(&address).unwrap();
+
+        let start = Instant::now();
+        shutdown_server.shutdown();
+
+        let reason_code = match receive_packet(&mut drone1).unwrap() {
+            PacketReceived::Disconnect(disconnect) => disconnect.properties.disconnect_reason_code,
+            _ => panic!("Se esperaba un DISCONNECT"),
+        };
+        assert_eq!(reason_code, ReasonCode::ServerShuttingDown.get_id());
+
+        let result = result_receiver
+            .recv_timeout(Duration::from_secs(server_config.shutdown_timeout + 1))
+            .unwrap();
+        assert!(result.is_ok(), "{:?}", result);
+        assert!(start.elapsed() < Duration::from_secs(server_config.shutdown_timeout));
+
+        // El servidor ya no acepta conexiones
+        assert!(TcpStream::connect(&address).is_err());
+        drop(idle_connection);
+
+        // La sesión quedó persistida como desconectada
+        let db_path = server_config.db_path.clone().unwrap();
+        let register = SessionRegister::new(Some(db_path));
+        assert!(register.has_session("drone1"));
+        assert!(!register.is_session_active("drone1"));
+
+        let _ = remove_file(client_log_path);
+        remove_server_files(&server_config);
+    }
+}
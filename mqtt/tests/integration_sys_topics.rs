@@ -0,0 +1,135 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::{client_message::MqttClientMessage, mqtt_client::MqttClient},
+        common::reason_codes::ReasonCode,
+        config::{client_config::ClientConfig, server_config::ServerConfig},
+        server::{mqtt_server::MqttServer, server_acl::ServerAcl},
+    };
+
+    use std::{
+        fs::remove_file,
+        sync::mpsc::Receiver,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    /// Configuracion de un cliente que se autentica con su id como usuario
+    fn drone_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/drone_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config
+    }
+
+    fn receive_for(
+        receiver: &Receiver<MqttClientMessage>,
+        duration: Duration,
+    ) -> Vec<MqttClientMessage> {
+        let deadline = Instant::now() + duration;
+        let mut received = Vec::new();
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            if let Ok(message) = receiver.recv_timeout(remaining) {
+                received.push(message);
+            }
+        }
+        received
+    }
+
+    fn last_payload(messages: &[MqttClientMessage], topic: &str) -> Option<String> {
+        messages
+            .iter()
+            .rev()
+            .find(|message| message.topic == topic)
+            .map(|message| String::from_utf8(message.data.clone()).unwrap())
+    }
+
+    #[test]
+    fn test_sys_topics_publish_statistics_to_authorized_clients() {
+        let server_config = server_config("tests/config/sys_server_config.txt");
+        let acl =
+            ServerAcl::from_file(&config_path(&server_config.acl_path.clone().unwrap())).unwrap();
+
+        // SERVER: publica sus estadisticas cada segundo
+        let users = vec!["admin".to_string(), "drone1".to_string()];
+        let (server, result_receiver) =
+            start_server(MqttServer::new(server_config.clone(), users).with_acl(acl));
+
+        let client_log_path = drone_config("admin", &server_config).general.log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        let mut admin = MqttClient::init(drone_config("admin", &server_config)).unwrap();
+        let admin_listener = admin.run_listener(&logger).unwrap();
+        admin.subscribe(vec!["$SYS/broker/#"], &logger).unwrap();
+
+        // Un dron habilitado en `#` no recibe los topicos `$SYS` ni puede publicar en ellos
+        let mut drone1 = MqttClient::init(drone_config("drone1", &server_config)).unwrap();
+        let drone_listener = drone1.run_listener(&logger).unwrap();
+        drone1.subscribe(vec!["$SYS/#"], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(
+            drone1
+                .publish(b"0".to_vec(), "$SYS/broker/uptime".to_string(), &logger)
+                .unwrap(),
+            ReasonCode::NotAuthorized
+        );
+        assert_eq!(
+            drone1
+                .publish(b"estado".to_vec(), "drone/drone1".to_string(), &logger)
+                .unwrap(),
+            ReasonCode::NoMatchingSubscribers
+        );
+
+        let received = receive_for(&admin_listener.receiver, Duration::from_millis(2500));
+        assert_eq!(
+            last_payload(&received, "$SYS/broker/clients/connected"),
+            Some("2".to_string())
+        );
+        assert_eq!(
+            last_payload(&received, "$SYS/broker/clients/disconnected"),
+            Some("0".to_string())
+        );
+        assert_eq!(
+            last_payload(&received, "$SYS/broker/messages/received"),
+            Some("2".to_string())
+        );
+        assert_eq!(
+            last_payload(&received, "$SYS/broker/retained/count"),
+            Some("0".to_string())
+        );
+        assert_eq!(
+            last_payload(&received, "$SYS/broker/sessions/drone1/queued"),
+            Some("0".to_string())
+        );
+        for topic in [
+            "$SYS/broker/uptime",
+            "$SYS/broker/load/messages/received",
+            "$SYS/broker/load/messages/sent",
+        ] {
+            assert!(last_payload(&received, topic).is_some(), "{}", topic);
+        }
+        for topic in ["$SYS/broker/bytes/received", "$SYS/broker/bytes/sent"] {
+            let bytes: u64 = last_payload(&received, topic).unwrap().parse().unwrap();
+            assert!(bytes > 0, "{}", topic);
+        }
+        assert!(drone_listener
+            .receiver
+            .recv_timeout(Duration::from_millis(100))
+            .is_err());
+
+        for client in [&mut admin, &mut drone1] {
+            client
+                .disconnect(ReasonCode::NormalDisconnection, &logger)
+                .unwrap();
+        }
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
@@ -0,0 +1,138 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, server_config, start_server, stop_server};
+    use logger::logger_handler::create_logger_handler;
+    use mqtt::{
+        client::mqtt_client::MqttClient, common::reason_codes::ReasonCode,
+        config::mqtt_config::Config, server::mqtt_server::MqttServer,
+    };
+
+    use std::{
+        fs::remove_file,
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::{Arc, Mutex},
+        thread,
+        time::Duration,
+    };
+
+    const TOPIC: &str = "secret_topic";
+    const PAYLOAD: &[u8] = b"very secret payload";
+    /// Puerto en el que escucha el proxy, entre el cliente y el servidor
+    const PROXY_PORT: u16 = 6101;
+
+    fn contains(bytes: &[u8], pattern: &[u8]) -> bool {
+        bytes.windows(pattern.len()).any(|w| w == pattern)
+    }
+
+    /// Copia los bytes de `from` a `to`, guardando una copia de todo lo que pasa por el socket
+    fn forward(mut from: TcpStream, mut to: TcpStream, captured: Arc<Mutex<Vec<u8>>>) {
+        let mut buf = [0u8; 1024];
+        loop {
+            let n = match from.read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            captured.lock().unwrap().extend_from_slice(&buf[..n]);
+            if to.write_all(&buf[..n]).is_err() {
+                break;
+            }
+        }
+    }
+
+    /// Bytes registrados por el proxy en cada sentido de la conexion
+    type Recording = Arc<Mutex<Vec<u8>>>;
+
+    /// Proxy TCP entre el cliente y el servidor que registra los bytes que viajan por la red
+    fn spawn_recording_proxy(
+        listen_address: &str,
+        server_address: String,
+    ) -> (Recording, Recording) {
+        let listener = TcpListener::bind(listen_address).unwrap();
+        let to_server = Arc::new(Mutex::new(Vec::new()));
+        let to_client = Arc::new(Mutex::new(Vec::new()));
+
+        let (to_server_cpy, to_client_cpy) = (to_server.clone(), to_client.clone());
+        thread::spawn(move || {
+            let (client, _) = listener.accept().unwrap();
+            let server = TcpStream::connect(server_address).unwrap();
+
+            let (client_cpy, server_cpy) =
+                (client.try_clone().unwrap(), server.try_clone().unwrap());
+            thread::spawn(move || forward(client_cpy, server_cpy, to_server_cpy));
+            forward(server, client, to_client_cpy);
+        });
+
+        (to_server, to_client)
+    }
+
+    #[test]
+    fn test_session_traffic_is_encrypted() {
+        let server_config = server_config("tests/config/broker_server_config.txt");
+        let mut config = client_config("tests/config/monitoring_client_config.txt", &server_config);
+        config.general.id = "tls_app".to_string();
+        config.general.port = PROXY_PORT;
+
+        // PROXY
+        let (to_server, to_client) = spawn_recording_proxy(
+            &config.get_socket_address().to_string(),
+            server_config.get_socket_address().to_string(),
+        );
+
+        // SERVER
+        let (server, result_receiver) = start_server(MqttServer::new(
+            server_config.clone(),
+            vec!["tls_app".to_string()],
+        ));
+
+        // CLIENT
+        let mut client = MqttClient::init(config.clone()).unwrap();
+
+        let logger_handler = create_logger_handler(&config.general.log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        let listener = client.run_listener(&logger).unwrap();
+        client.subscribe(vec![TOPIC], &logger).unwrap();
+        thread::sleep(Duration::from_millis(500));
+        client
+            .publish(PAYLOAD.to_vec(), TOPIC.to_string(), &logger)
+            .unwrap();
+
+        let mut received = None;
+        while let Ok(message) = listener.receiver.recv_timeout(Duration::from_secs(5)) {
+            if message.topic == TOPIC {
+                received = Some(message.data);
+                break;
+            }
+        }
+
+        client
+            .disconnect(ReasonCode::NormalDisconnection, &logger)
+            .unwrap();
+
+        // El mensaje llega al cliente a traves de la sesion TLS
+        assert_eq!(received, Some(PAYLOAD.to_vec()));
+
+        // Los paquetes MQTT no viajan en texto plano
+        let to_server = to_server.lock().unwrap().clone();
+        let to_client = to_client.lock().unwrap().clone();
+
+        // 0x16: Handshake TLS. Un CONNECT en texto plano comenzaria con 0x10
+        assert_eq!(to_server.first(), Some(&0x16));
+        assert_eq!(to_client.first(), Some(&0x16));
+
+        for captured in [&to_server, &to_client] {
+            assert!(!contains(captured, TOPIC.as_bytes()));
+            assert!(!contains(captured, PAYLOAD));
+            assert!(!contains(captured, b"MQTT"));
+            assert!(!contains(captured, b"tls_app"));
+        }
+
+        logger.close();
+        logger_handler.close();
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(&config.general.log_path);
+    }
+}
@@ -0,0 +1,201 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{client_config, config_path, server_config, start_server, stop_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{
+            client_connector::connect,
+            mqtt_client::{receive_packet, MqttClient},
+        },
+        common::{mqtt_stream::MqttStream, reason_codes::ReasonCode},
+        config::{client_config::ClientConfig, mqtt_config::Config, server_config::ServerConfig},
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::{connect::Connect, publish::Publish, subscribe::Subscribe},
+            properties::{
+                connect_payload::ConnectPayload, connect_properties::ConnectProperties,
+                publish_properties::PublishProperties, subscribe_properties::SubscribeProperties,
+            },
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{fs::remove_file, thread, time::Duration};
+
+    const TOPIC: &str = "drone";
+
+    /// Configuracion de un cliente que acepta hasta 5 Topic Aliases del servidor
+    fn alias_config(id: &str, server_config: &ServerConfig) -> ClientConfig {
+        let mut config = client_config("tests/config/monitoring_client_config.txt", server_config);
+        config.general.id = id.to_string();
+        config.connect_properties.topic_alias_maximum = Some(5);
+        config
+    }
+
+    /// Conecta un cliente que acepta `topic_alias_maximum` aliases y devuelve el
+    /// Topic Alias Maximum del CONNACK
+    fn connect_raw_client(
+        address: &str,
+        client_id: &str,
+        topic_alias_maximum: u16,
+        logger: &Logger,
+    ) -> (MqttStream, Option<u16>) {
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &client_id.to_string(),
+            logger,
+        )
+        .unwrap();
+        let mut stream = MqttStream::new(tls_stream).unwrap();
+
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            topic_alias_maximum: Some(topic_alias_maximum),
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: client_id.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(connack) => {
+                let topic_alias_maximum = connack.properties.topic_alias_maximum;
+                (stream, topic_alias_maximum)
+            }
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+    }
+
+    fn raw_publish(stream: &mut MqttStream, topic: &str, topic_alias: Option<u16>) {
+        let properties = PublishProperties {
+            topic_name: topic.to_string(),
+            topic_alias,
+            application_message: b"drone 1".to_vec(),
+            ..Default::default()
+        };
+        Publish::new(0, 0, 0, properties).send(stream).unwrap();
+    }
+
+    fn receive_publish(stream: &mut MqttStream) -> Publish {
+        loop {
+            match receive_packet(stream).unwrap() {
+                PacketReceived::Publish(publish) => return *publish,
+                PacketReceived::Suback(_) => continue,
+                _ => panic!("Se esperaba un PUBLISH"),
+            }
+        }
+    }
+
+    fn receive_disconnect_reason(stream: &mut MqttStream) -> u8 {
+        match receive_packet(stream).unwrap() {
+            PacketReceived::Disconnect(disconnect) => disconnect.properties.disconnect_reason_code,
+            _ => panic!("Se esperaba un DISCONNECT"),
+        }
+    }
+
+    #[test]
+    fn test_topic_aliases_in_both_directions() {
+        let server_config = server_config("tests/config/topic_alias_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+
+        // SERVER
+        let users = vec!["camssystem".to_string(), "monitoring".to_string()];
+        let (server, result_receiver) = start_server(MqttServer::new(server_config.clone(), users));
+
+        let client_log_path = alias_config("monitoring", &server_config).general.log_path;
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // Suscriptor que acepta aliases: el servidor envia el topico completo solo la primera vez
+        let (mut raw_subscriber, topic_alias_maximum) =
+            connect_raw_client(&address, "aliassub", 2, &logger);
+        assert_eq!(topic_alias_maximum, Some(2));
+        let mut properties = SubscribeProperties {
+            packet_identifier: 1,
+            ..Default::default()
+        };
+        properties.add_topic_filter(format!("aliassub/{}", TOPIC), 0, false, false, 0);
+        Subscribe::new(properties)
+            .send(&mut raw_subscriber)
+            .unwrap();
+
+        let mut monitoring = MqttClient::init(alias_config("monitoring", &server_config)).unwrap();
+        let listener = monitoring.run_listener(&logger).unwrap();
+        monitoring.subscribe(vec![TOPIC], &logger).unwrap();
+        thread::sleep(Duration::from_millis(300));
+
+        // El publicador asigna los aliases de forma transparente
+        let mut publisher = MqttClient::init(alias_config("aliaspub", &server_config)).unwrap();
+        for _ in 0..3 {
+            let reason_code = publisher
+                .publish(b"drone 1".to_vec(), TOPIC.to_string(), &logger)
+                .unwrap();
+            assert_eq!(reason_code, ReasonCode::Success);
+        }
+
+        let first = receive_publish(&mut raw_subscriber);
+        assert_eq!(first.properties.topic_name, TOPIC);
+        assert_eq!(first.properties.topic_alias, Some(1));
+        for _ in 0..2 {
+            let publish = receive_publish(&mut raw_subscriber);
+            assert_eq!(publish.properties.topic_name, "");
+            assert_eq!(publish.properties.topic_alias, Some(1));
+        }
+
+        for _ in 0..3 {
+            let message = listener
+                .receiver
+                .recv_timeout(Duration::from_secs(2))
+                .unwrap();
+            assert_eq!(message.topic, TOPIC);
+        }
+
+        // Un publicador que define un alias y luego lo usa
+        let (mut raw_publisher, _) = connect_raw_client(&address, "rawpub", 0, &logger);
+        raw_publish(&mut raw_publisher, TOPIC, Some(1));
+        raw_publish(&mut raw_publisher, "", Some(1));
+        for _ in 0..2 {
+            let message = listener
+                .receiver
+                .recv_timeout(Duration::from_secs(2))
+                .unwrap();
+            assert_eq!(message.topic, TOPIC);
+        }
+
+        // Un alias desconocido es un error de protocolo
+        raw_publish(&mut raw_publisher, "", Some(2));
+        assert_eq!(
+            receive_disconnect_reason(&mut raw_publisher),
+            ReasonCode::ProtocolError.get_id()
+        );
+
+        // Un alias mayor al Topic Alias Maximum del servidor es invalido
+        let (mut raw_publisher, _) = connect_raw_client(&address, "rawpub", 0, &logger);
+        raw_publish(&mut raw_publisher, TOPIC, Some(3));
+        assert_eq!(
+            receive_disconnect_reason(&mut raw_publisher),
+            ReasonCode::TopicAliasInvalid.get_id()
+        );
+
+        for client in [&mut monitoring, &mut publisher] {
+            client
+                .disconnect(ReasonCode::NormalDisconnection, &logger)
+                .unwrap();
+        }
+        let _ = raw_subscriber.shutdown();
+        logger.close();
+        logger_handler.close();
+
+        stop_server(server, result_receiver, &server_config);
+        let _ = remove_file(client_log_path);
+    }
+}
@@ -0,0 +1,178 @@
+mod common;
+
+#[cfg(test)]
+mod test {
+    use crate::common::{config_path, remove_server_files, server_config, start_server};
+    use logger::logger_handler::{create_logger_handler, Logger};
+    use mqtt::{
+        client::{client_connector::connect, mqtt_client::receive_packet},
+        common::{
+            mqtt_stream::MqttStream, reason_codes::ReasonCode, websocket::WebSocketTransport,
+        },
+        config::mqtt_config::Config,
+        mqtt_packets::{
+            packet::generic_packet::{PacketReceived, Serialization},
+            packets::{connect::Connect, publish::Publish, subscribe::Subscribe},
+            properties::{
+                connect_payload::ConnectPayload, connect_properties::ConnectProperties,
+                publish_properties::PublishProperties, subscribe_properties::SubscribeProperties,
+            },
+        },
+        server::mqtt_server::MqttServer,
+    };
+
+    use std::{
+        fs::remove_file,
+        io::{Read, Write},
+        net::TcpStream,
+        time::Duration,
+    };
+
+    const TOPIC: &str = "incidents";
+
+    /// Envia el CONNECT sobre el stream y espera el CONNACK
+    fn mqtt_connect(mut stream: MqttStream, client_id: &str) -> MqttStream {
+        let properties = ConnectProperties {
+            protocol_name: "MQTT".to_string(),
+            protocol_version: 5,
+            keep_alive: 60,
+            ..Default::default()
+        };
+        let payload = ConnectPayload {
+            client_id: client_id.to_string(),
+            ..Default::default()
+        };
+        Connect::new(properties, payload).send(&mut stream).unwrap();
+
+        match receive_packet(&mut stream).unwrap() {
+            PacketReceived::Connack(_) => stream,
+            _ => panic!("Se esperaba un CONNACK"),
+        }
+    }
+
+    /// Conecta un cliente como lo haria un navegador: WebSocket con el subprotocolo `mqtt`
+    fn connect_websocket_client(address: &str, path: &str, client_id: &str) -> MqttStream {
+        let tcp_stream = TcpStream::connect(address).unwrap();
+        let transport = WebSocketTransport::connect(tcp_stream, "localhost", path).unwrap();
+        mqtt_connect(MqttStream::new(transport).unwrap(), client_id)
+    }
+
+    fn connect_tls_client(address: &str, client_id: &str, logger: &Logger) -> MqttStream {
+        let tls_stream = connect(
+            address,
+            "localhost",
+            &Some(config_path("tests/certificates/ca.crt")),
+            &1,
+            &client_id.to_string(),
+            logger,
+        )
+        .unwrap();
+        mqtt_connect(MqttStream::new(tls_stream).unwrap(), client_id)
+    }
+
+    /// Suscribe al cliente, indicando su id en el filtro como lo hace `MqttClient`
+    fn subscribe(stream: &mut MqttStream, client_id: &str, topic: &str) {
+        let mut properties = SubscribeProperties {
+            packet_identifier: 1,
+            ..Default::default()
+        };
+        properties.add_topic_filter(format!("{}/{}", client_id, topic), 0, false, false, 0);
+        Subscribe::new(properties).send(stream).unwrap();
+        match receive_packet(stream).unwrap() {
+            PacketReceived::Suback(_) => {}
+            _ => panic!("Se esperaba un SUBACK"),
+        }
+    }
+
+    fn publish(stream: &mut MqttStream, topic: &str, message: &[u8]) {
+        let properties = PublishProperties {
+            topic_name: topic.to_string(),
+            application_message: message.to_vec(),
+            ..Default::default()
+        };
+        Publish::new(0, 0, 0, properties).send(stream).unwrap();
+    }
+
+    fn receive_publish(stream: &mut MqttStream) -> Publish {
+        match receive_packet(stream).unwrap() {
+            PacketReceived::Publish(publish) => *publish,
+            _ => panic!("Se esperaba un PUBLISH"),
+        }
+    }
+
+    #[test]
+    fn test_websocket_clients_share_sessions_with_tls_clients() {
+        let server_config = server_config("tests/config/websocket_server_config.txt");
+        let address = server_config.get_socket_address().to_string();
+        let websocket_address = server_config.get_websocket_address().unwrap().to_string();
+        let path = server_config.websocket_path.clone();
+
+        // SERVER
+        let (shutdown_server, result_receiver) =
+            start_server(MqttServer::new(server_config.clone(), vec![]));
+
+        let client_log_path = config_path("log_websocket_client.tmp");
+        let logger_handler = create_logger_handler(&client_log_path).unwrap();
+        let logger = logger_handler.get_logger();
+
+        // Un dashboard suscripto por WebSocket recibe lo que se publica por TLS
+        let mut dashboard = connect_websocket_client(&websocket_address, &path, "dashboard");
+        subscribe(&mut dashboard, "dashboard", TOPIC);
+        let mut camera = connect_tls_client(&address, "camera", &logger);
+        subscribe(&mut camera, "camera", "acks");
+
+        publish(&mut camera, TOPIC, b"incidente 1");
+        let received = receive_publish(&mut dashboard);
+        assert_eq!(received.properties.topic_name, TOPIC);
+        assert_eq!(received.properties.application_message, b"incidente 1");
+
+        // Y lo que publica por WebSocket llega a los clientes TLS
+        publish(&mut dashboard, "acks", b"incidente 1 visto");
+        let received = receive_publish(&mut camera);
+        assert_eq!(
+            received.properties.application_message,
+            b"incidente 1 visto"
+        );
+
+        // Un pedido sin el subprotocolo mqtt se rechaza
+        let mut browser = TcpStream::connect(&websocket_address).unwrap();
+        browser
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        browser
+            .write_all(
+                format!(
+                    "GET {} HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                     Connection: Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                     Sec-WebSocket-Version: 13\r\n\r\n",
+                    path
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        let mut response = String::new();
+        let _ = browser.read_to_string(&mut response);
+        assert!(response.starts_with("HTTP/1.1 400"), "{}", response);
+
+        // Al apagarse, el servidor tambien desconecta a los clientes WebSocket
+        shutdown_server.shutdown();
+        let reason_code = match receive_packet(&mut dashboard).unwrap() {
+            PacketReceived::Disconnect(disconnect) => disconnect.properties.disconnect_reason_code,
+            _ => panic!("Se esperaba un DISCONNECT"),
+        };
+        assert_eq!(reason_code, ReasonCode::ServerShuttingDown.get_id());
+
+        let result = result_receiver
+            .recv_timeout(Duration::from_secs(server_config.shutdown_timeout + 1))
+            .unwrap();
+        assert!(result.is_ok(), "{:?}", result);
+        assert!(TcpStream::connect(&websocket_address).is_err());
+
+        let _ = camera.shutdown();
+        logger.close();
+        logger_handler.close();
+
+        let _ = remove_file(client_log_path);
+        remove_server_files(&server_config);
+    }
+}
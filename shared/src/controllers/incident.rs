@@ -7,12 +7,22 @@ pub mod incident_controller {
 
     use crate::models::inc_model::{incident::Incident, incident_list::IncidentList};
 
+    /// QoS de los mensajes de incidentes: se entregan exactamente una vez
+    const INCIDENT_QOS: u8 = 2;
+
     fn send_incident(
         client: &mut MqttClient,
         inc: &Incident,
         logger: &Logger,
     ) -> Result<(), Error> {
-        client.publish(inc.as_bytes().clone(), "inc".to_string(), logger)
+        client
+            .publish_with_qos(
+                inc.as_bytes().clone(),
+                "inc".to_string(),
+                INCIDENT_QOS,
+                logger,
+            )
+            .map(|_| ())
     }
 
     pub fn add_incident(